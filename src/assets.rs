@@ -0,0 +1,90 @@
+//! Checks for the assets required to initialize the game.
+//!
+//! Assets are loaded asynchronously, so a missing or broken asset would otherwise only be noticed
+//! once something tries to use it. These checks let initialization fail early with an error which
+//! can be shown to the player.
+
+use amethyst::utils::application_root_dir;
+use serde::de::DeserializeOwned;
+
+use std::{error, fmt, fs, io, path::PathBuf};
+
+/// An error raised while initializing the game.
+#[derive(Debug)]
+pub enum InitError {
+    /// The asset directory could not be located.
+    AssetsDir(io::Error),
+    /// A required asset is missing.
+    MissingAsset(PathBuf),
+    /// A required asset could not be read or parsed.
+    BrokenAsset(PathBuf, String),
+    /// A replay was recorded with other experimental features than the ones enabled. Holds the
+    /// features it was recorded with.
+    IncompatibleReplay(PathBuf, Vec<&'static str>),
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InitError::AssetsDir(ref e) => write!(fmt, "failed to locate assets: {}", e),
+            InitError::MissingAsset(ref path) => write!(fmt, "missing asset: {}", path.display()),
+            InitError::BrokenAsset(ref path, ref e) => {
+                write!(fmt, "broken asset: {}: {}", path.display(), e)
+            }
+            InitError::IncompatibleReplay(ref path, ref features) if features.is_empty() => write!(
+                fmt,
+                "replay recorded without experimental features: {}",
+                path.display()
+            ),
+            InitError::IncompatibleReplay(ref path, ref features) => write!(
+                fmt,
+                "replay recorded with experimental features, run with --enable {}: {}",
+                features.join(","),
+                path.display()
+            ),
+        }
+    }
+}
+
+impl error::Error for InitError {}
+
+/// Require that the asset at the given path (relative to the asset directory) exists.
+pub fn require(path: &str) -> Result<PathBuf, InitError> {
+    let path = application_root_dir()
+        .map_err(InitError::AssetsDir)?
+        .join("assets")
+        .join(path);
+
+    if !path.is_file() {
+        return Err(InitError::MissingAsset(path));
+    }
+
+    Ok(path)
+}
+
+/// Require that the RON asset at the given path exists, and is syntactically valid.
+pub fn require_ron(path: &str) -> Result<PathBuf, InitError> {
+    let path = require(path)?;
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| InitError::BrokenAsset(path.clone(), e.to_string()))?;
+
+    if let Err(e) = ron::de::from_str::<ron::Value>(&content) {
+        return Err(InitError::BrokenAsset(path, e.to_string()));
+    }
+
+    Ok(path)
+}
+
+/// Load the RON asset at the given path, parsing it into `T`.
+pub fn load_ron<T>(path: &str) -> Result<T, InitError>
+where
+    T: DeserializeOwned,
+{
+    let path = require(path)?;
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| InitError::BrokenAsset(path.clone(), e.to_string()))?;
+
+    ron::de::from_str(&content).map_err(|e| InitError::BrokenAsset(path, e.to_string()))
+}
@@ -3,14 +3,32 @@ use amethyst::{
     audio::{output::Output, AudioSink, OggFormat, Source, SourceHandle},
     ecs::prelude::World,
 };
-use crate::resources::RandomGen;
+use crate::{settings::SfxBudgets, theme::Theme};
+use log::{trace, warn};
+use rodio::Source as RodioSource;
 
-pub struct Silent;
+use std::{cmp::Ordering, io::Cursor, iter::Cycle, vec};
+
+/// Background music, looped for as long as the game runs.
+pub struct Music {
+    tracks: Cycle<vec::IntoIter<SourceHandle>>,
+}
+
+impl Music {
+    /// The next track to play.
+    pub fn next(&mut self) -> Option<SourceHandle> {
+        self.tracks.next()
+    }
+}
 
 pub struct Sounds {
     pub pew_sfx: RandomSfx,
     pub collision_sfx: RandomSfx,
     pub explosion_sfx: RandomSfx,
+    pub tick_sfx: RandomSfx,
+    pub hyperspace_sfx: RandomSfx,
+    /// Device used to play pitch shifted sounds, see `RandomSfx::play_pitched`.
+    pub device: Option<rodio::Device>,
 }
 
 pub struct RandomSfx {
@@ -30,17 +48,369 @@ impl RandomSfx {
         RandomSfx { sources }
     }
 
-    /// Play a sound at random.
-    pub fn play(&self, rand: &RandomGen, storage: &AssetStorage<Source>, output: Option<&Output>) {
+    /// Play a sound at random, at the volume of sound effects in the given mix.
+    ///
+    /// NB: sounds are picked with a generator separate from the game's, so that whether audio is
+    /// played doesn't affect the outcome of a run.
+    pub fn play(
+        &self,
+        storage: &AssetStorage<Source>,
+        output: Option<&Output>,
+        mix: &AudioMix,
+    ) {
         let output = match output.as_ref() {
             Some(output) => output,
             None => return,
         };
 
-        let index = rand.next_usize() % self.sources.len();
+        let index = rand::random::<usize>() % self.sources.len();
 
         if let Some(sound) = self.sources.get(index).and_then(|s| storage.get(s)) {
-            output.play_once(sound, 1.0);
+            output.play_once(sound, mix.sfx_volume());
+        }
+    }
+
+    /// Play a sound at random, shifted to the given pitch where `1.0` is the original pitch.
+    ///
+    /// Amethyst's `Output` can't shift the pitch of a sound, so it's decoded and played through
+    /// rodio directly. The sound is still only played if there is an output, so that it respects
+    /// muting.
+    pub fn play_pitched(
+        &self,
+        storage: &AssetStorage<Source>,
+        output: Option<&Output>,
+        device: Option<&rodio::Device>,
+        mix: &AudioMix,
+        pitch: f32,
+    ) {
+        let device = match device {
+            Some(device) if output.is_some() => device,
+            _ => return self.play(storage, output, mix),
+        };
+
+        let index = rand::random::<usize>() % self.sources.len();
+
+        let sound = match self.sources.get(index).and_then(|s| storage.get(s)) {
+            Some(sound) => sound,
+            None => return,
+        };
+
+        match rodio::Decoder::new(Cursor::new(sound.bytes.clone())) {
+            Ok(decoder) => rodio::play_raw(
+                device,
+                decoder
+                    .speed(pitch)
+                    .convert_samples()
+                    .amplify(mix.sfx_volume()),
+            ),
+            Err(e) => warn!("failed to decode sound: {}", e),
+        }
+    }
+}
+
+/// Category of sound effects, each with a budget of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SfxCategory {
+    Collision,
+    Explosion,
+}
+
+impl SfxCategory {
+    /// The sounds of the category.
+    fn sfx(self, sounds: &Sounds) -> &RandomSfx {
+        match self {
+            SfxCategory::Collision => &sounds.collision_sfx,
+            SfxCategory::Explosion => &sounds.explosion_sfx,
+        }
+    }
+
+    /// The budget of the category.
+    fn budget(self, budgets: &SfxBudgets) -> usize {
+        match self {
+            SfxCategory::Collision => budgets.collision,
+            SfxCategory::Explosion => budgets.explosion,
+        }
+    }
+}
+
+/// A sound effect waiting to be played.
+#[derive(Debug, Clone, Copy)]
+struct SfxRequest {
+    category: SfxCategory,
+    /// Higher priority sounds are played first.
+    priority: f32,
+    /// Pitch the sound is played at, see `RandomSfx::play_pitched`.
+    pitch: f32,
+}
+
+/// Sound effects requested during the frame, which are played by the `AudioMixSystem`.
+///
+/// Dense fields of asteroids can collide many times in a single frame, so each category of sound
+/// effects may only play as many times as its budget allows within `WINDOW`. Each frame, requests
+/// are played by priority until the budget runs out and the rest are dropped.
+#[derive(Default)]
+pub struct SfxQueue {
+    requests: Vec<SfxRequest>,
+    /// Sounds played within the window, and how long ago they were played (s).
+    played: Vec<(SfxCategory, f32)>,
+}
+
+impl SfxQueue {
+    /// Window budgets apply within (s).
+    pub const WINDOW: f32 = 0.1;
+    /// Distance over which sounds lose half of their priority (units).
+    pub const FALLOFF: f32 = 100.0;
+
+    /// Priority of a sound as loud as the given radius, at the given distance from the nearest
+    /// ship.
+    pub fn priority(radius: f32, distance: Option<f32>) -> f32 {
+        radius / (1.0 + distance.unwrap_or(0.0) / Self::FALLOFF)
+    }
+
+    /// Request that a sound of the given category is played.
+    pub fn request(&mut self, category: SfxCategory, priority: f32, pitch: f32) {
+        self.requests.push(SfxRequest {
+            category,
+            priority,
+            pitch,
+        });
+    }
+
+    /// Play the requested sounds which fit in their budgets, and drop the rest.
+    pub fn update(
+        &mut self,
+        time_delta: f32,
+        budgets: &SfxBudgets,
+        sounds: Option<&Sounds>,
+        storage: &AssetStorage<Source>,
+        output: Option<&Output>,
+        mix: &AudioMix,
+    ) {
+        for (_, age) in &mut self.played {
+            *age += time_delta;
+        }
+
+        self.played.retain(|(_, age)| *age < Self::WINDOW);
+
+        if self.requests.is_empty() {
+            return;
+        }
+
+        let sounds = match sounds {
+            Some(sounds) => sounds,
+            None => {
+                self.requests.clear();
+                return;
+            }
+        };
+
+        self.requests.sort_by(|a, b| {
+            b.priority
+                .partial_cmp(&a.priority)
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let mut dropped = 0;
+
+        for request in self.requests.drain(..) {
+            let category = request.category;
+            let played = self.played.iter().filter(|(c, _)| *c == category).count();
+
+            if played >= category.budget(budgets) {
+                dropped += 1;
+                continue;
+            }
+
+            category.sfx(sounds).play_pitched(
+                storage,
+                output,
+                sounds.device.as_ref(),
+                mix,
+                request.pitch,
+            );
+
+            self.played.push((category, 0f32));
+        }
+
+        if dropped > 0 {
+            trace!("dropped {} sounds over budget", dropped);
+        }
+    }
+}
+
+/// A short musical cue played on milestones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stinger {
+    /// Narrowly escaped an asteroid.
+    NearMiss,
+    /// A wave of asteroids was cleared.
+    WaveClear,
+}
+
+impl Stinger {
+    /// How long the stinger plays, during which music is ducked (s).
+    pub fn duration(self) -> f32 {
+        match self {
+            Stinger::NearMiss => 0.6,
+            Stinger::WaveClear => 0.9,
+        }
+    }
+}
+
+/// Stingers layered over the music.
+///
+/// Only one stinger plays at a time, and a stinger only interrupts another one if it has a higher
+/// priority (as defined by the ordering of `Stinger`). The music is ducked while a stinger plays.
+pub struct Stingers {
+    near_miss: SourceHandle,
+    wave_clear: SourceHandle,
+    /// Stinger waiting to be played.
+    pending: Option<Stinger>,
+    /// Stinger currently playing, and how long is left of it.
+    playing: Option<(Stinger, f32)>,
+    /// Current ducking envelope, where 1.0 is fully ducked.
+    envelope: f32,
+}
+
+impl Stingers {
+    /// How much the music volume is reduced while ducked.
+    pub const DUCK_DEPTH: f32 = 0.7;
+    /// How fast the music is ducked (1 / s).
+    pub const DUCK_ATTACK: f32 = 20.0;
+    /// How fast the music recovers after a stinger (1 / s).
+    pub const DUCK_RELEASE: f32 = 2.0;
+
+    /// Request that the given stinger is played.
+    pub fn request(&mut self, stinger: Stinger) {
+        if self.pending.map(|p| p < stinger).unwrap_or(true) {
+            self.pending = Some(stinger);
+        }
+    }
+
+    /// Advance the stingers, starting any pending stinger which should be played.
+    pub fn update(
+        &mut self,
+        time_delta: f32,
+        storage: &AssetStorage<Source>,
+        output: Option<&Output>,
+        device: Option<&rodio::Device>,
+        mix: &AudioMix,
+    ) {
+        if let Some((_, ref mut remaining)) = self.playing {
+            *remaining -= time_delta;
+        }
+
+        if let Some((_, remaining)) = self.playing {
+            if remaining <= 0f32 {
+                self.playing = None;
+            }
+        }
+
+        if let Some(stinger) = self.pending.take() {
+            let interrupts = match self.playing {
+                Some((playing, _)) => stinger > playing,
+                None => true,
+            };
+
+            if interrupts {
+                let source = match stinger {
+                    Stinger::NearMiss => &self.near_miss,
+                    Stinger::WaveClear => &self.wave_clear,
+                };
+
+                if let (Some(output), Some(sound)) = (output, storage.get(source)) {
+                    mix.play(sound, output, device);
+                }
+
+                self.playing = Some((stinger, stinger.duration()));
+            }
+        }
+
+        if self.playing.is_some() {
+            self.envelope = f32::min(self.envelope + Self::DUCK_ATTACK * time_delta, 1.0);
+        } else {
+            self.envelope = f32::max(self.envelope - Self::DUCK_RELEASE * time_delta, 0.0);
+        }
+    }
+
+    /// The factor the music volume should be multiplied with.
+    pub fn duck(&self) -> f32 {
+        1.0 - Self::DUCK_DEPTH * self.envelope
+    }
+}
+
+/// Mix of the game audio, which is muffled while the game is paused or in menus.
+///
+/// Music is attenuated, and sound effects are attenuated and low-pass filtered.
+#[derive(Debug)]
+pub struct AudioMix {
+    /// If the audio should be muffled.
+    pub muffled: bool,
+    /// Master volume, from `0.0` to `1.0`, applied to everything.
+    pub volume: f32,
+    /// Current muffling envelope, where 1.0 is fully muffled.
+    envelope: f32,
+}
+
+impl Default for AudioMix {
+    fn default() -> Self {
+        Self {
+            muffled: false,
+            volume: 1.0,
+            envelope: 0.0,
+        }
+    }
+}
+
+impl AudioMix {
+    /// Volume of the music while muffled.
+    pub const MUFFLED_MUSIC: f32 = 0.4;
+    /// Volume of sound effects while muffled.
+    pub const MUFFLED_SFX: f32 = 0.5;
+    /// Cutoff frequency of the low-pass filter applied to sound effects while muffled (Hz).
+    pub const CUTOFF: u32 = 800;
+    /// How fast the audio is muffled and restored (1 / s).
+    pub const FADE: f32 = 4.0;
+
+    /// Advance the muffling envelope towards its target.
+    pub fn update(&mut self, time_delta: f32) {
+        if self.muffled {
+            self.envelope = f32::min(self.envelope + Self::FADE * time_delta, 1.0);
+        } else {
+            self.envelope = f32::max(self.envelope - Self::FADE * time_delta, 0.0);
+        }
+    }
+
+    /// The factor the music volume should be multiplied with.
+    pub fn music_volume(&self) -> f32 {
+        self.volume * (1.0 - (1.0 - Self::MUFFLED_MUSIC) * self.envelope)
+    }
+
+    /// The factor the volume of sound effects should be multiplied with.
+    pub fn sfx_volume(&self) -> f32 {
+        self.volume * (1.0 - (1.0 - Self::MUFFLED_SFX) * self.envelope)
+    }
+
+    /// Play a sound effect through the mix.
+    ///
+    /// Like with `RandomSfx::play_pitched`, filtered sounds are played through rodio directly.
+    pub fn play(&self, sound: &Source, output: &Output, device: Option<&rodio::Device>) {
+        let volume = self.sfx_volume();
+
+        let device = match device {
+            Some(device) if self.muffled => device,
+            _ => return output.play_once(sound, volume),
+        };
+
+        match rodio::Decoder::new(Cursor::new(sound.bytes.clone())) {
+            Ok(decoder) => rodio::play_raw(
+                device,
+                decoder
+                    .convert_samples()
+                    .low_pass(Self::CUTOFF)
+                    .amplify(volume),
+            ),
+            Err(e) => warn!("failed to decode sound: {}", e),
         }
     }
 }
@@ -54,12 +424,25 @@ fn load_ogg(loader: &Loader, world: &World, file: &str) -> SourceHandle {
     loader.load(file, OggFormat, (), (), &world.read_resource())
 }
 
+/// Volume of the music sink.
+pub const MUSIC_VOLUME: f32 = 0.1;
+
+/// Tracks of background music, played in order when the theme has none.
+const MUSIC_TRACKS: &[&str] = &["audio/music.wav"];
+
 pub fn initialise_audio(world: &mut World) {
-    {
-        let mut sink = world.write_resource::<AudioSink>();
-        sink.set_volume(0.1);
+    // NB: there's no sink without an audio device.
+    if let Some(mut sink) = world.res.try_fetch_mut::<AudioSink>() {
+        sink.set_volume(MUSIC_VOLUME);
     }
 
+    // NB: sounds are never played without an output, so don't bother opening a device.
+    let device = if world.res.has_value::<Output>() {
+        rodio::default_output_device()
+    } else {
+        None
+    };
+
     let pew_sfx = RandomSfx::load(
         world,
         vec![
@@ -93,11 +476,53 @@ pub fn initialise_audio(world: &mut World) {
         ],
     );
 
+    let tick_sfx = RandomSfx::load(world, vec!["audio/tick.wav"]);
+    let hyperspace_sfx = RandomSfx::load(world, vec!["audio/hyperspace.wav"]);
+
     world.add_resource(Sounds {
         pew_sfx,
         collision_sfx,
         explosion_sfx,
+        tick_sfx,
+        hyperspace_sfx,
+        device,
     });
 
-    world.add_resource(Silent);
+    let stingers = {
+        let loader = world.read_resource::<Loader>();
+
+        Stingers {
+            near_miss: load_wav(&loader, &world, "audio/stinger_escape.wav"),
+            wave_clear: load_wav(&loader, &world, "audio/stinger_wave.wav"),
+            pending: None,
+            playing: None,
+            envelope: 0.0,
+        }
+    };
+
+    world.add_resource(stingers);
+    world.add_resource(SfxQueue::default());
+
+    let music = {
+        let loader = world.read_resource::<Loader>();
+
+        // NB: the theme picks the music, if it has any.
+        let paths = world
+            .res
+            .try_fetch::<Theme>()
+            .map(|theme| theme.music.clone())
+            .filter(|music| !music.is_empty())
+            .unwrap_or_else(|| MUSIC_TRACKS.iter().map(|t| t.to_string()).collect());
+
+        let tracks = paths
+            .iter()
+            .map(|track| load_wav(&loader, &world, track))
+            .collect::<Vec<_>>();
+
+        Music {
+            tracks: tracks.into_iter().cycle(),
+        }
+    };
+
+    world.add_resource(music);
 }
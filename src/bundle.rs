@@ -2,9 +2,32 @@ use amethyst::{
     core::bundle::{Result, SystemBundle},
     ecs::prelude::DispatcherBuilder,
 };
+use crate::{
+    config::ExperimentalFeatures,
+    schedule::{Schedule, StateId},
+};
+#[cfg(feature = "external_control")]
+use crate::systems::ExternalControlSystem;
+#[cfg(feature = "external_control")]
+use std::path::PathBuf;
+#[cfg(feature = "clips")]
+use crate::systems::ClipSystem;
+#[cfg(feature = "god_view")]
+use crate::systems::GodViewSystem;
+#[cfg(feature = "timings")]
+use crate::systems::TimingsSystem;
 use crate::systems::{
-    CollisionSystem, GlobalInputSystem, HandleUiSystem, KillBulletsSystem, LimitObjectsSystem,
-    PhysicsSystem, RandomAsteroidSystem, ShipInputSystem,
+    AccretionSystem, AimAssistSystem, AnnounceSystem, AudioMixSystem, BulletTimeSystem,
+    CollisionGraceSystem, CollisionSystem, CometSystem, CullingSystem, DebugOverlaySystem,
+    EffectsSystem, EnergySystem, EntityBudgetSystem, GameLogSystem, GeigerSystem, GhostSystem,
+    GlobalInputSystem, GravitySystem, HandleUiSystem, HealthBarSystem, HistogramSystem,
+    HitboxSystem, InvulnerabilitySystem, KillBulletsSystem, LatencyProbeSystem, LayoutSystem,
+    LimitObjectsSystem, MagnetSystem, NearMissSystem, ParticleSystem, PebbleSystem, PhantomSystem,
+    PhotoCameraSystem, PhysicsSystem, PowerUpSystem, RespawnSystem, ScorePopupSystem,
+    ScriptRunnerSystem, ShipDecalSystem, ShipInputSystem, ShockwaveSystem, SpawnerSystem,
+    SplinterSystem, SqueezeSystem, StationSystem, StingerSystem, TrajectorySystem, TweenSystem,
+    UfoAiSystem, UfoSpawnSystem, UnstableSystem, VelocityArrowSystem, WaveSystem,
+    WindowSettingsSystem,
 };
 
 pub struct GlobalBundle;
@@ -12,21 +35,130 @@ pub struct GlobalBundle;
 impl<'a, 'b> SystemBundle<'a, 'b> for GlobalBundle {
     fn build(self, builder: &mut DispatcherBuilder<'a, 'b>) -> Result<()> {
         builder.add(GlobalInputSystem::default(), "global_input", &[]);
+        builder.add(WindowSettingsSystem::default(), "window_settings", &[]);
+        builder.add(AnnounceSystem::default(), "announce", &[]);
+        builder.add(LayoutSystem::default(), "layout", &[]);
+        builder.add(HitboxSystem, "hitboxes", &["global_input"]);
+        builder.add(DebugOverlaySystem::default(), "debug_overlay", &["global_input"]);
+        builder.add(HistogramSystem::default(), "histogram", &["global_input"]);
+        builder.add(AudioMixSystem, "audio_mix", &["global_input"]);
+        #[cfg(feature = "god_view")]
+        builder.add_thread_local(GodViewSystem::new());
+        #[cfg(feature = "timings")]
+        builder.add(TimingsSystem::default(), "timings", &[]);
         Ok(())
     }
 }
 
-pub struct MainBundle;
+/// Systems for the game itself, scheduled to run in the states where they apply.
+#[derive(Default)]
+pub struct MainBundle {
+    /// Experimental mechanics whose systems are added.
+    pub features: ExperimentalFeatures,
+    /// Socket through which an external program controls the ship, instead of the player.
+    #[cfg(feature = "external_control")]
+    pub control_socket: Option<PathBuf>,
+}
+
+impl MainBundle {
+    /// Add the system which controls the ship.
+    #[cfg(not(feature = "external_control"))]
+    fn add_ship_control(self, main: &mut Schedule) {
+        main.add(ShipInputSystem, "ship_input_system", &["latency_probe"]);
+    }
+
+    /// Add the system which controls the ship, which might be an external program.
+    #[cfg(feature = "external_control")]
+    fn add_ship_control(self, main: &mut Schedule) {
+        match self.control_socket {
+            Some(path) => {
+                let system = ExternalControlSystem::new(path);
+                main.add(system, "ship_input_system", &["latency_probe"]);
+            }
+            None => main.add(ShipInputSystem, "ship_input_system", &["latency_probe"]),
+        }
+    }
+}
 
 impl<'a, 'b> SystemBundle<'a, 'b> for MainBundle {
     fn build(self, builder: &mut DispatcherBuilder<'a, 'b>) -> Result<()> {
-        builder.add(KillBulletsSystem, "kill_bullets", &[]);
-        builder.add(RandomAsteroidSystem::new(), "random_asteroids", &[]);
-        builder.add(ShipInputSystem, "ship_input_system", &[]);
-        builder.add(PhysicsSystem, "physics_system", &[]);
-        builder.add(LimitObjectsSystem, "limit_objects", &["physics_system"]);
-        builder.add(CollisionSystem, "collisions", &["physics_system"]);
-        builder.add(HandleUiSystem, "handle_ui", &[]);
+        let features = self.features;
+
+        let mut main = Schedule::new(builder, &[StateId::Main]);
+        main.add(KillBulletsSystem::default(), "kill_bullets", &[]);
+        main.add(SpawnerSystem::default(), "spawner", &[]);
+        main.add(ScriptRunnerSystem, "script_runner", &[]);
+        main.add(WaveSystem, "waves", &["script_runner"]);
+        main.add(UfoSpawnSystem::new(), "ufo_spawn", &[]);
+        main.add(LatencyProbeSystem::default(), "latency_probe", &[]);
+        self.add_ship_control(&mut main);
+        main.add(EnergySystem::default(), "energy", &["ship_input_system"]);
+        main.add(UfoAiSystem, "ufo_ai", &[]);
+        main.add(MagnetSystem, "magnets", &[]);
+
+        // NB: systems can only depend on systems which have been added.
+        let mut forces = vec!["ufo_ai", "magnets"];
+
+        if features.gravity {
+            main.add(GravitySystem, "gravity", &[]);
+            forces.push("gravity");
+        }
+
+        main.add(PhysicsSystem, "physics_system", &forces);
+        main.add(SqueezeSystem, "squeeze", &[]);
+        main.add(LimitObjectsSystem, "limit_objects", &["physics_system", "squeeze"]);
+        main.add(CollisionSystem::new(), "collisions", &["physics_system"]);
+        main.add(UnstableSystem, "unstable", &["collisions"]);
+        main.add(SplinterSystem, "splinters", &["collisions", "unstable"]);
+
+        if features.accretion {
+            main.add(AccretionSystem::default(), "accretion", &["collisions"]);
+        }
+
+        main.add(CollisionGraceSystem, "collision_grace", &["collisions"]);
+        main.add(RespawnSystem::default(), "respawn", &["collisions"]);
+        main.add(HealthBarSystem::default(), "health_bar", &["collisions"]);
+        main.add(StationSystem::default(), "station", &["collisions", "respawn"]);
+        main.add(InvulnerabilitySystem, "invulnerability", &["respawn"]);
+        main.add(ShipDecalSystem, "ship_decals", &["invulnerability"]);
+        main.add(PowerUpSystem, "power_ups", &["collisions"]);
+        main.add(EntityBudgetSystem::default(), "entity_budget", &["splinters"]);
+        main.add(AimAssistSystem::default(), "aim_assist", &["limit_objects"]);
+        main.add(TrajectorySystem::default(), "trajectories", &["limit_objects"]);
+        main.add(GhostSystem::default(), "ghosts", &["limit_objects"]);
+        main.add(PhantomSystem::default(), "phantoms", &["limit_objects"]);
+        main.add(GeigerSystem::default(), "geiger", &["limit_objects"]);
+        main.add(ScorePopupSystem, "score_popups", &[]);
+        main.add(PebbleSystem, "pebbles", &[]);
+
+        if features.comets {
+            main.add(CometSystem, "comets", &["physics_system"]);
+        }
+
+        main.add(ParticleSystem, "particles", &[]);
+        main.add(ShockwaveSystem, "shockwaves", &["collisions"]);
+        main.add(TweenSystem::default(), "tweens", &["shockwaves"]);
+        main.add(EffectsSystem::default(), "effects", &["kill_bullets"]);
+        main.add(VelocityArrowSystem, "velocity_arrow", &["physics_system"]);
+        main.add(NearMissSystem::default(), "near_miss", &["collisions"]);
+        main.add(BulletTimeSystem::default(), "bullet_time", &["collisions"]);
+        main.add(GameLogSystem::default(), "game_log", &[]);
+        #[cfg(feature = "clips")]
+        main.add(ClipSystem::default(), "clips", &["limit_objects"]);
+
+        let mut any = main.in_states(&[
+            StateId::Main,
+            StateId::Paused,
+            StateId::GameOver,
+            StateId::Draft,
+        ]);
+        any.add(HandleUiSystem, "handle_ui", &[]);
+        any.add(StingerSystem, "stingers", &["near_miss"]);
+        any.add(CullingSystem, "culling", &[]);
+
+        // NB: spectators move the camera around while the game is running.
+        let mut camera = main.in_states(&[StateId::Main, StateId::Paused, StateId::Photo]);
+        camera.add(PhotoCameraSystem, "photo_camera", &[]);
         Ok(())
     }
 }
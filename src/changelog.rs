@@ -0,0 +1,52 @@
+//! The changelog shown on the "What's new" screen.
+//!
+//! It's compiled into the binary, so that it always matches the version being played.
+
+use log::error;
+use serde::Deserialize;
+
+const CHANGELOG: &str = include_str!("../resources/changelog.ron");
+
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    pub version: String,
+    pub changes: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Changelog {
+    /// Releases, newest first.
+    pub releases: Vec<Release>,
+}
+
+impl Changelog {
+    /// Load the embedded changelog.
+    pub fn embedded() -> Changelog {
+        match ron::de::from_str(CHANGELOG) {
+            Ok(changelog) => changelog,
+            Err(e) => {
+                error!("failed to parse changelog: {}", e);
+                Changelog::default()
+            }
+        }
+    }
+
+    /// Lines of text to show for the changelog.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for release in &self.releases {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+
+            lines.push(format!("Version {}", release.version));
+
+            for change in &release.changes {
+                lines.push(format!("- {}", change));
+            }
+        }
+
+        lines
+    }
+}
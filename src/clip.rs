@@ -0,0 +1,193 @@
+//! Clips of the last seconds of a run, enabled with the `clips` feature.
+//!
+//! While the game runs, the arena is sketched a few times per second and sent to a worker thread,
+//! which draws it downscaled into a ring buffer of frames. On game over the player can save the
+//! buffer as an animated GIF, to share how they died.
+//!
+//! Like the god view, frames are drawn in software rather than read back from the renderer, so
+//! clips show the outlines of things rather than their sprites.
+
+use amethyst::utils::application_root_dir;
+use crate::sketch::{Canvas, Frame};
+use gif::SetParameter;
+use log::{info, warn};
+
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, SyncSender, TrySendError},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+enum Command {
+    /// Add a frame to the end of the clip.
+    Frame(Frame),
+    /// Save the clip to the given path.
+    Save(PathBuf),
+    /// Forget every frame, when a new run starts.
+    Clear,
+}
+
+/// Handle to the worker recording clips.
+pub struct ClipRecorder {
+    /// Commands sent to the worker, or `None` if it has stopped.
+    commands: Option<SyncSender<Command>>,
+}
+
+impl ClipRecorder {
+    /// Frames captured each second.
+    pub const FRAME_RATE: f32 = 10.0;
+    /// Length of clips (s).
+    const LENGTH: f32 = 10.0;
+    /// Width and height of clips, in pixels.
+    const SIZE: usize = 200;
+    /// Colors of the background and arena edges, as `0RGB`.
+    const BACKGROUND: u32 = 0x00_00_00_00;
+    const ARENA: u32 = 0x00_40_40_40;
+
+    /// Start the worker.
+    pub fn start() -> Self {
+        // NB: saving can take a moment, so leave room for a few frames meanwhile.
+        let (tx, rx) = mpsc::sync_channel(4);
+
+        let result = thread::Builder::new()
+            .name("clips".to_string())
+            .spawn(move || Self::run(rx));
+
+        if let Err(e) = result {
+            warn!("failed to start clip recorder: {}", e);
+            return Self { commands: None };
+        }
+
+        Self { commands: Some(tx) }
+    }
+
+    /// Add a frame to the clip, unless the worker is busy.
+    pub fn capture(&mut self, frame: Frame) {
+        self.send(Command::Frame(frame));
+    }
+
+    /// Save the clip recorded so far.
+    pub fn save(&mut self) {
+        match Self::path() {
+            Ok(path) => self.send(Command::Save(path)),
+            Err(e) => warn!("failed to locate clip: {}", e),
+        }
+    }
+
+    /// Forget the clip recorded so far.
+    pub fn clear(&mut self) {
+        self.send(Command::Clear);
+    }
+
+    fn send(&mut self, command: Command) {
+        let result = match self.commands.as_ref() {
+            Some(commands) => commands.try_send(command),
+            None => return,
+        };
+
+        match result {
+            Err(TrySendError::Full(Command::Save(_))) => warn!("clip recorder is busy"),
+            Err(TrySendError::Disconnected(_)) => self.commands = None,
+            _ => {}
+        }
+    }
+
+    /// Path to save a new clip to.
+    fn path() -> io::Result<PathBuf> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        Ok(application_root_dir()?.join(format!("clip-{}.gif", now)))
+    }
+
+    /// Run the worker until the game stops sending commands.
+    fn run(commands: Receiver<Command>) {
+        let capacity = (Self::LENGTH * Self::FRAME_RATE) as usize;
+        let mut frames = VecDeque::with_capacity(capacity);
+        let mut canvas = Canvas::new(Self::SIZE);
+
+        for command in commands {
+            match command {
+                Command::Frame(frame) => {
+                    Self::draw(&mut canvas, &frame);
+
+                    if frames.len() >= capacity {
+                        frames.pop_front();
+                    }
+
+                    frames.push_back(Self::rgb(&canvas));
+                }
+                Command::Save(path) => match Self::write(&frames, &path) {
+                    Ok(()) => info!("saved clip: {}", path.display()),
+                    Err(e) => warn!("failed to save clip: {}: {}", path.display(), e),
+                },
+                Command::Clear => frames.clear(),
+            }
+        }
+    }
+
+    fn draw(canvas: &mut Canvas, frame: &Frame) {
+        let (width, height) = (frame.arena[0], frame.arena[1]);
+
+        let size = canvas.size as f32;
+        let scale = size / width.max(height);
+
+        let project = |p: [f32; 2]| {
+            (
+                (p[0] * scale) as i32,
+                // y grows downwards in images.
+                (size - p[1] * scale) as i32,
+            )
+        };
+
+        canvas.clear(Self::BACKGROUND);
+
+        let corners = [[0.0, 0.0], [width, 0.0], [width, height], [0.0, height]];
+        canvas.polygon(corners.iter().map(|c| project(*c)), Self::ARENA);
+
+        for object in &frame.objects {
+            canvas.outline(&object.shape, &project, object.color);
+        }
+    }
+
+    /// Unpack the pixels of the canvas into RGB triples.
+    fn rgb(canvas: &Canvas) -> Vec<u8> {
+        let mut out = Vec::with_capacity(canvas.pixels.len() * 3);
+
+        for p in &canvas.pixels {
+            out.push((p >> 16) as u8);
+            out.push((p >> 8) as u8);
+            out.push(*p as u8);
+        }
+
+        out
+    }
+
+    fn write(frames: &VecDeque<Vec<u8>>, path: &Path) -> io::Result<()> {
+        if frames.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::Other, "nothing recorded"));
+        }
+
+        let size = Self::SIZE as u16;
+        let out = BufWriter::new(File::create(path)?);
+        let mut encoder = gif::Encoder::new(out, size, size, &[])?;
+        encoder.set(gif::Repeat::Infinite)?;
+
+        // NB: delays are in hundredths of a second.
+        let delay = (100.0 / Self::FRAME_RATE) as u16;
+
+        for pixels in frames {
+            let mut frame = gif::Frame::from_rgb(size, size, pixels);
+            frame.delay = delay;
+            encoder.write_frame(&frame)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,15 +1,22 @@
 use amethyst::{
     core::{
-        nalgebra::{self, Isometry2, Vector2},
+        nalgebra::{self, Isometry2, Point2, Vector2},
         transform::Transform,
     },
     ecs::prelude::{Component, DenseVecStorage, Entity, NullStorage},
+    renderer::ScreenDimensions,
+    ui::{UiText, UiTransform},
 };
 use ncollide2d::{
     bounding_volume::{self, AABB},
-    broad_phase::BroadPhase,
-    shape::Ball,
+    query::{self, Proximity},
+    shape::{Ball, ConvexPolygon, Shape},
 };
+use smallvec::SmallVec;
+
+use crate::config::{EnergyConfig, GameConfig};
+
+use std::time::Instant;
 
 #[derive(Debug)]
 pub struct Physical {
@@ -19,6 +26,11 @@ pub struct Physical {
     pub max_velocity: f32,
     /// Current rotation (radians / s).
     pub rotation: f32,
+    /// Constant acceleration applied to the entity (units / s**2).
+    pub acceleration: Vector2<f32>,
+    /// Acceleration perpendicular to the current velocity, curving the path of the entity without
+    /// changing its speed (units / s**2).
+    pub lateral_acceleration: f32,
 }
 
 impl Physical {
@@ -27,6 +39,8 @@ impl Physical {
             velocity: Vector2::new(0f32, 0f32),
             max_velocity: 100f32,
             rotation: 0f32,
+            acceleration: Vector2::new(0f32, 0f32),
+            lateral_acceleration: 0f32,
         }
     }
 }
@@ -37,35 +51,66 @@ impl Component for Physical {
 
 #[derive(Debug, Clone)]
 pub struct Bounded {
-    /// Bounding volume of the entity.
+    /// Bounding volume of the entity, which contains its hull if it has one.
     pub shape: Ball<f32>,
+    /// Exact shape of the entity relative to its transform, for entities which aren't round.
+    pub hull: Option<ConvexPolygon<f32>>,
 }
 
 impl Bounded {
     pub fn new(shape: Ball<f32>) -> Self {
-        Self { shape }
+        Self { shape, hull: None }
     }
 
     pub fn from_local(size: f32) -> Self {
         Bounded::new(Ball::new(size))
     }
 
-    /// Apply this bounding volume to a broad phase.
-    pub fn apply_to_broad_phase<B>(
-        &self,
-        collider: Collider,
-        e: Entity,
-        local: &Transform,
-        broad_phase: &mut B,
-    ) -> AABB<f32>
-    where
-        B: BroadPhase<f32, AABB<f32>, (Collider, Entity)>,
-    {
+    /// Construct a bounding volume from a convex hull, given as points in counter-clockwise order.
+    ///
+    /// The bounding ball is the smallest one around the origin which contains all points.
+    pub fn from_hull(points: Vec<Point2<f32>>) -> Self {
+        let radius = points.iter().map(|p| p.coords.norm()).fold(0f32, f32::max);
+
+        Self {
+            shape: Ball::new(radius),
+            hull: ConvexPolygon::try_new(points),
+        }
+    }
+
+    /// Position and rotation of an entity with the given transform.
+    pub fn isometry(local: &Transform) -> Isometry2<f32> {
+        let t = local.translation();
+        let (_, _, angle) = local.rotation().euler_angles();
+        Isometry2::new(Vector2::new(t.x, t.y), angle)
+    }
+
+    /// Test if the exact shapes of this and another bounding volume intersect.
+    pub fn intersects(&self, local: &Transform, other: &Bounded, other_local: &Transform) -> bool {
+        let proximity = query::proximity(
+            &Self::isometry(local),
+            self.exact_shape(),
+            &Self::isometry(other_local),
+            other.exact_shape(),
+            0f32,
+        );
+
+        proximity == Proximity::Intersecting
+    }
+
+    /// The hull if there is one, otherwise the bounding ball.
+    fn exact_shape(&self) -> &dyn Shape<f32> {
+        match self.hull {
+            Some(ref hull) => hull,
+            None => &self.shape,
+        }
+    }
+
+    /// Axis-aligned bounding box of this volume, as positioned by the given transform.
+    pub fn bounding_box(&self, local: &Transform) -> AABB<f32> {
         let t = local.translation();
         let pos = Isometry2::new(Vector2::new(t.x, t.y), nalgebra::zero());
-        let vol = bounding_volume::aabb(&self.shape, &pos);
-        let _ = broad_phase.create_proxy(vol.clone(), (collider, e));
-        vol
+        bounding_volume::aabb(&self.shape, &pos)
     }
 }
 
@@ -86,17 +131,110 @@ pub struct Ship {
     pub bullet_velocity: f32,
     /// Amount of jitter from original shooting position.
     pub bullet_jitter: f32,
+    /// How long bullets fired by the ship live (s).
+    pub bullet_time_to_live: f32,
+    /// Number of extra pairs of bullets fired to the sides of each shot.
+    pub spread_pairs: u32,
+    /// Factor applied to the energy cost of raising shields.
+    pub shield_drain: f32,
+    /// How long the ram power-up remains active (s).
+    pub ram_timer: f32,
+    /// How long the shield power-up remains active (s).
+    pub shield_timer: f32,
+    /// If shields are currently raised using energy.
+    pub shield_raised: bool,
+    /// How long the rapid-fire power-up remains active (s).
+    pub rapid_fire_timer: f32,
+    /// How long the spread shot power-up remains active (s).
+    pub spread_shot_timer: f32,
+    /// Time until the next exhaust particle while accelerating (s).
+    pub exhaust_timer: f32,
+    /// Time until the ship can jump through hyperspace again (s).
+    pub hyperspace_timer: f32,
+    /// Time it takes before the ship can jump through hyperspace again (s).
+    pub hyperspace_cooldown: f32,
 }
 
-impl Default for Ship {
-    fn default() -> Ship {
+impl Ship {
+    /// How long the ram power-up lasts (s).
+    pub const RAM_TIME: f32 = 8.0;
+    /// Factor by which acceleration and max velocity is boosted while ramming.
+    pub const RAM_SPEED_BOOST: f32 = 1.5;
+    /// Factor by which acceleration and max velocity is boosted by the afterburner.
+    pub const AFTERBURNER_BOOST: f32 = 1.8;
+    /// How long the shield power-up lasts (s).
+    pub const SHIELD_TIME: f32 = 6.0;
+    /// How long the rapid-fire power-up lasts (s).
+    pub const RAPID_FIRE_TIME: f32 = 8.0;
+    /// How long the spread shot power-up lasts (s).
+    pub const SPREAD_SHOT_TIME: f32 = 8.0;
+    /// Angle between the bullets of a spread shot (radians).
+    pub const SPREAD_ANGLE: f32 = 0.2;
+    /// Interval between exhaust particles while accelerating (s).
+    pub const EXHAUST_INTERVAL: f32 = 0.03;
+    /// Approximate radius of the ship, for effects which don't need its exact hull.
+    pub const RADIUS: f32 = 6.0;
+
+    /// Test if the ship is currently ramming.
+    pub fn is_ramming(&self) -> bool {
+        self.ram_timer > 0f32
+    }
+
+    /// Test if the ship is currently shielded, making it immune to collisions.
+    pub fn is_shielded(&self) -> bool {
+        self.shield_timer > 0f32 || self.shield_raised
+    }
+
+    /// Test if the ship currently fires spread shots.
+    pub fn has_spread_shot(&self) -> bool {
+        self.spread_shot_timer > 0f32
+    }
+
+    /// Number of pairs of bullets fired to the sides of each shot, one of which comes from the
+    /// spread shot power-up.
+    pub fn side_pairs(&self) -> u32 {
+        self.spread_pairs + if self.has_spread_shot() { 1 } else { 0 }
+    }
+
+    /// How long it currently takes to reload, which is halved by rapid-fire.
+    pub fn reload_time(&self) -> f32 {
+        if self.rapid_fire_timer > 0f32 {
+            self.time_to_reload / 2.0
+        } else {
+            self.time_to_reload
+        }
+    }
+
+    /// Count down the timers of all active power-ups.
+    pub fn tick_power_ups(&mut self, time_delta: f32) {
+        let tick = |timer: &mut f32| *timer = f32::max(*timer - time_delta, 0f32);
+        tick(&mut self.ram_timer);
+        tick(&mut self.shield_timer);
+        tick(&mut self.rapid_fire_timer);
+        tick(&mut self.spread_shot_timer);
+    }
+}
+
+impl Ship {
+    pub fn new(config: &GameConfig) -> Ship {
         Ship {
-            acceleration: 80f32,
-            rotation: 180f32,
+            acceleration: config.ship.acceleration,
+            rotation: config.ship.rotation,
             reload_timer: 0f32,
-            time_to_reload: 0.1f32,
-            bullet_velocity: 150f32,
-            bullet_jitter: 2.0f32,
+            time_to_reload: config.ship.time_to_reload,
+            bullet_velocity: config.ship.bullet_velocity,
+            bullet_jitter: config.ship.bullet_jitter,
+            bullet_time_to_live: config.bullet_time_to_live,
+            spread_pairs: 0,
+            shield_drain: 1f32,
+            ram_timer: 0f32,
+            shield_timer: 0f32,
+            shield_raised: false,
+            rapid_fire_timer: 0f32,
+            spread_shot_timer: 0f32,
+            exhaust_timer: 0f32,
+            hyperspace_timer: 0f32,
+            hyperspace_cooldown: config.ship.hyperspace_cooldown,
         }
     }
 }
@@ -105,14 +243,130 @@ impl Component for Ship {
     type Storage = DenseVecStorage<Self>;
 }
 
+/// The energy pool of a ship, spent by shooting, the afterburner, shields, and hyperspace.
+///
+/// Energy regenerates over time, but not until a while after it was last spent.
+#[derive(Debug, Clone)]
+pub struct Energy {
+    /// Energy currently available.
+    pub current: f32,
+    /// Maximum amount of energy.
+    pub capacity: f32,
+    /// Time until energy starts to regenerate (s).
+    pub regeneration_timer: f32,
+}
+
+impl Energy {
+    pub fn new(config: &EnergyConfig) -> Self {
+        Self {
+            current: config.capacity,
+            capacity: config.capacity,
+            regeneration_timer: 0f32,
+        }
+    }
+
+    /// Spend the given amount of energy at once.
+    ///
+    /// Returns `false`, without spending anything, if there isn't enough energy.
+    pub fn spend(&mut self, amount: f32, config: &EnergyConfig) -> bool {
+        if self.current < amount {
+            return false;
+        }
+
+        self.current -= amount;
+        self.regeneration_timer = config.regeneration_delay;
+        true
+    }
+
+    /// Spend energy at the given rate for the duration of a frame.
+    ///
+    /// Returns `false` if the energy ran out, in which case whatever was left is spent.
+    pub fn drain(&mut self, rate: f32, time_delta: f32, config: &EnergyConfig) -> bool {
+        if self.current <= 0f32 {
+            return false;
+        }
+
+        let amount = rate * time_delta;
+        self.regeneration_timer = config.regeneration_delay;
+
+        if self.current < amount {
+            self.current = 0f32;
+            return false;
+        }
+
+        self.current -= amount;
+        true
+    }
+
+    /// Regenerate energy over the duration of a frame.
+    pub fn regenerate(&mut self, time_delta: f32, config: &EnergyConfig) {
+        if self.regeneration_timer > 0f32 {
+            self.regeneration_timer = f32::max(self.regeneration_timer - time_delta, 0f32);
+            return;
+        }
+
+        self.current = f32::min(self.current + config.regeneration * time_delta, self.capacity);
+    }
+
+    /// Fraction of the capacity which is available, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        if self.capacity <= 0f32 {
+            return 0f32;
+        }
+
+        (self.current / self.capacity).max(0.0).min(1.0)
+    }
+}
+
+impl Component for Energy {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Hit points of something which survives being hit a few times.
+///
+/// Things without health are destroyed by the first hit.
+#[derive(Debug, Clone, Copy)]
+pub struct Health {
+    /// Hit points left.
+    pub current: u32,
+    /// Hit points when undamaged.
+    pub max: u32,
+}
+
+impl Health {
+    pub fn new(max: u32) -> Self {
+        Self { current: max, max }
+    }
+
+    /// Take a single hit.
+    ///
+    /// Returns `true` if there are hit points left to survive it.
+    pub fn hit(&mut self) -> bool {
+        self.current = self.current.saturating_sub(1);
+        self.current > 0
+    }
+}
+
+impl Component for Health {
+    type Storage = DenseVecStorage<Self>;
+}
+
 pub struct Bullet {
     /// How many seconds this bullet should live.
     pub time_to_live: f32,
+    /// How many more times this bullet can wrap around the arena before it expires.
+    pub wraps: u32,
 }
 
 impl Bullet {
-    pub fn new() -> Bullet {
-        Bullet { time_to_live: 2f32 }
+    /// How many times a bullet can wrap around the arena.
+    pub const WRAPS: u32 = 1;
+
+    pub fn new(time_to_live: f32) -> Bullet {
+        Bullet {
+            time_to_live,
+            wraps: Self::WRAPS,
+        }
     }
 }
 
@@ -120,6 +374,612 @@ impl Component for Bullet {
     type Storage = DenseVecStorage<Self>;
 }
 
+/// A floating score popup, shown where an asteroid was destroyed.
+#[derive(Debug)]
+pub struct ScorePopup {
+    /// How many seconds this popup should live.
+    pub time_to_live: f32,
+}
+
+impl ScorePopup {
+    /// How long score popups live (s).
+    pub const TIME_TO_LIVE: f32 = 0.8;
+    /// How fast score popups float upwards (pixels / s).
+    pub const SPEED: f32 = 40.0;
+
+    pub fn new() -> ScorePopup {
+        ScorePopup {
+            time_to_live: Self::TIME_TO_LIVE,
+        }
+    }
+}
+
+impl Component for ScorePopup {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// A comet, which curves through the arena leaving a tail of particles.
+#[derive(Debug, Default)]
+pub struct Comet {
+    /// Time until the next tail particle is emitted.
+    pub tail_timer: f32,
+}
+
+impl Component for Comet {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// A short-lived visual particle.
+#[derive(Debug)]
+pub struct Particle {
+    /// Velocity of the particle (units / s).
+    pub velocity: Vector2<f32>,
+    /// How many seconds this particle should live.
+    pub time_to_live: f32,
+    /// How many seconds this particle lives in total.
+    pub lifetime: f32,
+    /// Scale of the particle when spawned, shrinking as it fades.
+    pub scale: f32,
+}
+
+impl Particle {
+    pub fn new(velocity: Vector2<f32>, lifetime: f32, scale: f32) -> Particle {
+        Particle {
+            velocity,
+            time_to_live: lifetime,
+            lifetime,
+            scale,
+        }
+    }
+}
+
+impl Component for Particle {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// A pebble left behind by a destroyed asteroid.
+#[derive(Debug)]
+pub struct Pebble {
+    /// How many seconds this pebble should live.
+    pub time_to_live: f32,
+}
+
+impl Pebble {
+    pub fn new(time_to_live: f32) -> Pebble {
+        Pebble { time_to_live }
+    }
+}
+
+impl Component for Pebble {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// An asteroid spawned as a boss, which leaves its hull behind when destroyed.
+#[derive(Debug, Default)]
+pub struct Boss;
+
+impl Component for Boss {
+    type Storage = NullStorage<Self>;
+}
+
+/// A chunk of the hull of a destroyed boss, which drifts around until the next boss arrives.
+#[derive(Debug, Default)]
+pub struct Hull;
+
+impl Component for Hull {
+    type Storage = NullStorage<Self>;
+}
+
+/// Makes a freshly respawned ship invulnerable for a while, blinking until it wears off.
+#[derive(Debug)]
+pub struct Invulnerable {
+    /// How many seconds the ship stays invulnerable.
+    pub time_to_live: f32,
+    /// Time until the ship is shown or hidden again (s).
+    pub blink_timer: f32,
+}
+
+impl Invulnerable {
+    /// How long a respawned ship is invulnerable (s).
+    pub const RESPAWN: f32 = 2.5;
+    /// How long a ship is invulnerable after surviving a hit (s).
+    pub const HIT: f32 = 1.0;
+    /// Interval at which an invulnerable ship blinks (s).
+    pub const BLINK_INTERVAL: f32 = 0.1;
+
+    pub fn new(time_to_live: f32) -> Self {
+        Self {
+            time_to_live,
+            blink_timer: Self::BLINK_INTERVAL,
+        }
+    }
+}
+
+impl Component for Invulnerable {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// A flying saucer, which crosses the arena while shooting at the ship.
+#[derive(Debug)]
+pub struct Ufo {
+    /// Time until the next shot (s).
+    pub fire_timer: f32,
+    /// Time until the next change of course (s).
+    pub course_timer: f32,
+    /// How many seconds until the UFO leaves the arena.
+    pub time_to_live: f32,
+}
+
+impl Ufo {
+    pub fn new(time_to_live: f32) -> Ufo {
+        Ufo {
+            fire_timer: 0f32,
+            course_timer: 0f32,
+            time_to_live,
+        }
+    }
+}
+
+impl Component for Ufo {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// A planet, pulling everything which moves towards it.
+#[derive(Debug)]
+pub struct Planet {
+    /// Strength of the gravity well, where the acceleration towards the planet is this divided by
+    /// the squared distance to it (units**3 / s**2).
+    pub gravity: f32,
+}
+
+impl Component for Planet {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// A shockwave expanding from where the ship exploded, pushing nearby asteroids away.
+#[derive(Debug)]
+pub struct Shockwave {
+    /// How many seconds the ring should be visible.
+    pub time_to_live: f32,
+    /// If the shockwave has pushed asteroids yet.
+    pub pushed: bool,
+}
+
+impl Shockwave {
+    /// Radius within which asteroids are pushed.
+    pub const RADIUS: f32 = 40.0;
+    /// Velocity added to an asteroid right at the center of the shockwave (units / s).
+    pub const IMPULSE: f32 = 80.0;
+    /// How long the ring animation lasts (s).
+    pub const LIFETIME: f32 = 0.4;
+
+    pub fn new() -> Shockwave {
+        Shockwave {
+            time_to_live: Self::LIFETIME,
+            pushed: false,
+        }
+    }
+}
+
+impl Component for Shockwave {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// An easing curve, mapping linear progress in `[0, 1]` to eased progress.
+#[derive(Debug, Clone, Copy)]
+pub enum Easing {
+    Linear,
+    /// Starts slow and speeds up.
+    QuadIn,
+    /// Starts fast and slows down.
+    QuadOut,
+    /// Starts and ends slow.
+    QuadInOut,
+}
+
+impl Easing {
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => t * (2.0 - t),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    -1.0 + (4.0 - 2.0 * t) * t
+                }
+            }
+        }
+    }
+}
+
+/// A property animated by a tween, and the values it's animated between.
+#[derive(Debug, Clone, Copy)]
+pub enum TweenTarget {
+    /// Uniform scale of the transform.
+    Scale(f32, f32),
+    /// Color of an UI text.
+    TextColor([f32; 4], [f32; 4]),
+    /// Alpha of the sprite tint.
+    Alpha(f32, f32),
+}
+
+/// Animates one or more properties of an entity over time.
+///
+/// The component is removed once the tween has completed.
+#[derive(Debug)]
+pub struct Tween {
+    /// Properties being animated.
+    pub targets: SmallVec<[TweenTarget; 2]>,
+    /// Easing curve of the animation.
+    pub easing: Easing,
+    /// How long the animation lasts (s).
+    pub duration: f32,
+    /// How long the animation has been running (s).
+    pub elapsed: f32,
+}
+
+impl Tween {
+    pub fn new(duration: f32, easing: Easing) -> Tween {
+        Tween {
+            targets: SmallVec::new(),
+            easing,
+            duration,
+            elapsed: 0f32,
+        }
+    }
+
+    /// Animate the given target.
+    pub fn with(mut self, target: TweenTarget) -> Tween {
+        self.targets.push(target);
+        self
+    }
+
+    /// Eased progress of the tween, in `[0, 1]`.
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0f32 {
+            return 1f32;
+        }
+
+        self.easing.apply(f32::min(self.elapsed / self.duration, 1f32))
+    }
+
+    /// If the tween has completed.
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+impl Component for Tween {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Positions an UI element relative to the size of the window.
+///
+/// Offsets from the anchor, sizes and font sizes are given in pixels for a window of the
+/// reference size, and scaled along with the window.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    /// Horizontal offset from the anchor.
+    pub x: f32,
+    /// Vertical offset from the anchor.
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Font size of the text of the element, if it has any.
+    pub font_size: Option<f32>,
+    /// If the layout has been applied to the element.
+    pub applied: bool,
+}
+
+impl Layout {
+    /// Size of the window the layout is designed for.
+    pub const REFERENCE_SIZE: f32 = 1000.0;
+
+    /// Lay out an element as it's positioned by the given transform in a window of the reference
+    /// size.
+    pub fn of(transform: &UiTransform, font_size: Option<f32>) -> Self {
+        Self {
+            x: transform.local_x,
+            y: transform.local_y,
+            width: transform.width,
+            height: transform.height,
+            font_size,
+            applied: false,
+        }
+    }
+
+    /// How much to scale layouts by for the given window, and the UI scale picked in settings.
+    ///
+    /// Scales by the shortest side of the window, so that elements fit both in wide and tall
+    /// windows.
+    pub fn scale(screen: &ScreenDimensions, ui_scale: f32) -> f32 {
+        f32::min(screen.width(), screen.height()) / Self::REFERENCE_SIZE * ui_scale
+    }
+
+    /// Apply the layout to an element, using the given scale.
+    pub fn apply(&mut self, scale: f32, transform: &mut UiTransform, text: Option<&mut UiText>) {
+        transform.local_x = self.x * scale;
+        transform.local_y = self.y * scale;
+        transform.width = self.width * scale;
+        transform.height = self.height * scale;
+
+        if let (Some(font_size), Some(text)) = (self.font_size, text) {
+            text.font_size = font_size * scale;
+        }
+
+        self.applied = true;
+    }
+}
+
+impl Component for Layout {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// A copy of the sprite of an object crossing an edge of the arena, shown on the opposite side.
+#[derive(Debug, Default)]
+pub struct Ghost;
+
+impl Component for Ghost {
+    type Storage = NullStorage<Self>;
+}
+
+/// A harmless copy of the sprite of an asteroid, which wobbles around it.
+#[derive(Debug, Default)]
+pub struct Phantom;
+
+impl Component for Phantom {
+    type Storage = NullStorage<Self>;
+}
+
+/// A single dot in the trajectory line drawn by the aim assist.
+#[derive(Debug, Default)]
+pub struct AimDot;
+
+impl Component for AimDot {
+    type Storage = NullStorage<Self>;
+}
+
+/// The kind of a power-up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerUpKind {
+    /// The ship destroys small and medium asteroids on contact instead of dying.
+    Ram,
+    /// The ship passes through everything unharmed.
+    Shield,
+    /// The ship reloads twice as fast.
+    RapidFire,
+    /// The ship fires three bullets at a time, spread out in a fan.
+    SpreadShot,
+}
+
+impl PowerUpKind {
+    /// All kinds of power-ups, which are dropped with equal chance.
+    pub const ALL: [PowerUpKind; 4] = [
+        PowerUpKind::Ram,
+        PowerUpKind::Shield,
+        PowerUpKind::RapidFire,
+        PowerUpKind::SpreadShot,
+    ];
+
+    /// Human readable name of the power-up.
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerUpKind::Ram => "ram",
+            PowerUpKind::Shield => "shield",
+            PowerUpKind::RapidFire => "rapid-fire",
+            PowerUpKind::SpreadShot => "spread shot",
+        }
+    }
+}
+
+/// A power-up which can be picked up by the ship.
+#[derive(Debug)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    /// How many seconds this power-up remains before disappearing.
+    pub time_to_live: f32,
+}
+
+impl PowerUp {
+    pub fn new(kind: PowerUpKind) -> PowerUp {
+        PowerUp {
+            kind,
+            time_to_live: 10f32,
+        }
+    }
+
+    /// Apply the power-up to the given ship.
+    pub fn apply(&self, ship: &mut Ship) {
+        match self.kind {
+            PowerUpKind::Ram => ship.ram_timer = Ship::RAM_TIME,
+            PowerUpKind::Shield => ship.shield_timer = Ship::SHIELD_TIME,
+            PowerUpKind::RapidFire => ship.rapid_fire_timer = Ship::RAPID_FIRE_TIME,
+            PowerUpKind::SpreadShot => ship.spread_shot_timer = Ship::SPREAD_SHOT_TIME,
+        }
+    }
+}
+
+impl Component for PowerUp {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// The visual shown in front of the parent ship while it is ramming.
+#[derive(Debug, Default)]
+pub struct RamVisual;
+
+impl Component for RamVisual {
+    type Storage = NullStorage<Self>;
+}
+
+/// The bubble shown around the parent ship while it is shielded.
+#[derive(Debug, Default)]
+pub struct ShieldVisual;
+
+impl ShieldVisual {
+    /// Scale of the bubble sprite, to fit around the ship.
+    pub const SCALE: f32 = 1.5;
+}
+
+impl Component for ShieldVisual {
+    type Storage = NullStorage<Self>;
+}
+
+/// A decal drawn on top of the parent ship, as picked by its player.
+#[derive(Debug, Default)]
+pub struct ShipDecal;
+
+impl Component for ShipDecal {
+    type Storage = NullStorage<Self>;
+}
+
+/// Arrow indicating the velocity of the parent ship.
+#[derive(Debug, Default)]
+pub struct VelocityArrow;
+
+impl VelocityArrow {
+    /// Width of the arrow sprite.
+    pub const SPRITE_WIDTH: f32 = 16.0;
+    /// Length of the arrow when the ship moves at max velocity.
+    pub const MAX_LENGTH: f32 = 24.0;
+    /// Distance from the center of the ship to the start of the arrow.
+    pub const OFFSET: f32 = 8.0;
+    /// Speeds below this don't show an arrow.
+    pub const MIN_SPEED: f32 = 1.0;
+}
+
+impl Component for VelocityArrow {
+    type Storage = NullStorage<Self>;
+}
+
+/// A grace period during which a freshly spawned asteroid or hull chunk can't kill the player.
+///
+/// It can still be shot.
+#[derive(Debug)]
+pub struct CollisionGrace {
+    /// How many seconds of grace are left.
+    pub time_to_live: f32,
+}
+
+impl CollisionGrace {
+    /// Grace period of freshly spawned splinters (s).
+    pub const SPLINTER: f32 = 0.3;
+    /// Grace period of hull chunks left behind by a boss (s).
+    pub const HULL: f32 = 1.0;
+}
+
+impl Component for CollisionGrace {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// An unstable asteroid, which pulses and splits on its own once its fuse runs out.
+#[derive(Debug)]
+pub struct Unstable {
+    /// How many seconds are left until the asteroid splits.
+    pub fuse: f32,
+    /// Scale of the asteroid between pulses.
+    pub scale: f32,
+    /// Time until the next pulse (s).
+    pub time_to_pulse: f32,
+}
+
+impl Unstable {
+    /// How much an asteroid grows with each pulse, relative to its scale.
+    pub const PULSE_SCALE: f32 = 0.15;
+    /// Time between pulses when the fuse is long (s).
+    pub const SLOW_PULSE: f32 = 1.0;
+    /// Time between pulses just before splitting (s).
+    pub const FAST_PULSE: f32 = 0.15;
+    /// Remaining fuse below which pulses start speeding up (s).
+    pub const URGENT: f32 = 4.0;
+    /// Tint of unstable asteroids, to tell them apart between pulses.
+    pub const TINT: [f32; 4] = [1.0, 0.55, 0.45, 1.0];
+
+    pub fn new(fuse: f32, scale: f32) -> Self {
+        Self {
+            fuse,
+            scale,
+            time_to_pulse: 0f32,
+        }
+    }
+
+    /// Time until the pulse after this one, which speeds up as the fuse runs out.
+    pub fn pulse_interval(&self) -> f32 {
+        let urgency = 1.0 - (self.fuse / Self::URGENT).max(0.0).min(1.0);
+        Self::SLOW_PULSE + (Self::FAST_PULSE - Self::SLOW_PULSE) * urgency
+    }
+}
+
+impl Component for Unstable {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// A magnetized asteroid, which curves toward the nearest ship and crackles while doing so.
+#[derive(Debug, Default)]
+pub struct Magnetized {
+    /// Time until the next crackle (s).
+    pub time_to_crackle: f32,
+}
+
+impl Magnetized {
+    /// Average time between crackles (s).
+    pub const CRACKLE_INTERVAL: f32 = 0.2;
+    /// Tint of magnetized asteroids.
+    pub const TINT: [f32; 4] = [0.55, 0.75, 1.0, 1.0];
+}
+
+impl Component for Magnetized {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// The station of objective mode, see `StationPolicy`.
+#[derive(Debug, Default)]
+pub struct Station {
+    /// How long the ship of each player has been in the docking zone, indexed by player (s).
+    pub docking: Vec<f32>,
+}
+
+impl Station {
+    /// Tint of the station, to tell it apart from planets.
+    pub const TINT: [f32; 4] = [0.6, 0.8, 1.0, 1.0];
+}
+
+impl Component for Station {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// The player who owns an entity, like a ship or the bullets it fired.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Owner {
+    pub player: usize,
+}
+
+impl Component for Owner {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Marks an entity hidden because it's outside of the camera view.
+///
+/// Used to tell entities hidden by culling apart from ones hidden for other reasons.
+#[derive(Debug, Default)]
+pub struct Culled;
+
+impl Component for Culled {
+    type Storage = NullStorage<Self>;
+}
+
+/// Marks a bullet whose shoot press is being measured by the `LatencyProbe`.
+#[derive(Debug)]
+pub struct LatencyMarker {
+    /// When shoot was pressed.
+    pub pressed_at: Instant,
+}
+
+impl Component for LatencyMarker {
+    type Storage = DenseVecStorage<Self>;
+}
+
 #[derive(Debug, Default)]
 pub struct ConstrainedObject;
 
@@ -132,6 +992,7 @@ impl Component for ConstrainedObject {
 pub enum DeferredCollider {
     Bullet,
     Asteroid,
+    Hull,
 }
 
 impl DeferredCollider {
@@ -141,6 +1002,7 @@ impl DeferredCollider {
         match self {
             Bullet => Collider::Bullet,
             Asteroid => Collider::Asteroid,
+            Hull => Collider::Hull,
         }
     }
 }
@@ -150,6 +1012,20 @@ pub enum Collider {
     Bullet,
     Ship,
     Asteroid,
+    PowerUp,
+    /// Destroys everything which falls into it.
+    Planet,
+    /// The station of objective mode, which is damaged by asteroids and docked at by ships.
+    Station,
+    /// A flying saucer, shot down by the player's bullets.
+    Ufo,
+    /// Bullets fired by UFOs, which damage the ship but not other UFOs.
+    UfoBullet,
+    /// Debris left behind by asteroids, which deflects bullets but is otherwise harmless.
+    Pebble,
+    /// A chunk of the hull of a destroyed boss, which blocks bullets and ships until it has been
+    /// chipped away.
+    Hull,
     /// Certain things start spawned while intersecting with other things.
     ///
     /// To avoid causing additional collisions, this defers adding a collider until it is no longer
@@ -157,6 +1033,28 @@ pub enum Collider {
     Deferred(DeferredCollider),
 }
 
+impl Collider {
+    /// Test if colliding with this destroys the ship.
+    pub fn is_lethal(&self) -> bool {
+        match *self {
+            Collider::Asteroid
+            | Collider::Planet
+            | Collider::Ufo
+            | Collider::UfoBullet
+            | Collider::Hull => true,
+            _ => false,
+        }
+    }
+
+    /// Test if this is an asteroid, including asteroids which can't collide yet.
+    pub fn is_asteroid(&self) -> bool {
+        match *self {
+            Collider::Asteroid | Collider::Deferred(DeferredCollider::Asteroid) => true,
+            _ => false,
+        }
+    }
+}
+
 impl Component for Collider {
     type Storage = DenseVecStorage<Self>;
 }
@@ -0,0 +1,653 @@
+use amethyst::core::nalgebra::{Vector2, Vector3};
+use crate::{
+    resources::{Asteroids, RandomGen},
+    ARENA_HEIGHT, ARENA_WIDTH,
+};
+use serde::{Deserialize, Serialize};
+
+use std::f32::consts;
+
+/// How many splinters an asteroid breaks into.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum SplinterCount {
+    /// One splinter for each minimum sized asteroid that fits in the area of the destroyed
+    /// asteroid, keeping one in reserve.
+    Area,
+    /// A fixed number of splinters, for asteroids large enough to splinter.
+    Fixed(usize),
+}
+
+/// How splinters are spread out around the destroyed asteroid.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum SplinterSpread {
+    /// Each splinter is rotated a random amount from the previous one.
+    ///
+    /// This can cluster splinters in one direction.
+    Cumulative,
+    /// Splinters are evenly distributed, each offset by a random angle of up to `jitter` radians.
+    Even { jitter: f32 },
+}
+
+/// Policy for splintering asteroids.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SplinterPolicy {
+    pub count: SplinterCount,
+    pub spread: SplinterSpread,
+    /// Maximum velocity of splinters (units / s).
+    pub max_velocity: f32,
+}
+
+impl SplinterPolicy {
+    /// Number of splinters to spawn for an asteroid with the given area.
+    pub fn count(&self, mut area: f32) -> usize {
+        let min_area = Asteroids::MIN_RADIUS.powf(2.0) * consts::PI;
+
+        if area <= min_area * 2.0 {
+            return 0;
+        }
+
+        match self.count {
+            SplinterCount::Area => {
+                let mut count = 0;
+
+                while area > min_area * 2.0 {
+                    area -= min_area;
+                    count += 1;
+                }
+
+                count
+            }
+            SplinterCount::Fixed(count) => count,
+        }
+    }
+
+    /// Angles (in radians) to send each splinter off in.
+    pub fn angles<'a>(
+        &self,
+        count: usize,
+        rand: &'a RandomGen,
+    ) -> impl Iterator<Item = f32> + 'a {
+        let spread = self.spread;
+        let step = 2.0 * consts::PI / count as f32;
+        let offset = rand.next_f32() * 2.0 * consts::PI;
+        let mut angle = 0.0f32;
+
+        (0..count).map(move |i| match spread {
+            SplinterSpread::Cumulative => {
+                angle += rand.next_f32() * consts::PI;
+                angle
+            }
+            SplinterSpread::Even { jitter } => {
+                offset + step * i as f32 + (rand.next_f32() - 0.5) * 2.0 * jitter
+            }
+        })
+    }
+}
+
+impl Default for SplinterPolicy {
+    fn default() -> Self {
+        Self {
+            count: SplinterCount::Area,
+            spread: SplinterSpread::Even { jitter: 0.3 },
+            max_velocity: 100f32,
+        }
+    }
+}
+
+/// How the spin of spawned asteroids relates to their motion.
+///
+/// Asteroids roll off the edge they spawned from, like a wheel: the faster they move, the faster
+/// they spin.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpinPolicy {
+    /// Spin for each unit of speed (radians / unit).
+    pub per_velocity: f32,
+    /// Maximum spin (radians / s).
+    pub max: f32,
+    /// Random variation of the spin, as a fraction of it.
+    pub jitter: f32,
+}
+
+impl SpinPolicy {
+    /// Spin for an asteroid moving at `velocity`, spawned on an edge whose `normal` points into
+    /// the arena.
+    pub fn spin(&self, normal: Vector2<f32>, velocity: Vector2<f32>, rand: &RandomGen) -> f32 {
+        // rolling along the edge in the direction of travel.
+        let handedness = if normal.x * velocity.y - normal.y * velocity.x < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+
+        let jitter = 1.0 + (rand.next_f32() - 0.5) * 2.0 * self.jitter;
+        let spin = velocity.magnitude() * self.per_velocity * jitter;
+        handedness * spin.max(0.0).min(self.max)
+    }
+}
+
+impl Default for SpinPolicy {
+    fn default() -> Self {
+        Self {
+            per_velocity: 0.08,
+            max: 15.0,
+            jitter: 0.25,
+        }
+    }
+}
+
+/// Policy for small asteroids which merge into larger ones when they overlap for long enough.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccretionPolicy {
+    /// How long small asteroids have to overlap before merging (s).
+    pub time: f32,
+}
+
+impl Default for AccretionPolicy {
+    fn default() -> Self {
+        Self { time: 2.0 }
+    }
+}
+
+/// Policy for phantom twins of asteroids, which wobble around them without colliding with
+/// anything.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DoubleVisionPolicy {
+    /// Distance between an asteroid and its phantom (units).
+    pub distance: f32,
+    /// How fast phantoms wobble around their asteroids (revolutions / s).
+    pub wobble: f32,
+}
+
+impl Default for DoubleVisionPolicy {
+    fn default() -> Self {
+        Self {
+            distance: 8.0,
+            wobble: 0.3,
+        }
+    }
+}
+
+/// Policy for the walls closing in, where two opposing edges of the arena every now and then
+/// become solid and slowly move inward, before retracting again.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SqueezePolicy {
+    /// Time between the walls retracting and closing in again (s).
+    pub interval: f32,
+    /// How long the walls close in for (s).
+    pub duration: f32,
+    /// How long the walls take to retract (s).
+    pub retract: f32,
+    /// How far each wall moves in, as a fraction of the size of the arena.
+    pub depth: f32,
+    /// Fraction of velocity retained by a bounce off the walls.
+    pub restitution: f32,
+}
+
+impl Default for SqueezePolicy {
+    fn default() -> Self {
+        Self {
+            interval: 45.0,
+            duration: 10.0,
+            retract: 3.0,
+            depth: 0.25,
+            restitution: 0.8,
+        }
+    }
+}
+
+/// Policy for unstable asteroids, which pulse and split on their own once their fuse runs out.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnstablePolicy {
+    /// Chance that a spawned asteroid is unstable, from 0 to 1.
+    pub chance: f32,
+    /// Shortest time before an unstable asteroid splits (s).
+    pub min_fuse: f32,
+    /// Longest time before an unstable asteroid splits (s).
+    pub max_fuse: f32,
+}
+
+impl UnstablePolicy {
+    /// Roll the fuse of a spawned asteroid, or `None` if it's stable.
+    pub fn roll(&self, rand: &RandomGen) -> Option<f32> {
+        if rand.next_f32() >= self.chance {
+            return None;
+        }
+
+        let spread = (self.max_fuse - self.min_fuse).max(0.0);
+        Some(self.min_fuse.max(1.0) + rand.next_f32() * spread)
+    }
+}
+
+impl Default for UnstablePolicy {
+    fn default() -> Self {
+        Self {
+            chance: 0.15,
+            min_fuse: 6.0,
+            max_fuse: 12.0,
+        }
+    }
+}
+
+/// Policy for magnetized asteroids, which curve toward the nearest ship.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MagnetizedPolicy {
+    /// Chance that a spawned asteroid is magnetized, from 0 to 1.
+    pub chance: f32,
+    /// Fastest a magnetized asteroid turns toward a ship (degrees / s).
+    pub turn_rate: f32,
+    /// Distance within which magnetized asteroids are drawn toward ships (units).
+    pub range: f32,
+    /// How many times the regular points a magnetized asteroid is worth.
+    pub points_multiplier: u32,
+}
+
+impl MagnetizedPolicy {
+    /// Roll if a spawned asteroid is magnetized.
+    pub fn roll(&self, rand: &RandomGen) -> bool {
+        rand.next_f32() < self.chance
+    }
+}
+
+impl Default for MagnetizedPolicy {
+    fn default() -> Self {
+        Self {
+            chance: 0.05,
+            turn_rate: 20.0,
+            range: 120.0,
+            points_multiplier: 3,
+        }
+    }
+}
+
+/// Policy for objective mode, where a station in the arena has to be defended from asteroids.
+///
+/// Ships which slow down inside of the docking zone around the station are repaired and bank their
+/// points, while the points they scored since they last docked are lost along with them. The run is
+/// over once the station is destroyed.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StationPolicy {
+    /// Number of hits by asteroids the station survives, including the one that destroys it.
+    pub hit_points: u32,
+    /// Radius of the station (units).
+    pub radius: f32,
+    /// Radius of the docking zone around the station (units).
+    pub dock_radius: f32,
+    /// Fastest a ship can go and still dock (units / s).
+    pub dock_speed: f32,
+    /// How long a ship has to stay in the docking zone to dock (s).
+    pub dock_time: f32,
+}
+
+impl Default for StationPolicy {
+    fn default() -> Self {
+        Self {
+            hit_points: 10,
+            radius: 12.0,
+            dock_radius: 30.0,
+            dock_speed: 15.0,
+            dock_time: 1.5,
+        }
+    }
+}
+
+/// Handling of the ship.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShipConfig {
+    /// Acceleration of the ship when thrusting (units / s**2).
+    pub acceleration: f32,
+    /// Rotation of the ship when turning (degrees / s).
+    pub rotation: f32,
+    /// Maximum velocity of the ship (units / s).
+    pub max_velocity: f32,
+    /// Time it takes to reload after shooting (s).
+    pub time_to_reload: f32,
+    /// Velocity of the bullets fired by the ship (units / s).
+    pub bullet_velocity: f32,
+    /// Random variation of where bullets are fired from (units).
+    pub bullet_jitter: f32,
+    /// Number of hits the ship survives, including the one that destroys it.
+    pub hit_points: u32,
+    /// Time before the ship can jump through hyperspace again (s).
+    pub hyperspace_cooldown: f32,
+    /// Chance that the ship is destroyed when arriving from a hyperspace jump, from 0 to 1.
+    pub hyperspace_failure_chance: f32,
+}
+
+impl Default for ShipConfig {
+    fn default() -> Self {
+        Self {
+            acceleration: 80.0,
+            rotation: 180.0,
+            max_velocity: 100.0,
+            time_to_reload: 0.1,
+            bullet_velocity: 150.0,
+            bullet_jitter: 2.0,
+            hit_points: 3,
+            hyperspace_cooldown: 3.0,
+            hyperspace_failure_chance: 0.1,
+        }
+    }
+}
+
+/// The energy pool of the ship, which is spent by shooting, the afterburner, shields, and
+/// hyperspace.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnergyConfig {
+    /// Maximum amount of energy.
+    pub capacity: f32,
+    /// Energy regenerated (energy / s).
+    pub regeneration: f32,
+    /// Time after spending energy before it starts to regenerate (s).
+    pub regeneration_delay: f32,
+    /// Energy spent by each shot.
+    pub shot: f32,
+    /// Energy spent while the afterburner is on (energy / s).
+    pub afterburner: f32,
+    /// Energy spent while shields are raised (energy / s).
+    pub shield: f32,
+    /// Energy spent by a hyperspace jump.
+    pub hyperspace: f32,
+}
+
+impl Default for EnergyConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 100.0,
+            regeneration: 25.0,
+            regeneration_delay: 0.5,
+            shot: 3.0,
+            afterburner: 30.0,
+            shield: 40.0,
+            hyperspace: 60.0,
+        }
+    }
+}
+
+/// The spawner deciding how asteroids are spawned in endless mode.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SpawnerKind {
+    /// Asteroids and the odd comet by the top edge, at random intervals.
+    Random,
+    /// Bursts of asteroids sized like waves, every `interval` seconds.
+    Bursts { interval: f32 },
+    /// A script like the ones of levels, played over and over.
+    Scripted { script: Vec<String> },
+    /// Asteroids whenever they cover less than `target` of the arena.
+    Density { target: f32 },
+}
+
+impl Default for SpawnerKind {
+    fn default() -> Self {
+        SpawnerKind::Random
+    }
+}
+
+/// Policy for spawning asteroids in endless mode.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpawnPolicy {
+    /// Time before the first asteroid is spawned (s).
+    pub initial_delay: f32,
+    /// Average time between spawned asteroids, for the random spawner (s).
+    pub average_time: f32,
+    /// Maximum velocity along each axis of spawned asteroids, for the random spawner (units / s).
+    pub max_velocity: f32,
+    /// The spawner used.
+    pub spawner: SpawnerKind,
+}
+
+impl Default for SpawnPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: 2.0,
+            average_time: 0.5,
+            max_velocity: 100.0,
+            spawner: SpawnerKind::Random,
+        }
+    }
+}
+
+/// Policy for waves of asteroids, where each wave has to be cleared before the next one arrives.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WavePolicy {
+    /// Number of asteroids in the first wave.
+    pub initial_count: usize,
+    /// Number of asteroids added to each following wave.
+    pub count_increase: usize,
+    /// Maximum number of asteroids in a wave.
+    pub max_count: usize,
+    /// Maximum velocity along each axis of asteroids in the first wave (units / s).
+    pub initial_velocity: f32,
+    /// Velocity added to each following wave (units / s).
+    pub velocity_increase: f32,
+    /// Maximum velocity along each axis of asteroids in any wave (units / s).
+    pub max_velocity: f32,
+    /// Time before each wave arrives (s).
+    pub delay: f32,
+    /// A boss arrives with every this many waves, if at all.
+    pub boss_every: Option<u32>,
+}
+
+impl WavePolicy {
+    /// Number of asteroids in the given wave, starting at 1.
+    pub fn count(&self, wave: u32) -> usize {
+        let increase = self.count_increase * wave.saturating_sub(1) as usize;
+        (self.initial_count + increase).min(self.max_count)
+    }
+
+    /// Maximum velocity along each axis of asteroids in the given wave, starting at 1.
+    pub fn velocity(&self, wave: u32) -> f32 {
+        let increase = self.velocity_increase * wave.saturating_sub(1) as f32;
+        (self.initial_velocity + increase).min(self.max_velocity)
+    }
+
+    /// Test if a boss arrives with the given wave, starting at 1.
+    pub fn is_boss(&self, wave: u32) -> bool {
+        match self.boss_every {
+            Some(every) if every > 0 => wave % every == 0,
+            _ => false,
+        }
+    }
+}
+
+impl Default for WavePolicy {
+    fn default() -> Self {
+        Self {
+            initial_count: 4,
+            count_increase: 2,
+            max_count: 20,
+            initial_velocity: 40.0,
+            velocity_increase: 8.0,
+            max_velocity: 120.0,
+            delay: 3.0,
+            boss_every: Some(5),
+        }
+    }
+}
+
+/// What happens when objects reach the edges of the arena.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ArenaEdges {
+    /// Objects leaving the arena re-appear on the other side.
+    Wrap,
+    /// The arena has solid walls which objects bounce off.
+    ///
+    /// `restitution` is the fraction of velocity retained by a bounce.
+    Walls { restitution: f32 },
+}
+
+impl ArenaEdges {
+    /// Default restitution used when walls are enabled from the command line.
+    pub const DEFAULT_RESTITUTION: f32 = 0.8;
+
+    /// Move a position with the given radius so that it's fully inside of the arena.
+    ///
+    /// Only has an effect if the arena has walls.
+    pub fn contain(&self, mut t: Vector3<f32>, radius: f32) -> Vector3<f32> {
+        if let ArenaEdges::Walls { .. } = *self {
+            t.x = t.x.max(radius).min(ARENA_WIDTH - radius);
+            t.y = t.y.max(radius).min(ARENA_HEIGHT - radius);
+        }
+
+        t
+    }
+}
+
+/// Experimental mechanics, which are left out of the game unless enabled.
+///
+/// Enabled in `resources/game.ron`, or with `--enable` on the command line. Gravity and comets
+/// are enabled by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExperimentalFeatures {
+    /// Planets pull on everything around them.
+    pub gravity: bool,
+    /// Small asteroids merge if they overlap for long enough, as tuned by `AccretionPolicy`.
+    pub accretion: bool,
+    /// Comets spawn every now and then in endless mode.
+    pub comets: bool,
+}
+
+impl Default for ExperimentalFeatures {
+    fn default() -> Self {
+        Self {
+            gravity: true,
+            accretion: false,
+            comets: true,
+        }
+    }
+}
+
+impl ExperimentalFeatures {
+    /// Names of every feature, as given to `--enable`.
+    pub const NAMES: &'static [&'static str] = &["gravity", "accretion", "comets"];
+
+    /// Enable the feature with the given name.
+    ///
+    /// Returns `false` if there is no such feature.
+    pub fn enable(&mut self, name: &str) -> bool {
+        match name {
+            "gravity" => self.gravity = true,
+            "accretion" => self.accretion = true,
+            "comets" => self.comets = true,
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Names of the enabled features.
+    pub fn enabled(&self) -> Vec<&'static str> {
+        let flags = [self.gravity, self.accretion, self.comets];
+
+        Self::NAMES
+            .iter()
+            .zip(flags.iter())
+            .filter(|(_, enabled)| **enabled)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+/// Gameplay configuration, loaded from `resources/game.ron`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    /// Maximum number of bullets alive at once.
+    ///
+    /// The oldest bullets are culled when exceeded.
+    pub max_bullets: usize,
+    /// Maximum number of asteroids alive at once.
+    ///
+    /// The asteroids furthest away from the ship are culled when exceeded.
+    pub max_asteroids: usize,
+    /// How asteroids splinter when destroyed.
+    pub splinters: SplinterPolicy,
+    /// Fraction by which the ship's collision radius is shrunk, without affecting its sprite.
+    ///
+    /// For example, `0.25` makes the ship 25% easier to miss.
+    pub ship_hitbox_shrink: f32,
+    /// What happens when objects reach the edges of the arena.
+    pub edges: ArenaEdges,
+    /// How asteroids spin when spawned.
+    pub spin: SpinPolicy,
+    /// Small asteroids pass through each other, and merge if they overlap for long enough.
+    ///
+    /// Disabled if not set.
+    pub accretion: Option<AccretionPolicy>,
+    /// Every asteroid is shadowed by a harmless phantom, making it harder to tell which are real.
+    ///
+    /// Disabled if not set.
+    pub double_vision: Option<DoubleVisionPolicy>,
+    /// Two opposing edges of the arena close in every now and then.
+    ///
+    /// Disabled if not set.
+    pub squeeze: Option<SqueezePolicy>,
+    /// Some asteroids are unstable, and split on their own after a while.
+    ///
+    /// Disabled if not set.
+    pub unstable: Option<UnstablePolicy>,
+    /// Some asteroids are magnetized, and curve toward the nearest ship.
+    ///
+    /// Disabled if not set.
+    pub magnetized: Option<MagnetizedPolicy>,
+    /// Play in objective mode, defending a station which ships dock at.
+    ///
+    /// Disabled if not set.
+    pub station: Option<StationPolicy>,
+    /// Experimental mechanics which are enabled.
+    pub experimental: ExperimentalFeatures,
+    /// Handling of the ship.
+    pub ship: ShipConfig,
+    /// The energy pool of the ship.
+    pub energy: EnergyConfig,
+    /// How asteroids are spawned in endless mode.
+    pub spawns: SpawnPolicy,
+    /// How asteroids are spawned in waves.
+    pub waves: WavePolicy,
+    /// How long bullets live (s).
+    pub bullet_time_to_live: f32,
+}
+
+impl GameConfig {
+    /// The ship hitbox shrink, limited to a sensible range.
+    pub fn ship_hitbox_shrink(&self) -> f32 {
+        self.ship_hitbox_shrink.max(0.0).min(0.9)
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            max_bullets: 200,
+            max_asteroids: 300,
+            splinters: SplinterPolicy::default(),
+            ship_hitbox_shrink: 0.0,
+            edges: ArenaEdges::Wrap,
+            spin: SpinPolicy::default(),
+            accretion: None,
+            double_vision: None,
+            squeeze: None,
+            unstable: None,
+            magnetized: None,
+            station: None,
+            experimental: ExperimentalFeatures::default(),
+            ship: ShipConfig::default(),
+            energy: EnergyConfig::default(),
+            spawns: SpawnPolicy::default(),
+            waves: WavePolicy::default(),
+            bullet_time_to_live: 2.0,
+        }
+    }
+}
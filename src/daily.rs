@@ -0,0 +1,121 @@
+//! The daily challenge, played with `--daily`.
+//!
+//! Everyone playing on the same day gets the same run: the seed is derived from the date, and the
+//! game is configured the same way regardless of `resources/game.ron` or command line options.
+//! Which optional rules are in play changes from day to day. Scores are kept in their own table,
+//! which starts over every day.
+//!
+//! Days start at midnight UTC.
+
+use crate::{
+    config::{
+        AccretionPolicy, DoubleVisionPolicy, GameConfig, MagnetizedPolicy, SqueezePolicy,
+        UnstablePolicy,
+    },
+    resources::RandomGen,
+};
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of optional rules in play each day.
+const RULES: usize = 2;
+
+/// Salt mixed into the seed, so that daily runs don't repeat runs played with `--seed`.
+const SALT: u64 = 0xda11_c0de_da11_c0de;
+
+/// Length of a day (s).
+const DAY: u64 = 24 * 60 * 60;
+
+/// Seconds since the UNIX epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// The current day, counted in days since the UNIX epoch.
+pub fn today() -> u64 {
+    now() / DAY
+}
+
+/// Time left until the challenge of the next day, like `5h 07m`.
+pub fn time_to_next() -> String {
+    let left = DAY - now() % DAY;
+    format!("{}h {:02}m", left / 3600, left % 3600 / 60)
+}
+
+/// Seed of the run of the given day.
+pub fn seed(day: u64) -> u64 {
+    day ^ SALT
+}
+
+/// The date of the given day, like `2019-01-31`.
+pub fn date(day: u64) -> String {
+    // NB: converts days to a civil date in the proleptic Gregorian calendar, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = day + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// The configuration of the given day.
+///
+/// Starts from the defaults, with a few of the optional rules picked at random for the day.
+pub fn config(day: u64) -> GameConfig {
+    let mut config = GameConfig::default();
+
+    // NB: rolled separately from the run itself, so that the rules don't change its seed.
+    let rand = RandomGen::new(seed(day).rotate_left(32));
+
+    let mut rules = vec![0, 1, 2, 3, 4];
+
+    for _ in 0..RULES {
+        let rule = rules.remove(rand.next_usize() % rules.len());
+
+        match rule {
+            0 => config.accretion = Some(AccretionPolicy::default()),
+            1 => config.double_vision = Some(DoubleVisionPolicy::default()),
+            2 => config.squeeze = Some(SqueezePolicy::default()),
+            3 => config.unstable = Some(UnstablePolicy::default()),
+            _ => config.magnetized = Some(MagnetizedPolicy::default()),
+        }
+    }
+
+    config
+}
+
+/// Names of the optional rules in play with the given configuration.
+pub fn rules(config: &GameConfig) -> Vec<&'static str> {
+    let mut rules = Vec::new();
+
+    if config.accretion.is_some() {
+        rules.push("accretion");
+    }
+
+    if config.double_vision.is_some() {
+        rules.push("double vision");
+    }
+
+    if config.squeeze.is_some() {
+        rules.push("squeeze");
+    }
+
+    if config.unstable.is_some() {
+        rules.push("unstable");
+    }
+
+    if config.magnetized.is_some() {
+        rules.push("magnetized");
+    }
+
+    rules
+}
@@ -0,0 +1,88 @@
+//! Events emitted by gameplay systems, which other systems can react to.
+
+use amethyst::{core::nalgebra::Vector2, ecs::prelude::Entity};
+use crate::{components::PowerUpKind, resources::AsteroidSize};
+
+/// Something notable happened in the game.
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+    /// A new game was started.
+    GameStarted,
+    /// The player died, with no lives left.
+    PlayerDied,
+    /// The ship of the given player was destroyed.
+    ShipDestroyed { player: usize },
+    /// The ship was destroyed, but the player has lives left.
+    LifeLost { remaining: u32 },
+    /// An asteroid was destroyed, awarding points.
+    AsteroidDestroyed { size: AsteroidSize, points: u32 },
+    /// A UFO appeared.
+    UfoSpawned,
+    /// A UFO was destroyed, awarding points.
+    UfoDestroyed { points: u32 },
+    /// The score passed a milestone.
+    ScoreMilestone(u32),
+    /// The ship picked up a power-up.
+    PowerUpPickedUp(PowerUpKind),
+    /// An asteroid (or comet) was spawned.
+    AsteroidSpawned {
+        entity: Entity,
+        position: Vector2<f32>,
+        velocity: Vector2<f32>,
+        rotation: f32,
+        lateral_acceleration: f32,
+        scale: f32,
+        comet: bool,
+    },
+    /// A chunk of the hull of a destroyed boss was spawned.
+    HullSpawned {
+        entity: Entity,
+        position: Vector2<f32>,
+        velocity: Vector2<f32>,
+        rotation: f32,
+    },
+    /// A hull chunk was chipped away, awarding points.
+    HullDestroyed { points: u32 },
+    /// A new wave of asteroids arrived.
+    WaveStarted { wave: u32 },
+    /// A bullet ran out of time to live at the given position.
+    BulletExpired(Vector2<f32>),
+    /// An asteroid, ship or UFO with the given radius blew up, scattering debris.
+    Explosion { position: Vector2<f32>, radius: f32 },
+    /// The ship of the given player docked at the station, banking the given number of points.
+    Docked { player: usize, banked: u32 },
+    /// The station was destroyed, ending the run.
+    StationDestroyed,
+}
+
+impl GameEvent {
+    /// Text describing the event to the player, if it should be announced.
+    pub fn announcement(&self) -> Option<String> {
+        use self::GameEvent::*;
+
+        match *self {
+            GameStarted => Some(String::from("Game started")),
+            PlayerDied => Some(String::from("You died, press R to restart")),
+            ShipDestroyed { .. } => None,
+            LifeLost { remaining: 1 } => Some(String::from("Ship lost, last life left")),
+            LifeLost { remaining } => Some(format!("Ship lost, {} lives left", remaining)),
+            AsteroidDestroyed { .. } => None,
+            UfoSpawned => Some(String::from("UFO incoming")),
+            UfoDestroyed { .. } => Some(String::from("UFO destroyed")),
+            ScoreMilestone(score) => Some(format!("Score {}", score)),
+            PowerUpPickedUp(kind) => Some(format!("Picked up {}", kind.name())),
+            AsteroidSpawned { .. } => None,
+            HullSpawned { .. } => None,
+            HullDestroyed { .. } => None,
+            WaveStarted { wave } => Some(format!("Wave {}", wave)),
+            BulletExpired(..) => None,
+            Explosion { .. } => None,
+            Docked { banked, .. } => Some(format!("Docked, {} points banked", banked)),
+            StationDestroyed => Some(String::from("Station destroyed")),
+        }
+    }
+}
+
+/// A constrained object wrapped around the edges of the arena.
+#[derive(Debug, Clone, Copy)]
+pub struct Wrapped(pub Entity);
@@ -0,0 +1,189 @@
+//! A local control API, which lets external programs like bots play the game.
+//!
+//! The game listens on a Unix socket. Once a client has connected, the game sends an `Observation`
+//! on a single line of RON each frame, and waits for the client to answer with a `Command` on a
+//! single line before continuing.
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    time::Duration,
+};
+
+/// The ship, as observed by a client.
+#[derive(Debug, Serialize)]
+pub struct ShipObservation {
+    /// Position in the arena.
+    pub position: [f32; 2],
+    /// Velocity (units / s).
+    pub velocity: [f32; 2],
+    /// Direction the ship is pointing in (radians, counter-clockwise from the x axis).
+    pub heading: f32,
+    /// If the ship has reloaded and has enough energy to shoot.
+    pub can_shoot: bool,
+    /// Fraction of the ship's energy which is available, from 0 to 1.
+    pub energy: f32,
+}
+
+/// An asteroid, as observed by a client.
+#[derive(Debug, Serialize)]
+pub struct AsteroidObservation {
+    /// Shortest offset from the ship to the asteroid, taking into account that the arena wraps.
+    pub offset: [f32; 2],
+    /// Velocity (units / s).
+    pub velocity: [f32; 2],
+    /// Radius of the asteroid.
+    pub radius: f32,
+}
+
+/// Everything a client gets to see in a single frame.
+#[derive(Debug, Default, Serialize)]
+pub struct Observation {
+    /// Duration of the frame (s).
+    pub delta: f32,
+    /// Points scored so far.
+    pub points: u32,
+    /// The ship, unless it has been destroyed.
+    pub ship: Option<ShipObservation>,
+    /// The asteroids closest to the ship, nearest first.
+    pub asteroids: Vec<AsteroidObservation>,
+}
+
+/// Controls sent back by a client.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Command {
+    /// Rotation, from -1 to 1, in the same direction as the `rotate` axis.
+    pub rotate: f32,
+    /// Thrust, from -1 to 1, in the same direction as the `accelerate` axis.
+    pub thrust: f32,
+    /// Shoot, if the ship has reloaded and has enough energy.
+    pub shoot: bool,
+    /// Use the afterburner, boosting thrust at the cost of energy.
+    pub afterburner: bool,
+    /// Raise shields, at the cost of energy.
+    pub shield: bool,
+    /// Jump through hyperspace, if the ship has cooled down and has enough energy.
+    pub hyperspace: bool,
+}
+
+/// The socket clients connect to, and the currently connected client.
+pub struct ExternalControl {
+    listener: Option<UnixListener>,
+    client: Option<(BufReader<UnixStream>, UnixStream)>,
+    /// Reused buffer for lines read from the client.
+    line: String,
+}
+
+impl ExternalControl {
+    /// How long to wait for a client to answer before disconnecting it.
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Listen for clients on the socket at the given path.
+    pub fn bind(path: PathBuf) -> Self {
+        // NB: a socket left behind by a previous run would prevent binding.
+        if path.exists() {
+            if let Err(e) = fs::remove_file(&path) {
+                warn!("failed to remove old control socket: {}: {}", path.display(), e);
+            }
+        }
+
+        let listener = match UnixListener::bind(&path).and_then(|l| {
+            l.set_nonblocking(true)?;
+            Ok(l)
+        }) {
+            Ok(listener) => {
+                info!("waiting for external control on: {}", path.display());
+                Some(listener)
+            }
+            Err(e) => {
+                warn!("failed to bind control socket: {}: {}", path.display(), e);
+                None
+            }
+        };
+
+        Self {
+            listener,
+            client: None,
+            line: String::new(),
+        }
+    }
+
+    /// Send an observation to the connected client and wait for its command.
+    ///
+    /// Accepts a new client if none is connected. Returns `None` if there is no client to control
+    /// the ship.
+    pub fn exchange(&mut self, observation: &Observation) -> Option<Command> {
+        if self.client.is_none() {
+            self.client = self.accept();
+        }
+
+        let result = {
+            let (reader, writer) = self.client.as_mut()?;
+            let line = &mut self.line;
+
+            Self::send(writer, observation).and_then(|_| {
+                line.clear();
+                Self::receive(reader, line)
+            })
+        };
+
+        match result {
+            Ok(command) => Some(command),
+            Err(e) => {
+                warn!("disconnecting external control: {}", e);
+                self.client = None;
+                None
+            }
+        }
+    }
+
+    /// Accept a waiting client, if there is one.
+    fn accept(&self) -> Option<(BufReader<UnixStream>, UnixStream)> {
+        let (stream, _) = match self.listener.as_ref()?.accept() {
+            Ok(client) => client,
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return None,
+            Err(e) => {
+                warn!("failed to accept external control: {}", e);
+                return None;
+            }
+        };
+
+        let client = stream
+            .set_nonblocking(false)
+            .and_then(|_| stream.set_read_timeout(Some(Self::TIMEOUT)))
+            .and_then(|_| stream.try_clone());
+
+        match client {
+            Ok(writer) => {
+                info!("external control connected");
+                Some((BufReader::new(stream), writer))
+            }
+            Err(e) => {
+                warn!("failed to set up external control: {}", e);
+                None
+            }
+        }
+    }
+
+    fn send(writer: &mut UnixStream, observation: &Observation) -> io::Result<()> {
+        let line = ron::ser::to_string(observation)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+        writeln!(writer, "{}", line)
+    }
+
+    fn receive(reader: &mut BufReader<UnixStream>, line: &mut String) -> io::Result<Command> {
+        if reader.read_line(line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "client disconnected"));
+        }
+
+        ron::de::from_str(line.trim())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+}
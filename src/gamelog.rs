@@ -0,0 +1,126 @@
+//! Recording of the gameplay event stream, which can be exported to share a run and viewed later.
+//!
+//! Unlike replaying inputs, the log is an authoritative record of what happened: when and where
+//! asteroids and hull chunks were spawned, which were destroyed for how many points, and when the
+//! player died.
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+/// Version of the log format, bumped whenever it changes.
+const VERSION: u32 = 2;
+
+/// An event in the log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LogEvent {
+    /// A new run was started.
+    Started,
+    /// An asteroid (or comet) was spawned.
+    AsteroidSpawned {
+        /// Identifies the asteroid in later events.
+        id: u32,
+        position: [f32; 2],
+        velocity: [f32; 2],
+        /// Rotation of the asteroid (radians / s).
+        rotation: f32,
+        /// Acceleration perpendicular to its velocity (units / s**2).
+        lateral_acceleration: f32,
+        scale: f32,
+        comet: bool,
+    },
+    /// An asteroid was removed from the arena, for any reason.
+    AsteroidRemoved { id: u32 },
+    /// An asteroid was destroyed by the player, awarding points.
+    AsteroidDestroyed { points: u32 },
+    /// A UFO was destroyed by the player, awarding points.
+    UfoDestroyed { points: u32 },
+    /// A chunk of the hull of a destroyed boss was spawned.
+    HullSpawned {
+        /// Identifies the chunk in later events.
+        id: u32,
+        position: [f32; 2],
+        velocity: [f32; 2],
+        /// Rotation of the chunk (radians / s).
+        rotation: f32,
+    },
+    /// A hull chunk was removed from the arena, for any reason.
+    HullRemoved { id: u32 },
+    /// A hull chunk was chipped away by the player, awarding points.
+    HullDestroyed { points: u32 },
+    /// The player died.
+    PlayerDied,
+}
+
+/// A single timestamped event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    /// Seconds since the start of the run.
+    pub time: f32,
+    pub event: LogEvent,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LogFile {
+    version: u32,
+    records: Vec<Record>,
+}
+
+/// Records the event stream of the current run, to be exported when the run ends.
+///
+/// Only present if exporting is enabled.
+pub struct GameLog {
+    path: PathBuf,
+    pub records: Vec<Record>,
+}
+
+impl GameLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            records: Vec::new(),
+        }
+    }
+
+    /// Add an event to the log.
+    pub fn push(&mut self, time: f32, event: LogEvent) {
+        self.records.push(Record { time, event });
+    }
+
+    /// Export the current log, replacing any previously exported one.
+    pub fn save(&self) {
+        match self.write() {
+            Ok(()) => info!("exported game log: {}", self.path.display()),
+            Err(e) => warn!("failed to export game log: {}: {}", self.path.display(), e),
+        }
+    }
+
+    fn write(&self) -> bincode::Result<()> {
+        let file = LogFile {
+            version: VERSION,
+            records: self.records.clone(),
+        };
+
+        let out = BufWriter::new(File::create(&self.path)?);
+        bincode::serialize_into(out, &file)
+    }
+
+    /// Read the records of an exported log.
+    pub fn read(path: &Path) -> bincode::Result<Vec<Record>> {
+        let file: LogFile = bincode::deserialize_from(BufReader::new(File::open(path)?))?;
+
+        if file.version != VERSION {
+            return Err(Box::new(bincode::ErrorKind::Custom(format!(
+                "unsupported version {}, expected {}",
+                file.version, VERSION
+            ))));
+        }
+
+        Ok(file.records)
+    }
+}
@@ -0,0 +1,122 @@
+//! A second debug window, enabled with the `god_view` feature.
+//!
+//! The window shows a zoomed-out view of the whole arena, independent of the main camera, with the
+//! collision shape and velocity of everything which collides. Parts of the arena beyond its edges
+//! are included, so that objects wrapping around or bouncing off walls can be followed.
+//!
+//! The window is drawn in software, so it doesn't interfere with the main renderer. It's driven
+//! from the main thread along with the main window, since windows can only be created and updated
+//! from there on some platforms, like macOS.
+
+use crate::sketch::{Canvas, Frame};
+use log::{info, warn};
+use minifb::{Key, Window, WindowOptions};
+
+/// The god view window.
+pub struct GodView {
+    /// The window, or `None` if it has been closed.
+    window: Option<Window>,
+    canvas: Canvas,
+}
+
+impl GodView {
+    /// Size of the window, in pixels.
+    const SIZE: usize = 600;
+    /// Fraction of the arena size shown beyond each edge.
+    const MARGIN: f32 = 0.25;
+    /// Seconds of velocity shown by velocity vectors.
+    const VELOCITY_SCALE: f32 = 0.5;
+    /// Colors of the background, arena edges, and velocity vectors, as `0RGB`.
+    const BACKGROUND: u32 = 0x00_10_10_18;
+    const ARENA: u32 = 0x00_60_60_60;
+    const VELOCITY: u32 = 0x00_ff_ff_00;
+
+    /// Open the god view window.
+    ///
+    /// Must be called from the main thread.
+    pub fn open() -> Self {
+        let window = match Window::new(
+            "Asteroids! - god view",
+            Self::SIZE,
+            Self::SIZE,
+            WindowOptions::default(),
+        ) {
+            Ok(window) => Some(window),
+            Err(e) => {
+                warn!("failed to open god view: {}", e);
+                None
+            }
+        };
+
+        Self {
+            window,
+            canvas: Canvas::new(Self::SIZE),
+        }
+    }
+
+    /// If the window is still open.
+    pub fn is_open(&self) -> bool {
+        self.window.is_some()
+    }
+
+    /// Draw a frame in the window, and handle its events.
+    ///
+    /// Must be called from the main thread.
+    pub fn show(&mut self, frame: &Frame) {
+        let closed = match self.window.as_ref() {
+            Some(window) => !window.is_open() || window.is_key_down(Key::Escape),
+            None => return,
+        };
+
+        if closed {
+            info!("god view closed");
+            self.window = None;
+            return;
+        }
+
+        Self::draw(&mut self.canvas, frame);
+
+        let result = match self.window.as_mut() {
+            Some(window) => window.update_with_buffer(&self.canvas.pixels),
+            None => return,
+        };
+
+        if let Err(e) = result {
+            warn!("failed to update god view: {}", e);
+            self.window = None;
+        }
+    }
+
+    fn draw(canvas: &mut Canvas, frame: &Frame) {
+        let (width, height) = (frame.arena[0], frame.arena[1]);
+
+        // NB: keep the aspect of the arena, with its longest side plus margins filling the window.
+        let extent = width.max(height) * (1.0 + 2.0 * Self::MARGIN);
+        let size = canvas.size as f32;
+        let scale = size / extent;
+        let offset = [(extent - width) / 2.0, (extent - height) / 2.0];
+
+        let project = |p: [f32; 2]| {
+            (
+                ((p[0] + offset[0]) * scale) as i32,
+                // y grows downwards in the window.
+                (size - (p[1] + offset[1]) * scale) as i32,
+            )
+        };
+
+        canvas.clear(Self::BACKGROUND);
+
+        let corners = [[0.0, 0.0], [width, 0.0], [width, height], [0.0, height]];
+        canvas.polygon(corners.iter().map(|c| project(*c)), Self::ARENA);
+
+        for object in &frame.objects {
+            canvas.outline(&object.shape, &project, object.color);
+
+            let end = [
+                object.position[0] + object.velocity[0] * Self::VELOCITY_SCALE,
+                object.position[1] + object.velocity[1] * Self::VELOCITY_SCALE,
+            ];
+            canvas.line(project(object.position), project(end), Self::VELOCITY);
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! A headless simulation of the game, run with `--headless [FRAMES]`.
+//!
+//! The game runs without a window, renderer or audio output, for a fixed number of frames with a
+//! fixed timestep. The ships are played by random controls, or by the replay given with
+//! `--replay`. How long frames took to simulate and the final score are reported to the console.
+//!
+//! With the `count_allocations` feature, the number of heap allocations made while simulating is
+//! reported as well.
+//!
+//! Assets are still loaded, but never processed since nothing is drawn or played.
+
+use amethyst::{
+    assets::{AssetStorage, Loader},
+    audio::Source,
+    core::{frame_limiter::FrameLimiter, timing::Time, ArcThreadPool},
+    ecs::prelude::{Join, World},
+    prelude::{DataInit, StateData},
+    renderer::{DebugLines, ScreenDimensions, SpriteSheet, Texture},
+    ui::FontAsset,
+    StateMachine,
+};
+use crate::{
+    config::GameConfig,
+    input::InputContexts,
+    mutations::Mutations,
+    replay::{Frame, Playback, Replay},
+    resources::{Game, RandomGen, Score, SessionSettings, Wave},
+    states::{DataBuilder, MainGameState},
+    systems::ShipControls,
+    ARENA_HEIGHT, ARENA_WIDTH,
+};
+use log::{error, info};
+use rayon::ThreadPoolBuilder;
+
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Number of frames simulated if not specified.
+pub const DEFAULT_FRAMES: usize = 3600;
+/// Duration of every frame (s).
+const TIMESTEP: f32 = 1.0 / 60.0;
+/// Number of frames random controls are held for.
+const HOLD: usize = 20;
+
+/// Simulate the given number of frames, and report how it went.
+pub fn run(
+    frames: usize,
+    assets_dir: PathBuf,
+    mut session: SessionSettings,
+    game_config: GameConfig,
+    data: DataBuilder<'static, 'static>,
+) -> amethyst::Result<()> {
+    let seed = session.seed.unwrap_or_else(RandomGen::random_seed);
+    session.seed = Some(seed);
+
+    info!("simulating {} frames headless with seed: {}", frames, seed);
+
+    let pool = match ThreadPoolBuilder::new().build() {
+        Ok(pool) => Arc::new(pool),
+        Err(e) => {
+            error!("failed to build thread pool: {}", e);
+            return Ok(());
+        }
+    };
+
+    let mut world = World::new();
+    initialize(&mut world, pool, assets_dir, game_config);
+
+    let mut data = data.build(&mut world);
+
+    // NB: scripted controls come from the replay, which is loaded when the run starts.
+    let random = session.replay.is_none();
+    let players = session.players();
+
+    let mut states = StateMachine::new(MainGameState::new(session));
+    states.start(StateData::new(&mut world, &mut data))?;
+
+    if random {
+        let replay = random_replay(frames, players, &RandomGen::new(seed));
+        world.add_resource(Playback::new(replay));
+    }
+
+    let mut durations = Vec::with_capacity(frames);
+    let mut allocations = 0;
+
+    for _ in 0..frames {
+        let dead = world
+            .res
+            .try_fetch::<Game>()
+            .map(|game| game.modifiers.player_is_dead)
+            .unwrap_or(false);
+
+        if !states.is_running() || dead {
+            break;
+        }
+
+        {
+            let mut time = world.write_resource::<Time>();
+            time.set_delta_seconds(TIMESTEP);
+            time.increment_frame_number();
+        }
+
+        let allocated = allocation_count();
+        let started = Instant::now();
+        states.update(StateData::new(&mut world, &mut data));
+        world.maintain();
+        durations.push(started.elapsed());
+
+        if let (Some(before), Some(after)) = (allocated, allocation_count()) {
+            allocations += after - before;
+        }
+    }
+
+    let allocations = allocation_count().map(|_| allocations);
+    report(&world, &mut durations, allocations);
+    Ok(())
+}
+
+/// Add the resources which are otherwise added by the application and the bundles which need a
+/// window or an audio device.
+fn initialize(
+    world: &mut World,
+    pool: ArcThreadPool,
+    assets_dir: PathBuf,
+    game_config: GameConfig,
+) {
+    world.add_resource(Loader::new(assets_dir, pool.clone()));
+    world.add_resource(pool);
+    world.add_resource(Time::default());
+    world.add_resource(FrameLimiter::default());
+    world.add_resource(AssetStorage::<Texture>::new());
+    world.add_resource(AssetStorage::<SpriteSheet>::new());
+    world.add_resource(AssetStorage::<FontAsset>::new());
+    world.add_resource(AssetStorage::<Source>::new());
+    world.add_resource(ScreenDimensions::new(ARENA_WIDTH as u32, ARENA_HEIGHT as u32, 1.0));
+    world.add_resource(DebugLines::new());
+    world.add_resource(InputContexts::default());
+    world.add_resource(game_config);
+}
+
+/// Random controls for every frame, which change every now and then.
+fn random_replay(frames: usize, players: usize, rand: &RandomGen) -> Replay {
+    let mut controls = vec![ShipControls::default(); players];
+    let mut replay = Replay::default();
+
+    for frame in 0..frames {
+        if frame % HOLD == 0 {
+            for c in &mut controls {
+                *c = ShipControls {
+                    rotate: Some(f64::from(rand.next_f32()) * 2.0 - 1.0),
+                    accelerate: Some(f64::from(rand.next_f32())),
+                    shoot: rand.next_f32() < 0.5,
+                    afterburner: rand.next_f32() < 0.1,
+                    shield: rand.next_f32() < 0.1,
+                    hyperspace: rand.next_f32() < 0.01,
+                };
+            }
+        }
+
+        replay.frames.push(Frame {
+            delta: TIMESTEP,
+            controls: controls.clone(),
+        });
+    }
+
+    // NB: more picks than there could possibly be drafts.
+    replay.picks = (0..frames / HOLD)
+        .map(|_| rand.next_usize() % Mutations::CHOICES)
+        .collect();
+
+    replay
+}
+
+/// Print how long frames took to simulate, how many allocations they made if counted, and the
+/// final score.
+fn report(world: &World, durations: &mut [Duration], allocations: Option<usize>) {
+    let entities = (&*world.entities()).join().count();
+
+    println!(
+        "simulated {} frames ({:.1} s of game time)",
+        durations.len(),
+        durations.len() as f32 * TIMESTEP
+    );
+
+    if !durations.is_empty() {
+        durations.sort();
+
+        let millis = |d: Duration| d.as_secs() as f64 * 1e3 + f64::from(d.subsec_nanos()) / 1e6;
+        let total = durations.iter().cloned().map(millis).sum::<f64>();
+        let percentile = |p: f64| millis(durations[((durations.len() - 1) as f64 * p) as usize]);
+
+        println!(
+            "  frame time: mean {:.3} ms, median {:.3} ms, 99th percentile {:.3} ms, max {:.3} ms",
+            total / durations.len() as f64,
+            percentile(0.5),
+            percentile(0.99),
+            percentile(1.0),
+        );
+
+        if let Some(allocations) = allocations {
+            println!(
+                "  allocations: {} ({:.1} per frame)",
+                allocations,
+                allocations as f64 / durations.len() as f64
+            );
+        }
+    }
+
+    let score = world.res.try_fetch::<Score>();
+    let wave = world.res.try_fetch::<Wave>();
+
+    // NB: neither is present if the run failed to start.
+    if let (Some(score), Some(wave)) = (score, wave) {
+        println!(
+            "  score: {} points, {} asteroids destroyed, wave {}",
+            score.points, score.asteroids, wave.number
+        );
+    }
+
+    println!("  entities alive: {}", entities);
+}
+
+/// Number of heap allocations made so far.
+#[cfg(feature = "count_allocations")]
+fn allocation_count() -> Option<usize> {
+    Some(counting::ALLOCATIONS.load(std::sync::atomic::Ordering::Relaxed))
+}
+
+/// Allocations are only counted with the `count_allocations` feature.
+#[cfg(not(feature = "count_allocations"))]
+fn allocation_count() -> Option<usize> {
+    None
+}
+
+/// The system allocator, counting every allocation made through it.
+#[cfg(feature = "count_allocations")]
+mod counting {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    /// Number of allocations made so far, including reallocations.
+    pub static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counting;
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: Counting = Counting;
+}
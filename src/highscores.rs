@@ -0,0 +1,136 @@
+//! The high-score table, persisted between runs of the game.
+
+use amethyst::{config::Config, utils::application_root_dir};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use std::{io, path::PathBuf};
+
+/// A single entry in the high-score table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighScore {
+    /// Name of the player who set the score.
+    pub name: String,
+    /// Points scored.
+    pub points: u32,
+    /// Number of asteroids destroyed.
+    pub asteroids: u32,
+}
+
+/// The best scores across all runs, or across the runs of the daily challenge.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighScores {
+    /// Scores in the table, best first.
+    pub scores: Vec<HighScore>,
+    /// Day of the daily challenge the scores were set on, if this is the table of daily scores.
+    pub day: Option<u64>,
+}
+
+impl HighScores {
+    /// Number of scores kept in the table.
+    pub const MAX_SCORES: usize = 10;
+    /// Name used for scores set without an active profile.
+    pub const ANONYMOUS: &'static str = "anonymous";
+
+    /// Path to the high-score file.
+    pub fn path() -> io::Result<PathBuf> {
+        Ok(application_root_dir()?.join("highscores.ron"))
+    }
+
+    /// Path to the table of daily scores.
+    pub fn daily_path() -> io::Result<PathBuf> {
+        Ok(application_root_dir()?.join("daily_scores.ron"))
+    }
+
+    /// Read high scores from disk, falling back to an empty table if they are missing or broken.
+    pub fn read() -> HighScores {
+        HighScores::read_from(HighScores::path())
+    }
+
+    /// Read the daily scores of the given day, starting over with an empty table on a new day.
+    pub fn read_daily(day: u64) -> HighScores {
+        let high_scores = HighScores::read_from(HighScores::daily_path());
+
+        if high_scores.day == Some(day) {
+            return high_scores;
+        }
+
+        HighScores {
+            scores: Vec::new(),
+            day: Some(day),
+        }
+    }
+
+    fn read_from(path: io::Result<PathBuf>) -> HighScores {
+        let path = match path {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("failed to locate high scores: {}", e);
+                return HighScores::default();
+            }
+        };
+
+        if !path.is_file() {
+            return HighScores::default();
+        }
+
+        match HighScores::load_no_fallback(&path) {
+            Ok(high_scores) => high_scores,
+            Err(e) => {
+                warn!("failed to load high scores: {}: {}", path.display(), e);
+                HighScores::default()
+            }
+        }
+    }
+
+    /// Save high scores to disk.
+    pub fn save(&self) {
+        let path = match self.day {
+            Some(_) => HighScores::daily_path(),
+            None => HighScores::path(),
+        };
+
+        let path = match path {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("failed to locate high scores: {}", e);
+                return;
+            }
+        };
+
+        match self.write(&path) {
+            Ok(()) => info!("saved high scores: {}", path.display()),
+            Err(e) => warn!("failed to save high scores: {}: {}", path.display(), e),
+        }
+    }
+
+    /// The best score in the table, if any.
+    pub fn best(&self) -> Option<u32> {
+        self.scores.first().map(|s| s.points)
+    }
+
+    /// Add a score to the table.
+    ///
+    /// Returns the rank of the score, starting at 0, or `None` if it didn't make it into the table.
+    pub fn record(&mut self, score: HighScore) -> Option<usize> {
+        if score.points == 0 {
+            return None;
+        }
+
+        // NB: ties are ranked below the scores already in the table.
+        let rank = self
+            .scores
+            .iter()
+            .position(|s| s.points < score.points)
+            .unwrap_or_else(|| self.scores.len());
+
+        if rank >= Self::MAX_SCORES {
+            return None;
+        }
+
+        self.scores.insert(rank, score);
+        self.scores.truncate(Self::MAX_SCORES);
+        Some(rank)
+    }
+}
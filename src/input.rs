@@ -0,0 +1,388 @@
+//! Helpers for dealing with input.
+
+use amethyst::{
+    input::InputHandler,
+    renderer::{Event, ScreenDimensions, WindowEvent},
+};
+use crate::{systems::ShipControls, ARENA_HEIGHT, ARENA_WIDTH};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Action {
+    Active,
+    Inactive,
+}
+
+impl Default for Action {
+    fn default() -> Self {
+        Action::Inactive
+    }
+}
+
+impl Action {
+    /// Test an action, and transition it into a different state if applicable.
+    pub fn test(&mut self, input: &InputHandler<String, String>, name: &str) -> ActionTransition {
+        self.update(input.action_is_down(name).unwrap_or(false))
+    }
+
+    /// Transition the action depending on if it's held down.
+    pub fn update(&mut self, down: bool) -> ActionTransition {
+        match *self {
+            Action::Inactive => {
+                if down {
+                    *self = Action::Active;
+                    return ActionTransition::Activated;
+                }
+            }
+            Action::Active => {
+                if !down {
+                    *self = Action::Inactive;
+                    return ActionTransition::Deactivated;
+                }
+            }
+        }
+
+        ActionTransition::None
+    }
+}
+
+/// The transition of an action.
+#[derive(Debug, Clone, Copy)]
+pub enum ActionTransition {
+    Activated,
+    Deactivated,
+    None,
+}
+
+impl ActionTransition {
+    /// Call the given callback if action is activated.
+    pub fn activated(self, mut c: impl FnMut()) {
+        if let ActionTransition::Activated = self {
+            c();
+        }
+    }
+
+    /// Call the given callback if action is deactivated.
+    pub fn deactivated(self, mut c: impl FnMut()) {
+        if let ActionTransition::Deactivated = self {
+            c();
+        }
+    }
+}
+
+/// A set of actions which are bound together, depending on what the player is doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputContext {
+    /// Playing the game.
+    Gameplay,
+    /// Navigating menus.
+    Menu,
+    /// Typing in text, where keys bound to other actions are treated as text instead.
+    TextEntry,
+    /// Editing a level.
+    Editor,
+    /// Moving the camera around in photo mode.
+    Photo,
+}
+
+impl InputContext {
+    /// Actions bound in this context.
+    pub fn actions(self) -> &'static [&'static str] {
+        match self {
+            InputContext::Gameplay => &[
+                "shoot",
+                "shoot_p2",
+                "afterburner",
+                "afterburner_p2",
+                "shield",
+                "shield_p2",
+                "hyperspace",
+                "hyperspace_p2",
+                "shoot_lefty",
+                "afterburner_lefty",
+                "shield_lefty",
+                "hyperspace_lefty",
+                "shoot_pad",
+                "afterburner_pad",
+                "shield_pad",
+                "hyperspace_pad",
+                "restart",
+                "pause",
+                "quit",
+                "changelog",
+                "profiles",
+                "immortal",
+                "hitboxes",
+                "debug",
+                "dump_timings",
+                "trajectories",
+                "photo",
+                "zoom_in",
+                "zoom_out",
+                "save_clip",
+                "volume_up",
+                "volume_down",
+                "mute",
+            ],
+            InputContext::Menu => &[
+                "menu_up",
+                "menu_down",
+                "confirm",
+                "quit",
+                "pause",
+                "changelog",
+                "profiles",
+                "immortal",
+                "hitboxes",
+                "debug",
+                "dump_timings",
+                "volume_up",
+                "volume_down",
+                "mute",
+            ],
+            InputContext::TextEntry => &["menu_up", "menu_down", "confirm", "quit"],
+            InputContext::Photo => &[
+                "zoom_in",
+                "zoom_out",
+                "photo",
+                "pause",
+                "quit",
+                "volume_up",
+                "volume_down",
+                "mute",
+            ],
+            InputContext::Editor => &[
+                "place",
+                "undo",
+                "save",
+                "playtest",
+                "quit",
+                "hitboxes",
+                "debug",
+                "dump_timings",
+                "volume_up",
+                "volume_down",
+                "mute",
+            ],
+        }
+    }
+}
+
+/// The stack of input contexts, where the context on top decides which actions are bound.
+///
+/// States push their context when they start, and pop it when they stop. Every action is bound if
+/// no context has been pushed.
+#[derive(Debug, Default)]
+pub struct InputContexts {
+    stack: Vec<InputContext>,
+}
+
+impl InputContexts {
+    /// Push a context, which applies until it's popped.
+    pub fn push(&mut self, context: InputContext) {
+        self.stack.push(context);
+    }
+
+    /// Pop the topmost instance of the given context.
+    pub fn pop(&mut self, context: InputContext) {
+        if let Some(index) = self.stack.iter().rposition(|c| *c == context) {
+            self.stack.remove(index);
+        }
+    }
+
+    /// The current context, if any.
+    pub fn current(&self) -> Option<InputContext> {
+        self.stack.last().cloned()
+    }
+
+    /// Test if an action is bound in the current context.
+    pub fn is_bound(&self, name: &str) -> bool {
+        self.current().map(|c| c.actions().contains(&name)).unwrap_or(true)
+    }
+
+    /// Test an action, treating it as released unless it's bound in the current context.
+    pub fn test(
+        &self,
+        action: &mut Action,
+        input: &InputHandler<String, String>,
+        name: &str,
+    ) -> ActionTransition {
+        action.update(self.is_bound(name) && input.action_is_down(name).unwrap_or(false))
+    }
+}
+
+/// A named set of bindings for the controls of a ship, which players pick between.
+///
+/// Each control names the axis or action it's read from, as bound in the input configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BindingProfile {
+    pub name: String,
+    /// Axis rotating the ship.
+    pub rotate: String,
+    /// Axis accelerating the ship.
+    pub accelerate: String,
+    pub shoot: String,
+    pub afterburner: String,
+    pub shield: String,
+    pub hyperspace: String,
+}
+
+impl BindingProfile {
+    /// Profiles available unless others have been configured, in the order they're assigned to
+    /// players.
+    pub fn builtin() -> Vec<BindingProfile> {
+        vec![
+            BindingProfile::with_suffix("Player 1 keyboard", ""),
+            BindingProfile::with_suffix("Player 2 keyboard", "_p2"),
+            BindingProfile::with_suffix("Lefty keyboard", "_lefty"),
+            BindingProfile::with_suffix("Gamepad", "_pad"),
+        ]
+    }
+
+    /// A profile reading every control from the bindings with the given suffix, like `shoot_p2`.
+    fn with_suffix(name: &str, suffix: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            rotate: format!("rotate{}", suffix),
+            accelerate: format!("accelerate{}", suffix),
+            shoot: format!("shoot{}", suffix),
+            afterburner: format!("afterburner{}", suffix),
+            shield: format!("shield{}", suffix),
+            hyperspace: format!("hyperspace{}", suffix),
+        }
+    }
+
+    /// Read the controls of a ship, treating actions as released unless they're bound in the
+    /// current context.
+    pub fn controls(
+        &self,
+        input: &InputHandler<String, String>,
+        contexts: &InputContexts,
+    ) -> ShipControls {
+        let down =
+            |name: &str| contexts.is_bound(name) && input.action_is_down(name).unwrap_or(false);
+
+        ShipControls {
+            rotate: input.axis_value(&self.rotate),
+            accelerate: input.axis_value(&self.accelerate),
+            shoot: down(&self.shoot),
+            afterburner: down(&self.afterburner),
+            shield: down(&self.shield),
+            hyperspace: down(&self.hyperspace),
+        }
+    }
+}
+
+/// Auto-repeat for a held down action, independent of the frame rate.
+///
+/// Fires once when the action is pressed, again after an initial delay, and then at a fixed
+/// interval for as long as the action is held down.
+#[derive(Debug, Clone, Copy)]
+pub struct Repeat {
+    /// Delay before repeating starts (s).
+    delay: f32,
+    /// Interval between repeats (s).
+    interval: f32,
+    /// Time until the next repeat, if the action is held down.
+    timer: Option<f32>,
+}
+
+impl Repeat {
+    pub fn new(delay: f32, interval: f32) -> Self {
+        Self {
+            delay,
+            interval: interval.max(0.001),
+            timer: None,
+        }
+    }
+
+    /// Test an action, returning how many times it fired during the last `time_delta` seconds.
+    ///
+    /// This can be more than once if a frame took longer than the repeat interval.
+    pub fn test(
+        &mut self,
+        input: &InputHandler<String, String>,
+        name: &str,
+        time_delta: f32,
+    ) -> u32 {
+        let down = input.action_is_down(name).unwrap_or(false);
+
+        if !down {
+            self.timer = None;
+            return 0;
+        }
+
+        let mut timer = match self.timer {
+            Some(timer) => timer - time_delta,
+            None => {
+                self.timer = Some(self.delay);
+                return 1;
+            }
+        };
+
+        let mut fired = 0;
+
+        while timer <= 0f32 {
+            fired += 1;
+            timer += self.interval;
+        }
+
+        self.timer = Some(timer);
+        fired
+    }
+}
+
+/// The position of the mouse in arena coordinates, if it's inside of the window.
+///
+/// The mouse position is in pixels from the top left corner of the window, while the arena has its
+/// origin in the bottom left corner.
+pub fn mouse_in_arena(
+    input: &InputHandler<String, String>,
+    screen: &ScreenDimensions,
+) -> Option<(f32, f32)> {
+    let (x, y) = input.mouse_position()?;
+
+    let x = x as f32 / screen.width() * ARENA_WIDTH;
+    let y = (1.0 - y as f32 / screen.height()) * ARENA_HEIGHT;
+    Some((x, y))
+}
+
+/// A single line of text typed in by the player.
+#[derive(Debug, Default, Clone)]
+pub struct TextEntry {
+    /// The text entered so far.
+    pub text: String,
+    /// Maximum number of characters which can be entered.
+    max_len: usize,
+}
+
+impl TextEntry {
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            text: String::new(),
+            max_len,
+        }
+    }
+
+    /// Feed a window event into the entry, returning `true` if the text changed.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        let c = match *event {
+            Event::WindowEvent {
+                event: WindowEvent::ReceivedCharacter(c),
+                ..
+            } => c,
+            _ => return false,
+        };
+
+        match c {
+            // backspace
+            '\u{8}' => self.text.pop().is_some(),
+            c if c.is_control() => false,
+            c if self.text.chars().count() < self.max_len => {
+                self.text.push(c);
+                true
+            }
+            _ => false,
+        }
+    }
+}
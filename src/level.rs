@@ -0,0 +1,96 @@
+//! Levels with a fixed layout of asteroids, which can be created in the level editor.
+
+use serde::{Deserialize, Serialize};
+
+use std::f32::consts;
+
+/// An asteroid placed in a level.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelAsteroid {
+    /// Position in the arena.
+    pub position: [f32; 2],
+    /// Initial velocity (units / s).
+    pub velocity: [f32; 2],
+    /// Scale of the asteroid, which determines its size.
+    pub scale: f32,
+}
+
+/// A planet in a level, with a gravity well pulling everything towards it.
+///
+/// Anything falling into the planet is destroyed, including the ship.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelPlanet {
+    /// Position in the arena.
+    pub position: [f32; 2],
+    /// Radius of the planet.
+    pub radius: f32,
+    /// Strength of the gravity well, where the acceleration towards the planet is this divided by
+    /// the squared distance to it (units**3 / s**2).
+    pub gravity: f32,
+}
+
+/// A ring of asteroids orbiting the planet of a level.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelRing {
+    /// Distance from the center of the planet.
+    pub radius: f32,
+    /// Number of asteroids in the ring, evenly spaced out.
+    pub count: usize,
+    /// Scale of the asteroids in the ring.
+    pub scale: f32,
+    /// If the ring orbits clockwise, instead of counter-clockwise.
+    #[serde(default)]
+    pub clockwise: bool,
+}
+
+/// A level, stored as a RON file.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Level {
+    /// Asteroids present when the level starts.
+    pub asteroids: Vec<LevelAsteroid>,
+    /// Where the ship spawns, if not in the middle of the arena.
+    pub spawn: Option<[f32; 2]>,
+    /// Planet in the level, if any.
+    pub planet: Option<LevelPlanet>,
+    /// Rings of asteroids orbiting the planet. Ignored if the level has no planet.
+    pub rings: Vec<LevelRing>,
+    /// Scripted timeline of asteroids, one line each, like `at t=5 spawn 3 large from north`.
+    ///
+    /// See `script` for the full syntax.
+    pub script: Vec<String>,
+}
+
+impl Level {
+    /// Asteroids in the rings around the planet, with the velocity needed to stay in orbit.
+    pub fn ring_asteroids(&self) -> Vec<LevelAsteroid> {
+        let planet = match self.planet {
+            Some(ref planet) => planet,
+            None => return Vec::new(),
+        };
+
+        let mut asteroids = Vec::new();
+
+        for ring in &self.rings {
+            // speed of a circular orbit.
+            let speed = (planet.gravity / ring.radius).sqrt();
+            let speed = if ring.clockwise { -speed } else { speed };
+
+            for i in 0..ring.count {
+                let angle = 2.0 * consts::PI * i as f32 / ring.count as f32;
+                let (sin, cos) = angle.sin_cos();
+
+                asteroids.push(LevelAsteroid {
+                    position: [
+                        planet.position[0] + cos * ring.radius,
+                        planet.position[1] + sin * ring.radius,
+                    ],
+                    velocity: [-sin * speed, cos * speed],
+                    scale: ring.scale,
+                });
+            }
+        }
+
+        asteroids
+    }
+}
@@ -1,23 +1,73 @@
 use amethyst::{
     audio::AudioBundle,
-    core::{frame_limiter::FrameRateLimitStrategy, transform::TransformBundle},
+    core::transform::TransformBundle,
     input::InputBundle,
-    renderer::{ColorMask, DisplayConfig, DrawFlat2D, Pipeline, RenderBundle, Stage, ALPHA},
+    prelude::{Application, State, StateEvent},
+    renderer::{
+        ColorMask, DebugLines, DisplayConfig, DrawDebugLines, DrawFlat2D, Pipeline, PosColorNorm,
+        RenderBundle, Stage, ALPHA,
+    },
     ui::{DrawUi, UiBundle},
-    utils::application_root_dir,
+    utils::{application_root_dir, fps_counter::FPSCounterBundle},
 };
 
+mod assets;
 mod audio;
 mod bundle;
+mod changelog;
+#[cfg(feature = "clips")]
+mod clip;
 mod components;
+mod config;
+mod daily;
+mod events;
+#[cfg(feature = "external_control")]
+mod external;
+mod gamelog;
+mod headless;
+#[cfg(feature = "god_view")]
+mod god_view;
+mod highscores;
+mod input;
+mod level;
+mod mutations;
+mod net;
+mod profiles;
+mod replay;
 mod resources;
+mod schedule;
+mod script;
+mod settings;
+#[cfg(any(feature = "god_view", feature = "clips"))]
+mod sketch;
+mod spawner;
+mod spawns;
 mod states;
 mod systems;
 mod textures;
+mod theme;
+#[cfg(feature = "timings")]
+mod timings;
 
-use std::time::Duration;
+use std::{io, path::PathBuf};
 
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches};
+use log::{error, info};
+
+use crate::{
+    bundle::MainBundle,
+    config::{ExperimentalFeatures, GameConfig},
+    highscores::HighScores,
+    input::InputContexts,
+    net::NetSession,
+    profiles::{ControlScheme, Profiles},
+    replay::Replay,
+    resources::{DevTools, RandomGen, SessionSettings},
+    schedule::DispatchPlan,
+    settings::Settings,
+    states::{frame_rate_limit_strategy, Data, DataBuilder, FRAME_RATE},
+    theme::Themes,
+};
 
 const ARENA_HEIGHT: f32 = 300.0;
 const ARENA_WIDTH: f32 = 300.0;
@@ -32,70 +82,568 @@ fn opts() -> App<'static, 'static> {
                 .long("god")
                 .help("Want to be immortal? Now is your chance!"),
         )
+        .arg(
+            Arg::with_name("hitboxes")
+                .long("hitboxes")
+                .help("Draw the hitboxes of everything which collides."),
+        )
+        .arg(
+            Arg::with_name("debug")
+                .long("debug")
+                .help("Show the frame rate and how many objects there are of each kind."),
+        )
+        .arg(
+            Arg::with_name("measure-latency")
+                .long("measure-latency")
+                .help("Measure the latency between pressing shoot and the bullet being rendered."),
+        )
+        .arg(
+            Arg::with_name("tuned-dispatch")
+                .long("tuned-dispatch")
+                .help("Dispatch systems as suggested by the plan saved when dumping timings."),
+        )
+        .arg(
+            Arg::with_name("geiger")
+                .long("geiger")
+                .help("Tick faster the closer the nearest asteroid is to your ship."),
+        )
+        .arg(
+            Arg::with_name("aim-assist")
+                .long("aim-assist")
+                .help("Show a dotted line predicting where your bullets will go."),
+        )
+        .arg(
+            Arg::with_name("trajectories")
+                .long("trajectories")
+                .help("Show faint lines predicting where asteroids are headed (toggle with F7)."),
+        )
+        .arg(
+            Arg::with_name("aim-snap")
+                .long("aim-snap")
+                .value_name("STRENGTH")
+                .validator(|v| v.parse::<f32>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Pull your aim toward asteroids you have nearly lined up, from 0 to 1."),
+        )
+        .arg(
+            Arg::with_name("announce")
+                .long("announce")
+                .help("Announce key game events in text, for use with a screen reader."),
+        )
+        .arg(
+            Arg::with_name("endless")
+                .long("endless")
+                .help("Asteroids keep drifting in forever, instead of arriving in waves."),
+        )
+        .arg(
+            Arg::with_name("daily")
+                .long("daily")
+                .help("Play the daily challenge, which is the same for everyone on the same day."),
+        )
+        .arg(
+            Arg::with_name("walls")
+                .long("walls")
+                .help("Play in an arena with solid walls, instead of one that wraps around."),
+        )
+        .arg(
+            Arg::with_name("accretion")
+                .long("accretion")
+                .help("Small asteroids which overlap for long enough merge into larger ones."),
+        )
+        .arg(
+            Arg::with_name("double-vision")
+                .long("double-vision")
+                .help("Every asteroid is shadowed by a harmless phantom twin."),
+        )
+        .arg(
+            Arg::with_name("squeeze")
+                .long("squeeze")
+                .help("Two opposing edges of the arena close in every now and then."),
+        )
+        .arg(
+            Arg::with_name("unstable")
+                .long("unstable")
+                .help("Some asteroids are unstable, and split on their own after a while."),
+        )
+        .arg(
+            Arg::with_name("magnetized")
+                .long("magnetized")
+                .help("Some asteroids are magnetized, and curve toward the nearest ship."),
+        )
+        .arg(
+            Arg::with_name("station")
+                .long("station")
+                .help("Defend a station, which repairs ships and banks their points as they dock."),
+        )
+        .arg(
+            Arg::with_name("enable")
+                .long("enable")
+                .value_name("FEATURES")
+                .use_delimiter(true)
+                .multiple(true)
+                .possible_values(ExperimentalFeatures::NAMES)
+                .help("Enable experimental mechanics, separated by commas."),
+        )
+        .arg(
+            Arg::with_name("export-log")
+                .long("export-log")
+                .value_name("FILE")
+                .help("Export a log of the gameplay events of each run to the given file."),
+        )
+        .arg(
+            Arg::with_name("view-log")
+                .long("view-log")
+                .value_name("FILE")
+                .help("Watch a run from a previously exported log.")
+                .conflicts_with("export-log"),
+        )
+        .arg(
+            Arg::with_name("record")
+                .long("record")
+                .value_name("FILE")
+                .help("Record the inputs of each run as a replay to the given file."),
+        )
+        .arg(
+            Arg::with_name("replay")
+                .long("replay")
+                .value_name("FILE")
+                .help("Play back a previously recorded replay.")
+                .conflicts_with_all(&["record", "view-log"]),
+        )
+        .arg(
+            Arg::with_name("host")
+                .long("host")
+                .value_name("ADDRESS")
+                .help("Host an experimental online co-op game, like on 0.0.0.0:7777.")
+                .conflicts_with_all(&[
+                    "join",
+                    "spectate",
+                    "replay",
+                    "headless",
+                    "view-log",
+                    "editor",
+                ]),
+        )
+        .arg(
+            Arg::with_name("join")
+                .long("join")
+                .value_name("ADDRESS")
+                .help("Join an experimental online co-op game hosted at the given address.")
+                .conflicts_with_all(&["spectate", "replay", "headless", "view-log", "editor"]),
+        )
+        .arg(
+            Arg::with_name("spectate")
+                .long("spectate")
+                .value_name("ADDRESS")
+                .help("Watch an experimental online co-op game hosted at the given address.")
+                .conflicts_with_all(&["replay", "headless", "view-log", "editor"]),
+        )
+        .arg(
+            Arg::with_name("editor")
+                .long("editor")
+                .value_name("FILE")
+                .help("Edit the layout of asteroids in the given level file.")
+                .conflicts_with("view-log"),
+        )
+        .arg(
+            Arg::with_name("level")
+                .long("level")
+                .value_name("FILE")
+                .help("Play the given level file."),
+        )
+        .arg(
+            Arg::with_name("profile")
+                .long("profile")
+                .value_name("NAME")
+                .help("Play as the profile with the given name, creating it if it doesn't exist."),
+        )
+        .arg(Arg::with_name("quick").long("quick").help(
+            "Skip menus and loading screens, jumping straight into a run. The run is picked \
+             with --endless, --level, --daily, --players and --seed.",
+        ))
+        .arg(
+            Arg::with_name("players")
+                .long("players")
+                .value_name("N")
+                .possible_values(&["1", "2"])
+                .help("Number of players, where two players share the keyboard in co-op."),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .value_name("SEED")
+                .validator(|v| v.parse::<u64>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Seed every run with the given number, so that it plays out the same way."),
+        )
+        .arg(
+            Arg::with_name("headless")
+                .long("headless")
+                .value_name("FRAMES")
+                .min_values(0)
+                .max_values(1)
+                .validator(|v| v.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Simulate a number of frames without a window, then report how it went.")
+                .conflicts_with_all(&["record", "view-log", "editor"]),
+        )
+        .arg(
+            Arg::with_name("simulate-spawns")
+                .long("simulate-spawns")
+                .value_name("COUNT")
+                .validator(|v| v.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Report how asteroids spawn over the given number of spawns, then exit."),
+        )
+}
+
+/// Options only available with the `external_control` feature.
+#[cfg(feature = "external_control")]
+fn external_control_opts(app: App<'static, 'static>) -> App<'static, 'static> {
+    app.arg(
+        Arg::with_name("control-socket")
+            .long("control-socket")
+            .value_name("SOCKET")
+            .help("Let an external program control the ship through the given Unix socket."),
+    )
+}
+
+/// Set up the systems for the game itself.
+#[cfg(not(feature = "external_control"))]
+fn main_bundle(_: &ArgMatches, features: ExperimentalFeatures) -> MainBundle {
+    MainBundle { features }
+}
+
+/// Set up the systems for the game itself, letting an external program control the ship if
+/// requested.
+#[cfg(feature = "external_control")]
+fn main_bundle(matches: &ArgMatches, features: ExperimentalFeatures) -> MainBundle {
+    MainBundle {
+        features,
+        control_socket: matches.value_of_os("control-socket").map(PathBuf::from),
+    }
+}
+
+/// Number of threads to dispatch the systems of the game on, if a tuned dispatch was requested.
+fn dispatch_threads(matches: &ArgMatches) -> Option<usize> {
+    if !matches.is_present("tuned-dispatch") {
+        return None;
+    }
+
+    let plan = DispatchPlan::read()?;
+    plan.log();
+    Some(plan.threads)
+}
+
+/// Translate command line options into settings for the session.
+fn session_settings(matches: &ArgMatches) -> SessionSettings {
+    SessionSettings {
+        dev: DevTools {
+            immortal: matches.is_present("god"),
+            hitboxes: matches.is_present("hitboxes"),
+            measure_latency: matches.is_present("measure-latency"),
+            debug: matches.is_present("debug"),
+        },
+        quick: matches.is_present("quick"),
+        coop: matches.value_of("players") == Some("2"),
+        palette: SessionSettings::DEFAULT_PALETTE.to_vec(),
+        looks: Vec::new(),
+        export_log: matches.value_of_os("export-log").map(PathBuf::from),
+        level: matches.value_of_os("level").map(PathBuf::from),
+        seed: matches.value_of("seed").and_then(|s| s.parse().ok()),
+        endless: matches.is_present("endless"),
+        record: matches.value_of_os("record").map(PathBuf::from),
+        replay: matches.value_of_os("replay").map(PathBuf::from),
+        daily: None,
+        geiger: matches.is_present("geiger"),
+        aim_assist: matches.is_present("aim-assist"),
+        announce: matches.is_present("announce"),
+        aim_snap: matches.value_of("aim-snap").and_then(|s| s.parse().ok()),
+    }
 }
 
 fn main() -> amethyst::Result<()> {
     use amethyst::{
         shred::DispatcherBuilder,
         core::bundle::SystemBundle,
-        prelude::{Application, Config, GameDataBuilder}
+        prelude::{Config, GameDataBuilder}
     };
     use crate::{
-        audio::Silent,
-        states::{MainGameState, DataBuilder},
-        bundle::{GlobalBundle, MainBundle},
+        audio::Music,
+        states::{EditorState, LogViewerState, MainGameState, MenuState, SetupState},
+        bundle::GlobalBundle,
+        config::{
+            AccretionPolicy, ArenaEdges, DoubleVisionPolicy, MagnetizedPolicy, SqueezePolicy,
+            StationPolicy, UnstablePolicy,
+        },
     };
 
     amethyst::start_logger(Default::default());
 
     let app = opts();
+    #[cfg(feature = "external_control")]
+    let app = external_control_opts(app);
     let matches = app.get_matches();
 
-    let mut game = MainGameState::default();
-    game.player_is_immortal = matches.is_present("god");
+    let mut session = session_settings(&matches);
+
+    if matches.is_present("daily") {
+        let day = daily::today();
+        session.daily = Some(day);
+        session.seed = Some(daily::seed(day));
+        session.level = None;
+        session.endless = false;
+    }
+
+    let mut replay_config = None;
+
+    if let Some(path) = session.replay.clone() {
+        let replay = match Replay::read(&path) {
+            Ok(replay) => replay,
+            Err(e) => {
+                error!("failed to read replay: {}: {}", path.display(), e);
+                return Ok(());
+            }
+        };
+
+        session = session.for_replay(path, &replay);
+        replay_config = Some(replay.config);
+    }
+
+    let net = match connect(&matches, &mut session) {
+        Ok(net) => net,
+        Err(e) => {
+            error!("failed to start online game: {}", e);
+            return Ok(());
+        }
+    };
 
     let app_root = application_root_dir()?;
 
     let display_config_path = app_root.join("resources/display.ron");
-    let config = DisplayConfig::load(&display_config_path);
+    let mut config = DisplayConfig::load(&display_config_path);
+
+    let mut game_config = GameConfig::load(app_root.join("resources/game.ron"));
+
+    if matches.is_present("walls") {
+        if let ArenaEdges::Wrap = game_config.edges {
+            game_config.edges = ArenaEdges::Walls {
+                restitution: ArenaEdges::DEFAULT_RESTITUTION,
+            };
+        }
+    }
+
+    if matches.is_present("accretion") && game_config.accretion.is_none() {
+        game_config.accretion = Some(AccretionPolicy::default());
+    }
+
+    if matches.is_present("double-vision") && game_config.double_vision.is_none() {
+        game_config.double_vision = Some(DoubleVisionPolicy::default());
+    }
+
+    if matches.is_present("squeeze") && game_config.squeeze.is_none() {
+        game_config.squeeze = Some(SqueezePolicy::default());
+    }
+
+    if matches.is_present("unstable") && game_config.unstable.is_none() {
+        game_config.unstable = Some(UnstablePolicy::default());
+    }
+
+    if matches.is_present("magnetized") && game_config.magnetized.is_none() {
+        game_config.magnetized = Some(MagnetizedPolicy::default());
+    }
+
+    if matches.is_present("station") && game_config.station.is_none() {
+        game_config.station = Some(StationPolicy::default());
+    }
+
+    if let Some(features) = matches.values_of("enable") {
+        for feature in features {
+            game_config.experimental.enable(feature);
+        }
+    }
+
+    // NB: everyone plays the daily challenge the same way, whatever their options.
+    if let Some(day) = session.daily {
+        game_config = daily::config(day);
+        let rules = daily::rules(&game_config).join(" and ");
+        info!("daily challenge of {}, with {}", daily::date(day), rules);
+    }
+
+    // NB: a replay is played back with the configuration it was recorded with.
+    if let Some(config) = replay_config {
+        game_config = config;
+    }
+
+    // NB: accretion is both enabled as a feature and tuned by its policy, so either implies the
+    // other.
+    if game_config.experimental.accretion && game_config.accretion.is_none() {
+        game_config.accretion = Some(AccretionPolicy::default());
+    }
+
+    if game_config.accretion.is_some() {
+        game_config.experimental.accretion = true;
+    }
+
+    let features = game_config.experimental;
+
+    if features != ExperimentalFeatures::default() {
+        info!("experimental features: {}", features.enabled().join(", "));
+    }
+
+    if let Some(count) = matches.value_of("simulate-spawns").and_then(|c| c.parse().ok()) {
+        let seed = session.seed.unwrap_or_else(RandomGen::random_seed);
+        spawns::audit(count, &game_config, seed);
+        return Ok(());
+    }
+
+    // NB: checked before anything gets the chance to save settings.
+    let first_run = !Settings::exists();
+    let mut settings = Settings::read();
+    session.palette = settings.palette.colors().to_vec();
+
+    if matches.is_present("trajectories") {
+        settings.trajectories = true;
+    }
+    settings.apply_to_display_config(&mut config);
+
+    let mut profiles = Profiles::read();
+
+    if let Some(name) = matches.value_of("profile") {
+        profiles.select(name);
+        profiles.save();
+    }
+
+    // NB: the background is cleared with the color of the theme picked when the game launched.
+    let background = Themes::read().get(&settings.theme).background;
 
     let pipe = Pipeline::build().with_stage(
         Stage::with_backbuffer()
-            .clear_target([0.0, 0.0, 0.0, 1.0], 1.0)
+            .clear_target(background, 1.0)
             .with_pass(DrawFlat2D::new().with_transparency(ColorMask::all(), ALPHA, None))
+            .with_pass(DrawDebugLines::<PosColorNorm>::new())
             .with_pass(DrawUi::new()),
     );
 
-    let key_bindings_path = {
-        if cfg!(feature = "sdl_controller") {
+    let key_bindings_path = match profiles.controls() {
+        ControlScheme::Controller if cfg!(feature = "sdl_controller") => {
             app_root.join("resources/input_controller.ron")
-        } else {
-            app_root.join("resources/input.ron")
         }
+        _ => app_root.join("resources/input.ron"),
     };
 
     let assets_dir = app_root.join("assets");
 
+    if matches.is_present("headless") {
+        let frames = matches
+            .value_of("headless")
+            .and_then(|f| f.parse().ok())
+            .unwrap_or(headless::DEFAULT_FRAMES);
+
+        // NB: everything which needs a window or an audio device is left out.
+        let base = GameDataBuilder::default()
+            .with_bundle(
+                InputBundle::<String, String>::new().with_bindings_from_file(&key_bindings_path)?,
+            )?
+            .with_bundle(TransformBundle::new())?
+            .with_bundle(GlobalBundle)?;
+
+        let mut main = DispatcherBuilder::default();
+        main_bundle(&matches, features).build(&mut main)?;
+
+        let data = DataBuilder {
+            base,
+            main,
+            threads: dispatch_threads(&matches),
+        };
+        return headless::run(frames, assets_dir, session, game_config, data);
+    }
+
     let base = GameDataBuilder::default()
         .with_bundle(
             InputBundle::<String, String>::new().with_bindings_from_file(&key_bindings_path)?,
         )?
         .with_bundle(RenderBundle::new(pipe, Some(config)).with_sprite_sheet_processor())?
         .with_bundle(TransformBundle::new())?
-        .with_bundle(AudioBundle::new(|_: &mut Silent| None))?
+        .with_bundle(AudioBundle::new(|music: &mut Music| music.next()))?
         .with_bundle(UiBundle::<String, String>::new())?
+        .with_bundle(FPSCounterBundle::default())?
         .with_bundle(GlobalBundle)?;
 
     let mut main = DispatcherBuilder::default();
-    MainBundle.build(&mut main)?;
+    main_bundle(&matches, features).build(&mut main)?;
 
-    let mut game = Application::build(assets_dir, game)?
-        .with_frame_limit(
-            FrameRateLimitStrategy::SleepAndYield(Duration::from_millis(2)),
-            144,
-        )
-        .build(DataBuilder { base, main })?;
+    let data = DataBuilder {
+        base,
+        main,
+        threads: dispatch_threads(&matches),
+    };
+
+    let mut game = if let Some(path) = matches.value_of_os("view-log") {
+        let state = LogViewerState::new(PathBuf::from(path));
+        build(assets_dir, state, settings, profiles, game_config, data)?
+    } else if let Some(path) = matches.value_of_os("editor") {
+        let state = EditorState::new(PathBuf::from(path), session);
+        build(assets_dir, state, settings, profiles, game_config, data)?
+    } else if let Some(net) = net {
+        let state = MainGameState::online(session, net);
+        build(assets_dir, state, settings, profiles, game_config, data)?
+    } else if session.quick {
+        let state = MainGameState::new(session);
+        build(assets_dir, state, settings, profiles, game_config, data)?
+    } else if first_run {
+        let state = SetupState::new(session);
+        build(assets_dir, state, settings, profiles, game_config, data)?
+    } else {
+        let state = MenuState::new(session);
+        build(assets_dir, state, settings, profiles, game_config, data)?
+    };
 
     game.run();
     Ok(())
 }
+
+/// Wait for the other player of an online game to connect, or connect to them, if requested.
+///
+/// Online games are always played in co-op, with the seed picked by the host. Spectators connect to
+/// the host like the other player does.
+fn connect(matches: &ArgMatches, session: &mut SessionSettings) -> io::Result<Option<NetSession>> {
+    let net = if let Some(address) = matches.value_of("host") {
+        let seed = session.seed.unwrap_or_else(RandomGen::random_seed);
+        session.seed = Some(seed);
+        NetSession::host(address, seed)?
+    } else if let Some(address) = matches.value_of("join") {
+        let (net, seed) = NetSession::join(address)?;
+        session.seed = Some(seed);
+        net
+    } else if let Some(address) = matches.value_of("spectate") {
+        let (net, seed) = NetSession::spectate(address)?;
+        session.seed = Some(seed);
+        net
+    } else {
+        return Ok(None);
+    };
+
+    session.coop = true;
+    session.quick = true;
+    Ok(Some(net))
+}
+
+/// Build the application, starting in the given state.
+fn build<S>(
+    assets_dir: PathBuf,
+    state: S,
+    settings: Settings,
+    profiles: Profiles,
+    game_config: GameConfig,
+    data: DataBuilder<'static, 'static>,
+) -> amethyst::Result<Application<'static, Data<'static, 'static>>>
+where
+    S: State<Data<'static, 'static>, StateEvent> + 'static,
+{
+    Application::build(assets_dir, state)?
+        .with_resource(settings)
+        .with_resource(profiles)
+        .with_resource(HighScores::read())
+        .with_resource(InputContexts::default())
+        .with_resource(game_config)
+        .with_resource(DebugLines::new())
+        .with_frame_limit(frame_rate_limit_strategy(), FRAME_RATE)
+        .build(data)
+}
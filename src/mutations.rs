@@ -0,0 +1,114 @@
+//! Upgrades drafted between waves, which last for the rest of the run.
+
+use crate::{
+    components::{Energy, Ship},
+    resources::RandomGen,
+};
+
+/// An upgrade to the ship, picked between waves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mutation {
+    /// The ship reloads faster.
+    FasterReload,
+    /// Every shot fires an extra pair of bullets to the sides.
+    WiderSpread,
+    /// Raising shields costs less energy.
+    StrongerShield,
+    /// Bullets live for longer.
+    LongerRange,
+    /// The ship accelerates faster.
+    StrongerEngine,
+    /// The ship can hold more energy.
+    LargerBattery,
+}
+
+impl Mutation {
+    pub const ALL: [Mutation; 6] = [
+        Mutation::FasterReload,
+        Mutation::WiderSpread,
+        Mutation::StrongerShield,
+        Mutation::LongerRange,
+        Mutation::StrongerEngine,
+        Mutation::LargerBattery,
+    ];
+
+    /// Factor by which reload time is multiplied by `FasterReload`.
+    const RELOAD_FACTOR: f32 = 0.8;
+    /// Factor by which the energy cost of shields is multiplied by `StrongerShield`.
+    const SHIELD_DRAIN_FACTOR: f32 = 0.75;
+    /// Factor by which the lifetime of bullets is multiplied by `LongerRange`.
+    const RANGE_FACTOR: f32 = 1.25;
+    /// Factor by which acceleration is multiplied by `StrongerEngine`.
+    const ENGINE_FACTOR: f32 = 1.15;
+    /// Energy capacity added by `LargerBattery`.
+    const BATTERY_CAPACITY: f32 = 25.0;
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mutation::FasterReload => "Faster reload",
+            Mutation::WiderSpread => "Wider spread",
+            Mutation::StrongerShield => "Stronger shield",
+            Mutation::LongerRange => "Longer range",
+            Mutation::StrongerEngine => "Stronger engine",
+            Mutation::LargerBattery => "Larger battery",
+        }
+    }
+
+    /// A short description of what the mutation does.
+    pub fn description(self) -> &'static str {
+        match self {
+            Mutation::FasterReload => "reload 20% faster",
+            Mutation::WiderSpread => "fire an extra pair of bullets",
+            Mutation::StrongerShield => "shields cost 25% less energy",
+            Mutation::LongerRange => "bullets live 25% longer",
+            Mutation::StrongerEngine => "accelerate 15% faster",
+            Mutation::LargerBattery => "hold 25 more energy",
+        }
+    }
+
+    /// Apply the mutation to a ship.
+    pub fn apply(self, ship: &mut Ship, energy: &mut Energy) {
+        match self {
+            Mutation::FasterReload => ship.time_to_reload *= Self::RELOAD_FACTOR,
+            Mutation::WiderSpread => ship.spread_pairs += 1,
+            Mutation::StrongerShield => ship.shield_drain *= Self::SHIELD_DRAIN_FACTOR,
+            Mutation::LongerRange => ship.bullet_time_to_live *= Self::RANGE_FACTOR,
+            Mutation::StrongerEngine => ship.acceleration *= Self::ENGINE_FACTOR,
+            Mutation::LargerBattery => {
+                energy.capacity += Self::BATTERY_CAPACITY;
+                energy.current += Self::BATTERY_CAPACITY;
+            }
+        }
+    }
+}
+
+/// The mutations picked during the current run, in the order they were picked.
+#[derive(Debug, Default, Clone)]
+pub struct Mutations {
+    pub picked: Vec<Mutation>,
+}
+
+impl Mutations {
+    /// Number of mutations to choose between in each draft.
+    pub const CHOICES: usize = 3;
+
+    /// Apply every picked mutation to a newly spawned ship.
+    pub fn apply(&self, ship: &mut Ship, energy: &mut Energy) {
+        for mutation in &self.picked {
+            mutation.apply(ship, energy);
+        }
+    }
+
+    /// Draw distinct mutations at random to choose between.
+    pub fn draft(rand: &RandomGen) -> Vec<Mutation> {
+        let mut remaining = Mutation::ALL.to_vec();
+        let mut choices = Vec::with_capacity(Self::CHOICES);
+
+        while choices.len() < Self::CHOICES && !remaining.is_empty() {
+            let index = rand.next_usize() % remaining.len();
+            choices.push(remaining.swap_remove(index));
+        }
+
+        choices
+    }
+}
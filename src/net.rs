@@ -0,0 +1,437 @@
+//! Experimental online co-op, through deterministic lockstep.
+//!
+//! Both games run the same seeded simulation with a fixed timestep, and only exchange the controls
+//! of their own player for every frame over TCP. Controls are sent `INPUT_DELAY` frames before
+//! they're used, which hides the latency of the connection. If the controls of the other player
+//! haven't arrived by the time they're needed, the simulation waits for them.
+//!
+//! The host (`--host ADDRESS`) is the first player, and decides the seed and the mutations picked
+//! between waves. The guest (`--join ADDRESS`) is the second player. Both have to run the same
+//! version of the game with the same configuration, or their simulations drift apart.
+//!
+//! Anyone else connecting to the host (`--spectate ADDRESS`) watches the game. The host sends
+//! spectators the controls of both players for every frame once they're known, starting with
+//! everything that has happened so far, so that spectators can join at any time.
+
+use crate::systems::ShipControls;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::{self, BufReader, Write},
+    mem,
+    net::{TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Mutex,
+    },
+    thread,
+};
+
+/// Version of the protocol, bumped whenever it changes.
+const VERSION: u32 = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Message {
+    /// Sent by everyone connecting to the host, before anything else.
+    Join { spectator: bool },
+    /// Sent by the host once the guest or a spectator has connected.
+    Hello { version: u32, seed: u64 },
+    /// Controls of the sender's player during the given frame.
+    Controls { frame: u64, controls: ShipControls },
+    /// Controls of both players during the given frame, sent by the host to spectators.
+    Frame {
+        frame: u64,
+        controls: [ShipControls; 2],
+    },
+    /// Mutation picked by the host in a draft.
+    Pick { index: usize },
+}
+
+/// The spectators of a game, kept by the host.
+struct Spectators {
+    /// Seed of the game, which spectators are greeted with.
+    seed: u64,
+    /// Connections of spectators which have joined since the last poll.
+    joining: Mutex<Receiver<TcpStream>>,
+    streams: Vec<TcpStream>,
+    /// Everything sent to spectators so far, which is sent again to those joining late.
+    history: Vec<Message>,
+}
+
+impl Spectators {
+    /// Accept spectators on the given listener, after the ones which connected before the game
+    /// started.
+    fn new(listener: TcpListener, early: Vec<TcpStream>, seed: u64) -> Spectators {
+        let (sender, joining) = mpsc::channel();
+
+        for stream in early {
+            let _ = sender.send(stream);
+        }
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!("failed to accept spectator: {}", e);
+                        continue;
+                    }
+                };
+
+                match read_join(&stream) {
+                    Ok(true) => {
+                        if sender.send(stream).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(false) => warn!("turned away a player, the game is full"),
+                    Err(e) => warn!("failed to accept spectator: {}", e),
+                }
+            }
+        });
+
+        Spectators {
+            seed,
+            joining: Mutex::new(joining),
+            streams: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    /// Greet the spectators which have joined since the last poll, and catch them up.
+    fn poll(&mut self) {
+        loop {
+            let stream = match self.joining.get_mut() {
+                Ok(joining) => joining.try_recv(),
+                Err(_) => Err(TryRecvError::Disconnected),
+            };
+
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => break,
+            };
+
+            let hello = Message::Hello {
+                version: VERSION,
+                seed: self.seed,
+            };
+
+            let history = &self.history;
+
+            let result = write_message(&mut stream, &hello)
+                .and_then(|_| history.iter().try_for_each(|m| write_message(&mut stream, m)));
+
+            match result {
+                Ok(()) => {
+                    info!("spectator joined");
+                    self.streams.push(stream);
+                }
+                Err(e) => warn!("failed to greet spectator: {}", e),
+            }
+        }
+    }
+
+    /// Send a message to every spectator, dropping those who have left.
+    fn send(&mut self, message: Message) {
+        for mut stream in mem::replace(&mut self.streams, Vec::new()) {
+            match write_message(&mut stream, &message) {
+                Ok(()) => self.streams.push(stream),
+                Err(_) => info!("spectator left"),
+            }
+        }
+
+        self.history.push(message);
+    }
+}
+
+/// A connection to the other player of an online game, and the controls exchanged so far.
+///
+/// When spectating, the connection is to the host instead, and controls are only received.
+pub struct NetSession {
+    stream: TcpStream,
+    /// Messages received by the reader thread.
+    messages: Mutex<Receiver<Message>>,
+    /// Player controlled from this game, where the host is player 0, or `None` when spectating.
+    pub local_player: Option<usize>,
+    /// Next frame to simulate.
+    frame: u64,
+    /// Controls of the local player for the upcoming frames, in order.
+    local: VecDeque<ShipControls>,
+    /// Controls of the remote player received so far, by frame.
+    remote: BTreeMap<u64, ShipControls>,
+    /// Controls of both players received so far when spectating, by frame.
+    watched: BTreeMap<u64, [ShipControls; 2]>,
+    /// Spectators of the game, if this game is the host.
+    spectators: Option<Spectators>,
+    /// Mutations picked by the host which haven't been applied yet.
+    picks: VecDeque<usize>,
+    /// If the connection has been lost.
+    disconnected: bool,
+}
+
+impl NetSession {
+    /// Duration of every frame (s).
+    pub const TIMESTEP: f32 = 1.0 / 60.0;
+    /// Number of frames controls are sent ahead of when they're used.
+    const INPUT_DELAY: u64 = 4;
+    /// Number of frames a spectator simulates at most in one update, to catch up with the game.
+    pub const CATCH_UP: usize = 4;
+
+    /// Wait for the other player to connect on the given address, and start a game with the given
+    /// seed.
+    ///
+    /// Spectators connecting before the other player start watching once the game has started.
+    pub fn host(address: &str, seed: u64) -> io::Result<NetSession> {
+        let listener = TcpListener::bind(address)?;
+        info!("waiting for the other player on: {}", listener.local_addr()?);
+
+        let mut early = Vec::new();
+
+        let stream = loop {
+            let (stream, peer) = listener.accept()?;
+
+            match read_join(&stream) {
+                Ok(true) => early.push(stream),
+                Ok(false) => {
+                    info!("player joined from: {}", peer);
+                    break stream;
+                }
+                Err(e) => warn!("failed to accept player: {}", e),
+            }
+        };
+
+        let mut net = NetSession::new(stream, Some(0))?;
+        net.spectators = Some(Spectators::new(listener, early, seed));
+
+        net.send(&Message::Hello {
+            version: VERSION,
+            seed,
+        });
+
+        if net.disconnected {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "player left"));
+        }
+
+        Ok(net)
+    }
+
+    /// Join the game hosted on the given address, returning the seed picked by the host.
+    pub fn join(address: &str) -> io::Result<(NetSession, u64)> {
+        NetSession::connect(address, Some(1))
+    }
+
+    /// Watch the game hosted on the given address, returning the seed picked by the host.
+    pub fn spectate(address: &str) -> io::Result<(NetSession, u64)> {
+        NetSession::connect(address, None)
+    }
+
+    fn connect(address: &str, local_player: Option<usize>) -> io::Result<(NetSession, u64)> {
+        let mut stream = TcpStream::connect(address)?;
+
+        let join = Message::Join {
+            spectator: local_player.is_none(),
+        };
+
+        write_message(&mut stream, &join)?;
+        info!("joined game at: {}", address);
+
+        let mut net = NetSession::new(stream, local_player)?;
+
+        let hello = match net.messages.get_mut() {
+            Ok(messages) => messages.recv().ok(),
+            Err(_) => None,
+        };
+
+        match hello {
+            Some(Message::Hello { version, seed }) if version == VERSION => Ok((net, seed)),
+            Some(Message::Hello { version, .. }) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("host runs version {}, expected {}", version, VERSION),
+            )),
+            Some(_) => Err(io::Error::new(io::ErrorKind::InvalidData, "expected hello")),
+            None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "host left")),
+        }
+    }
+
+    fn new(stream: TcpStream, local_player: Option<usize>) -> io::Result<NetSession> {
+        stream.set_nodelay(true)?;
+        let reader = BufReader::new(stream.try_clone()?);
+        let (sender, messages) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut reader = reader;
+
+            // NB: ends when the connection is lost, which disconnects the channel.
+            while let Ok(message) = bincode::deserialize_from(&mut reader) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        // NB: nothing is sent for the first frames, so they're played without controls.
+        let delay = Self::INPUT_DELAY as usize;
+
+        Ok(NetSession {
+            stream,
+            messages: Mutex::new(messages),
+            local_player,
+            frame: 0,
+            local: vec![ShipControls::default(); delay].into(),
+            remote: (0..Self::INPUT_DELAY)
+                .map(|f| (f, ShipControls::default()))
+                .collect(),
+            watched: BTreeMap::new(),
+            spectators: None,
+            picks: VecDeque::new(),
+            disconnected: false,
+        })
+    }
+
+    /// If this game is only watching.
+    pub fn is_spectating(&self) -> bool {
+        self.local_player.is_none()
+    }
+
+    /// Take the messages received from the other end, and greet any new spectators.
+    pub fn poll(&mut self) {
+        if let Some(spectators) = &mut self.spectators {
+            spectators.poll();
+        }
+
+        loop {
+            let message = match self.messages.get_mut() {
+                Ok(messages) => messages.try_recv(),
+                Err(_) => Err(TryRecvError::Disconnected),
+            };
+
+            match message {
+                Ok(Message::Controls { frame, controls }) => {
+                    self.remote.insert(frame, controls);
+                }
+                Ok(Message::Frame { frame, controls }) => {
+                    self.watched.insert(frame, controls);
+                }
+                Ok(Message::Pick { index }) => self.picks.push_back(index),
+                Ok(message) => warn!("unexpected message from the other end: {:?}", message),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if !self.disconnected {
+                        warn!("lost connection to the other end");
+                        self.disconnected = true;
+                    }
+
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Number of frames which can be simulated right away.
+    ///
+    /// This is more than one when a spectator has fallen behind the game. Once the connection is
+    /// lost, the other ship is left to drift, or both of them when spectating.
+    pub fn frames_ready(&self) -> usize {
+        if self.disconnected {
+            return 1;
+        }
+
+        if self.is_spectating() {
+            self.watched.range(self.frame..).count()
+        } else {
+            self.remote.contains_key(&self.frame) as usize
+        }
+    }
+
+    /// Send the controls of the local player, and take the controls of both players for the next
+    /// frame.
+    ///
+    /// When spectating, the local controls are ignored.
+    pub fn advance(&mut self, local: ShipControls) -> [ShipControls; 2] {
+        let player = match self.local_player {
+            Some(player) => player,
+            None => {
+                let controls = self.watched.remove(&self.frame).unwrap_or_default();
+                self.frame += 1;
+                return controls;
+            }
+        };
+
+        let frame = self.frame + Self::INPUT_DELAY;
+        self.local.push_back(local);
+        self.send(&Message::Controls {
+            frame,
+            controls: local,
+        });
+
+        let local = self.local.pop_front().unwrap_or_default();
+        let remote = self.remote.remove(&self.frame).unwrap_or_default();
+
+        let controls = if player == 0 {
+            [local, remote]
+        } else {
+            [remote, local]
+        };
+
+        if let Some(spectators) = &mut self.spectators {
+            spectators.send(Message::Frame {
+                frame: self.frame,
+                controls,
+            });
+        }
+
+        self.frame += 1;
+        controls
+    }
+
+    /// If this game picks the mutations in drafts.
+    pub fn picks_mutations(&self) -> bool {
+        self.local_player == Some(0) || self.disconnected
+    }
+
+    /// Tell the other player and the spectators which mutation was picked.
+    pub fn send_pick(&mut self, index: usize) {
+        self.send(&Message::Pick { index });
+
+        if let Some(spectators) = &mut self.spectators {
+            spectators.send(Message::Pick { index });
+        }
+    }
+
+    /// Take the next mutation picked by the host, if it has arrived.
+    pub fn next_pick(&mut self) -> Option<usize> {
+        self.picks.pop_front()
+    }
+
+    fn send(&mut self, message: &Message) {
+        if self.disconnected || self.is_spectating() {
+            return;
+        }
+
+        if let Err(e) = write_message(&mut self.stream, message) {
+            warn!("lost connection to the other player: {}", e);
+            self.disconnected = true;
+        }
+    }
+}
+
+fn write_message(stream: &mut TcpStream, message: &Message) -> io::Result<()> {
+    let bytes = bincode::serialize(message)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    stream.write_all(&bytes)
+}
+
+/// Read the message a new connection starts with, returning if it's from a spectator.
+///
+/// NB: unbuffered, since nothing read past the message may be lost.
+fn read_join(stream: &TcpStream) -> io::Result<bool> {
+    let message = bincode::deserialize_from(stream)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+    match message {
+        Message::Join { spectator } => Ok(spectator),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "expected join")),
+    }
+}
@@ -0,0 +1,262 @@
+//! Player profiles, persisted between runs of the game.
+
+use amethyst::{config::Config, utils::application_root_dir};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use std::{io, path::PathBuf};
+
+/// Which kind of controls a player prefers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ControlScheme {
+    Keyboard,
+    Controller,
+}
+
+impl Default for ControlScheme {
+    fn default() -> Self {
+        if cfg!(feature = "sdl_controller") {
+            ControlScheme::Controller
+        } else {
+            ControlScheme::Keyboard
+        }
+    }
+}
+
+/// Statistics of a single finished run.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RunStats {
+    /// Points scored during the run.
+    pub points: u32,
+    /// How long the run lasted (s).
+    pub duration: f32,
+}
+
+/// How a player's ship looks, which has no effect on how it plays.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ShipLook {
+    /// Color the hull is tinted with.
+    pub color: [f32; 4],
+    /// Index of the decal shown on the ship, if any.
+    pub decal: Option<usize>,
+}
+
+impl ShipLook {
+    /// Hull colors to pick between, by name.
+    pub const COLORS: [(&'static str, [f32; 4]); 6] = [
+        ("white", [1.0, 1.0, 1.0, 1.0]),
+        ("red", [1.0, 0.4, 0.4, 1.0]),
+        ("green", [0.5, 1.0, 0.5, 1.0]),
+        ("blue", [0.5, 0.7, 1.0, 1.0]),
+        ("gold", [1.0, 0.85, 0.4, 1.0]),
+        ("violet", [0.8, 0.5, 1.0, 1.0]),
+    ];
+
+    /// Names of the decals to pick between, matching the sprites in `texture/decals`.
+    pub const DECALS: [&'static str; 4] = ["stripe", "cockpit", "band", "wingtips"];
+
+    /// If the hull has been tinted.
+    pub fn is_tinted(&self) -> bool {
+        self.color != Self::COLORS[0].1
+    }
+
+    /// Name of the hull color, if it's one of the colors to pick between.
+    pub fn color_name(&self) -> &'static str {
+        Self::COLORS
+            .iter()
+            .find(|(_, c)| *c == self.color)
+            .map(|(name, _)| *name)
+            .unwrap_or("custom")
+    }
+
+    /// Name of the decal.
+    pub fn decal_name(&self) -> &'static str {
+        self.decal
+            .and_then(|d| Self::DECALS.get(d).cloned())
+            .unwrap_or("none")
+    }
+}
+
+impl Default for ShipLook {
+    fn default() -> Self {
+        Self {
+            color: Self::COLORS[0].1,
+            decal: None,
+        }
+    }
+}
+
+/// A single player profile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// Name of the player.
+    pub name: String,
+    /// Color of the player's ship.
+    pub ship_color: [f32; 4],
+    /// Index of the decal shown on the player's ship, if any.
+    pub decal: Option<usize>,
+    /// Which controls the player prefers.
+    pub controls: ControlScheme,
+    /// Statistics of past runs, oldest first.
+    pub history: Vec<RunStats>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            ship_color: [1.0, 1.0, 1.0, 1.0],
+            decal: None,
+            controls: ControlScheme::default(),
+            history: Vec::new(),
+        }
+    }
+}
+
+impl Profile {
+    /// Maximum number of runs kept in the history of a profile.
+    pub const MAX_HISTORY: usize = 100;
+
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            ..Self::default()
+        }
+    }
+
+    /// Best score across the recorded runs.
+    pub fn best(&self) -> Option<u32> {
+        self.history.iter().map(|run| run.points).max()
+    }
+
+    /// How the player's ship looks.
+    pub fn look(&self) -> ShipLook {
+        ShipLook {
+            color: self.ship_color,
+            decal: self.decal,
+        }
+    }
+}
+
+/// Manages all player profiles, and which one is active.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profiles {
+    /// All known profiles.
+    pub profiles: Vec<Profile>,
+    /// Index of the active profile, if any.
+    pub active: Option<usize>,
+}
+
+impl Profiles {
+    /// Maximum length of a profile name.
+    pub const MAX_NAME: usize = 16;
+
+    /// Path to the profiles file.
+    pub fn path() -> io::Result<PathBuf> {
+        Ok(application_root_dir()?.join("profiles.ron"))
+    }
+
+    /// Read profiles from disk, falling back to no profiles if they are missing or broken.
+    pub fn read() -> Profiles {
+        let path = match Profiles::path() {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("failed to locate profiles: {}", e);
+                return Profiles::default();
+            }
+        };
+
+        if !path.is_file() {
+            return Profiles::default();
+        }
+
+        match Profiles::load_no_fallback(&path) {
+            Ok(profiles) => profiles,
+            Err(e) => {
+                warn!("failed to load profiles: {}: {}", path.display(), e);
+                Profiles::default()
+            }
+        }
+    }
+
+    /// Save profiles to disk.
+    pub fn save(&self) {
+        let path = match Profiles::path() {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("failed to locate profiles: {}", e);
+                return;
+            }
+        };
+
+        match self.write(&path) {
+            Ok(()) => info!("saved profiles: {}", path.display()),
+            Err(e) => warn!("failed to save profiles: {}: {}", path.display(), e),
+        }
+    }
+
+    /// The active profile, if any.
+    pub fn active(&self) -> Option<&Profile> {
+        self.profiles.get(self.active?)
+    }
+
+    /// Make the profile with the given name active, creating it if it doesn't exist.
+    pub fn select(&mut self, name: &str) {
+        let name = name.trim();
+
+        let index = match self.profiles.iter().position(|p| p.name == name) {
+            Some(index) => index,
+            None => {
+                let name = name.chars().take(Self::MAX_NAME).collect();
+                self.profiles.push(Profile::new(name));
+                self.profiles.len() - 1
+            }
+        };
+
+        self.active = Some(index);
+    }
+
+    /// Switch the active profile to the next hull color.
+    pub fn cycle_color(&mut self) {
+        if let Some(profile) = self.active.and_then(|i| self.profiles.get_mut(i)) {
+            let colors = &ShipLook::COLORS;
+            let current = colors.iter().position(|(_, c)| *c == profile.ship_color);
+            let next = current.map(|i| (i + 1) % colors.len()).unwrap_or(0);
+            profile.ship_color = colors[next].1;
+        }
+    }
+
+    /// Switch the active profile to the next decal, where no decal follows the last one.
+    pub fn cycle_decal(&mut self) {
+        if let Some(profile) = self.active.and_then(|i| self.profiles.get_mut(i)) {
+            profile.decal = match profile.decal {
+                None => Some(0),
+                Some(d) if d + 1 < ShipLook::DECALS.len() => Some(d + 1),
+                Some(_) => None,
+            };
+        }
+    }
+
+    /// The control scheme preferred by the active profile.
+    pub fn controls(&self) -> ControlScheme {
+        self.active().map(|p| p.controls).unwrap_or_default()
+    }
+
+    /// Record a finished run in the active profile.
+    ///
+    /// Returns `false` if there is no active profile to record it in.
+    pub fn record(&mut self, stats: RunStats) -> bool {
+        let profile = match self.active.and_then(|i| self.profiles.get_mut(i)) {
+            Some(profile) => profile,
+            None => return false,
+        };
+
+        profile.history.push(stats);
+
+        let excess = profile.history.len().saturating_sub(Profile::MAX_HISTORY);
+        profile.history.drain(..excess);
+        true
+    }
+}
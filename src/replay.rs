@@ -0,0 +1,199 @@
+//! Recording of the inputs of a run, which can be played back to reproduce it.
+//!
+//! Unlike the game log, a replay only holds what the players did: the seed of the run, the
+//! controls of every ship during every frame, and the mutations picked between waves. The game is
+//! configured the same way as when it was recorded while it's played back, so that it reproduces
+//! the run.
+//!
+//! How the ships looked is also recorded, so that they look the same when played back.
+
+use amethyst::utils::application_root_dir;
+use crate::{
+    config::GameConfig, profiles::ShipLook, resources::SessionSettings, systems::ShipControls,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+/// Version of the replay format, bumped whenever it changes.
+const VERSION: u32 = 4;
+
+/// The inputs of a single frame.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Frame {
+    /// Duration of the frame in real time, before any slowing down of time (s).
+    pub delta: f32,
+    /// Controls of each player's ship, indexed by player.
+    pub controls: Vec<ShipControls>,
+}
+
+/// Everything needed to reproduce a run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Replay {
+    /// Seed of the run.
+    pub seed: u64,
+    /// Number of players playing.
+    pub players: usize,
+    /// How the ship of each player looked, indexed by player.
+    pub looks: Vec<ShipLook>,
+    pub frames: Vec<Frame>,
+    /// Index of the mutation picked in each draft, in order.
+    pub picks: Vec<usize>,
+    /// Configuration of the game the run was played with.
+    pub config: GameConfig,
+    /// If asteroids kept drifting in at random, instead of arriving in waves.
+    pub endless: bool,
+    /// Path to the level played, if any.
+    pub level: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReplayFile {
+    version: u32,
+    replay: Replay,
+}
+
+impl Replay {
+    /// Read a recorded replay.
+    pub fn read(path: &Path) -> bincode::Result<Replay> {
+        let file: ReplayFile = bincode::deserialize_from(BufReader::new(File::open(path)?))?;
+
+        if file.version != VERSION {
+            return Err(Box::new(bincode::ErrorKind::Custom(format!(
+                "unsupported version {}, expected {}",
+                file.version, VERSION
+            ))));
+        }
+
+        Ok(file.replay)
+    }
+
+    /// Path to the replay of the run which set the best score.
+    pub fn best_path() -> io::Result<PathBuf> {
+        Ok(application_root_dir()?.join("best_run.replay"))
+    }
+}
+
+/// Records the inputs of the current run.
+///
+/// Every run is recorded, so that the best one can be kept. The replay is only saved to the path
+/// given with `--record` if there is one.
+#[derive(Default)]
+pub struct Recorder {
+    path: Option<PathBuf>,
+    replay: Replay,
+}
+
+impl Recorder {
+    /// Record a run of the given session with the given seed and configuration.
+    pub fn new(session: &SessionSettings, seed: u64, config: GameConfig) -> Self {
+        Self {
+            path: session.record.clone(),
+            replay: Replay {
+                seed,
+                players: session.players(),
+                looks: session.looks.clone(),
+                config,
+                endless: session.endless,
+                level: session.level.clone(),
+                ..Replay::default()
+            },
+        }
+    }
+
+    /// Record the controls of a single frame.
+    pub fn record(&mut self, delta: f32, controls: &[ShipControls]) {
+        self.replay.frames.push(Frame {
+            delta,
+            controls: controls.to_vec(),
+        });
+    }
+
+    /// Record the mutation picked in a draft.
+    pub fn pick(&mut self, index: usize) {
+        self.replay.picks.push(index);
+    }
+
+    /// Save the recorded replay if recording was asked for, replacing any previously saved one.
+    pub fn save(&self) {
+        if let Some(path) = &self.path {
+            self.save_to(path);
+        }
+    }
+
+    /// Save the recorded replay as the replay of the best run.
+    pub fn save_best(&self) {
+        match Replay::best_path() {
+            Ok(path) => self.save_to(&path),
+            Err(e) => warn!("failed to locate best run: {}", e),
+        }
+    }
+
+    fn save_to(&self, path: &Path) {
+        match self.write(path) {
+            Ok(()) => info!("saved replay: {}", path.display()),
+            Err(e) => warn!("failed to save replay: {}: {}", path.display(), e),
+        }
+    }
+
+    fn write(&self, path: &Path) -> bincode::Result<()> {
+        let file = ReplayFile {
+            version: VERSION,
+            replay: self.replay.clone(),
+        };
+
+        let out = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(out, &file)
+    }
+}
+
+/// Plays back a recorded replay, frame by frame.
+///
+/// Inactive unless a replay is being played back.
+#[derive(Default)]
+pub struct Playback {
+    replay: Option<Replay>,
+    /// Index of the next frame.
+    frame: usize,
+    /// Index of the next pick.
+    pick: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay: Some(replay),
+            frame: 0,
+            pick: 0,
+        }
+    }
+
+    /// If a replay is being played back.
+    pub fn is_active(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Duration of the next frame, unless the replay has run out.
+    pub fn delta(&self) -> Option<f32> {
+        self.replay.as_ref()?.frames.get(self.frame).map(|f| f.delta)
+    }
+
+    /// Take the controls of the next frame, unless the replay has run out.
+    pub fn next_controls(&mut self) -> Option<&[ShipControls]> {
+        let frame = self.replay.as_ref()?.frames.get(self.frame)?;
+        self.frame += 1;
+        Some(&frame.controls)
+    }
+
+    /// Take the next recorded pick, unless the replay has run out.
+    pub fn next_pick(&mut self) -> Option<usize> {
+        let pick = self.replay.as_ref()?.picks.get(self.pick).cloned()?;
+        self.pick += 1;
+        Some(pick)
+    }
+}
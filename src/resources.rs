@@ -1,26 +1,82 @@
 use amethyst::{
+    core::{
+        nalgebra::{Point2, Vector2},
+        transform::Transform,
+    },
     ecs::{prelude::Entity, World},
-    renderer::SpriteRender,
+    renderer::{ElementState, Event, Projection, SpriteRender, WindowEvent},
+    ui::FontHandle,
 };
 
-use crate::{components::Bounded, textures::SpriteSheet};
+use crate::{
+    assets::{self, InitError},
+    components::{Bounded, Health, PowerUpKind},
+    config::GameConfig,
+    profiles::ShipLook,
+    replay::Replay,
+    settings::Settings,
+    textures::SpriteSheet,
+    ARENA_HEIGHT, ARENA_WIDTH,
+};
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use serde::Deserialize;
+
+use std::{
+    collections::VecDeque,
+    f32::consts,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 
 pub struct Ships {
     pub sprite_sheet: SpriteSheet,
+    pub velocity_sprite_sheet: SpriteSheet,
+    pub decal_sprite_sheet: SpriteSheet,
 }
 
 impl Ships {
-    pub fn initialize(world: &mut World) {
-        let sprite_sheet = SpriteSheet::from_path(world, "texture/ship");
-        world.add_resource(Ships { sprite_sheet });
+    /// Hull of the ship, matching its triangular sprite, in counter-clockwise order.
+    const HULL: [(f32, f32); 3] = [(0.0, 6.0), (-5.5, -5.0), (5.5, -5.0)];
+
+    pub fn initialize(world: &mut World) -> Result<(), InitError> {
+        let sprite_sheet = SpriteSheet::themed(world, "ship")?;
+        let velocity_sprite_sheet = SpriteSheet::themed(world, "velocity")?;
+        let decal_sprite_sheet = SpriteSheet::themed(world, "decals")?;
+
+        world.add_resource(Ships {
+            sprite_sheet,
+            velocity_sprite_sheet,
+            decal_sprite_sheet,
+        });
+        Ok(())
     }
 
     pub fn new_sprite_render(&self) -> SpriteRender {
         self.sprite_sheet.sprite_render(0)
     }
 
-    pub fn new_bounded(&self) -> Bounded {
-        Bounded::from_local(6.0)
+    /// Sprite for the arrow indicating the velocity of the ship.
+    pub fn new_velocity_sprite_render(&self) -> SpriteRender {
+        self.velocity_sprite_sheet.sprite_render(0)
+    }
+
+    /// Sprite for the decal with the given index, drawn on top of the ship.
+    pub fn new_decal_sprite_render(&self, decal: usize) -> SpriteRender {
+        self.decal_sprite_sheet.sprite_render(decal)
+    }
+
+    /// Bounding volume of the ship, shrunk according to `GameConfig::ship_hitbox_shrink`.
+    pub fn new_bounded(&self, config: &GameConfig) -> Bounded {
+        let scale = 1.0 - config.ship_hitbox_shrink();
+
+        Bounded::from_hull(
+            Self::HULL
+                .iter()
+                .map(|&(x, y)| Point2::new(x * scale, y * scale))
+                .collect(),
+        )
     }
 }
 
@@ -29,9 +85,10 @@ pub struct Bullets {
 }
 
 impl Bullets {
-    pub fn initialize(world: &mut World) {
-        let sprite_sheet = SpriteSheet::from_path(world, "texture/bullet");
+    pub fn initialize(world: &mut World) -> Result<(), InitError> {
+        let sprite_sheet = SpriteSheet::themed(world, "bullet")?;
         world.add_resource(Bullets { sprite_sheet });
+        Ok(())
     }
 
     pub fn new_sprite_render(&self) -> SpriteRender {
@@ -43,17 +100,67 @@ impl Bullets {
     }
 }
 
+/// The size class of an asteroid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsteroidSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl AsteroidSize {
+    /// Points awarded for destroying an asteroid of this size.
+    pub fn points(self) -> u32 {
+        match self {
+            AsteroidSize::Small => 100,
+            AsteroidSize::Medium => 50,
+            AsteroidSize::Large => 20,
+        }
+    }
+
+    /// Color of the score popup when destroying an asteroid of this size.
+    pub fn color(self) -> [f32; 4] {
+        match self {
+            AsteroidSize::Small => [1.0, 0.4, 0.3, 1.0],
+            AsteroidSize::Medium => [1.0, 0.9, 0.3, 1.0],
+            AsteroidSize::Large => [0.6, 0.8, 1.0, 1.0],
+        }
+    }
+}
+
+/// Which sprites asteroid sprites fracture into, loaded from `texture/asteroid_pieces.ron`.
+///
+/// This lives next to the sprite sheet, since the sprite sheet format can't hold any extra data.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AsteroidPieces {
+    /// For each whole asteroid sprite, the sprites of its pieces.
+    ///
+    /// Pieces are ordered counter-clockwise, each covering an equal slice of the asteroid starting
+    /// from its right-hand side.
+    pub pieces: Vec<Vec<usize>>,
+}
+
 pub struct Asteroids {
     pub sprite_sheet: SpriteSheet,
+    pub pieces: AsteroidPieces,
 }
 
 impl Asteroids {
     pub const MIN_RADIUS: f32 = 4.0;
+    /// Number of sprites of whole asteroids, which come first in the sprite sheet.
     pub const NUM_SPRITES: usize = 3;
+    /// Scale of bosses, relative to the smallest asteroid.
+    pub const BOSS_SCALE: f32 = 3.0;
 
-    pub fn initialize(world: &mut World) {
-        let sprite_sheet = SpriteSheet::from_path(world, "texture/asteroids");
-        world.add_resource(Asteroids { sprite_sheet });
+    pub fn initialize(world: &mut World) -> Result<(), InitError> {
+        let sprite_sheet = SpriteSheet::themed(world, "asteroids")?;
+        let pieces = assets::load_ron("texture/asteroid_pieces.ron")?;
+        world.add_resource(Asteroids {
+            sprite_sheet,
+            pieces,
+        });
+        Ok(())
     }
 
     pub fn new_sprite_render(&self, random_gen: &RandomGen) -> SpriteRender {
@@ -61,32 +168,886 @@ impl Asteroids {
         self.sprite_sheet.sprite_render(index)
     }
 
+    /// Sprite for the piece of an asteroid which broke off in the given direction.
+    ///
+    /// The direction is in radians, relative to the rotation of the asteroid. Falls back to a
+    /// random whole asteroid if the sprite has no pieces.
+    pub fn piece_sprite_render(
+        &self,
+        parent: usize,
+        angle: f32,
+        random_gen: &RandomGen,
+    ) -> SpriteRender {
+        let pieces = match self.pieces.pieces.get(parent) {
+            Some(pieces) if !pieces.is_empty() => pieces,
+            _ => return self.new_sprite_render(random_gen),
+        };
+
+        let full = 2.0 * consts::PI;
+        let angle = ((angle % full) + full) % full;
+        let index = (angle / full * pieces.len() as f32) as usize % pieces.len();
+        self.sprite_sheet.sprite_render(pieces[index])
+    }
+
     pub fn new_bounded(&self, scale: f32) -> Bounded {
-        Bounded::from_local(Self::MIN_RADIUS * scale)
+        Bounded::from_local(Self::radius(scale))
+    }
+
+    /// Radius of an asteroid with the given scale.
+    pub fn radius(scale: f32) -> f32 {
+        Self::MIN_RADIUS * scale
+    }
+
+    /// Pitch of sounds made by an asteroid with the given radius, where `1.0` is the original
+    /// pitch.
+    ///
+    /// Smaller asteroids sound higher, so it's possible to hear what an explosion broke up into.
+    pub fn pitch(radius: f32) -> f32 {
+        let reference = Self::MIN_RADIUS * 1.5;
+        (reference / radius).sqrt().max(0.6).min(1.6)
+    }
+
+    /// Number of bullets it takes to destroy an asteroid with the given radius.
+    pub fn hit_points(radius: f32) -> u32 {
+        match Self::size(radius) {
+            AsteroidSize::Large => 2,
+            _ => 1,
+        }
+    }
+
+    /// Classify an asteroid by its radius.
+    pub fn size(radius: f32) -> AsteroidSize {
+        if radius <= Self::MIN_RADIUS {
+            AsteroidSize::Small
+        } else if radius < Self::MIN_RADIUS * 1.5 {
+            AsteroidSize::Medium
+        } else {
+            AsteroidSize::Large
+        }
+    }
+}
+
+pub struct Comets {
+    pub sprite_sheet: SpriteSheet,
+}
+
+impl Comets {
+    /// Radius of a comet.
+    pub const RADIUS: f32 = 3.0;
+    /// Points awarded for destroying a comet.
+    pub const POINTS: u32 = 250;
+    /// Color of the score popup when destroying a comet.
+    pub const COLOR: [f32; 4] = [0.5, 1.0, 1.0, 1.0];
+    /// Chance that a randomly spawned asteroid is a comet.
+    pub const SPAWN_CHANCE: f32 = 0.05;
+    /// Speed of comets (units / s).
+    pub const VELOCITY: f32 = 60.0;
+    /// Maximum lateral acceleration of comets, curving their path (units / s**2).
+    pub const MAX_LATERAL_ACCELERATION: f32 = 30.0;
+    /// Interval at which tail particles are emitted (s).
+    pub const TAIL_INTERVAL: f32 = 0.03;
+
+    pub fn initialize(world: &mut World) -> Result<(), InitError> {
+        let sprite_sheet = SpriteSheet::themed(world, "comet")?;
+        world.add_resource(Comets { sprite_sheet });
+        Ok(())
+    }
+
+    pub fn new_sprite_render(&self) -> SpriteRender {
+        self.sprite_sheet.sprite_render(0)
+    }
+
+    pub fn new_bounded(&self) -> Bounded {
+        Bounded::from_local(Self::RADIUS)
     }
 }
 
-pub struct RandomGen;
+pub struct Ufos {
+    pub sprite_sheet: SpriteSheet,
+}
+
+impl Ufos {
+    /// Radius of a UFO.
+    pub const RADIUS: f32 = 6.0;
+    /// Points awarded for destroying a UFO.
+    pub const POINTS: u32 = 500;
+    /// Color of the score popup when destroying a UFO, and of the bullets they fire.
+    pub const COLOR: [f32; 4] = [0.5, 1.0, 0.5, 1.0];
+    /// Average time between UFOs (s).
+    pub const AVERAGE_SPAWN_TIME: f32 = 30.0;
+    /// Speed at which UFOs cross the arena (units / s).
+    pub const VELOCITY: f32 = 40.0;
+    /// Interval at which UFOs change their vertical course (s).
+    pub const COURSE_INTERVAL: f32 = 1.5;
+    /// Interval at which UFOs fire (s).
+    pub const FIRE_INTERVAL: f32 = 1.2;
+    /// Velocity of the bullets fired by UFOs (units / s).
+    pub const BULLET_VELOCITY: f32 = 90.0;
+    /// Maximum angle by which UFOs miss their aim (radians).
+    pub const INACCURACY: f32 = 0.35;
+
+    pub fn initialize(world: &mut World) -> Result<(), InitError> {
+        let sprite_sheet = SpriteSheet::themed(world, "ufo")?;
+        world.add_resource(Ufos { sprite_sheet });
+        Ok(())
+    }
+
+    pub fn new_sprite_render(&self) -> SpriteRender {
+        self.sprite_sheet.sprite_render(0)
+    }
+
+    pub fn new_bounded(&self) -> Bounded {
+        Bounded::from_local(Self::RADIUS)
+    }
+}
+
+/// Slow drifting debris left behind by destroyed asteroids.
+///
+/// Pebbles are small pieces of the asteroid they came from, so they don't have sprites of their
+/// own.
+pub struct Pebbles;
+
+impl Pebbles {
+    /// Scale of pebbles, relative to the smallest asteroid.
+    pub const SCALE: f32 = 0.4;
+    /// Points awarded for clearing a pebble.
+    pub const POINTS: u32 = 5;
+    /// Color of the score popup when clearing a pebble.
+    pub const COLOR: [f32; 4] = [0.7, 0.7, 0.7, 1.0];
+    /// Least number of pebbles left behind by an asteroid.
+    pub const MIN_COUNT: usize = 2;
+    /// Most number of pebbles left behind by an asteroid.
+    pub const MAX_COUNT: usize = 3;
+    /// Speed at which pebbles drift away from where the asteroid was destroyed (units / s).
+    pub const VELOCITY: f32 = 8.0;
+    /// Fraction of the velocity of the asteroid which pebbles keep.
+    pub const INHERITED_VELOCITY: f32 = 0.2;
+    /// How long pebbles linger before crumbling away (s).
+    pub const TIME_TO_LIVE: f32 = 12.0;
+    /// Largest angle bullets passing through a pebble are deflected by (radians).
+    pub const DEFLECTION: f32 = 0.3;
+
+    pub fn new_bounded() -> Bounded {
+        Bounded::from_local(Asteroids::MIN_RADIUS * Self::SCALE)
+    }
+}
+
+/// Chunks of hull left behind by destroyed bosses.
+///
+/// Like pebbles, chunks are pieces of the boss they came from. They stick around as obstacles
+/// until they are chipped away, or the next boss arrives.
+pub struct Hulls;
+
+impl Hulls {
+    /// Scale of hull chunks, relative to the smallest asteroid.
+    pub const SCALE: f32 = 1.5;
+    /// Number of chunks left behind by a boss.
+    pub const COUNT: usize = 3;
+    /// Hits it takes to chip away a chunk.
+    pub const HIT_POINTS: u32 = 12;
+    /// Points awarded for chipping away a chunk.
+    pub const POINTS: u32 = 50;
+    /// Speed at which chunks drift away from where the boss was destroyed (units / s).
+    pub const VELOCITY: f32 = 3.0;
+    /// Fastest a chunk spins (radians / s).
+    pub const ROTATION: f32 = 0.3;
+    /// Tint of chunks, a burnt out version of the boss.
+    pub const TINT: [f32; 4] = [0.55, 0.5, 0.45, 1.0];
+
+    pub fn new_bounded() -> Bounded {
+        Bounded::from_local(Asteroids::radius(Self::SCALE))
+    }
+}
+
+pub struct Particles {
+    pub sprite_sheet: SpriteSheet,
+}
+
+impl Particles {
+    pub fn initialize(world: &mut World) -> Result<(), InitError> {
+        let sprite_sheet = SpriteSheet::themed(world, "particles")?;
+        world.add_resource(Particles { sprite_sheet });
+        Ok(())
+    }
+
+    pub fn new_sprite_render(&self) -> SpriteRender {
+        self.sprite_sheet.sprite_render(0)
+    }
+
+    /// Sprite for debris scattered by explosions.
+    pub fn new_debris_sprite_render(&self) -> SpriteRender {
+        self.sprite_sheet.sprite_render(1)
+    }
+
+    /// Sprite for the exhaust of a thrusting ship.
+    pub fn new_exhaust_sprite_render(&self) -> SpriteRender {
+        self.sprite_sheet.sprite_render(2)
+    }
+}
+
+pub struct Shockwaves {
+    pub sprite_sheet: SpriteSheet,
+}
+
+impl Shockwaves {
+    /// Radius of the ring in the sprite, used to scale it to the size of a shockwave.
+    pub const SPRITE_RADIUS: f32 = 16.0;
+
+    pub fn initialize(world: &mut World) -> Result<(), InitError> {
+        let sprite_sheet = SpriteSheet::themed(world, "shockwave")?;
+        world.add_resource(Shockwaves { sprite_sheet });
+        Ok(())
+    }
+
+    pub fn new_sprite_render(&self) -> SpriteRender {
+        self.sprite_sheet.sprite_render(0)
+    }
+}
+
+pub struct Planets {
+    pub sprite_sheet: SpriteSheet,
+}
+
+impl Planets {
+    /// Radius of the planet in the sprite, used to scale it to the size of a planet.
+    pub const SPRITE_RADIUS: f32 = 30.0;
+
+    pub fn initialize(world: &mut World) -> Result<(), InitError> {
+        let sprite_sheet = SpriteSheet::themed(world, "planet")?;
+        world.add_resource(Planets { sprite_sheet });
+        Ok(())
+    }
+
+    pub fn new_sprite_render(&self) -> SpriteRender {
+        self.sprite_sheet.sprite_render(0)
+    }
+}
+
+/// Where the ship spawns and respawns.
+#[derive(Debug, Clone, Copy)]
+pub struct SpawnPoint(pub Vector2<f32>);
+
+impl SpawnPoint {
+    /// Distance between the ships of neighbouring players when they spawn (units).
+    pub const SPACING: f32 = 30.0;
+
+    /// Where the ship of the given player spawns, when there are `players` players.
+    ///
+    /// Ships are lined up side by side, centered on the spawn point.
+    pub fn for_player(self, player: usize, players: usize) -> SpawnPoint {
+        let offset = (player as f32 - (players - 1) as f32 / 2.0) * Self::SPACING;
+        SpawnPoint(Vector2::new(self.0.x + offset, self.0.y))
+    }
+}
+
+impl Default for SpawnPoint {
+    fn default() -> Self {
+        SpawnPoint(Vector2::new(ARENA_WIDTH / 2.0, ARENA_HEIGHT / 2.0))
+    }
+}
+
+pub struct PowerUps {
+    pub sprite_sheet: SpriteSheet,
+}
+
+impl PowerUps {
+    /// Chance that a destroyed asteroid drops a power-up.
+    pub const DROP_CHANCE: f32 = 0.05;
+
+    pub fn initialize(world: &mut World) -> Result<(), InitError> {
+        let sprite_sheet = SpriteSheet::themed(world, "powerups")?;
+        world.add_resource(PowerUps { sprite_sheet });
+        Ok(())
+    }
+
+    /// Pick a random kind of power-up to drop.
+    pub fn random_kind(random_gen: &RandomGen) -> PowerUpKind {
+        PowerUpKind::ALL[random_gen.next_usize() % PowerUpKind::ALL.len()]
+    }
+
+    pub fn new_sprite_render(&self, kind: PowerUpKind) -> SpriteRender {
+        match kind {
+            PowerUpKind::Ram => self.sprite_sheet.sprite_render(0),
+            PowerUpKind::Shield => self.sprite_sheet.sprite_render(2),
+            PowerUpKind::RapidFire => self.sprite_sheet.sprite_render(3),
+            PowerUpKind::SpreadShot => self.sprite_sheet.sprite_render(4),
+        }
+    }
+
+    /// Sprite for the battering ram shown in front of a ramming ship.
+    pub fn new_ram_sprite_render(&self) -> SpriteRender {
+        self.sprite_sheet.sprite_render(1)
+    }
+
+    /// Sprite for the bubble shown around a shielded ship.
+    pub fn new_shield_sprite_render(&self) -> SpriteRender {
+        self.sprite_sheet.sprite_render(5)
+    }
+
+    pub fn new_bounded(&self) -> Bounded {
+        Bounded::from_local(5.0)
+    }
+}
+
+/// A splinter waiting to be spawned.
+#[derive(Debug, Clone)]
+pub struct Splinter {
+    /// Where the splinter should be spawned.
+    pub local: Transform,
+    /// The initial velocity of the splinter.
+    pub velocity: Vector2<f32>,
+    /// Sprite of the asteroid the splinter broke off from, and the direction it broke off in
+    /// relative to the rotation of that asteroid (radians).
+    pub piece: Option<(usize, f32)>,
+}
+
+/// Splinters waiting to be spawned.
+///
+/// A frame with a lot of collisions can produce a large number of splinters, so instead of spawning
+/// them all at once they are queued up here and spawned at a limited rate.
+#[derive(Debug, Default)]
+pub struct SplinterQueue {
+    pub queue: VecDeque<Splinter>,
+}
+
+impl SplinterQueue {
+    /// Maximum number of splinters to spawn each frame.
+    pub const BUDGET: usize = 8;
+}
+
+/// Pairs of small asteroids found overlapping by the `CollisionSystem` this frame, when accretion
+/// is enabled.
+#[derive(Debug, Default)]
+pub struct Overlaps {
+    pub pairs: Vec<(Entity, Entity)>,
+}
+
+/// Source of randomness for everything in the game.
+///
+/// Starting from the same seed gives the same sequence of numbers, so runs with the same seed
+/// (`--seed`) play out the same way given the same input. During a run, every consumer draws from
+/// a stream of its own, see `RandomStreams`.
+pub struct RandomGen {
+    rng: Mutex<SmallRng>,
+}
 
 impl RandomGen {
+    pub fn new(seed: u64) -> Self {
+        let mut bytes = <SmallRng as SeedableRng>::Seed::default();
+        let mut state = seed;
+
+        // NB: spread the seed over the whole state of the generator using splitmix64, since it
+        // does poorly with seeds that are mostly zeroes.
+        for chunk in bytes.as_mut().chunks_mut(8) {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+
+            for (i, b) in chunk.iter_mut().enumerate() {
+                *b = (z >> (i * 8)) as u8;
+            }
+        }
+
+        Self {
+            rng: Mutex::new(SmallRng::from_seed(bytes)),
+        }
+    }
+
+    /// Pick a seed for a run which wasn't given one.
+    pub fn random_seed() -> u64 {
+        rand::random()
+    }
+
     /// Generate a random usize.
     pub fn next_usize(&self) -> usize {
-        use rand::Rng;
-        rand::thread_rng().gen::<usize>()
+        self.rng.lock().expect("random lock poisoned").gen::<usize>()
     }
 
     pub fn next_f32(&self) -> f32 {
-        use rand::Rng;
-        rand::thread_rng().gen::<f32>()
+        self.rng.lock().expect("random lock poisoned").gen::<f32>()
+    }
+}
+
+/// Consumers of random numbers during a run, each drawing from a stream of its own.
+///
+/// Systems which don't depend on each other run in whatever order the dispatcher picks. If they
+/// drew from the same generator, which numbers each of them got would change from one run to the
+/// next, even with the same seed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    /// Ships, whether controlled by a player or an external program.
+    Ships,
+    Squeeze,
+    Effects,
+    Spawner,
+    Waves,
+    Script,
+    Collisions,
+    Splinters,
+    Unstable,
+    Magnets,
+    Accretion,
+    Comets,
+    UfoSpawn,
+    UfoAi,
+    /// States, which run in between dispatches.
+    States,
+}
+
+impl Stream {
+    /// Number of streams.
+    const COUNT: usize = 15;
+}
+
+/// Streams of random numbers for a run, all derived from its seed.
+pub struct RandomStreams {
+    streams: Vec<RandomGen>,
+}
+
+impl RandomStreams {
+    pub fn new(seed: u64) -> Self {
+        // NB: `RandomGen` spreads each seed over the state of its generator, so neighbouring
+        // seeds give unrelated streams.
+        let streams = (0..Stream::COUNT as u64)
+            .map(|i| RandomGen::new(seed.wrapping_add(i.wrapping_mul(0x9e37_79b9_7f4a_7c15))))
+            .collect();
+
+        Self { streams }
+    }
+
+    /// The generator of the given stream.
+    pub fn get(&self, stream: Stream) -> &RandomGen {
+        &self.streams[stream as usize]
+    }
+}
+
+/// Settings for a single session of the game, as selected on the command line.
+///
+/// These carry over when the game is restarted.
+#[derive(Debug, Default, Clone)]
+pub struct SessionSettings {
+    /// Developer tools and cheats in use.
+    pub dev: DevTools,
+    /// Skip menus and loading screens, jumping straight into a run.
+    pub quick: bool,
+    /// Two players are playing together.
+    pub coop: bool,
+    /// Color assigned to each player, indexed by player.
+    pub palette: Vec<[f32; 4]>,
+    /// How the ship of each player looks, indexed by player.
+    ///
+    /// Picked from the active profile when a run starts, or from the replay being played back.
+    pub looks: Vec<ShipLook>,
+    /// Export a log of the gameplay events of each run to this path.
+    pub export_log: Option<PathBuf>,
+    /// Play the level at this path.
+    pub level: Option<PathBuf>,
+    /// Seed every run with this, instead of a random seed.
+    pub seed: Option<u64>,
+    /// Asteroids keep drifting in at random, instead of arriving in waves.
+    pub endless: bool,
+    /// Record the inputs of each run as a replay to this path.
+    pub record: Option<PathBuf>,
+    /// Play back the replay at this path, instead of reading inputs from the players.
+    pub replay: Option<PathBuf>,
+    /// Play the daily challenge of this day, see the `daily` module.
+    pub daily: Option<u64>,
+    /// Play the geiger tick, without saving it in the settings (`--geiger`).
+    pub geiger: bool,
+    /// Show the aim assist, without saving it in the settings (`--aim-assist`).
+    pub aim_assist: bool,
+    /// Announce key game events, without saving it in the settings (`--announce`).
+    pub announce: bool,
+    /// Strength of aim snapping, without saving it in the settings (`--aim-snap`).
+    pub aim_snap: Option<f32>,
+}
+
+impl SessionSettings {
+    /// Default colors assigned to players.
+    pub const DEFAULT_PALETTE: [[f32; 4]; 2] = [[0.4, 0.8, 1.0, 1.0], [1.0, 0.6, 0.3, 1.0]];
+
+    /// Number of players playing.
+    pub fn players(&self) -> usize {
+        if self.coop {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// The color used to tint things owned by the given player.
+    ///
+    /// Only tinted in co-op, where it matters who did what. A player who picked a hull color has
+    /// things tinted with it instead of their color in the palette.
+    pub fn player_color(&self, player: usize) -> Option<[f32; 4]> {
+        if !self.coop {
+            return None;
+        }
+
+        match self.looks.get(player) {
+            Some(look) if look.is_tinted() => Some(look.color),
+            _ => self.palette.get(player).cloned(),
+        }
+    }
+
+    /// How the ship of the given player looks, where the hull is tinted by the color of the
+    /// player in co-op.
+    pub fn look(&self, player: usize) -> ShipLook {
+        let mut look = self.looks.get(player).cloned().unwrap_or_default();
+
+        if let Some(color) = self.player_color(player) {
+            look.color = color;
+        }
+
+        look
+    }
+
+    /// If the geiger tick is played, as turned on for the session or in the settings.
+    pub fn geiger(&self, settings: &Settings) -> bool {
+        self.geiger || settings.geiger
+    }
+
+    /// If the aim assist is shown, as turned on for the session or in the settings.
+    pub fn aim_assist(&self, settings: &Settings) -> bool {
+        self.aim_assist || settings.aim_assist
+    }
+
+    /// If key game events are announced, as turned on for the session or in the settings.
+    pub fn announce(&self, settings: &Settings) -> bool {
+        self.announce || settings.announce
+    }
+
+    /// Strength of aim snapping, as given for the session or in the settings, limited to a
+    /// sensible range.
+    pub fn aim_snap(&self, settings: &Settings) -> f32 {
+        self.aim_snap.unwrap_or(settings.aim_snap).max(0.0).min(1.0)
+    }
+
+    /// Settings to play back the given replay, read from the given path.
+    ///
+    /// A replay is played back with the seed, players and mode it was recorded with, and isn't
+    /// recorded again.
+    pub fn for_replay(&self, path: PathBuf, replay: &Replay) -> SessionSettings {
+        SessionSettings {
+            seed: Some(replay.seed),
+            coop: replay.players == 2,
+            looks: replay.looks.clone(),
+            endless: replay.endless,
+            level: replay.level.clone(),
+            quick: true,
+            record: None,
+            replay: Some(path),
+            ..self.clone()
+        }
+    }
+}
+
+/// The bar showing the hit points left on each player's ship.
+pub struct HealthBar {
+    /// Text showing the bar.
+    pub text: Entity,
+}
+
+impl HealthBar {
+    /// The bars of the given health, one for each player.
+    ///
+    /// Players without a ship are shown with an empty bar.
+    pub fn as_text(healths: &[Option<Health>]) -> String {
+        let bars = healths
+            .iter()
+            .map(|health| {
+                let (current, max) = health.map(|h| (h.current, h.max)).unwrap_or((0, 0));
+                let current = current.min(max) as usize;
+
+                format!("[{}{}]", "#".repeat(current), "-".repeat(max as usize - current))
+            }).collect::<Vec<_>>();
+
+        format!("hull: {}", bars.join(" "))
+    }
+}
+
+/// The bar showing the health of the station, in objective mode.
+pub struct StationBar {
+    /// Text showing the bar.
+    pub text: Entity,
+}
+
+impl StationBar {
+    /// The bar of the given health of the station, with how far along the ship furthest along
+    /// docking is, from 0 to 1.
+    ///
+    /// A destroyed station is shown without a bar.
+    pub fn as_text(health: Option<Health>, docking: f32) -> String {
+        let health = match health {
+            Some(health) => health,
+            None => return "station: destroyed".to_string(),
+        };
+
+        let current = health.current.min(health.max) as usize;
+        let bar = format!("[{}{}]", "#".repeat(current), "-".repeat(health.max as usize - current));
+
+        if docking > 0f32 {
+            let percent = (docking.min(1.0) * 100.0).round() as u32;
+            return format!("station: {} docking {}%", bar, percent);
+        }
+
+        format!("station: {}", bar)
+    }
+}
+
+/// The bar showing the energy of each player's ship.
+pub struct EnergyBar {
+    /// Text showing the bar.
+    pub text: Entity,
+}
+
+impl EnergyBar {
+    /// Number of segments in the bar of each ship.
+    pub const SEGMENTS: usize = 20;
+
+    /// The bars of the given energy fractions, one for each player.
+    ///
+    /// Players without a ship are shown with an empty bar.
+    pub fn as_text(fractions: &[Option<f32>]) -> String {
+        let bars = fractions
+            .iter()
+            .map(|fraction| {
+                let fraction = fraction.unwrap_or(0f32).max(0.0).min(1.0);
+                let filled = (fraction * Self::SEGMENTS as f32).round() as usize;
+
+                format!(
+                    "[{}{}]",
+                    "|".repeat(filled),
+                    ".".repeat(Self::SEGMENTS - filled)
+                )
+            }).collect::<Vec<_>>();
+
+        format!("energy: {}", bars.join(" "))
+    }
+}
+
+/// Text showing frame rate and entity counts, when enabled through `DevTools::debug`.
+///
+/// Also shows the measured input latency and the slowest systems, when they're being measured.
+#[derive(Debug)]
+pub struct DebugOverlay {
+    pub text: Entity,
+    /// Lines showing the slowest systems, kept up to date with the `timings` feature.
+    pub timings: String,
+}
+
+/// Text plotting histograms of the radii and speeds of asteroids, shown along with the
+/// `DebugOverlay`.
+#[derive(Debug)]
+pub struct HistogramOverlay {
+    pub text: Entity,
+}
+
+/// Where the camera looks in photo mode.
+#[derive(Debug, Clone, Copy)]
+pub struct PhotoCamera {
+    /// Center of the view, in arena coordinates.
+    pub center: Vector2<f32>,
+    /// How far the camera is zoomed in, where `1.0` shows the whole arena.
+    pub zoom: f32,
+}
+
+impl PhotoCamera {
+    /// How fast the camera pans when fully zoomed out (units / s).
+    pub const PAN_SPEED: f32 = 150.0;
+    /// Factor the zoom changes by every second while zooming.
+    pub const ZOOM_SPEED: f32 = 2.0;
+    /// How far the camera can zoom in.
+    pub const MAX_ZOOM: f32 = 4.0;
+
+    /// Keep the view inside of the arena.
+    pub fn constrain(&mut self) {
+        self.zoom = self.zoom.max(1.0).min(Self::MAX_ZOOM);
+
+        let half_width = ARENA_WIDTH / self.zoom / 2.0;
+        let half_height = ARENA_HEIGHT / self.zoom / 2.0;
+
+        self.center.x = self.center.x.max(half_width).min(ARENA_WIDTH - half_width);
+        self.center.y = self.center.y.max(half_height).min(ARENA_HEIGHT - half_height);
+    }
+
+    /// Projection showing the current view.
+    pub fn projection(&self) -> Projection {
+        let half_width = ARENA_WIDTH / self.zoom / 2.0;
+        let half_height = ARENA_HEIGHT / self.zoom / 2.0;
+
+        Projection::orthographic(
+            self.center.x - half_width,
+            self.center.x + half_width,
+            self.center.y - half_height,
+            self.center.y + half_height,
+        )
+    }
+}
+
+impl Default for PhotoCamera {
+    fn default() -> Self {
+        Self {
+            center: Vector2::new(ARENA_WIDTH / 2.0, ARENA_HEIGHT / 2.0),
+            zoom: 1.0,
+        }
+    }
+}
+
+/// The part of the arena objects are kept inside of, which shrinks while the walls close in.
+#[derive(Debug, Clone, Copy)]
+pub struct ArenaBounds {
+    pub min: Vector2<f32>,
+    pub max: Vector2<f32>,
+    /// Axis along which the edges are solid walls, regardless of the configured `ArenaEdges`, and
+    /// the restitution of the walls.
+    pub walls: Option<(usize, f32)>,
+}
+
+impl ArenaBounds {
+    /// Restitution of the walls along the given axis, if they're solid because of the squeeze.
+    pub fn wall_restitution(&self, axis: usize) -> Option<f32> {
+        match self.walls {
+            Some((a, restitution)) if a == axis => Some(restitution),
+            _ => None,
+        }
+    }
+}
+
+impl Default for ArenaBounds {
+    fn default() -> Self {
+        Self {
+            min: Vector2::new(0.0, 0.0),
+            max: Vector2::new(ARENA_WIDTH, ARENA_HEIGHT),
+            walls: None,
+        }
+    }
+}
+
+/// Progress of the walls closing in from two opposing edges of the arena.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Squeeze {
+    /// Time since the walls last retracted, or since the run started (s).
+    pub idle: f32,
+    /// Axis the walls close in along, and the time since they started to (s), while squeezing.
+    pub active: Option<(usize, f32)>,
+}
+
+/// Progression through the waves of asteroids, unless playing in endless mode.
+pub struct Wave {
+    /// Number of the current wave, starting at 1, or 0 before the first wave has arrived.
+    pub number: u32,
+    /// Time until the next wave arrives, once the current one has been cleared.
+    pub time_to_spawn: Option<f32>,
+    /// If the current wave was just cleared, and the player gets to draft a mutation.
+    pub draft_pending: bool,
+    /// Banner announcing each wave.
+    pub banner: Entity,
+}
+
+impl Wave {
+    /// How long the banner announcing a wave is shown (s).
+    pub const BANNER_TIME: f32 = 2.5;
+
+    pub fn new(banner: Entity) -> Self {
+        Self {
+            number: 0,
+            time_to_spawn: None,
+            draft_pending: false,
+            banner,
+        }
+    }
+}
+
+/// Slows down time for a moment when a player on their last life is about to be hit.
+pub struct BulletTime {
+    /// Charges left during the current wave.
+    pub charges: u32,
+    /// Real time left until time runs at full speed again (s), if slowed down.
+    pub remaining: Option<f32>,
+    /// Text showing the charges left.
+    pub text: Entity,
+}
+
+impl BulletTime {
+    /// Charges available in each wave.
+    pub const CHARGES: u32 = 2;
+    /// How much time is slowed down.
+    pub const SCALE: f32 = 0.5;
+    /// How long time is slowed down for, in real time (s).
+    pub const DURATION: f32 = 1.5;
+    /// How soon an asteroid has to be about to hit the ship to trigger bullet time (s).
+    pub const HORIZON: f32 = 0.6;
+
+    pub fn new(text: Entity) -> Self {
+        Self {
+            charges: Self::CHARGES,
+            remaining: None,
+            text,
+        }
+    }
+
+    pub fn as_text(&self) -> String {
+        format!("bullet time: {}", self.charges)
+    }
+}
+
+/// Developer tools and cheats.
+///
+/// All of these can be enabled from the command line, and most can be toggled while playing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DevTools {
+    /// Player is immortal (`--god`, F2).
+    pub immortal: bool,
+    /// Draw the hitboxes of everything which collides (`--hitboxes`, F4).
+    pub hitboxes: bool,
+    /// Measure input latency (`--measure-latency`).
+    pub measure_latency: bool,
+    /// Show the debug overlay (`--debug`, F1).
+    pub debug: bool,
+}
+
+impl DevTools {
+    /// Get a text describing the dev tools in use.
+    pub fn as_text(&self) -> String {
+        let mut list = Vec::new();
+
+        if self.immortal {
+            list.push("immortal (F2)");
+        }
+
+        if self.hitboxes {
+            list.push("hitboxes (F4)");
+        }
+
+        if self.measure_latency {
+            list.push("latency");
+        }
+
+        if self.debug {
+            list.push("debug (F1)");
+        }
+
+        if list.is_empty() {
+            return String::new();
+        }
+
+        format!("dev: {}", list.join(", "))
     }
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct GameModifiers {
-    /// Player is immortal.
-    pub player_is_immortal: bool,
     /// Player is dead.
     pub player_is_dead: bool,
+    /// Percentage by which the ship hitbox is shrunk.
+    pub ship_hitbox_shrink: u32,
+    /// Volume of the game audio, as a percentage.
+    pub volume: u32,
+    /// All audio is muted.
+    pub muted: bool,
 }
 
 impl GameModifiers {
@@ -94,32 +1055,276 @@ impl GameModifiers {
     pub fn as_text(&self) -> String {
         let mut list = Vec::new();
 
-        if self.player_is_immortal {
-            list.push("immortal (F2)");
+        if self.player_is_dead {
+            list.push(String::from("dead (R to Restart)"));
         }
 
-        if self.player_is_dead {
-            list.push("dead (R to Restart)");
+        if self.ship_hitbox_shrink > 0 {
+            list.push(format!("hitbox -{}%", self.ship_hitbox_shrink));
+        }
+
+        if self.muted {
+            list.push(String::from("muted"));
+        } else if self.volume < 100 {
+            list.push(format!("volume {}%", self.volume));
         }
 
         list.join(", ")
     }
 }
 
+/// Lives left, and the countdown until the ship respawns after being destroyed.
+#[derive(Debug)]
+pub struct Lives {
+    /// Number of lives left for each player, including their current one.
+    pub remaining: Vec<u32>,
+    /// Time until the ship of each player respawns (s), if it's been destroyed.
+    pub respawn_timers: Vec<Option<f32>>,
+    /// Text showing the lives left.
+    pub text: Entity,
+}
+
+impl Lives {
+    /// Lives at the start of a game.
+    pub const STARTING: u32 = 3;
+    /// How long it takes for the ship to respawn after being destroyed (s).
+    pub const RESPAWN_DELAY: f32 = 2.0;
+
+    pub fn new(text: Entity, players: usize) -> Self {
+        Self {
+            remaining: vec![Self::STARTING; players],
+            respawn_timers: vec![None; players],
+            text,
+        }
+    }
+
+    /// Test if every player has run out of lives.
+    pub fn all_lost(&self) -> bool {
+        self.remaining.iter().all(|r| *r == 0)
+    }
+
+    pub fn as_text(&self) -> String {
+        let remaining = self
+            .remaining
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<_>>();
+
+        format!("lives: {}", remaining.join(" | "))
+    }
+}
+
 #[derive(Default)]
 pub struct Game {
     /// Restart the game.
     pub restart: bool,
     /// Pause the game.
     pub pause: bool,
+    /// Return to the main menu.
+    pub menu: bool,
     /// Game modifiers in place.
     pub modifiers: GameModifiers,
 }
 
+/// Rolling accuracy over the most recent shots, which multiplies the points of asteroids.
+///
+/// Shots which haven't been fired yet count as misses, so spraying bullets early on doesn't pay
+/// off.
+#[derive(Debug, Default)]
+pub struct Accuracy {
+    /// Outcome of the most recent shots, oldest first, where `true` is a hit.
+    shots: VecDeque<bool>,
+    /// Number of hits among the most recent shots.
+    hits: usize,
+}
+
+impl Accuracy {
+    /// Number of shots accuracy is tracked over.
+    pub const SHOTS: usize = 20;
+    /// Accuracy above which points are multiplied.
+    pub const THRESHOLD: f32 = 0.5;
+    /// Multiplier at perfect accuracy.
+    pub const MAX_MULTIPLIER: f32 = 2.0;
+
+    /// Record the outcome of a single shot.
+    pub fn record(&mut self, hit: bool) {
+        if self.shots.len() == Self::SHOTS {
+            if let Some(true) = self.shots.pop_front() {
+                self.hits -= 1;
+            }
+        }
+
+        self.shots.push_back(hit);
+
+        if hit {
+            self.hits += 1;
+        }
+    }
+
+    /// Ratio of hits over the most recent shots, from 0 to 1.
+    pub fn ratio(&self) -> f32 {
+        self.hits as f32 / Self::SHOTS as f32
+    }
+
+    /// The current points multiplier, in steps of a tenth.
+    pub fn multiplier(&self) -> f32 {
+        let above = ((self.ratio() - Self::THRESHOLD) / (1.0 - Self::THRESHOLD)).max(0.0);
+        let multiplier = 1.0 + above * (Self::MAX_MULTIPLIER - 1.0);
+        (multiplier * 10.0).round() / 10.0
+    }
+
+    /// Apply the current multiplier to the given points.
+    pub fn apply(&self, points: u32) -> u32 {
+        (points as f32 * self.multiplier()).round() as u32
+    }
+}
+
 #[derive(Debug)]
 pub struct Score {
+    /// Font used for score related text.
+    pub font: FontHandle,
     pub score_text: Entity,
+    /// Number of asteroids destroyed.
     pub asteroids: u32,
+    /// Points scored.
+    pub points: u32,
     pub modifiers_text: Entity,
     pub current_modifiers: GameModifiers,
+    /// Text showing the dev tools in use.
+    pub dev_tools_text: Entity,
+    pub current_dev_tools: DevTools,
+    /// Text showing the best score, which is the current score once it's been beaten.
+    pub best_text: Entity,
+    /// The best score shown.
+    pub best: u32,
+    /// Accuracy of the most recent shots.
+    pub accuracy: Accuracy,
+    /// Text showing the points multiplier from accuracy.
+    pub multiplier_text: Entity,
+    /// The multiplier shown.
+    pub current_multiplier: f32,
+    /// Points scored by each player, which add up to `points`.
+    pub player_points: Vec<u32>,
+    /// Points of each player banked by docking at the station, in objective mode.
+    ///
+    /// Points scored since are lost along with the ship of the player.
+    pub banked: Vec<u32>,
+    /// Text showing the points of each player, only used in co-op.
+    pub players_text: Entity,
+}
+
+impl Score {
+    /// Score milestones are passed every this many points.
+    pub const MILESTONE: u32 = 1000;
+
+    pub fn best_text(best: u32) -> String {
+        format!("best: {}", best)
+    }
+
+    pub fn multiplier_text(multiplier: f32) -> String {
+        format!("x{:.1}", multiplier)
+    }
+
+    /// Text showing the points of each player, which is empty unless there's more than one.
+    pub fn players_text(&self) -> String {
+        if self.player_points.len() < 2 {
+            return String::new();
+        }
+
+        let points = self
+            .player_points
+            .iter()
+            .enumerate()
+            .map(|(player, points)| format!("P{}: {}", player + 1, points))
+            .collect::<Vec<_>>();
+
+        points.join(" | ")
+    }
+}
+
+/// Diagnostics used to measure the latency between pressing shoot and the bullet being rendered.
+///
+/// Only present when the game was started with `--measure-latency`.
+#[derive(Debug)]
+pub struct LatencyProbe {
+    /// Quad flashed when shoot is pressed.
+    pub flash: Entity,
+    /// How long the flash remains visible (s).
+    pub flash_timer: f32,
+    /// When the first button was pressed since the last frame, as received from the window.
+    pub input_at: Option<Instant>,
+    /// When shoot was pressed, waiting to be attached to the bullet spawned the same frame.
+    pub pressed_at: Option<Instant>,
+    /// Number of samples collected.
+    pub samples: u32,
+    /// The most recent sample.
+    pub last: Duration,
+    /// Sum of all samples collected.
+    pub total: Duration,
+}
+
+impl LatencyProbe {
+    /// How long the flash is visible after pressing shoot (s).
+    pub const FLASH_TIME: f32 = 0.05;
+
+    pub fn new(flash: Entity) -> Self {
+        Self {
+            flash,
+            flash_timer: 0f32,
+            input_at: None,
+            pressed_at: None,
+            samples: 0,
+            last: Duration::default(),
+            total: Duration::default(),
+        }
+    }
+
+    /// Note when a button was pressed, as the window event is received.
+    ///
+    /// Only the first press since the last frame is kept, which is when shoot was pressed if it
+    /// was pressed.
+    pub fn stamp(&mut self, event: &Event) {
+        let state = match *event {
+            Event::WindowEvent {
+                event: WindowEvent::KeyboardInput { input, .. },
+                ..
+            } => input.state,
+            Event::WindowEvent {
+                event: WindowEvent::MouseInput { state, .. },
+                ..
+            } => state,
+            _ => return,
+        };
+
+        if state == ElementState::Pressed && self.input_at.is_none() {
+            self.input_at = Some(Instant::now());
+        }
+    }
+
+    /// Record a new latency sample.
+    pub fn record(&mut self, sample: Duration) {
+        self.samples += 1;
+        self.last = sample;
+        self.total += sample;
+    }
+
+    /// Get a text describing the collected samples.
+    pub fn as_text(&self) -> String {
+        if self.samples == 0 {
+            return String::from("latency: n/a");
+        }
+
+        let average = self.total / self.samples;
+
+        format!(
+            "latency: {:.1}ms (avg {:.1}ms, n={})",
+            as_millis(self.last),
+            as_millis(average),
+            self.samples
+        )
+    }
+}
+
+fn as_millis(d: Duration) -> f64 {
+    d.as_secs() as f64 * 1000.0 + f64::from(d.subsec_nanos()) / 1_000_000.0
 }
@@ -0,0 +1,243 @@
+//! A small scheduling layer, which lets bundles declare which states each system runs in.
+
+use amethyst::{
+    config::Config,
+    ecs::prelude::{DispatcherBuilder, Read, Resources, System, SystemData},
+    utils::application_root_dir,
+};
+#[cfg(feature = "timings")]
+use crate::timings::SystemTimings;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "timings")]
+use std::time::Instant;
+use std::{io, path::PathBuf};
+
+/// Identifies a state of the game, for the purpose of scheduling systems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateId {
+    /// The main game is running.
+    Main,
+    /// The game is paused.
+    Paused,
+    /// The main menu is shown, before any game has started.
+    Menu,
+    /// The player has run out of lives, and the final score is shown.
+    GameOver,
+    /// A wave has been cleared, and the player is drafting a mutation.
+    Draft,
+    /// The game is frozen, and the camera can be moved around to take pictures.
+    Photo,
+}
+
+impl StateId {
+    fn bit(self) -> u32 {
+        1 << (self as u32)
+    }
+}
+
+impl Default for StateId {
+    fn default() -> Self {
+        StateId::Main
+    }
+}
+
+/// The state the game is currently in.
+///
+/// Updated by each state as it becomes active.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CurrentState(pub StateId);
+
+/// A set of states.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StateSet(u32);
+
+impl StateSet {
+    /// Construct a set out of the given states.
+    pub fn of(states: &[StateId]) -> StateSet {
+        StateSet(states.iter().fold(0, |set, s| set | s.bit()))
+    }
+
+    /// Test if the set contains the given state.
+    pub fn contains(self, state: StateId) -> bool {
+        self.0 & state.bit() != 0
+    }
+}
+
+/// Wraps a system so that it only runs while the game is in one of the given states.
+///
+/// With the `timings` feature, it also records how long the system takes to run, and which systems
+/// it depends on.
+pub struct Enabled<S> {
+    system: S,
+    states: StateSet,
+    /// Name the system was scheduled with.
+    #[cfg_attr(not(feature = "timings"), allow(dead_code))]
+    name: String,
+    /// Names of the systems it was scheduled to run after.
+    #[cfg_attr(not(feature = "timings"), allow(dead_code))]
+    deps: Vec<String>,
+}
+
+impl<S> Enabled<S> {
+    pub fn new(system: S, states: StateSet, name: &str, deps: &[&str]) -> Self {
+        Self {
+            system,
+            states,
+            name: name.to_string(),
+            deps: deps.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+}
+
+#[cfg(not(feature = "timings"))]
+impl<'a, S> System<'a> for Enabled<S>
+where
+    S: System<'a>,
+    S::SystemData: SystemData<'a>,
+{
+    type SystemData = (Read<'a, CurrentState>, S::SystemData);
+
+    fn run(&mut self, (current, data): Self::SystemData) {
+        if self.states.contains(current.0) {
+            self.system.run(data);
+        }
+    }
+
+    fn setup(&mut self, res: &mut Resources) {
+        <Read<'a, CurrentState> as SystemData>::setup(res);
+        // NB: the wrapped system might do more than set up its data, like registering readers.
+        self.system.setup(res);
+    }
+}
+
+#[cfg(feature = "timings")]
+impl<'a, S> System<'a> for Enabled<S>
+where
+    S: System<'a>,
+    S::SystemData: SystemData<'a>,
+{
+    type SystemData = (Read<'a, CurrentState>, Read<'a, SystemTimings>, S::SystemData);
+
+    fn run(&mut self, (current, timings, data): Self::SystemData) {
+        if self.states.contains(current.0) {
+            let start = Instant::now();
+            self.system.run(data);
+            timings.record(&self.name, start.elapsed());
+        }
+    }
+
+    fn setup(&mut self, res: &mut Resources) {
+        <Read<'a, CurrentState> as SystemData>::setup(res);
+        <Read<'a, SystemTimings> as SystemData>::setup(res);
+        res.fetch::<SystemTimings>().declare(&self.name, &self.deps);
+        self.system.setup(res);
+    }
+}
+
+/// Adds systems to a dispatcher which only run in a given set of states.
+pub struct Schedule<'r, 'a, 'b> {
+    builder: &'r mut DispatcherBuilder<'a, 'b>,
+    states: StateSet,
+}
+
+impl<'r, 'a, 'b> Schedule<'r, 'a, 'b> {
+    /// Schedule systems to run in the given states.
+    pub fn new(builder: &'r mut DispatcherBuilder<'a, 'b>, states: &[StateId]) -> Self {
+        Self {
+            builder,
+            states: StateSet::of(states),
+        }
+    }
+
+    /// Schedule systems to run in a different set of states.
+    pub fn in_states<'s>(&'s mut self, states: &[StateId]) -> Schedule<'s, 'a, 'b> {
+        Schedule {
+            builder: &mut *self.builder,
+            states: StateSet::of(states),
+        }
+    }
+
+    /// Add a system which only runs in the scheduled states.
+    pub fn add<S>(&mut self, system: S, name: &str, dep: &[&str])
+    where
+        S: for<'c> System<'c> + Send + 'a,
+        Enabled<S>: for<'c> System<'c>,
+    {
+        self.builder.add(Enabled::new(system, self.states, name, dep), name, dep);
+    }
+}
+
+/// A suggested way to dispatch the systems of the game, derived from how long they took to run
+/// during a session with the `timings` feature.
+///
+/// Saved on `dump_timings`, and applied at the next launch with `--tuned-dispatch`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DispatchPlan {
+    /// Order to schedule systems in, each after its dependencies and the longest chains first.
+    pub order: Vec<String>,
+    /// The chain of dependent systems which took the longest to run.
+    pub critical_path: Vec<String>,
+    /// Average time all systems took to run each frame (us).
+    pub total: u64,
+    /// Average time the critical path took to run each frame (us).
+    pub critical: u64,
+    /// Number of threads which could be kept busy running systems.
+    pub threads: usize,
+}
+
+impl DispatchPlan {
+    /// Path the plan is saved to.
+    pub fn path() -> io::Result<PathBuf> {
+        Ok(application_root_dir()?.join("dispatch_plan.ron"))
+    }
+
+    /// Read the saved plan, if there is one.
+    pub fn read() -> Option<DispatchPlan> {
+        let path = match DispatchPlan::path() {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("failed to locate dispatch plan: {}", e);
+                return None;
+            }
+        };
+
+        match DispatchPlan::load_no_fallback(&path) {
+            Ok(plan) => Some(plan),
+            Err(e) => {
+                warn!("failed to load dispatch plan: {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Save the plan, replacing any previously saved one.
+    #[cfg_attr(not(feature = "timings"), allow(dead_code))]
+    pub fn save(&self) {
+        let path = match DispatchPlan::path() {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("failed to locate dispatch plan: {}", e);
+                return;
+            }
+        };
+
+        match self.write(&path) {
+            Ok(()) => info!("saved dispatch plan: {}", path.display()),
+            Err(e) => warn!("failed to save dispatch plan: {}: {}", path.display(), e),
+        }
+    }
+
+    /// Report the plan in the log.
+    pub fn log(&self) {
+        info!(
+            "systems take {} us per frame, of which {} us on the critical path: {}",
+            self.total,
+            self.critical,
+            self.critical_path.join(" -> ")
+        );
+        info!("suggested threads for the game dispatcher: {}", self.threads);
+        info!("suggested order of systems in bundle.rs: {}", self.order.join(", "));
+    }
+}
@@ -0,0 +1,212 @@
+//! Scripted timelines of asteroids for levels, for encounters paced by hand.
+//!
+//! A script is a list of lines, each spawning something once its trigger fires:
+//!
+//! ```text
+//! at t=5 spawn 3 large from north
+//! at t=12 spawn 6 small from east
+//! at clear spawn boss
+//! ```
+//!
+//! `at t=SECONDS` fires that many seconds after the level started, and `at clear` fires once every
+//! asteroid has been destroyed. Lines fire in order, so a line never fires before the ones above
+//! it. Regular waves start once the whole script has run.
+
+use crate::resources::Asteroids;
+
+use std::{error, fmt};
+
+/// Size of scripted asteroids.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Size {
+    Small,
+    Medium,
+    Large,
+    /// A single huge asteroid, with hit points to match, which leaves its hull behind.
+    Boss,
+}
+
+impl Size {
+    /// Scale of asteroids of this size.
+    pub fn scale(self) -> f32 {
+        match self {
+            Size::Small => 1.0,
+            Size::Medium => 1.5,
+            Size::Large => 2.0,
+            Size::Boss => Asteroids::BOSS_SCALE,
+        }
+    }
+}
+
+/// Edge of the arena scripted asteroids drift in from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edge {
+    North,
+    South,
+    East,
+    West,
+}
+
+/// When a line of a script fires.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trigger {
+    /// Seconds after the level started.
+    At(f32),
+    /// Once every asteroid has been destroyed.
+    Clear,
+}
+
+/// A single line of a script.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScriptLine {
+    pub trigger: Trigger,
+    /// Number of asteroids spawned.
+    pub count: usize,
+    pub size: Size,
+    pub edge: Edge,
+}
+
+/// Error raised when parsing a script.
+#[derive(Debug)]
+pub struct ParseError {
+    /// Line the error is on, starting at 1.
+    line: usize,
+    message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "script line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// The script of the current level, and how far along it is.
+#[derive(Debug, Default, Clone)]
+pub struct WaveScript {
+    lines: Vec<ScriptLine>,
+    /// Index of the next line to fire.
+    next: usize,
+    /// Time since the level started (s).
+    pub elapsed: f32,
+}
+
+impl WaveScript {
+    /// Parse the lines of a script.
+    pub fn parse<S: AsRef<str>>(lines: &[S]) -> Result<WaveScript, ParseError> {
+        let lines = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                parse_line(line.as_ref()).map_err(|message| ParseError {
+                    line: i + 1,
+                    message,
+                })
+            }).collect::<Result<Vec<_>, _>>()?;
+
+        Ok(WaveScript {
+            lines,
+            next: 0,
+            elapsed: 0f32,
+        })
+    }
+
+    /// Start the script over from its first line.
+    pub fn rewind(&mut self) {
+        self.next = 0;
+        self.elapsed = 0f32;
+    }
+
+    /// If every line of the script has fired.
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.lines.len()
+    }
+
+    /// Take the next line, if its trigger has fired.
+    pub fn next_line(&mut self, cleared: bool) -> Option<ScriptLine> {
+        let line = *self.lines.get(self.next)?;
+
+        let fired = match line.trigger {
+            Trigger::At(t) => self.elapsed >= t,
+            Trigger::Clear => cleared,
+        };
+
+        if !fired {
+            return None;
+        }
+
+        self.next += 1;
+        Some(line)
+    }
+}
+
+/// Parse a single line, like `at t=5 spawn 3 large from north`.
+fn parse_line(line: &str) -> Result<ScriptLine, String> {
+    let mut words = line.split_whitespace();
+
+    let mut expect = |what: &str| {
+        words
+            .next()
+            .ok_or_else(|| format!("expected {}, but the line ended", what))
+    };
+
+    if expect("`at`")? != "at" {
+        return Err("expected line to start with `at`".to_string());
+    }
+
+    let trigger = match expect("trigger")? {
+        "clear" => Trigger::Clear,
+        trigger if trigger.starts_with("t=") => match trigger[2..].parse::<f32>() {
+            Ok(t) if t >= 0f32 => Trigger::At(t),
+            _ => return Err(format!("bad time: {}", &trigger[2..])),
+        },
+        other => return Err(format!("expected `t=SECONDS` or `clear`, but got `{}`", other)),
+    };
+
+    if expect("`spawn`")? != "spawn" {
+        return Err("expected `spawn` after the trigger".to_string());
+    }
+
+    let (count, size) = match expect("count")? {
+        "boss" => (1, Size::Boss),
+        count => {
+            let count = count
+                .parse::<usize>()
+                .map_err(|_| format!("bad count: {}", count))?;
+
+            let size = match expect("size")? {
+                "small" => Size::Small,
+                "medium" => Size::Medium,
+                "large" => Size::Large,
+                other => return Err(format!("unknown size: {}", other)),
+            };
+
+            (count, size)
+        }
+    };
+
+    let edge = match words.next() {
+        None => Edge::North,
+        Some("from") => match words.next() {
+            Some("north") => Edge::North,
+            Some("south") => Edge::South,
+            Some("east") => Edge::East,
+            Some("west") => Edge::West,
+            Some(other) => return Err(format!("unknown edge: {}", other)),
+            None => return Err("expected edge, but the line ended".to_string()),
+        },
+        Some(other) => return Err(format!("expected `from` or the end, but got `{}`", other)),
+    };
+
+    if let Some(other) = words.next() {
+        return Err(format!("unexpected `{}` at the end", other));
+    }
+
+    Ok(ScriptLine {
+        trigger,
+        count,
+        size,
+        edge,
+    })
+}
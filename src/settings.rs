@@ -0,0 +1,310 @@
+use amethyst::{config::Config, renderer::DisplayConfig, utils::application_root_dir};
+use crate::{input::BindingProfile, resources::SessionSettings, theme::Theme};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use std::{io, path::PathBuf};
+
+/// Geometry of the game window.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowSettings {
+    /// Position of the window, if known.
+    pub position: Option<(f64, f64)>,
+    /// Size of the window.
+    pub size: (f64, f64),
+    /// If the window is fullscreen.
+    pub fullscreen: bool,
+}
+
+/// Volume of the game audio.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    /// Master volume, from `0.0` to `1.0`.
+    pub volume: f32,
+    /// If all audio is muted.
+    pub muted: bool,
+    /// How many times sound effects may play in a short window.
+    pub budgets: SfxBudgets,
+}
+
+impl AudioSettings {
+    /// How much the volume changes with each press of `volume_up` or `volume_down`.
+    pub const STEP: f32 = 0.1;
+
+    /// Change the volume by the given amount, which also unmutes the audio.
+    pub fn adjust(&mut self, delta: f32) {
+        let volume = ((self.volume + delta) / Self::STEP).round() * Self::STEP;
+        self.volume = volume.max(0.0).min(1.0);
+        self.muted = false;
+    }
+
+    /// Turn the volume up a step, starting over from silent after full volume.
+    pub fn cycle(&mut self) {
+        if self.volume >= 1.0 - Self::STEP / 2.0 {
+            self.volume = 0.0;
+            self.muted = false;
+        } else {
+            self.adjust(Self::STEP);
+        }
+    }
+
+    /// The volume audio is played at, taking muting into account.
+    pub fn effective_volume(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume.max(0.0).min(1.0)
+        }
+    }
+
+    /// The volume as a percentage.
+    pub fn percent(&self) -> u32 {
+        (self.volume.max(0.0).min(1.0) * 100.0).round() as u32
+    }
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            muted: false,
+            budgets: SfxBudgets::default(),
+        }
+    }
+}
+
+/// The most times each category of sound effects may play within `SfxQueue::WINDOW`.
+///
+/// Sounds over budget are dropped, keeping the loudest and nearest ones.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SfxBudgets {
+    /// Asteroids colliding with something.
+    pub collision: usize,
+    /// Things blowing up.
+    pub explosion: usize,
+}
+
+impl Default for SfxBudgets {
+    fn default() -> Self {
+        Self {
+            collision: 3,
+            explosion: 4,
+        }
+    }
+}
+
+/// Colors assigned to players, for telling them apart in co-op.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Palette {
+    /// Light blue and orange.
+    Default,
+    /// Sky blue and yellow, which stay apart with red-green color blindness.
+    Colorblind,
+}
+
+impl Palette {
+    /// Color of each player, indexed by player.
+    pub fn colors(self) -> [[f32; 4]; 2] {
+        match self {
+            Palette::Default => SessionSettings::DEFAULT_PALETTE,
+            Palette::Colorblind => [[0.35, 0.7, 0.9, 1.0], [0.95, 0.9, 0.25, 1.0]],
+        }
+    }
+
+    /// Name of the palette, as shown in menus.
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Default => "default",
+            Palette::Colorblind => "colorblind",
+        }
+    }
+
+    /// The other palette.
+    pub fn toggle(self) -> Palette {
+        match self {
+            Palette::Default => Palette::Colorblind,
+            Palette::Colorblind => Palette::Default,
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::Default
+    }
+}
+
+/// Settings persisted between runs of the game.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Window geometry from the last run.
+    pub window: Option<WindowSettings>,
+    /// Play a ticking sound which speeds up as asteroids get closer to the ship.
+    pub geiger: bool,
+    /// Show a dotted line predicting the trajectory of bullets.
+    pub aim_assist: bool,
+    /// Show faint lines predicting the trajectories of asteroids.
+    pub trajectories: bool,
+    /// Announce key game events in text, for use with a screen reader.
+    pub announce: bool,
+    /// How strongly the ship's rotation is pulled toward the nearest asteroid when a shot is
+    /// nearly lined up, from `0.0` (off) to `1.0`.
+    pub aim_snap: f32,
+    /// Volume of the game audio.
+    pub audio: AudioSettings,
+    /// How much larger the UI is drawn than it would be otherwise.
+    pub ui_scale: f32,
+    /// Colors assigned to players.
+    pub palette: Palette,
+    /// Name of the theme pack, see `Themes`.
+    pub theme: String,
+    /// Binding profiles players can pick between.
+    pub binding_profiles: Vec<BindingProfile>,
+    /// Name of the binding profile picked by each player, indexed by player.
+    ///
+    /// Players who haven't picked one use the profile with their index.
+    pub player_bindings: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            window: None,
+            geiger: false,
+            aim_assist: false,
+            trajectories: false,
+            announce: false,
+            aim_snap: 0.0,
+            audio: AudioSettings::default(),
+            ui_scale: 1.0,
+            palette: Palette::default(),
+            theme: Theme::default().name,
+            binding_profiles: BindingProfile::builtin(),
+            player_bindings: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// UI scales which can be picked, from smallest to largest.
+    pub const UI_SCALES: [f32; 4] = [0.75, 1.0, 1.25, 1.5];
+
+    /// Scale of the UI, limited to a sensible range.
+    pub fn ui_scale(&self) -> f32 {
+        self.ui_scale.max(Self::UI_SCALES[0]).min(Self::UI_SCALES[3])
+    }
+
+    /// Switch over to the next UI scale, starting over from the smallest after the largest.
+    pub fn cycle_ui_scale(&mut self) {
+        let current = self.ui_scale();
+
+        self.ui_scale = Self::UI_SCALES
+            .iter()
+            .cloned()
+            .find(|s| *s > current + 0.01)
+            .unwrap_or(Self::UI_SCALES[0]);
+    }
+
+    /// Index of the binding profile used by the given player, if it exists.
+    fn binding_index(&self, player: usize) -> Option<usize> {
+        match self.player_bindings.get(player).filter(|name| !name.is_empty()) {
+            Some(name) => self.binding_profiles.iter().position(|p| p.name == *name),
+            None => Some(player).filter(|i| *i < self.binding_profiles.len()),
+        }
+    }
+
+    /// The binding profile used by the given player, if any.
+    pub fn bindings(&self, player: usize) -> Option<&BindingProfile> {
+        self.binding_profiles.get(self.binding_index(player)?)
+    }
+
+    /// Switch the given player over to the next binding profile.
+    pub fn cycle_bindings(&mut self, player: usize) {
+        if self.binding_profiles.is_empty() {
+            return;
+        }
+
+        let next = self
+            .binding_index(player)
+            .map(|i| (i + 1) % self.binding_profiles.len())
+            .unwrap_or(0);
+
+        if self.player_bindings.len() <= player {
+            self.player_bindings.resize(player + 1, String::new());
+        }
+
+        self.player_bindings[player] = self.binding_profiles[next].name.clone();
+    }
+
+    /// Path to the settings file.
+    pub fn path() -> io::Result<PathBuf> {
+        Ok(application_root_dir()?.join("settings.ron"))
+    }
+
+    /// If settings have been saved before, which they haven't the first time the game runs.
+    pub fn exists() -> bool {
+        Settings::path().map(|p| p.is_file()).unwrap_or(false)
+    }
+
+    /// Read settings from disk, falling back to defaults if they are missing or broken.
+    pub fn read() -> Settings {
+        let path = match Settings::path() {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("failed to locate settings: {}", e);
+                return Settings::default();
+            }
+        };
+
+        if !path.is_file() {
+            return Settings::default();
+        }
+
+        match Settings::load_no_fallback(&path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                warn!("failed to load settings: {}: {}", path.display(), e);
+                Settings::default()
+            }
+        }
+    }
+
+    /// Save settings to disk.
+    pub fn save(&self) {
+        let path = match Settings::path() {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("failed to locate settings: {}", e);
+                return;
+            }
+        };
+
+        match self.write(&path) {
+            Ok(()) => info!("saved settings: {}", path.display()),
+            Err(e) => warn!("failed to save settings: {}: {}", path.display(), e),
+        }
+    }
+
+    /// Override the display configuration with persisted settings.
+    ///
+    /// If there are no persisted window settings, they are seeded from the display configuration.
+    pub fn apply_to_display_config(&mut self, config: &mut DisplayConfig) {
+        match self.window {
+            Some(window) => {
+                let (width, height) = window.size;
+                config.dimensions = Some((width as u32, height as u32));
+                config.fullscreen = window.fullscreen;
+            }
+            None => {
+                self.window = config.dimensions.map(|(width, height)| WindowSettings {
+                    position: None,
+                    size: (f64::from(width), f64::from(height)),
+                    fullscreen: config.fullscreen,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Outlines of the things in the arena, drawn in software.
+//!
+//! Used by the god view and by clips, which both draw the arena away from the main renderer.
+
+/// Outline of an object, in arena coordinates.
+#[derive(Debug, Clone)]
+pub enum Shape {
+    /// A circle, with a center and radius.
+    Circle([f32; 2], f32),
+    /// A closed polygon.
+    Polygon(Vec<[f32; 2]>),
+}
+
+/// A single object in a sketch.
+#[derive(Debug, Clone)]
+pub struct Object {
+    pub shape: Shape,
+    /// Center of the object.
+    pub position: [f32; 2],
+    /// Velocity (units / s).
+    pub velocity: [f32; 2],
+    /// Color of the outline, as `0RGB`.
+    pub color: u32,
+}
+
+/// Everything in a single frame of a sketch.
+#[derive(Debug, Clone, Default)]
+pub struct Frame {
+    /// Size of the arena.
+    pub arena: [f32; 2],
+    pub objects: Vec<Object>,
+}
+
+/// A square buffer of pixels drawn in software.
+pub struct Canvas {
+    pub size: usize,
+    /// Pixels as `0RGB`, row by row from the top.
+    pub pixels: Vec<u32>,
+}
+
+impl Canvas {
+    /// Number of line segments used to draw circles.
+    const SEGMENTS: usize = 16;
+
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            pixels: vec![0; size * size],
+        }
+    }
+
+    pub fn clear(&mut self, color: u32) {
+        for p in &mut self.pixels {
+            *p = color;
+        }
+    }
+
+    /// Draw the outline of a shape, with points projected from arena coordinates onto the canvas.
+    pub fn outline(&mut self, shape: &Shape, project: impl Fn([f32; 2]) -> (i32, i32), color: u32) {
+        match *shape {
+            Shape::Circle(center, radius) => {
+                let step = 2.0 * std::f32::consts::PI / Self::SEGMENTS as f32;
+
+                let points = (0..Self::SEGMENTS).map(|i| {
+                    let angle = step * i as f32;
+                    project([
+                        center[0] + radius * angle.cos(),
+                        center[1] + radius * angle.sin(),
+                    ])
+                });
+
+                self.polygon(points, color);
+            }
+            Shape::Polygon(ref points) => {
+                self.polygon(points.iter().map(|p| project(*p)), color);
+            }
+        }
+    }
+
+    /// Draw a closed polygon through the given points.
+    pub fn polygon(&mut self, points: impl Iterator<Item = (i32, i32)>, color: u32) {
+        let points = points.collect::<Vec<_>>();
+
+        for (i, a) in points.iter().enumerate() {
+            self.line(*a, points[(i + 1) % points.len()], color);
+        }
+    }
+
+    /// Draw a line using Bresenham's algorithm, clipped to the canvas.
+    pub fn line(&mut self, (mut x, mut y): (i32, i32), (x1, y1): (i32, i32), color: u32) {
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let sx = if x < x1 { 1 } else { -1 };
+        let sy = if y < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+
+        loop {
+            self.plot(x, y, color);
+
+            if x == x1 && y == y1 {
+                break;
+            }
+
+            let e2 = 2 * err;
+
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    fn plot(&mut self, x: i32, y: i32, color: u32) {
+        let size = self.size as i32;
+
+        if x < 0 || y < 0 || x >= size || y >= size {
+            return;
+        }
+
+        self.pixels[y as usize * self.size + x as usize] = color;
+    }
+}
@@ -0,0 +1,351 @@
+//! Pluggable strategies for spawning asteroids in endless mode.
+//!
+//! Each frame, the active `Spawner` is shown a snapshot of the world and decides what to spawn.
+//! Which spawner is used is picked with `SpawnPolicy::spawner` in `resources/game.ron`:
+//!
+//! * `Random` spawns asteroids and the odd comet by the top edge at random intervals, if comets
+//!   are enabled.
+//! * `Bursts` spawns bursts of asteroids at fixed intervals, growing like waves do.
+//! * `Scripted` plays a script like the ones of levels, over and over.
+//! * `Density` spawns asteroids whenever they cover too little of the arena.
+
+use amethyst::core::nalgebra::{Vector2, Vector3};
+use crate::{
+    config::{GameConfig, SpawnerKind},
+    resources::{Asteroids, Comets, RandomGen},
+    script::{Edge, Size, WaveScript},
+    systems::{roll_asteroid, AsteroidRoll, WaveSystem},
+    ARENA_HEIGHT, ARENA_WIDTH,
+};
+use log::{info, warn};
+
+use std::f32::consts;
+
+/// What the world looks like to a spawner.
+pub struct Snapshot<'a> {
+    /// Time since the last frame (s).
+    pub delta: f32,
+    /// Position and radius of every asteroid in the arena.
+    pub asteroids: &'a [(Vector2<f32>, f32)],
+    /// Position of every ship.
+    pub ships: &'a [Vector2<f32>],
+    /// If every asteroid has been destroyed, including those about to splinter.
+    pub cleared: bool,
+}
+
+/// Something a spawner decided to spawn.
+#[derive(Debug, Clone, Copy)]
+pub enum Spawn {
+    /// A comet at the given position.
+    Comet(Vector3<f32>),
+    /// An asteroid.
+    Asteroid(AsteroidRoll),
+}
+
+/// Decides when, where and what to spawn.
+pub trait Spawner: Send + Sync {
+    /// Name of the spawner, for logging.
+    fn name(&self) -> &'static str;
+
+    /// Decide what to spawn during the current frame, adding it to `spawns`.
+    fn spawn(
+        &mut self,
+        snapshot: &Snapshot,
+        rand: &RandomGen,
+        config: &GameConfig,
+        spawns: &mut Vec<Spawn>,
+    );
+}
+
+/// The spawner used by the current run.
+pub struct ActiveSpawner(pub Box<dyn Spawner>);
+
+impl ActiveSpawner {
+    /// Set up the spawner picked in the configuration.
+    ///
+    /// Falls back to the random spawner if the picked one is broken.
+    pub fn from_config(config: &GameConfig) -> ActiveSpawner {
+        let spawner: Box<dyn Spawner> = match &config.spawns.spawner {
+            SpawnerKind::Random => Box::new(RandomSpawner::default()),
+            SpawnerKind::Bursts { interval } => Box::new(BurstSpawner::new(config, *interval)),
+            SpawnerKind::Scripted { script } => match WaveScript::parse(script) {
+                Ok(script) => Box::new(ScriptedSpawner { script }),
+                Err(e) => {
+                    warn!("failed to parse spawn script: {}", e);
+                    Box::new(RandomSpawner::default())
+                }
+            },
+            SpawnerKind::Density { target } => Box::new(DensitySpawner::new(config, *target)),
+        };
+
+        info!("spawning asteroids with the {} spawner", spawner.name());
+        ActiveSpawner(spawner)
+    }
+}
+
+/// Spawns asteroids and the odd comet by the top edge, at random intervals.
+#[derive(Default)]
+pub struct RandomSpawner {
+    /// Time until the next spawn, or `None` before the first one is scheduled.
+    time_to_spawn: Option<f32>,
+}
+
+impl RandomSpawner {
+    /// Roll what to spawn next, and where.
+    pub fn roll(rand: &RandomGen, config: &GameConfig) -> Spawn {
+        let position = Vector3::new(rand.next_f32() * ARENA_HEIGHT, ARENA_WIDTH, 0.0);
+
+        // NB: always rolled, so that without comets their spawns are taken by asteroids instead.
+        let comet = rand.next_f32() < Comets::SPAWN_CHANCE;
+
+        if comet && config.experimental.comets {
+            return Spawn::Comet(config.edges.contain(position, Comets::RADIUS));
+        }
+
+        Spawn::Asteroid(roll_asteroid(rand, config, position, config.spawns.max_velocity))
+    }
+}
+
+impl Spawner for RandomSpawner {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn spawn(
+        &mut self,
+        snapshot: &Snapshot,
+        rand: &RandomGen,
+        config: &GameConfig,
+        spawns: &mut Vec<Spawn>,
+    ) {
+        let policy = &config.spawns;
+
+        let time_to_spawn = self.time_to_spawn.get_or_insert(policy.initial_delay);
+        *time_to_spawn -= snapshot.delta;
+
+        if *time_to_spawn <= 0f32 {
+            spawns.push(Self::roll(rand, config));
+            *time_to_spawn = rand.next_f32() * policy.average_time;
+        }
+    }
+}
+
+/// Spawns bursts of asteroids at fixed intervals, sized like waves without having to be cleared.
+pub struct BurstSpawner {
+    /// Time between bursts (s).
+    interval: f32,
+    /// Time until the next burst (s).
+    time_to_burst: f32,
+    /// Number of bursts so far.
+    bursts: u32,
+}
+
+impl BurstSpawner {
+    fn new(config: &GameConfig, interval: f32) -> Self {
+        Self {
+            interval: interval.max(1.0),
+            time_to_burst: config.spawns.initial_delay,
+            bursts: 0,
+        }
+    }
+}
+
+impl Spawner for BurstSpawner {
+    fn name(&self) -> &'static str {
+        "bursts"
+    }
+
+    fn spawn(
+        &mut self,
+        snapshot: &Snapshot,
+        rand: &RandomGen,
+        config: &GameConfig,
+        spawns: &mut Vec<Spawn>,
+    ) {
+        self.time_to_burst -= snapshot.delta;
+
+        if self.time_to_burst > 0f32 {
+            return;
+        }
+
+        self.time_to_burst += self.interval;
+        self.bursts += 1;
+
+        for _ in 0..config.waves.count(self.bursts) {
+            spawns.push(Spawn::Asteroid(WaveSystem::roll(rand, config, self.bursts)));
+        }
+    }
+}
+
+/// Plays a script like the ones of levels, starting over once it has run and every asteroid has
+/// been destroyed.
+pub struct ScriptedSpawner {
+    script: WaveScript,
+}
+
+impl Spawner for ScriptedSpawner {
+    fn name(&self) -> &'static str {
+        "scripted"
+    }
+
+    fn spawn(
+        &mut self,
+        snapshot: &Snapshot,
+        rand: &RandomGen,
+        config: &GameConfig,
+        spawns: &mut Vec<Spawn>,
+    ) {
+        if self.script.is_finished() {
+            if !snapshot.cleared {
+                return;
+            }
+
+            self.script.rewind();
+        }
+
+        self.script.elapsed += snapshot.delta;
+        let mut cleared = snapshot.cleared;
+
+        while let Some(line) = self.script.next_line(cleared) {
+            for _ in 0..line.count {
+                let roll = roll_from_edge(rand, config, line.size, line.edge);
+                spawns.push(Spawn::Asteroid(roll));
+            }
+
+            // NB: asteroids spawned this frame aren't in the snapshot.
+            cleared = cleared && line.count == 0;
+        }
+    }
+}
+
+/// Spawns asteroids whenever they cover less than a target fraction of the arena, from the edge
+/// furthest away from the ships.
+pub struct DensitySpawner {
+    /// Fraction of the arena to keep covered.
+    target: f32,
+    /// Time until the coverage is checked again (s).
+    time_to_check: f32,
+}
+
+impl DensitySpawner {
+    /// How often the coverage is checked (s).
+    const CHECK_INTERVAL: f32 = 0.25;
+
+    fn new(config: &GameConfig, target: f32) -> Self {
+        Self {
+            target: target.max(0.0).min(0.5),
+            time_to_check: config.spawns.initial_delay,
+        }
+    }
+
+    /// The edge furthest away from the ships.
+    fn furthest_edge(ships: &[Vector2<f32>]) -> Edge {
+        if ships.is_empty() {
+            return Edge::North;
+        }
+
+        let center = ships.iter().fold(Vector2::new(0.0, 0.0), |a, s| a + s) / ships.len() as f32;
+
+        let edges = [
+            (Edge::North, ARENA_HEIGHT - center.y),
+            (Edge::South, center.y),
+            (Edge::East, ARENA_WIDTH - center.x),
+            (Edge::West, center.x),
+        ];
+
+        edges
+            .iter()
+            .fold((Edge::North, 0f32), |a, e| if e.1 > a.1 { *e } else { a })
+            .0
+    }
+}
+
+impl Spawner for DensitySpawner {
+    fn name(&self) -> &'static str {
+        "density"
+    }
+
+    fn spawn(
+        &mut self,
+        snapshot: &Snapshot,
+        rand: &RandomGen,
+        config: &GameConfig,
+        spawns: &mut Vec<Spawn>,
+    ) {
+        self.time_to_check -= snapshot.delta;
+
+        if self.time_to_check > 0f32 {
+            return;
+        }
+
+        self.time_to_check += Self::CHECK_INTERVAL;
+
+        let covered = snapshot
+            .asteroids
+            .iter()
+            .map(|(_, r)| consts::PI * r * r)
+            .sum::<f32>();
+
+        if covered / (ARENA_WIDTH * ARENA_HEIGHT) >= self.target {
+            return;
+        }
+
+        let size = match rand.next_usize() % 3 {
+            0 => Size::Small,
+            1 => Size::Medium,
+            _ => Size::Large,
+        };
+
+        let edge = Self::furthest_edge(snapshot.ships);
+        spawns.push(Spawn::Asteroid(roll_from_edge(rand, config, size, edge)));
+    }
+}
+
+/// Roll an asteroid of the given size drifting in from the given edge.
+pub fn roll_from_edge(
+    rand: &RandomGen,
+    config: &GameConfig,
+    size: Size,
+    edge: Edge,
+) -> AsteroidRoll {
+    let along = rand.next_f32();
+
+    let (position, normal) = match edge {
+        Edge::North => (Vector3::new(along * ARENA_WIDTH, ARENA_HEIGHT, 0.0), (0.0, -1.0)),
+        Edge::South => (Vector3::new(along * ARENA_WIDTH, 0.0, 0.0), (0.0, 1.0)),
+        Edge::East => (Vector3::new(ARENA_WIDTH, along * ARENA_HEIGHT, 0.0), (-1.0, 0.0)),
+        Edge::West => (Vector3::new(0.0, along * ARENA_HEIGHT, 0.0), (1.0, 0.0)),
+    };
+
+    let scale = size.scale();
+    let position = config.edges.contain(position, Asteroids::radius(scale));
+
+    // drifts inwards, with some spread to the sides.
+    let normal = Vector2::new(normal.0, normal.1);
+    let tangent = Vector2::new(-normal.y, normal.x);
+    let speed = config.waves.velocity(1);
+    let velocity = normal * speed * (0.5 + rand.next_f32() * 0.5)
+        + tangent * speed * (rand.next_f32() - 0.5);
+
+    let rotation = config.spin.spin(normal, velocity, rand);
+
+    // NB: bosses are never unstable or magnetized.
+    let (fuse, magnetized) = match size {
+        Size::Boss => (None, false),
+        _ => {
+            let fuse = config.unstable.and_then(|u| u.roll(rand));
+            let magnetized = fuse.is_none() && config.magnetized.map_or(false, |m| m.roll(rand));
+            (fuse, magnetized)
+        }
+    };
+
+    AsteroidRoll {
+        position,
+        scale,
+        velocity,
+        rotation,
+        fuse,
+        magnetized,
+        boss: size == Size::Boss,
+    }
+}
@@ -0,0 +1,246 @@
+//! An offline audit of the asteroid spawners, run with `--simulate-spawns N`.
+//!
+//! Only the logic deciding where and how asteroids spawn is run, without opening a window or
+//! loading any assets. The distribution of spawns is reported to the console, and every spawn is
+//! written to `spawns.csv` for closer analysis.
+
+use amethyst::{
+    core::nalgebra::{Vector2, Vector3},
+    utils::application_root_dir,
+};
+use crate::{
+    config::GameConfig,
+    resources::{AsteroidSize, Asteroids, RandomGen},
+    spawner::{RandomSpawner, Spawn},
+    systems::{AsteroidRoll, WaveSystem},
+    ARENA_HEIGHT, ARENA_WIDTH,
+};
+use log::{error, info};
+
+use std::{
+    cmp::Ordering,
+    f32::consts,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// A single simulated spawn.
+#[derive(Debug, Clone, Copy)]
+pub struct SpawnSample {
+    /// Name of the spawner.
+    pub spawner: &'static str,
+    /// The wave the spawn belongs to, if spawned in waves.
+    pub wave: Option<u32>,
+    pub position: Vector3<f32>,
+    /// The rolled asteroid, or `None` for a comet.
+    pub asteroid: Option<AsteroidRoll>,
+}
+
+/// Number of sectors headings are counted in.
+const SECTORS: usize = 8;
+
+/// Path spawns are written to.
+pub fn path() -> io::Result<PathBuf> {
+    Ok(application_root_dir()?.join("spawns.csv"))
+}
+
+/// Simulate `count` spawns of each spawner, report their distribution and write them to CSV.
+pub fn audit(count: usize, config: &GameConfig, seed: u64) {
+    info!("simulating {} spawns with seed: {}", count, seed);
+
+    let samples = simulate(count, config, &RandomGen::new(seed));
+
+    for spawner in &["endless", "waves"] {
+        let samples = samples
+            .iter()
+            .filter(|s| s.spawner == *spawner)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        report(spawner, &samples);
+    }
+
+    let path = match path() {
+        Ok(path) => path,
+        Err(e) => {
+            error!("failed to locate spawns: {}", e);
+            return;
+        }
+    };
+
+    match write_csv(&samples, &path) {
+        Ok(()) => info!("wrote spawns: {}", path.display()),
+        Err(e) => error!("failed to write spawns: {}: {}", path.display(), e),
+    }
+}
+
+/// Run the spawners until each has spawned `count` times.
+pub fn simulate(count: usize, config: &GameConfig, rand: &RandomGen) -> Vec<SpawnSample> {
+    let mut samples = Vec::with_capacity(count * 2);
+
+    for _ in 0..count {
+        let sample = match RandomSpawner::roll(rand, config) {
+            Spawn::Comet(position) => SpawnSample {
+                spawner: "endless",
+                wave: None,
+                position,
+                asteroid: None,
+            },
+            Spawn::Asteroid(roll) => SpawnSample {
+                spawner: "endless",
+                wave: None,
+                position: roll.position,
+                asteroid: Some(roll),
+            },
+        };
+
+        samples.push(sample);
+    }
+
+    let mut spawned = 0;
+    let mut wave = 0;
+
+    while spawned < count {
+        wave += 1;
+
+        let wave_count = config.waves.count(wave).min(count - spawned);
+
+        // NB: a wave without asteroids would never be cleared.
+        if wave_count == 0 {
+            break;
+        }
+
+        for _ in 0..wave_count {
+            let roll = WaveSystem::roll(rand, config, wave);
+
+            samples.push(SpawnSample {
+                spawner: "waves",
+                wave: Some(wave),
+                position: roll.position,
+                asteroid: Some(roll),
+            });
+        }
+
+        spawned += wave_count;
+    }
+
+    samples
+}
+
+/// Print the distribution of the spawns of a single spawner.
+fn report(spawner: &str, samples: &[SpawnSample]) {
+    let asteroids = samples.iter().filter_map(|s| s.asteroid).collect::<Vec<_>>();
+
+    println!(
+        "{}: {} spawns, {} asteroids, {} comets",
+        spawner,
+        samples.len(),
+        asteroids.len(),
+        samples.len() - asteroids.len()
+    );
+
+    if samples.is_empty() {
+        return;
+    }
+
+    // top, bottom, left and right.
+    let mut edges = [0usize; 4];
+
+    for s in samples {
+        let p = s.position;
+        let distances = [ARENA_HEIGHT - p.y, p.y, p.x, ARENA_WIDTH - p.x];
+
+        let nearest = (0..edges.len())
+            .min_by(|a, b| {
+                distances[*a]
+                    .partial_cmp(&distances[*b])
+                    .unwrap_or(Ordering::Equal)
+            })
+            .unwrap_or(0);
+
+        edges[nearest] += 1;
+    }
+
+    println!(
+        "  nearest edge: top {}, bottom {}, left {}, right {}",
+        edges[0], edges[1], edges[2], edges[3]
+    );
+
+    let (min_x, max_x) = range(samples.iter().map(|s| s.position.x));
+    let (min_y, max_y) = range(samples.iter().map(|s| s.position.y));
+    println!("  x: {:.1} to {:.1} (arena width {})", min_x, max_x, ARENA_WIDTH);
+    println!("  y: {:.1} to {:.1} (arena height {})", min_y, max_y, ARENA_HEIGHT);
+
+    if asteroids.is_empty() {
+        return;
+    }
+
+    let mut sizes = [0usize; 3];
+
+    for a in &asteroids {
+        let index = match Asteroids::size(Asteroids::radius(a.scale)) {
+            AsteroidSize::Small => 0,
+            AsteroidSize::Medium => 1,
+            AsteroidSize::Large => 2,
+        };
+
+        sizes[index] += 1;
+    }
+
+    println!("  sizes: small {}, medium {}, large {}", sizes[0], sizes[1], sizes[2]);
+
+    let speeds = asteroids.iter().map(|a| a.velocity.norm()).collect::<Vec<_>>();
+    let (min_speed, max_speed) = range(speeds.iter().cloned());
+    let mean_speed = speeds.iter().sum::<f32>() / speeds.len() as f32;
+
+    println!(
+        "  speed: {:.1} to {:.1}, mean {:.1} (units / s)",
+        min_speed, max_speed, mean_speed
+    );
+
+    let mut sectors = [0usize; SECTORS];
+
+    for a in &asteroids {
+        sectors[sector(a.velocity)] += 1;
+    }
+
+    let sectors = sectors.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+    println!("  headings, counter-clockwise from +x: {}", sectors.join(", "));
+}
+
+/// The sector a velocity is heading in, counter-clockwise from the x axis.
+fn sector(velocity: Vector2<f32>) -> usize {
+    let full = 2.0 * consts::PI;
+    let angle = (velocity.y.atan2(velocity.x) + full) % full;
+    (angle / full * SECTORS as f32) as usize % SECTORS
+}
+
+/// The smallest and largest of the given values.
+fn range(values: impl Iterator<Item = f32>) -> (f32, f32) {
+    values.fold((std::f32::MAX, std::f32::MIN), |(min, max), v| (min.min(v), max.max(v)))
+}
+
+/// Write every spawn as CSV, with one row per spawn.
+fn write_csv(samples: &[SpawnSample], path: &Path) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+
+    writeln!(out, "spawner,wave,x,y,comet,scale,vx,vy,rotation")?;
+
+    for s in samples {
+        let wave = s.wave.map(|w| w.to_string()).unwrap_or_default();
+
+        write!(out, "{},{},{},{}", s.spawner, wave, s.position.x, s.position.y)?;
+
+        match s.asteroid {
+            Some(a) => writeln!(
+                out,
+                ",false,{},{},{},{}",
+                a.scale, a.velocity.x, a.velocity.y, a.rotation
+            )?,
+            None => writeln!(out, ",true,,,,")?,
+        }
+    }
+
+    out.flush()
+}
@@ -5,21 +5,30 @@ use amethyst::{
     assets::Loader,
     core::{
         ArcThreadPool,
+        frame_limiter::{FrameLimiter, FrameRateLimitStrategy},
+        nalgebra::{Vector2, Vector3},
+        timing::Time,
         transform::Transform,
     },
-    ecs::prelude::World,
+    ecs::prelude::{
+        Entities, Entity, EntityBuilder, Join, LazyUpdate, Read, ReadExpect, RunNow, World,
+    },
     prelude::{
-        State, StateEvent, StateData, GameDataBuilder, GameData, Trans, Builder, DataInit,
+        State, StateEvent, StateData, GameDataBuilder, GameData, Trans, Builder, Config,
+        DataInit,
+    },
+    renderer::{Camera, Hidden, Projection, Rgba, ScreenDimensions, VirtualKeyCode},
+    ui::{
+        Anchor, FontHandle, Interactable, TtfFormat, UiEvent, UiEventType, UiText, UiTransform,
     },
-    renderer::{Camera, Projection},
-    ui::{Anchor, TtfFormat, UiText, UiTransform},
-    input::is_close_requested,
+    input::{is_close_requested, InputHandler},
+    shrev::EventChannel,
 };
 
 pub struct Data<'a, 'b> {
     // Base dispatcher.
     pub base: GameData<'a, 'b>,
-    // Dispatcher for the main game.
+    // Dispatcher for the game, where each system is scheduled to run in specific states.
     pub main: Dispatcher<'a, 'b>,
 }
 
@@ -27,15 +36,28 @@ pub struct Data<'a, 'b> {
 pub struct DataBuilder<'a, 'b> {
     pub base: GameDataBuilder<'a, 'b>,
     pub main: DispatcherBuilder<'a, 'b>,
+    /// Number of threads the game dispatcher runs on, instead of sharing the pool of the
+    /// application, see `DispatchPlan`.
+    pub threads: Option<usize>,
 }
 
 impl<'a, 'b> DataInit<Data<'a, 'b>> for DataBuilder<'a, 'b> {
     fn build(self, world: &mut World) -> Data<'a, 'b> {
         let base = self.base.build(world);
 
+        let pool = self.threads.and_then(|threads| {
+            match ThreadPoolBuilder::new().num_threads(threads).build() {
+                Ok(pool) => Some(Arc::new(pool)),
+                Err(e) => {
+                    warn!("failed to build thread pool of {} threads: {}", threads, e);
+                    None
+                }
+            }
+        });
+
         let mut main = {
-            let pool = world.read_resource::<ArcThreadPool>();
-            self.main.with_pool(pool.clone()).build()
+            let pool = pool.unwrap_or_else(|| world.read_resource::<ArcThreadPool>().clone());
+            self.main.with_pool(pool).build()
         };
 
         main.setup(&mut world.res);
@@ -49,41 +71,349 @@ impl<'a, 'b> DataInit<Data<'a, 'b>> for DataBuilder<'a, 'b> {
 
 type CustomTrans<'a, 'b> = Trans<Data<'a, 'b>, StateEvent>;
 
+use log::{error, info, warn};
+use rayon::ThreadPoolBuilder;
+use std::{
+    collections::HashMap,
+    mem,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
 use crate::{
-    audio::initialise_audio,
-    components::{Collider, ConstrainedObject, Physical, Ship},
-    resources::{Asteroids, Bullets, Game, RandomGen, Score, Ships},
+    assets::{self, InitError},
+    audio::{initialise_audio, AudioMix},
+    changelog::Changelog,
+    daily,
+    components::{
+        Bounded, Collider, ConstrainedObject, Energy, Health, Layout, Physical, Planet, Ship,
+        Station,
+    },
+    resources::{
+        Accuracy, ArenaBounds, Asteroids, BulletTime, Bullets, Comets, DebugOverlay, DevTools,
+        EnergyBar, Game, HealthBar, HistogramOverlay, Hulls, LatencyProbe, Lives, Particles,
+        PhotoCamera, Planets, PowerUps, RandomGen, RandomStreams, Score, SessionSettings, Ships,
+        Shockwaves, SpawnPoint, SplinterQueue, Squeeze, StationBar, Stream, Ufos, Wave,
+    },
+    config::{GameConfig, StationPolicy},
+    events::GameEvent,
+    gamelog::{GameLog, LogEvent, Record},
+    highscores::{HighScore, HighScores},
+    input::{mouse_in_arena, Action, InputContext, InputContexts, Repeat, TextEntry},
+    level::{Level, LevelAsteroid, LevelPlanet},
+    mutations::{Mutation, Mutations},
+    profiles::{Profiles, RunStats, ShipLook},
+    net::NetSession,
+    replay::{Playback, Recorder, Replay},
+    schedule::{CurrentState, StateId},
+    script::WaveScript,
+    settings::Settings,
+    spawner::ActiveSpawner,
+    systems::{spawn_ship, LimitObjectsSystem, PhysicsSystem},
+    theme::{Theme, Themes},
     ARENA_HEIGHT, ARENA_WIDTH,
 };
+#[cfg(feature = "clips")]
+use crate::clip::ClipRecorder;
 
 #[derive(Default)]
 pub struct MainGameState {
-    pub player_is_immortal: bool,
+    /// Settings for the current session.
+    pub session: SessionSettings,
+    /// Error raised while initializing the game, if any.
+    error: Option<InitError>,
+    /// When the run started (s).
+    started: f64,
+    /// If the score of the run has been recorded in the high-score table.
+    recorded: bool,
+    /// Settings to go back to the menu with, if a replay is being watched from the menu.
+    menu_session: Option<SessionSettings>,
+    /// Connection to the other player of an online game, until it's been added to the world.
+    net: Option<NetSession>,
+    /// Configuration to go back to once a replay has been played back with the configuration it
+    /// was recorded with.
+    config: Option<GameConfig>,
 }
 
-impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for MainGameState {
-    fn on_start(&mut self, data: StateData<Data>) {
-        let StateData { world, .. } = data;
+impl MainGameState {
+    pub fn new(session: SessionSettings) -> Self {
+        Self {
+            session,
+            error: None,
+            started: 0f64,
+            recorded: false,
+            menu_session: None,
+            net: None,
+            config: None,
+        }
+    }
+
+    /// Play online with the other player at the other end of the given connection.
+    pub fn online(session: SessionSettings, net: NetSession) -> Self {
+        Self {
+            net: Some(net),
+            ..Self::new(session)
+        }
+    }
+
+    /// Watch the replay at the given path, and go back to the menu with the given settings after.
+    ///
+    /// NB: cheats are turned off, since the run was recorded without them.
+    pub fn watch(menu_session: SessionSettings, path: PathBuf, replay: &Replay) -> Self {
+        let mut session = menu_session.for_replay(path, replay);
+        session.dev.immortal = false;
+
+        Self {
+            menu_session: Some(menu_session),
+            ..Self::new(session)
+        }
+    }
+
+    /// Record the score of the run in the high-score table, unless it's already been recorded.
+    ///
+    /// Runs of custom levels, with cheats enabled or played back from a replay don't count.
+    ///
+    /// The replay of a run which tops the table is kept as the best run.
+    fn record_high_score(&mut self, world: &World) {
+        if mem::replace(&mut self.recorded, true) {
+            return;
+        }
+
+        if self.session.level.is_some() || self.session.replay.is_some() {
+            return;
+        }
+
+        let spectating = world
+            .res
+            .try_fetch::<NetSession>()
+            .map(|net| net.is_spectating())
+            .unwrap_or(false);
+
+        if spectating {
+            return;
+        }
+
+        if world.read_resource::<DevTools>().immortal {
+            return;
+        }
+
+        let mut high_scores = match world.res.try_fetch_mut::<HighScores>() {
+            Some(high_scores) => high_scores,
+            None => return,
+        };
+
+        let name = world
+            .res
+            .try_fetch::<Profiles>()
+            .and_then(|p| p.active().map(|p| p.name.clone()))
+            .unwrap_or_else(|| HighScores::ANONYMOUS.to_string());
 
-        Ships::initialize(world);
-        Bullets::initialize(world);
-        Asteroids::initialize(world);
-        world.add_resource(RandomGen);
+        let score = world.read_resource::<Score>();
+
+        let high_score = HighScore {
+            name,
+            points: score.points,
+            asteroids: score.asteroids,
+        };
+
+        if let Some(rank) = high_scores.record(high_score) {
+            info!("new high score #{}: {}", rank + 1, score.points);
+            high_scores.save();
+
+            // NB: the best run is only kept across all runs, not just those of the day.
+            if rank == 0 && high_scores.day.is_none() {
+                world.read_resource::<Recorder>().save_best();
+            }
+        }
+    }
+
+    /// Set up the world for a new game.
+    fn initialize(&mut self, world: &mut World) -> Result<(), InitError> {
+        // NB: sprite sheets and music are picked from the theme.
+        let theme = match world.res.try_fetch::<Settings>() {
+            Some(settings) => Themes::read().get(&settings.theme),
+            None => Theme::default(),
+        };
+
+        world.add_resource(theme);
+
+        Ships::initialize(world)?;
+        Bullets::initialize(world)?;
+        Asteroids::initialize(world)?;
+        PowerUps::initialize(world)?;
+        Comets::initialize(world)?;
+        Particles::initialize(world)?;
+        Shockwaves::initialize(world)?;
+        Planets::initialize(world)?;
+        Ufos::initialize(world)?;
+
+        let replay = match &self.session.replay {
+            Some(path) => Some(
+                Replay::read(path)
+                    .map_err(|e| InitError::BrokenAsset(path.clone(), e.to_string()))?,
+            ),
+            None => None,
+        };
+
+        // NB: a replay is played back with the configuration it was recorded with.
+        if let (Some(path), Some(replay)) = (&self.session.replay, &replay) {
+            let experimental = replay.config.experimental;
+
+            // NB: the systems of experimental features are only added when the game starts, so
+            // the replay wouldn't play back.
+            if world.read_resource::<GameConfig>().experimental != experimental {
+                return Err(InitError::IncompatibleReplay(
+                    path.clone(),
+                    experimental.enabled(),
+                ));
+            }
+
+            let config = replay.config.clone();
+            let config = mem::replace(&mut *world.write_resource::<GameConfig>(), config);
+            self.config = Some(config);
+        }
+
+        let seed = self.session.seed.unwrap_or_else(RandomGen::random_seed);
+        info!("random seed: {}", seed);
+        world.add_resource(RandomStreams::new(seed));
+
+        world.add_resource(SplinterQueue::default());
+        world.add_resource(SpawnPoint::default());
+        world.add_resource(WaveScript::default());
+        world.add_resource(Mutations::default());
+        world.add_resource(ArenaBounds::default());
+        world.add_resource(Squeeze::default());
+
+        let spawner = ActiveSpawner::from_config(&world.read_resource::<GameConfig>());
+        world.add_resource(spawner);
+
+        #[cfg(feature = "clips")]
+        {
+            if let Some(mut clips) = world.res.try_fetch_mut::<ClipRecorder>() {
+                clips.clear();
+            }
+        }
 
         let game = {
             let mut game = Game::default();
-            game.modifiers.player_is_immortal = self.player_is_immortal;
+
+            let config = world.read_resource::<GameConfig>();
+            let shrink = (config.ship_hitbox_shrink() * 100.0).round() as u32;
+            game.modifiers.ship_hitbox_shrink = shrink;
             game
         };
 
-        initialize_score(world, &game);
+        // NB: the daily challenge keeps its scores in a table of their own.
+        if let Some(day) = self.session.daily {
+            world.add_resource(HighScores::read_daily(day));
+        }
+
+        initialize_score(world, &game, self.session.dev, self.session.players())?;
 
         world.add_resource(game);
+        world.add_resource(self.session.dev);
+
+        // NB: the level decides where the ship spawns.
+        if let Some(path) = &self.session.level {
+            initialise_level(world, path)?;
+        }
 
         // Setup our game.
-        initialise_ship(world);
+        initialise_lives(world, self.session.players());
+        initialise_wave(world);
+        initialise_energy_bar(world);
+        initialise_health_bar(world);
+        initialise_bullet_time(world);
+
+        let station = world.read_resource::<GameConfig>().station;
+
+        if let Some(policy) = station {
+            initialise_station(world, &policy);
+        }
+
+        // NB: a replay is played back with the looks it was recorded with.
+        if self.session.replay.is_none() {
+            self.session.looks = player_looks(world, self.session.players());
+        }
+
+        initialise_ship(world, &self.session);
+        initialise_stars(world);
         initialise_camera(world);
         initialise_audio(world);
+
+        if self.session.dev.measure_latency {
+            initialise_latency_probe(world);
+        }
+
+        initialise_debug_overlay(world);
+
+        if let Some(path) = &self.session.export_log {
+            world.add_resource(GameLog::new(path.clone()));
+        }
+
+        let config = world.read_resource::<GameConfig>().clone();
+        world.add_resource(Recorder::new(&self.session, seed, config));
+
+        let playback = match replay {
+            Some(replay) => {
+                initialise_watch_label(world, "REPLAY");
+                Playback::new(replay)
+            }
+            None => Playback::default(),
+        };
+
+        world.add_resource(playback);
+
+        if let Some(net) = self.net.take() {
+            // NB: spectators look around the arena with the camera of photo mode.
+            if net.is_spectating() {
+                initialise_watch_label(world, "SPECTATING");
+                world.add_resource(PhotoCamera::default());
+            }
+
+            world.add_resource(net);
+        }
+
+        world.add_resource(self.session.clone());
+        world.add_resource(CurrentState(StateId::Main));
+
+        world
+            .write_resource::<EventChannel<GameEvent>>()
+            .single_write(GameEvent::GameStarted);
+
+        Ok(())
+    }
+}
+
+impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for MainGameState {
+    fn on_start(&mut self, data: StateData<Data>) {
+        let StateData { world, .. } = data;
+
+        if let Err(e) = self.initialize(world) {
+            self.error = Some(e);
+        }
+
+        self.started = world.read_resource::<Time>().absolute_time_seconds();
+        limit_frame_rate(world, false);
+        muffle_audio(world, false);
+        world.write_resource::<InputContexts>().push(InputContext::Gameplay);
+    }
+
+    fn on_stop(&mut self, data: StateData<Data>) {
+        data.world.write_resource::<InputContexts>().pop(InputContext::Gameplay);
+        data.world.write_resource::<Time>().set_time_scale(1.0);
+    }
+
+    fn on_pause(&mut self, data: StateData<Data>) {
+        // NB: bullet time picks up where it left off when resumed.
+        data.world.write_resource::<Time>().set_time_scale(1.0);
+    }
+
+    fn on_resume(&mut self, data: StateData<Data>) {
+        data.world.add_resource(CurrentState(StateId::Main));
+        limit_frame_rate(data.world, false);
+        muffle_audio(data.world, false);
     }
 
     fn update(&mut self, data: StateData<Data>) -> CustomTrans<'a, 'b> {
@@ -93,34 +423,124 @@ impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for MainGameState {
             ..
         } = data;
 
+        if let Some(error) = self.error.take() {
+            world.delete_all();
+            let retry = Retry::Game(self.session.clone());
+            return Trans::Switch(Box::new(ErrorState::new(error, retry)));
+        }
+
         let Data {
             ref mut base,
             ref mut main,
         } = *data;
 
+        // NB: frames of a replay are played back with the duration they were recorded with.
+        let delta = world.res.try_fetch::<Playback>().and_then(|p| p.delta());
+
+        if let Some(delta) = delta {
+            world.write_resource::<Time>().set_delta_seconds(delta);
+        }
+
+        // NB: online, every frame has the same duration, and the game waits for the other player.
+        // Spectators which have fallen behind simulate a few frames at once to catch up.
+        let frames = match world.res.try_fetch_mut::<NetSession>() {
+            Some(mut net) => {
+                net.poll();
+                world.write_resource::<Time>().set_delta_seconds(NetSession::TIMESTEP);
+                net.frames_ready().min(NetSession::CATCH_UP)
+            }
+            None => 1,
+        };
+
         base.update(world);
-        main.dispatch(&world.res);
 
-        let Game {
-            restart, modifiers, ..
-        } = *world.read_resource::<Game>();
+        for frame in 0..frames {
+            if frame > 0 {
+                world.maintain();
+            }
 
-        if restart {
+            main.dispatch(&world.res);
+        }
+
+        let (restart, menu, dead) = {
+            let game = world.read_resource::<Game>();
+            (game.restart, game.menu, game.modifiers.player_is_dead)
+        };
+
+        // NB: the run is over as soon as the player dies, so record it right away.
+        if dead || restart || menu {
+            self.record_high_score(world);
+        }
+
+        if restart || menu {
+            save_game_log(world);
+            save_replay(world);
+            record_run(world, self.started);
+
+            // NB: the other player can't follow along, so an online game ends here.
+            if world.res.has_value::<NetSession>() {
+                info!("left the online game");
+                return Trans::Quit;
+            }
+
+            // NB: entities created during this frame are not deleted unless they've been merged.
+            flush_world(world);
             world.delete_all();
 
+            if let Some(config) = self.config.take() {
+                *world.write_resource::<GameConfig>() = config;
+            }
+
+            // NB: a replay watched from the menu goes back to the menu as it was.
+            let session = match &self.menu_session {
+                Some(session) if menu => session.clone(),
+                _ => {
+                    // dev tools toggled while playing carry over.
+                    let mut session = self.session.clone();
+                    session.dev = *world.read_resource::<DevTools>();
+                    session
+                }
+            };
+
+            if menu {
+                return Trans::Switch(Box::new(MenuState::new(session)));
+            }
+
             return Trans::Switch(Box::new(MainGameState {
-                player_is_immortal: self.player_is_immortal || modifiers.player_is_immortal,
+                menu_session: self.menu_session.take(),
+                ..MainGameState::new(session)
             }));
         }
 
-        let Game {
-            ref mut pause, ..
-        } = *world.write_resource::<Game>();
+        if dead {
+            flush_world(world);
+            return Trans::Push(Box::new(GameOverState::new(self.started)));
+        }
 
-        if *pause {
+        let pause = {
+            let mut game = world.write_resource::<Game>();
             // NB: prevent a pause cycle by resetting the pause when acted on.
-            *pause = false;
-            return Trans::Push(Box::new(PauseState));
+            mem::replace(&mut game.pause, false)
+        };
+
+        if pause {
+            flush_world(world);
+            return Trans::Push(Box::new(PauseState::default()));
+        }
+
+        let draft = world
+            .res
+            .try_fetch_mut::<Wave>()
+            .map(|mut wave| mem::replace(&mut wave.draft_pending, false))
+            .unwrap_or(false);
+
+        if draft {
+            flush_world(world);
+            let choices = {
+                let streams = world.read_resource::<RandomStreams>();
+                Mutations::draft(streams.get(Stream::States))
+            };
+            return Trans::Push(Box::new(DraftState::new(choices)));
         }
 
         Trans::None
@@ -128,11 +548,19 @@ impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for MainGameState {
 
     fn handle_event(
         &mut self,
-        _data: StateData<Data>,
+        data: StateData<Data>,
         event: StateEvent,
     ) -> CustomTrans<'a, 'b> {
         if let StateEvent::Window(event) = &event {
+            if let Some(mut probe) = data.world.res.try_fetch_mut::<LatencyProbe>() {
+                probe.stamp(event);
+            }
+
             if is_close_requested(&event) {
+                data.world.read_resource::<Settings>().save();
+                save_game_log(data.world);
+                record_run(data.world, self.started);
+                self.record_high_score(data.world);
                 Trans::Quit
             } else {
                 Trans::None
@@ -143,6 +571,134 @@ impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for MainGameState {
     }
 }
 
+/// Export the log of the current run, if it is being recorded.
+fn save_game_log(world: &World) {
+    if let Some(log) = world.res.try_fetch::<GameLog>() {
+        log.save();
+    }
+}
+
+/// Save the replay of the current run, if it's being recorded.
+fn save_replay(world: &World) {
+    if let Some(recorder) = world.res.try_fetch::<Recorder>() {
+        recorder.save();
+    }
+}
+
+/// Record the statistics of the current run in the active profile, if there is one.
+fn record_run(world: &World, started: f64) {
+    let mut profiles = match world.res.try_fetch_mut::<Profiles>() {
+        Some(profiles) => profiles,
+        None => return,
+    };
+
+    let stats = RunStats {
+        points: world.read_resource::<Score>().points,
+        duration: (world.read_resource::<Time>().absolute_time_seconds() - started) as f32,
+    };
+
+    if profiles.record(stats) {
+        profiles.save();
+    }
+}
+
+/// Apply pending lazy updates and verify the world before transitioning between states.
+///
+/// Lazy updates queued up by the main dispatcher are otherwise only applied the next time the base
+/// dispatcher is run, which might be in a different state.
+///
+/// Deferred colliders which can never be resolved, because their entity is missing a bounding
+/// volume or a transform, are converted into their final collider so that they don't get stuck.
+fn flush_world(world: &mut World) {
+    world.maintain();
+
+    let stuck = {
+        let entities = world.entities();
+        let colliders = world.read_storage::<Collider>();
+        let bounded = world.read_storage::<Bounded>();
+        let locals = world.read_storage::<Transform>();
+
+        (&*entities, &colliders)
+            .join()
+            .filter_map(|(e, collider)| match *collider {
+                Collider::Deferred(next) if !bounded.contains(e) || !locals.contains(e) => {
+                    Some((e, next.to_collider()))
+                }
+                _ => None,
+            }).collect::<Vec<_>>()
+    };
+
+    if stuck.is_empty() {
+        return;
+    }
+
+    warn!("resolving {} stuck deferred collider(s)", stuck.len());
+
+    let mut colliders = world.write_storage::<Collider>();
+
+    for (e, collider) in stuck {
+        if let Err(e) = colliders.insert(e, collider) {
+            error!("failed to resolve deferred collider: {}", e);
+        }
+    }
+}
+
+/// Frame rate while playing.
+pub const FRAME_RATE: u32 = 144;
+/// Frame rate while paused or in menus, to save power.
+const IDLE_FRAME_RATE: u32 = 30;
+
+/// Strategy used to limit the frame rate.
+pub fn frame_rate_limit_strategy() -> FrameRateLimitStrategy {
+    FrameRateLimitStrategy::SleepAndYield(Duration::from_millis(2))
+}
+
+/// Drop the frame rate while nothing is moving, or restore it once the game runs again.
+fn limit_frame_rate(world: &mut World, idle: bool) {
+    let fps = if idle { IDLE_FRAME_RATE } else { FRAME_RATE };
+    world
+        .write_resource::<FrameLimiter>()
+        .set_rate(frame_rate_limit_strategy(), fps);
+}
+
+/// Muffle the audio while the game isn't being played, like when it's paused.
+fn muffle_audio(world: &mut World, muffled: bool) {
+    world.write_resource::<AudioMix>().muffled = muffled;
+}
+
+/// Initialise the stars drawn on the background, in the colors of the theme.
+fn initialise_stars(world: &mut World) {
+    /// Number of stars.
+    const COUNT: usize = 80;
+
+    let color = world.read_resource::<Theme>().stars;
+    let sprite_render = world.read_resource::<Particles>().new_sprite_render();
+
+    // NB: rolled separately, so that the stars don't change the run.
+    let rand = RandomGen::new(RandomGen::random_seed());
+
+    for _ in 0..COUNT {
+        let mut local = Transform::default();
+        local.set_xyz(
+            rand.next_f32() * ARENA_WIDTH,
+            rand.next_f32() * ARENA_HEIGHT,
+            -0.5,
+        );
+
+        let scale = 0.1 + rand.next_f32() * 0.2;
+        *local.scale_mut() = Vector3::new(scale, scale, 1.0);
+
+        let alpha = 0.3 + rand.next_f32() * 0.7;
+
+        world
+            .create_entity()
+            .with(local)
+            .with(sprite_render.clone())
+            .with(Rgba(color[0], color[1], color[2], color[3] * alpha))
+            .build();
+    }
+}
+
 /// Initialise the camera.
 fn initialise_camera(world: &mut World) {
     let mut transform = Transform::default();
@@ -158,48 +714,61 @@ fn initialise_camera(world: &mut World) {
         .build();
 }
 
-/// Initialises one ship in the middle-ish of the arena.
-fn initialise_ship(world: &mut World) {
-    use crate::{ARENA_HEIGHT, ARENA_WIDTH};
-
-    let mut local = Transform::default();
-    local.set_xyz(ARENA_WIDTH / 2.0, ARENA_HEIGHT / 2.0, 0.0);
+/// How the ship of each player looks, where the first player's ship looks as picked in the
+/// active profile.
+fn player_looks(world: &World, players: usize) -> Vec<ShipLook> {
+    let look = world
+        .res
+        .try_fetch::<Profiles>()
+        .and_then(|profiles| profiles.active().map(|p| p.look()))
+        .unwrap_or_default();
 
-    let sprite_render = {
-        let ship_resource = world.read_resource::<Ships>();
-        ship_resource.new_sprite_render()
-    };
+    let mut looks = vec![ShipLook::default(); players];
 
-    let bounding_volume = {
-        let ship_resource = world.read_resource::<Ships>();
-        ship_resource.new_bounded()
-    };
+    if let Some(first) = looks.first_mut() {
+        *first = look;
+    }
 
-    world
-        .create_entity()
-        .with(sprite_render)
-        .with(Ship::default())
-        .with(Physical::new())
-        .with(ConstrainedObject)
-        .with(local)
-        .with(Collider::Ship)
-        .with(bounding_volume)
-        .build();
+    looks
 }
 
-fn initialize_score(world: &mut World, game: &Game) {
-    let font = world.read_resource::<Loader>().load(
-        "font/square.ttf",
-        TtfFormat,
-        Default::default(),
-        (),
-        &world.read_resource(),
+/// Initialises a ship for each player in the middle-ish of the arena.
+fn initialise_ship(world: &mut World, session: &SessionSettings) {
+    let players = session.players();
+
+    world.exec(
+        |(entities, lazy, ships, power_ups, config, mutations, spawn): (
+            Entities,
+            Read<LazyUpdate>,
+            ReadExpect<Ships>,
+            ReadExpect<PowerUps>,
+            Read<GameConfig>,
+            Read<Mutations>,
+            Read<SpawnPoint>,
+        )| {
+            for player in 0..players {
+                // in co-op, ships are told apart by the color of their player.
+                let look = session.look(player);
+                let spawn = spawn.for_player(player, players);
+
+                spawn_ship(
+                    &entities, &lazy, &ships, &power_ups, &config, &mutations, spawn, player, look,
+                );
+            }
+        },
     );
 
-    let score_transform = UiTransform::new(
-        "Score".to_string(),
+    world.maintain();
+}
+
+/// Initialise the lives of each player, shown next to the score.
+fn initialise_lives(world: &mut World, players: usize) {
+    let font = world.read_resource::<Score>().font.clone();
+
+    let lives_transform = UiTransform::new(
+        "Lives".to_string(),
         Anchor::TopMiddle,
-        0.,
+        200.,
         -50.,
         1.,
         200.,
@@ -207,77 +776,3013 @@ fn initialize_score(world: &mut World, game: &Game) {
         0,
     );
 
-    let score_text = world
+    let text = world
         .create_entity()
-        .with(score_transform)
+        .with(Layout::of(&lives_transform, Some(20.)))
+        .with(lives_transform)
         .with(UiText::new(
-            font.clone(),
-            "0".to_string(),
+            font,
+            String::new(),
             [1.0, 1.0, 1.0, 1.0],
-            50.,
+            20.,
         )).build();
 
-    let mods_transform = UiTransform::new(
-        "Mods".to_string(),
-        Anchor::TopRight,
-        -200.,
-        -50.,
+    world.add_resource(Lives::new(text, players));
+}
+
+/// Initialise the banner announcing each wave of asteroids, which is hidden until the first wave.
+fn initialise_wave(world: &mut World) {
+    let font = world.read_resource::<Score>().font.clone();
+
+    let banner_transform = UiTransform::new(
+        "Wave".to_string(),
+        Anchor::Middle,
+        0.,
+        60.,
         1.,
-        200.,
-        50.,
+        400.,
+        60.,
         0,
     );
 
-    let modifiers_text = world
+    let banner = world
         .create_entity()
-        .with(mods_transform)
+        .with(Layout::of(&banner_transform, Some(40.)))
+        .with(banner_transform)
         .with(UiText::new(
-            font.clone(),
-            game.modifiers.as_text(),
-            [1.0, 0.0, 0.0, 1.0],
-            20.,
+            font,
+            String::new(),
+            [1.0, 1.0, 1.0, 0.0],
+            40.,
         )).build();
 
-    world.add_resource(Score {
-        score_text,
-        asteroids: 0,
-        modifiers_text,
-        current_modifiers: game.modifiers,
-    });
+    world.add_resource(Wave::new(banner));
 }
 
-/// State used when game is paused.
-#[derive(Default)]
-pub struct PauseState;
-
-impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for PauseState {
-    fn on_start(&mut self, _: StateData<Data>) {
-        println!("Game Paused");
-    }
+/// Initialise the bar showing the energy of each ship.
+fn initialise_energy_bar(world: &mut World) {
+    let font = world.read_resource::<Score>().font.clone();
 
-    fn update(&mut self, data: StateData<Data>) -> CustomTrans<'a, 'b> {
-        let StateData {
-            data,
-            world,
-            ..
-        } = data;
+    let energy_transform = UiTransform::new(
+        "Energy".to_string(),
+        Anchor::BottomMiddle,
+        0.,
+        30.,
+        1.,
+        600.,
+        30.,
+        0,
+    );
 
-        let Data {
-            ref mut base,
-            ..
-        } = *data;
+    let text = world
+        .create_entity()
+        .with(Layout::of(&energy_transform, Some(16.)))
+        .with(energy_transform)
+        .with(UiText::new(
+            font,
+            String::new(),
+            [0.4, 0.8, 1.0, 1.0],
+            16.,
+        )).build();
 
-        base.update(world);
+    world.add_resource(EnergyBar { text });
+}
 
-        let Game {
-            ref mut pause, ..
-        } = *world.write_resource::<Game>();
+/// Initialise the bar showing the hit points left on each ship.
+fn initialise_health_bar(world: &mut World) {
+    let font = world.read_resource::<Score>().font.clone();
 
-        if *pause {
-            *pause = false;
-            return Trans::Pop;
+    let health_transform = UiTransform::new(
+        "Health".to_string(),
+        Anchor::BottomMiddle,
+        0.,
+        55.,
+        1.,
+        600.,
+        30.,
+        0,
+    );
+
+    let text = world
+        .create_entity()
+        .with(Layout::of(&health_transform, Some(16.)))
+        .with(health_transform)
+        .with(UiText::new(
+            font,
+            String::new(),
+            [1.0, 0.5, 0.5, 1.0],
+            16.,
+        )).build();
+
+    world.add_resource(HealthBar { text });
+}
+
+/// Spawn the station of objective mode, and the text showing its health.
+fn initialise_station(world: &mut World, policy: &StationPolicy) {
+    let mut local = Transform::default();
+    local.set_xyz(ARENA_WIDTH / 2.0, ARENA_HEIGHT / 3.0, -0.1);
+
+    let scale = policy.radius / Planets::SPRITE_RADIUS;
+    *local.scale_mut() = Vector3::new(scale, scale, 1.0);
+
+    let sprite_render = world.read_resource::<Planets>().new_sprite_render();
+
+    world
+        .create_entity()
+        .with(local)
+        .with(sprite_render)
+        .with(Rgba::from(Station::TINT))
+        .with(Bounded::from_local(policy.radius))
+        .with(Collider::Station)
+        .with(Health::new(policy.hit_points))
+        .with(Station::default())
+        .build();
+
+    let font = world.read_resource::<Score>().font.clone();
+
+    let station_transform = UiTransform::new(
+        "Station".to_string(),
+        Anchor::BottomMiddle,
+        0.,
+        105.,
+        1.,
+        600.,
+        30.,
+        0,
+    );
+
+    let text = world
+        .create_entity()
+        .with(Layout::of(&station_transform, Some(16.)))
+        .with(station_transform)
+        .with(UiText::new(
+            font,
+            String::new(),
+            Station::TINT,
+            16.,
+        )).build();
+
+    world.add_resource(StationBar { text });
+}
+
+/// Initialise the text showing the charges of bullet time left.
+fn initialise_bullet_time(world: &mut World) {
+    let font = world.read_resource::<Score>().font.clone();
+
+    let bullet_time_transform = UiTransform::new(
+        "BulletTime".to_string(),
+        Anchor::BottomMiddle,
+        0.,
+        80.,
+        1.,
+        600.,
+        30.,
+        0,
+    );
+
+    let text = world
+        .create_entity()
+        .with(Layout::of(&bullet_time_transform, Some(16.)))
+        .with(bullet_time_transform)
+        .with(UiText::new(
+            font,
+            String::new(),
+            [0.7, 0.7, 1.0, 1.0],
+            16.,
+        )).build();
+
+    let bullet_time = BulletTime::new(text);
+
+    if let Some(ui_text) = world.write_storage::<UiText>().get_mut(text) {
+        ui_text.text = bullet_time.as_text();
+    }
+
+    world.add_resource(bullet_time);
+}
+
+/// Label the run as being watched rather than played, with the given text.
+fn initialise_watch_label(world: &mut World, label: &str) {
+    let font = world.read_resource::<Score>().font.clone();
+
+    let replay_transform = UiTransform::new(
+        "Watching".to_string(),
+        Anchor::Middle,
+        0.,
+        130.,
+        1.,
+        600.,
+        50.,
+        0,
+    );
+
+    world
+        .create_entity()
+        .with(Layout::of(&replay_transform, Some(40.)))
+        .with(replay_transform)
+        .with(UiText::new(
+            font,
+            label.to_string(),
+            [1.0, 0.3, 0.3, 0.6],
+            40.,
+        )).build();
+}
+
+/// Spawn the asteroids of the level at the given path.
+fn initialise_level(world: &mut World, path: &Path) -> Result<(), InitError> {
+    let level = Level::load_no_fallback(path)
+        .map_err(|e| InitError::BrokenAsset(path.to_owned(), e.to_string()))?;
+
+    if let Some([x, y]) = level.spawn {
+        world.add_resource(SpawnPoint(Vector2::new(x, y)));
+    }
+
+    if let Some(planet) = &level.planet {
+        initialise_planet(world, planet);
+    }
+
+    let script = WaveScript::parse(&level.script)
+        .map_err(|e| InitError::BrokenAsset(path.to_owned(), e.to_string()))?;
+    world.add_resource(script);
+
+    for asteroid in level.asteroids.iter().chain(&level.ring_asteroids()) {
+        let velocity = Vector2::new(asteroid.velocity[0], asteroid.velocity[1]);
+
+        let mut physical = Physical::new();
+        physical.velocity = velocity;
+
+        let health = Health::new(Asteroids::hit_points(Asteroids::radius(asteroid.scale)));
+
+        let e = level_asteroid(world, asteroid)
+            .with(physical)
+            .with(health)
+            .with(Collider::Asteroid)
+            .build();
+
+        world
+            .write_resource::<EventChannel<GameEvent>>()
+            .single_write(GameEvent::AsteroidSpawned {
+                entity: e,
+                position: Vector2::new(asteroid.position[0], asteroid.position[1]),
+                velocity,
+                rotation: 0f32,
+                lateral_acceleration: 0f32,
+                scale: asteroid.scale,
+                comet: false,
+            });
+    }
+
+    Ok(())
+}
+
+/// Spawn the planet of a level.
+fn initialise_planet(world: &mut World, planet: &LevelPlanet) {
+    let mut local = Transform::default();
+    local.set_xyz(planet.position[0], planet.position[1], -0.1);
+
+    let scale = planet.radius / Planets::SPRITE_RADIUS;
+    *local.scale_mut() = Vector3::new(scale, scale, 1.0);
+
+    let sprite_render = world.read_resource::<Planets>().new_sprite_render();
+
+    world
+        .create_entity()
+        .with(local)
+        .with(sprite_render)
+        .with(Bounded::from_local(planet.radius))
+        .with(Collider::Planet)
+        .with(Planet {
+            gravity: planet.gravity,
+        }).build();
+}
+
+/// Start building an asteroid from a level, which isn't moving or colliding yet.
+fn level_asteroid<'w>(world: &'w mut World, asteroid: &LevelAsteroid) -> EntityBuilder<'w> {
+    let mut local = Transform::default();
+    local.set_xyz(asteroid.position[0], asteroid.position[1], 0.0);
+    *local.scale_mut() = Vector3::new(asteroid.scale, asteroid.scale, 1.0);
+
+    let (sprite_render, bounded) = {
+        let asteroids = world.read_resource::<Asteroids>();
+        let streams = world.read_resource::<RandomStreams>();
+        let sprite_render = asteroids.new_sprite_render(streams.get(Stream::States));
+        (sprite_render, asteroids.new_bounded(asteroid.scale))
+    };
+
+    world
+        .create_entity()
+        .with(local)
+        .with(ConstrainedObject)
+        .with(sprite_render)
+        .with(bounded)
+}
+
+/// Load the font used for all text in the game.
+fn load_font(world: &mut World) -> Result<FontHandle, InitError> {
+    const FONT: &str = "font/square.ttf";
+
+    assets::require(FONT)?;
+
+    Ok(world.read_resource::<Loader>().load(
+        FONT,
+        TtfFormat,
+        Default::default(),
+        (),
+        &world.read_resource(),
+    ))
+}
+
+fn initialize_score(
+    world: &mut World,
+    game: &Game,
+    dev_tools: DevTools,
+    players: usize,
+) -> Result<(), InitError> {
+    let font = load_font(world)?;
+
+    let score_transform = UiTransform::new(
+        "Score".to_string(),
+        Anchor::TopMiddle,
+        0.,
+        -50.,
+        1.,
+        200.,
+        50.,
+        0,
+    );
+
+    let score_text = world
+        .create_entity()
+        .with(Layout::of(&score_transform, Some(50.)))
+        .with(score_transform)
+        .with(UiText::new(
+            font.clone(),
+            "0".to_string(),
+            [1.0, 1.0, 1.0, 1.0],
+            50.,
+        )).build();
+
+    let best = world
+        .res
+        .try_fetch::<HighScores>()
+        .and_then(|h| h.best())
+        .unwrap_or(0);
+
+    let best_transform = UiTransform::new(
+        "Best".to_string(),
+        Anchor::TopMiddle,
+        -200.,
+        -50.,
+        1.,
+        200.,
+        50.,
+        0,
+    );
+
+    let best_text = world
+        .create_entity()
+        .with(Layout::of(&best_transform, Some(20.)))
+        .with(best_transform)
+        .with(UiText::new(
+            font.clone(),
+            Score::best_text(best),
+            [1.0, 1.0, 1.0, 1.0],
+            20.,
+        )).build();
+
+    let multiplier_transform = UiTransform::new(
+        "Multiplier".to_string(),
+        Anchor::TopMiddle,
+        200.,
+        -50.,
+        1.,
+        200.,
+        50.,
+        0,
+    );
+
+    let multiplier_text = world
+        .create_entity()
+        .with(Layout::of(&multiplier_transform, Some(20.)))
+        .with(multiplier_transform)
+        .with(UiText::new(
+            font.clone(),
+            Score::multiplier_text(1.0),
+            [1.0, 1.0, 1.0, 1.0],
+            20.,
+        )).build();
+
+    let mods_transform = UiTransform::new(
+        "Mods".to_string(),
+        Anchor::TopRight,
+        -200.,
+        -50.,
+        1.,
+        200.,
+        50.,
+        0,
+    );
+
+    let modifiers_text = world
+        .create_entity()
+        .with(Layout::of(&mods_transform, Some(20.)))
+        .with(mods_transform)
+        .with(UiText::new(
+            font.clone(),
+            game.modifiers.as_text(),
+            [1.0, 0.0, 0.0, 1.0],
+            20.,
+        )).build();
+
+    let dev_tools_transform = UiTransform::new(
+        "DevTools".to_string(),
+        Anchor::TopRight,
+        -200.,
+        -80.,
+        1.,
+        200.,
+        50.,
+        0,
+    );
+
+    let dev_tools_text = world
+        .create_entity()
+        .with(Layout::of(&dev_tools_transform, Some(20.)))
+        .with(dev_tools_transform)
+        .with(UiText::new(
+            font.clone(),
+            dev_tools.as_text(),
+            [1.0, 1.0, 0.0, 1.0],
+            20.,
+        )).build();
+
+    let players_transform = UiTransform::new(
+        "Players".to_string(),
+        Anchor::TopMiddle,
+        0.,
+        -90.,
+        1.,
+        300.,
+        30.,
+        0,
+    );
+
+    let players_text = world
+        .create_entity()
+        .with(Layout::of(&players_transform, Some(20.)))
+        .with(players_transform)
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            [1.0, 1.0, 1.0, 1.0],
+            20.,
+        )).build();
+
+    world.add_resource(Score {
+        font,
+        score_text,
+        asteroids: 0,
+        points: 0,
+        modifiers_text,
+        current_modifiers: game.modifiers,
+        dev_tools_text,
+        current_dev_tools: dev_tools,
+        best_text,
+        best,
+        accuracy: Accuracy::default(),
+        multiplier_text,
+        current_multiplier: 1.0,
+        player_points: vec![0; players],
+        banked: vec![0; players],
+        players_text,
+    });
+
+    let text = world.read_resource::<Score>().players_text();
+
+    if let Some(players_text) = world.write_storage::<UiText>().get_mut(players_text) {
+        players_text.text = text;
+    }
+
+    Ok(())
+}
+
+/// Initialise the quad flashed when measuring input latency.
+fn initialise_latency_probe(world: &mut World) {
+    let mut local = Transform::default();
+    local.set_xyz(8.0, 8.0, 0.5);
+    *local.scale_mut() = Vector3::new(4.0, 4.0, 1.0);
+
+    let sprite_render = {
+        let bullet_resource = world.read_resource::<Bullets>();
+        bullet_resource.new_sprite_render()
+    };
+
+    let flash = world
+        .create_entity()
+        .with(sprite_render)
+        .with(local)
+        .with(Hidden)
+        .build();
+
+    world.add_resource(LatencyProbe::new(flash));
+}
+
+/// Initialise the text of the debug overlay, which is empty until enabled.
+fn initialise_debug_overlay(world: &mut World) {
+    let font = world.read_resource::<Score>().font.clone();
+
+    let debug_transform = UiTransform::new(
+        "Debug".to_string(),
+        Anchor::TopLeft,
+        160.,
+        -170.,
+        1.,
+        300.,
+        300.,
+        0,
+    );
+
+    let text = world
+        .create_entity()
+        .with(Layout::of(&debug_transform, Some(14.)))
+        .with(debug_transform)
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            [0.6, 1.0, 0.6, 1.0],
+            14.,
+        )).build();
+
+    world.add_resource(DebugOverlay {
+        text,
+        timings: String::new(),
+    });
+
+    let histogram_transform = UiTransform::new(
+        "Histogram".to_string(),
+        Anchor::TopLeft,
+        160.,
+        -470.,
+        1.,
+        300.,
+        300.,
+        0,
+    );
+
+    let text = world
+        .create_entity()
+        .with(Layout::of(&histogram_transform, Some(12.)))
+        .with(histogram_transform)
+        .with(UiText::new(
+            font,
+            String::new(),
+            [0.6, 1.0, 0.6, 1.0],
+            12.,
+        )).build();
+
+    world.add_resource(HistogramOverlay { text });
+}
+
+/// State used when game is paused.
+#[derive(Default)]
+pub struct PauseState {
+    changelog: Action,
+    profiles: Action,
+    photo: Action,
+}
+
+impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for PauseState {
+    fn on_start(&mut self, data: StateData<Data>) {
+        println!("Game Paused");
+        data.world.add_resource(CurrentState(StateId::Paused));
+        limit_frame_rate(data.world, true);
+        muffle_audio(data.world, true);
+        data.world.write_resource::<InputContexts>().push(InputContext::Gameplay);
+    }
+
+    fn on_stop(&mut self, data: StateData<Data>) {
+        data.world.write_resource::<InputContexts>().pop(InputContext::Gameplay);
+    }
+
+    fn on_resume(&mut self, data: StateData<Data>) {
+        data.world.add_resource(CurrentState(StateId::Paused));
+    }
+
+    fn update(&mut self, data: StateData<Data>) -> CustomTrans<'a, 'b> {
+        let StateData {
+            data,
+            world,
+            ..
+        } = data;
+
+        let Data {
+            ref mut base,
+            ref mut main,
+        } = *data;
+
+        base.update(world);
+        main.dispatch(&world.res);
+
+        let pause = {
+            let mut game = world.write_resource::<Game>();
+            mem::replace(&mut game.pause, false)
+        };
+
+        if pause {
+            flush_world(world);
+            return Trans::Pop;
+        }
+
+        let mut changelog = false;
+        let mut profiles = false;
+        let mut photo = false;
+
+        {
+            let input = world.read_resource::<InputHandler<String, String>>();
+            self.changelog.test(&input, "changelog").activated(|| changelog = true);
+            self.profiles.test(&input, "profiles").activated(|| profiles = true);
+            self.photo.test(&input, "photo").activated(|| photo = true);
+        }
+
+        if changelog {
+            return Trans::Push(Box::new(ChangelogState::new()));
+        }
+
+        if profiles {
+            return Trans::Push(Box::new(ProfileState::new()));
+        }
+
+        if photo {
+            flush_world(world);
+            return Trans::Push(Box::new(PhotoState::new()));
+        }
+
+        Trans::None
+    }
+}
+
+/// Photo mode, entered from the pause menu with `photo`.
+///
+/// The game stays frozen and the HUD is hidden, while the camera is panned and zoomed around the
+/// arena by the `PhotoCameraSystem`. Pressing `photo`, `pause` or `quit` goes back to the pause
+/// menu.
+pub struct PhotoState {
+    /// UI entities hidden by photo mode, shown again when leaving it.
+    hidden: Vec<Entity>,
+    photo: Action,
+    quit: Action,
+}
+
+impl PhotoState {
+    pub fn new() -> Self {
+        Self {
+            hidden: Vec::new(),
+            // NB: photo mode is entered by pressing `photo`, which has to be released first.
+            photo: Action::Active,
+            quit: Action::default(),
+        }
+    }
+}
+
+impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for PhotoState {
+    fn on_start(&mut self, data: StateData<Data>) {
+        let world = data.world;
+
+        world.add_resource(CurrentState(StateId::Photo));
+        world.add_resource(PhotoCamera::default());
+        world.write_resource::<InputContexts>().push(InputContext::Photo);
+
+        let entities = world.entities();
+        let transforms = world.read_storage::<UiTransform>();
+        let mut hidden = world.write_storage::<Hidden>();
+
+        for (e, _, _) in (&*entities, &transforms, !&hidden).join() {
+            self.hidden.push(e);
+        }
+
+        for e in &self.hidden {
+            if let Err(e) = hidden.insert(*e, Hidden) {
+                error!("failed to hide HUD: {}", e);
+            }
+        }
+    }
+
+    fn on_stop(&mut self, data: StateData<Data>) {
+        let world = data.world;
+
+        world.write_resource::<InputContexts>().pop(InputContext::Photo);
+
+        let mut hidden = world.write_storage::<Hidden>();
+
+        for e in self.hidden.drain(..) {
+            hidden.remove(e);
+        }
+
+        let projection = PhotoCamera::default().projection();
+
+        for camera in (&mut world.write_storage::<Camera>()).join() {
+            *camera = Camera::from(projection);
+        }
+    }
+
+    fn update(&mut self, data: StateData<Data>) -> CustomTrans<'a, 'b> {
+        let StateData {
+            data,
+            world,
+            ..
+        } = data;
+
+        let Data {
+            ref mut base,
+            ref mut main,
+        } = *data;
+
+        base.update(world);
+        main.dispatch(&world.res);
+
+        let pause = {
+            let mut game = world.write_resource::<Game>();
+            mem::replace(&mut game.pause, false)
+        };
+
+        let mut leave = pause;
+
+        {
+            let input = world.read_resource::<InputHandler<String, String>>();
+            self.photo.test(&input, "photo").activated(|| leave = true);
+            self.quit.test(&input, "quit").activated(|| leave = true);
+        }
+
+        if leave {
+            return Trans::Pop;
+        }
+
+        Trans::None
+    }
+}
+
+/// Shown between waves, letting the player pick one of a few mutations for the rest of the run.
+///
+/// Mutations are picked by clicking on them, or by moving to them with up and down and pressing
+/// Enter.
+pub struct DraftState {
+    /// Mutations to choose between.
+    choices: Vec<Mutation>,
+    /// The entity showing the title, and one entity for each choice.
+    entities: Vec<Entity>,
+    /// Highlighted choice.
+    cursor: usize,
+    up: Repeat,
+    down: Repeat,
+    confirm: Action,
+}
+
+impl DraftState {
+    /// Height of each choice.
+    const ROW_HEIGHT: f32 = 40.;
+
+    pub fn new(choices: Vec<Mutation>) -> Self {
+        Self {
+            choices,
+            entities: Vec::new(),
+            cursor: 0,
+            up: Repeat::new(0.4, 0.15),
+            down: Repeat::new(0.4, 0.15),
+            confirm: Action::default(),
+        }
+    }
+
+    /// Update the choices after moving the cursor.
+    fn refresh(&self, world: &mut World) {
+        let mut texts = world.write_storage::<UiText>();
+
+        for (i, e) in self.entities.iter().skip(1).enumerate() {
+            let marker = if i == self.cursor { ">" } else { " " };
+            let mutation = self.choices[i];
+
+            if let Some(text) = texts.get_mut(*e) {
+                text.text = format!("{} {}: {}", marker, mutation.name(), mutation.description());
+            }
+        }
+    }
+
+    /// The choice shown by the given entity, if any.
+    fn choice_at(&self, entity: Entity) -> Option<usize> {
+        self.entities.iter().skip(1).position(|e| *e == entity)
+    }
+
+    /// Pick the choice at the given index, applying it to every ship for the rest of the run.
+    fn pick<'a, 'b>(&mut self, world: &mut World, index: usize) -> CustomTrans<'a, 'b> {
+        let mutation = self.choices[index];
+        info!("drafted mutation: {}", mutation.name());
+
+        world.write_resource::<Mutations>().picked.push(mutation);
+
+        world.write_resource::<Recorder>().pick(index);
+
+        if let Some(mut net) = world.res.try_fetch_mut::<NetSession>() {
+            if net.picks_mutations() {
+                net.send_pick(index);
+            }
+        }
+
+        let mut ships = world.write_storage::<Ship>();
+        let mut energies = world.write_storage::<Energy>();
+
+        for (ship, energy) in (&mut ships, &mut energies).join() {
+            mutation.apply(ship, energy);
+        }
+
+        Trans::Pop
+    }
+}
+
+impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for DraftState {
+    fn on_start(&mut self, data: StateData<Data>) {
+        let world = data.world;
+
+        world.add_resource(CurrentState(StateId::Draft));
+        limit_frame_rate(world, true);
+        muffle_audio(world, true);
+        world.write_resource::<InputContexts>().push(InputContext::Menu);
+
+        let font = world.read_resource::<Score>().font.clone();
+
+        let title_transform = UiTransform::new(
+            "DraftTitle".to_string(),
+            Anchor::Middle,
+            0.,
+            100.,
+            1.,
+            600.,
+            50.,
+            0,
+        );
+
+        let title = world
+            .create_entity()
+            .with(title_transform)
+            .with(UiText::new(
+                font.clone(),
+                "Wave cleared! Pick a mutation".to_string(),
+                [1.0, 1.0, 1.0, 1.0],
+                35.,
+            )).build();
+
+        self.entities.push(title);
+
+        for i in 0..self.choices.len() {
+            let transform = UiTransform::new(
+                format!("DraftChoice{}", i),
+                Anchor::Middle,
+                0.,
+                20. - i as f32 * Self::ROW_HEIGHT,
+                1.,
+                700.,
+                Self::ROW_HEIGHT,
+                0,
+            );
+
+            let choice = world
+                .create_entity()
+                .with(transform)
+                .with(Interactable)
+                .with(UiText::new(
+                    font.clone(),
+                    String::new(),
+                    [0.8, 0.8, 0.8, 1.0],
+                    25.,
+                )).build();
+
+            self.entities.push(choice);
+        }
+
+        self.refresh(world);
+    }
+
+    fn on_stop(&mut self, data: StateData<Data>) {
+        data.world.write_resource::<InputContexts>().pop(InputContext::Menu);
+
+        if let Err(e) = data.world.delete_entities(&self.entities) {
+            error!("failed to delete draft entities: {}", e);
+        }
+
+        self.entities.clear();
+    }
+
+    fn update(&mut self, data: StateData<Data>) -> CustomTrans<'a, 'b> {
+        let StateData {
+            data,
+            world,
+            ..
+        } = data;
+
+        let Data {
+            ref mut base,
+            ref mut main,
+        } = *data;
+
+        base.update(world);
+        main.dispatch(&world.res);
+
+        // NB: the game can't be paused while drafting.
+        world.write_resource::<Game>().pause = false;
+
+        // NB: an empty draft has nothing to pick.
+        if self.choices.is_empty() {
+            return Trans::Pop;
+        }
+
+        let replayed = world
+            .res
+            .try_fetch_mut::<Playback>()
+            .and_then(|mut p| p.next_pick());
+
+        if let Some(index) = replayed {
+            return self.pick(world, index.min(self.choices.len() - 1));
+        }
+
+        // NB: online, the host picks for both players.
+        let waiting = match world.res.try_fetch_mut::<NetSession>() {
+            Some(mut net) if !net.picks_mutations() => {
+                net.poll();
+                Some(net.next_pick())
+            }
+            _ => None,
+        };
+
+        match waiting {
+            Some(Some(index)) => return self.pick(world, index.min(self.choices.len() - 1)),
+            Some(None) => return Trans::None,
+            None => {}
+        }
+
+        let time_delta = world.read_resource::<Time>().delta_seconds();
+
+        let (up, down, confirm) = {
+            let input = world.read_resource::<InputHandler<String, String>>();
+
+            let up = self.up.test(&input, "menu_up", time_delta);
+            let down = self.down.test(&input, "menu_down", time_delta);
+
+            let mut confirm = false;
+            self.confirm.test(&input, "confirm").activated(|| confirm = true);
+            (up as usize, down as usize, confirm)
+        };
+
+        if confirm {
+            return self.pick(world, self.cursor);
+        }
+
+        let cursor = (self.cursor + down)
+            .saturating_sub(up)
+            .min(self.choices.len() - 1);
+
+        if cursor != self.cursor {
+            self.cursor = cursor;
+            self.refresh(world);
+        }
+
+        Trans::None
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<Data>,
+        event: StateEvent,
+    ) -> CustomTrans<'a, 'b> {
+        // NB: online, the guest waits for the host to pick.
+        let guest = data
+            .world
+            .res
+            .try_fetch::<NetSession>()
+            .map(|net| !net.picks_mutations())
+            .unwrap_or(false);
+
+        if guest {
+            return Trans::None;
+        }
+
+        if let StateEvent::Ui(UiEvent { event_type, target }) = event {
+            let index = match self.choice_at(target) {
+                Some(index) => index,
+                None => return Trans::None,
+            };
+
+            match event_type {
+                UiEventType::Click => return self.pick(data.world, index),
+                UiEventType::HoverStart => {
+                    self.cursor = index;
+                    self.refresh(data.world);
+                }
+                _ => {}
+            }
+        }
+
+        Trans::None
+    }
+}
+
+/// Items of the main menu, from top to bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuItem {
+    Start,
+    WatchBestRun,
+    /// The binding profile of the given player.
+    Bindings(usize),
+    /// The hull color picked in the active profile.
+    HullColor,
+    /// The decal picked in the active profile.
+    Decal,
+    /// The theme pack, see `Themes`.
+    Theme,
+    GodMode,
+    Quit,
+}
+
+impl MenuItem {
+    const ALL: [MenuItem; 9] = [
+        MenuItem::Start,
+        MenuItem::WatchBestRun,
+        MenuItem::Bindings(0),
+        MenuItem::Bindings(1),
+        MenuItem::HullColor,
+        MenuItem::Decal,
+        MenuItem::Theme,
+        MenuItem::GodMode,
+        MenuItem::Quit,
+    ];
+
+    fn text(self, menu: &MenuState, settings: &Settings) -> String {
+        match self {
+            MenuItem::Start => "Start".to_string(),
+            MenuItem::WatchBestRun if menu.has_best_run => "Watch best run".to_string(),
+            MenuItem::WatchBestRun => "Watch best run (none yet)".to_string(),
+            MenuItem::Bindings(player) => {
+                let bindings = settings.bindings(player).map(|b| b.name.as_str());
+                format!("Player {}: {}", player + 1, bindings.unwrap_or("none"))
+            }
+            MenuItem::HullColor => match menu.look {
+                Some(look) => format!("Hull: {}", look.color_name()),
+                None => "Hull: pick a profile first".to_string(),
+            },
+            MenuItem::Decal => match menu.look {
+                Some(look) => format!("Decal: {}", look.decal_name()),
+                None => "Decal: pick a profile first".to_string(),
+            },
+            MenuItem::Theme => format!("Theme: {}", settings.theme),
+            MenuItem::GodMode if menu.dev_tools.immortal => "God mode: on".to_string(),
+            MenuItem::GodMode => "God mode: off".to_string(),
+            MenuItem::Quit => "Quit".to_string(),
+        }
+    }
+}
+
+/// The main menu, shown when the game starts unless it was started with `--quick`.
+///
+/// Items are picked by clicking on them, or by moving to them with up and down and pressing Enter.
+pub struct MenuState {
+    session: SessionSettings,
+    /// The entity showing the title, and one entity for each item.
+    entities: Vec<Entity>,
+    /// Highlighted item.
+    cursor: usize,
+    /// Dev tools as currently shown, since they can also be toggled with their key bindings.
+    dev_tools: DevTools,
+    /// If the replay of the best run has been saved.
+    has_best_run: bool,
+    /// Look of the ship picked in the active profile as currently shown, if there is one.
+    look: Option<ShipLook>,
+    /// Theme packs which can be picked.
+    themes: Themes,
+    /// Error raised while setting up the menu, if any.
+    error: Option<InitError>,
+    up: Repeat,
+    down: Repeat,
+    confirm: Action,
+}
+
+impl MenuState {
+    /// Height of each item.
+    const ROW_HEIGHT: f32 = 40.;
+
+    pub fn new(session: SessionSettings) -> Self {
+        Self {
+            session,
+            entities: Vec::new(),
+            cursor: 0,
+            dev_tools: DevTools::default(),
+            has_best_run: false,
+            look: None,
+            themes: Themes::default(),
+            error: None,
+            up: Repeat::new(0.4, 0.15),
+            down: Repeat::new(0.4, 0.15),
+            confirm: Action::default(),
+        }
+    }
+
+    /// Update the items after moving the cursor or toggling an option.
+    fn refresh(&mut self, world: &mut World) {
+        self.dev_tools = *world.read_resource::<DevTools>();
+        self.look = active_look(world);
+        let settings = world.read_resource::<Settings>();
+        let mut texts = world.write_storage::<UiText>();
+
+        for (i, e) in self.entities.iter().skip(1).enumerate() {
+            let marker = if i == self.cursor { ">" } else { " " };
+
+            if let Some(text) = texts.get_mut(*e) {
+                let item = MenuItem::ALL[i].text(self, &settings);
+                text.text = format!("{} {}", marker, item);
+            }
+        }
+    }
+
+    /// The item shown by the given entity, if any.
+    fn item_at(&self, entity: Entity) -> Option<usize> {
+        self.entities.iter().skip(1).position(|e| *e == entity)
+    }
+
+    /// Act on the item at the given index.
+    fn pick<'a, 'b>(&mut self, world: &mut World, index: usize) -> CustomTrans<'a, 'b> {
+        match MenuItem::ALL[index] {
+            MenuItem::Start => {
+                // god mode might also have been toggled with its regular key binding.
+                let mut session = self.session.clone();
+                session.dev = *world.read_resource::<DevTools>();
+                Trans::Switch(Box::new(MainGameState::new(session)))
+            }
+            MenuItem::WatchBestRun => {
+                let path = match Replay::best_path() {
+                    Ok(path) => path,
+                    Err(e) => {
+                        warn!("failed to locate best run: {}", e);
+                        return Trans::None;
+                    }
+                };
+
+                if !path.is_file() {
+                    return Trans::None;
+                }
+
+                match Replay::read(&path) {
+                    Ok(replay) => {
+                        let mut session = self.session.clone();
+                        session.dev = *world.read_resource::<DevTools>();
+                        Trans::Switch(Box::new(MainGameState::watch(session, path, &replay)))
+                    }
+                    Err(e) => {
+                        warn!("failed to read best run: {}: {}", path.display(), e);
+                        Trans::None
+                    }
+                }
+            }
+            MenuItem::Bindings(player) => {
+                world.write_resource::<Settings>().cycle_bindings(player);
+                self.refresh(world);
+                Trans::None
+            }
+            MenuItem::HullColor | MenuItem::Decal => {
+                if let Some(mut profiles) = world.res.try_fetch_mut::<Profiles>() {
+                    if profiles.active().is_some() {
+                        if MenuItem::ALL[index] == MenuItem::HullColor {
+                            profiles.cycle_color();
+                        } else {
+                            profiles.cycle_decal();
+                        }
+
+                        profiles.save();
+                    }
+                }
+
+                self.refresh(world);
+                Trans::None
+            }
+            MenuItem::Theme => {
+                {
+                    let mut settings = world.write_resource::<Settings>();
+                    settings.theme = self.themes.next(&settings.theme);
+                }
+
+                self.refresh(world);
+                Trans::None
+            }
+            MenuItem::GodMode => {
+                {
+                    let mut dev_tools = world.write_resource::<DevTools>();
+                    dev_tools.immortal = !dev_tools.immortal;
+                }
+
+                self.refresh(world);
+                Trans::None
+            }
+            MenuItem::Quit => {
+                world.read_resource::<Settings>().save();
+                Trans::Quit
+            }
+        }
+    }
+}
+
+impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for MenuState {
+    fn on_start(&mut self, data: StateData<Data>) {
+        let world = data.world;
+
+        world.add_resource(CurrentState(StateId::Menu));
+        limit_frame_rate(world, true);
+        muffle_audio(world, true);
+        world.write_resource::<InputContexts>().push(InputContext::Menu);
+
+        // NB: the global input system expects the game to be present.
+        world.add_resource(Game::default());
+        world.add_resource(self.session.dev);
+
+        self.has_best_run = Replay::best_path().map(|p| p.is_file()).unwrap_or(false);
+        self.themes = Themes::read();
+
+        let font = match load_font(world) {
+            Ok(font) => font,
+            Err(e) => {
+                self.error = Some(e);
+                return;
+            }
+        };
+
+        let title_transform = UiTransform::new(
+            "MenuTitle".to_string(),
+            Anchor::Middle,
+            0.,
+            100.,
+            1.,
+            600.,
+            60.,
+            0,
+        );
+
+        let title = world
+            .create_entity()
+            .with(title_transform)
+            .with(UiText::new(
+                font.clone(),
+                "Asteroids!".to_string(),
+                [1.0, 1.0, 1.0, 1.0],
+                50.,
+            )).build();
+
+        self.entities.push(title);
+
+        for i in 0..MenuItem::ALL.len() {
+            let transform = UiTransform::new(
+                format!("MenuItem{}", i),
+                Anchor::Middle,
+                0.,
+                20. - i as f32 * Self::ROW_HEIGHT,
+                1.,
+                300.,
+                Self::ROW_HEIGHT,
+                0,
+            );
+
+            let item = world
+                .create_entity()
+                .with(transform)
+                .with(Interactable)
+                .with(UiText::new(
+                    font.clone(),
+                    String::new(),
+                    [0.8, 0.8, 0.8, 1.0],
+                    25.,
+                )).build();
+
+            self.entities.push(item);
+        }
+
+        self.refresh(world);
+    }
+
+    fn on_stop(&mut self, data: StateData<Data>) {
+        data.world.write_resource::<InputContexts>().pop(InputContext::Menu);
+
+        if let Err(e) = data.world.delete_entities(&self.entities) {
+            error!("failed to delete menu entities: {}", e);
+        }
+
+        self.entities.clear();
+    }
+
+    fn update(&mut self, data: StateData<Data>) -> CustomTrans<'a, 'b> {
+        let StateData {
+            data,
+            world,
+            ..
+        } = data;
+
+        if let Some(error) = self.error.take() {
+            world.delete_all();
+            let retry = Retry::Menu(self.session.clone());
+            return Trans::Switch(Box::new(ErrorState::new(error, retry)));
+        }
+
+        data.base.update(world);
+
+        // NB: there is no game to pause yet.
+        world.write_resource::<Game>().pause = false;
+
+        let time_delta = world.read_resource::<Time>().delta_seconds();
+
+        let (up, down, confirm) = {
+            let input = world.read_resource::<InputHandler<String, String>>();
+
+            let up = self.up.test(&input, "menu_up", time_delta);
+            let down = self.down.test(&input, "menu_down", time_delta);
+
+            let mut confirm = false;
+            self.confirm.test(&input, "confirm").activated(|| confirm = true);
+            (up as usize, down as usize, confirm)
+        };
+
+        if confirm {
+            return self.pick(world, self.cursor);
+        }
+
+        let cursor = (self.cursor + down)
+            .saturating_sub(up)
+            .min(MenuItem::ALL.len() - 1);
+
+        // NB: the active profile might also have been changed from the profiles screen.
+        let changed = *world.read_resource::<DevTools>() != self.dev_tools
+            || active_look(world) != self.look;
+
+        if cursor != self.cursor || changed {
+            self.cursor = cursor;
+            self.refresh(world);
+        }
+
+        Trans::None
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<Data>,
+        event: StateEvent,
+    ) -> CustomTrans<'a, 'b> {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                data.world.read_resource::<Settings>().save();
+                return Trans::Quit;
+            }
+        }
+
+        if let StateEvent::Ui(UiEvent { event_type, target }) = event {
+            let index = match self.item_at(target) {
+                Some(index) => index,
+                None => return Trans::None,
+            };
+
+            match event_type {
+                UiEventType::Click => return self.pick(data.world, index),
+                UiEventType::HoverStart => {
+                    self.cursor = index;
+                    self.refresh(data.world);
+                }
+                _ => {}
+            }
+        }
+
+        Trans::None
+    }
+}
+
+/// Pages of the setup shown the first time the game runs, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SetupPage {
+    Controls,
+    Volume,
+    UiScale,
+    Palette,
+}
+
+impl SetupPage {
+    const ALL: [SetupPage; 4] = [
+        SetupPage::Controls,
+        SetupPage::Volume,
+        SetupPage::UiScale,
+        SetupPage::Palette,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            SetupPage::Controls => "Controls",
+            SetupPage::Volume => "Volume",
+            SetupPage::UiScale => "Text size",
+            SetupPage::Palette => "Player colors",
+        }
+    }
+
+    /// What is being picked on the page.
+    fn help(self) -> &'static str {
+        match self {
+            SetupPage::Controls => "How you fly your ship. Co-op players pick theirs in the menu.",
+            SetupPage::Volume => "Can also be changed with the volume keys while playing.",
+            SetupPage::UiScale => "How large text is drawn, on top of scaling with the window.",
+            SetupPage::Palette => "How players are told apart in co-op.",
+        }
+    }
+
+    /// The current choice on the page.
+    fn choice(self, settings: &Settings) -> String {
+        match self {
+            SetupPage::Controls => match settings.bindings(0) {
+                Some(bindings) => bindings.name.clone(),
+                None => "none".to_string(),
+            },
+            SetupPage::Volume => format!("{}%", settings.audio.percent()),
+            SetupPage::UiScale => format!("{}%", (settings.ui_scale() * 100.0).round()),
+            SetupPage::Palette => settings.palette.name().to_string(),
+        }
+    }
+
+    /// Switch over to the next choice on the page.
+    fn cycle(self, settings: &mut Settings) {
+        match self {
+            SetupPage::Controls => settings.cycle_bindings(0),
+            SetupPage::Volume => settings.audio.cycle(),
+            SetupPage::UiScale => settings.cycle_ui_scale(),
+            SetupPage::Palette => settings.palette = settings.palette.toggle(),
+        }
+    }
+}
+
+/// Items of each page of the setup, from top to bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SetupItem {
+    /// The choice made on the page.
+    Choice,
+    /// Move on to the next page, or finish on the last one.
+    Next,
+    /// Go back to the previous page, or skip the setup on the first one.
+    Back,
+}
+
+impl SetupItem {
+    const ALL: [SetupItem; 3] = [SetupItem::Choice, SetupItem::Next, SetupItem::Back];
+}
+
+/// A short setup shown the first time the game runs, before the main menu.
+///
+/// Walks through a page for each of the settings players are most likely to want to change before
+/// their first run, and writes the initial settings file once finished or skipped. Navigated like
+/// the main menu.
+pub struct SetupState {
+    session: SessionSettings,
+    /// The entities showing the title and help of the page, and one entity for each item.
+    entities: Vec<Entity>,
+    /// Index of the current page.
+    page: usize,
+    /// Highlighted item.
+    cursor: usize,
+    /// Error raised while setting up the page, if any.
+    error: Option<InitError>,
+    up: Repeat,
+    down: Repeat,
+    confirm: Action,
+}
+
+impl SetupState {
+    /// Height of each item.
+    const ROW_HEIGHT: f32 = 40.;
+
+    pub fn new(session: SessionSettings) -> Self {
+        Self {
+            session,
+            entities: Vec::new(),
+            page: 0,
+            cursor: 0,
+            error: None,
+            up: Repeat::new(0.4, 0.15),
+            down: Repeat::new(0.4, 0.15),
+            confirm: Action::default(),
+        }
+    }
+
+    /// Update the page after moving the cursor, changing a choice or turning the page.
+    fn refresh(&mut self, world: &mut World) {
+        let page = SetupPage::ALL[self.page];
+        let last = self.page + 1 == SetupPage::ALL.len();
+
+        let settings = world.read_resource::<Settings>();
+        let mut texts = world.write_storage::<UiText>();
+
+        if let Some(text) = self.entities.get(0).and_then(|e| texts.get_mut(*e)) {
+            text.text = format!(
+                "{} ({}/{})",
+                page.title(),
+                self.page + 1,
+                SetupPage::ALL.len()
+            );
+        }
+
+        if let Some(text) = self.entities.get(1).and_then(|e| texts.get_mut(*e)) {
+            text.text = page.help().to_string();
+        }
+
+        for (i, e) in self.entities.iter().skip(2).enumerate() {
+            let marker = if i == self.cursor { ">" } else { " " };
+
+            let item = match SetupItem::ALL[i] {
+                SetupItem::Choice => page.choice(&settings),
+                SetupItem::Next if last => "Done".to_string(),
+                SetupItem::Next => "Next".to_string(),
+                SetupItem::Back if self.page == 0 => "Skip".to_string(),
+                SetupItem::Back => "Back".to_string(),
+            };
+
+            if let Some(text) = texts.get_mut(*e) {
+                text.text = format!("{} {}", marker, item);
+
+                // NB: preview the colors of the palette on the choice itself.
+                text.color = match (page, SetupItem::ALL[i]) {
+                    (SetupPage::Palette, SetupItem::Choice) => settings.palette.colors()[0],
+                    _ => [0.8, 0.8, 0.8, 1.0],
+                };
+            }
+        }
+    }
+
+    /// The item shown by the given entity, if any.
+    fn item_at(&self, entity: Entity) -> Option<usize> {
+        self.entities.iter().skip(2).position(|e| *e == entity)
+    }
+
+    /// Act on the item at the given index.
+    fn pick<'a, 'b>(&mut self, world: &mut World, index: usize) -> CustomTrans<'a, 'b> {
+        match SetupItem::ALL[index] {
+            SetupItem::Choice => {
+                SetupPage::ALL[self.page].cycle(&mut world.write_resource::<Settings>());
+            }
+            SetupItem::Next if self.page + 1 == SetupPage::ALL.len() => return self.finish(world),
+            SetupItem::Next => {
+                self.page += 1;
+                self.cursor = 0;
+            }
+            SetupItem::Back if self.page == 0 => return self.finish(world),
+            SetupItem::Back => {
+                self.page -= 1;
+                self.cursor = 0;
+            }
+        }
+
+        self.refresh(world);
+        Trans::None
+    }
+
+    /// Save the settings picked so far, and move on to the main menu.
+    fn finish<'a, 'b>(&mut self, world: &mut World) -> CustomTrans<'a, 'b> {
+        let settings = world.read_resource::<Settings>();
+        settings.save();
+
+        let mut session = self.session.clone();
+        session.palette = settings.palette.colors().to_vec();
+        Trans::Switch(Box::new(MenuState::new(session)))
+    }
+}
+
+impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for SetupState {
+    fn on_start(&mut self, data: StateData<Data>) {
+        let world = data.world;
+
+        world.add_resource(CurrentState(StateId::Menu));
+        limit_frame_rate(world, true);
+        world.write_resource::<InputContexts>().push(InputContext::Menu);
+
+        // NB: the global input system expects the game to be present.
+        world.add_resource(Game::default());
+        world.add_resource(self.session.dev);
+
+        let font = match load_font(world) {
+            Ok(font) => font,
+            Err(e) => {
+                self.error = Some(e);
+                return;
+            }
+        };
+
+        // NB: laid out like the HUD, so that changing the UI scale shows right away.
+        let headers = [
+            ("SetupTitle", 100., 600., 60., 40.),
+            ("SetupHelp", 50., 900., 30., 20.),
+        ];
+
+        for (id, y, width, height, font_size) in headers.iter().cloned() {
+            let transform =
+                UiTransform::new(id.to_string(), Anchor::Middle, 0., y, 1., width, height, 0);
+
+            let header = world
+                .create_entity()
+                .with(Layout::of(&transform, Some(font_size)))
+                .with(transform)
+                .with(UiText::new(
+                    font.clone(),
+                    String::new(),
+                    [1.0, 1.0, 1.0, 1.0],
+                    font_size,
+                )).build();
+
+            self.entities.push(header);
+        }
+
+        for i in 0..SetupItem::ALL.len() {
+            let transform = UiTransform::new(
+                format!("SetupItem{}", i),
+                Anchor::Middle,
+                0.,
+                -(i as f32) * Self::ROW_HEIGHT,
+                1.,
+                400.,
+                Self::ROW_HEIGHT,
+                0,
+            );
+
+            let item = world
+                .create_entity()
+                .with(Layout::of(&transform, Some(25.)))
+                .with(transform)
+                .with(Interactable)
+                .with(UiText::new(
+                    font.clone(),
+                    String::new(),
+                    [0.8, 0.8, 0.8, 1.0],
+                    25.,
+                )).build();
+
+            self.entities.push(item);
+        }
+
+        self.refresh(world);
+    }
+
+    fn on_stop(&mut self, data: StateData<Data>) {
+        data.world.write_resource::<InputContexts>().pop(InputContext::Menu);
+
+        if let Err(e) = data.world.delete_entities(&self.entities) {
+            error!("failed to delete setup entities: {}", e);
+        }
+
+        self.entities.clear();
+    }
+
+    fn update(&mut self, data: StateData<Data>) -> CustomTrans<'a, 'b> {
+        let StateData {
+            data,
+            world,
+            ..
+        } = data;
+
+        if let Some(error) = self.error.take() {
+            world.delete_all();
+            let retry = Retry::Menu(self.session.clone());
+            return Trans::Switch(Box::new(ErrorState::new(error, retry)));
+        }
+
+        data.base.update(world);
+
+        // NB: there is no game to pause yet.
+        world.write_resource::<Game>().pause = false;
+
+        let time_delta = world.read_resource::<Time>().delta_seconds();
+
+        let (up, down, confirm) = {
+            let input = world.read_resource::<InputHandler<String, String>>();
+
+            let up = self.up.test(&input, "menu_up", time_delta);
+            let down = self.down.test(&input, "menu_down", time_delta);
+
+            let mut confirm = false;
+            self.confirm.test(&input, "confirm").activated(|| confirm = true);
+            (up as usize, down as usize, confirm)
+        };
+
+        if confirm {
+            return self.pick(world, self.cursor);
+        }
+
+        let cursor = (self.cursor + down)
+            .saturating_sub(up)
+            .min(SetupItem::ALL.len() - 1);
+
+        if cursor != self.cursor {
+            self.cursor = cursor;
+            self.refresh(world);
+        }
+
+        Trans::None
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<Data>,
+        event: StateEvent,
+    ) -> CustomTrans<'a, 'b> {
+        // NB: settings are left unsaved, so that the setup is shown again next time.
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+        }
+
+        if let StateEvent::Ui(UiEvent { event_type, target }) = event {
+            let index = match self.item_at(target) {
+                Some(index) => index,
+                None => return Trans::None,
+            };
+
+            match event_type {
+                UiEventType::Click => return self.pick(data.world, index),
+                UiEventType::HoverStart => {
+                    self.cursor = index;
+                    self.refresh(data.world);
+                }
+                _ => {}
+            }
+        }
+
+        Trans::None
+    }
+}
+
+/// How the ship looks in the active profile, if there is one.
+fn active_look(world: &World) -> Option<ShipLook> {
+    world
+        .res
+        .try_fetch::<Profiles>()
+        .and_then(|profiles| profiles.active().map(|p| p.look()))
+}
+
+/// Shown when the player has run out of lives, with the final score.
+///
+/// R restarts the game, and Escape returns to the main menu. With clips, F9 saves the last seconds
+/// of the run.
+pub struct GameOverState {
+    /// When the run started (s), used to record the run if the game is closed from here.
+    started: f64,
+    entities: Vec<Entity>,
+    menu: Action,
+    #[cfg(feature = "clips")]
+    save_clip: Action,
+}
+
+impl GameOverState {
+    #[cfg(not(feature = "clips"))]
+    const HELP: &'static str = "Press R to restart, or Escape for the menu";
+    #[cfg(feature = "clips")]
+    const HELP: &'static str = "Press R to restart, F9 to save a clip, or Escape for the menu";
+
+    pub fn new(started: f64) -> Self {
+        Self {
+            started,
+            entities: Vec::new(),
+            menu: Action::default(),
+            #[cfg(feature = "clips")]
+            save_clip: Action::default(),
+        }
+    }
+}
+
+impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for GameOverState {
+    fn on_start(&mut self, data: StateData<Data>) {
+        let world = data.world;
+
+        world.add_resource(CurrentState(StateId::GameOver));
+        limit_frame_rate(world, true);
+        world.write_resource::<InputContexts>().push(InputContext::Gameplay);
+
+        let (font, summary) = {
+            let score = world.read_resource::<Score>();
+            let summary = format!(
+                "Score: {}, asteroids destroyed: {}",
+                score.points, score.asteroids
+            );
+            (score.font.clone(), summary)
+        };
+
+        let day = world
+            .res
+            .try_fetch::<HighScores>()
+            .and_then(|high_scores| high_scores.day);
+
+        let title = match day {
+            Some(day) => format!(
+                "Daily {} over, next in {}",
+                daily::date(day),
+                daily::time_to_next()
+            ),
+            None => "Game Over".to_string(),
+        };
+
+        let lines = [
+            ("GameOverTitle", title, 40.),
+            ("GameOverScore", summary, 20.),
+            ("GameOverHelp", Self::HELP.to_string(), 18.),
+        ];
+
+        for (i, (id, text, size)) in lines.iter().enumerate() {
+            let transform = UiTransform::new(
+                id.to_string(),
+                Anchor::Middle,
+                0.,
+                50. - i as f32 * 50.,
+                2.,
+                800.,
+                50.,
+                0,
+            );
+
+            let line = world
+                .create_entity()
+                .with(transform)
+                .with(UiText::new(
+                    font.clone(),
+                    text.clone(),
+                    [1.0, 1.0, 1.0, 1.0],
+                    *size,
+                )).build();
+
+            self.entities.push(line);
+        }
+    }
+
+    fn on_stop(&mut self, data: StateData<Data>) {
+        data.world.write_resource::<InputContexts>().pop(InputContext::Gameplay);
+
+        if let Err(e) = data.world.delete_entities(&self.entities) {
+            error!("failed to delete game over entities: {}", e);
+        }
+
+        self.entities.clear();
+    }
+
+    fn update(&mut self, data: StateData<Data>) -> CustomTrans<'a, 'b> {
+        let StateData {
+            data,
+            world,
+            ..
+        } = data;
+
+        let Data {
+            ref mut base,
+            ref mut main,
+        } = *data;
+
+        base.update(world);
+        main.dispatch(&world.res);
+
+        let mut menu = false;
+
+        self.menu
+            .test(&world.read_resource::<InputHandler<String, String>>(), "quit")
+            .activated(|| menu = true);
+
+        #[cfg(feature = "clips")]
+        {
+            let mut save = false;
+
+            self.save_clip
+                .test(&world.read_resource::<InputHandler<String, String>>(), "save_clip")
+                .activated(|| save = true);
+
+            if save {
+                world.write_resource::<ClipRecorder>().save();
+            }
+        }
+
+        // the game state underneath restarts, or returns to the menu.
+        let done = {
+            let mut game = world.write_resource::<Game>();
+            game.pause = false;
+            game.menu |= menu;
+            game.restart || game.menu
+        };
+
+        if done {
+            flush_world(world);
+            return Trans::Pop;
+        }
+
+        Trans::None
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<Data>,
+        event: StateEvent,
+    ) -> CustomTrans<'a, 'b> {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                data.world.read_resource::<Settings>().save();
+                save_game_log(data.world);
+                record_run(data.world, self.started);
+                return Trans::Quit;
+            }
+        }
+
+        Trans::None
+    }
+}
+
+/// Shows a scrollable "What's new" screen, from the changelog embedded in the game.
+pub struct ChangelogState {
+    /// All lines of the changelog.
+    lines: Vec<String>,
+    /// The entity showing the title, and one entity for each visible line.
+    entities: Vec<Entity>,
+    /// Index of the first visible line.
+    offset: usize,
+    up: Repeat,
+    down: Repeat,
+    close: Action,
+}
+
+impl ChangelogState {
+    /// Number of lines visible at once.
+    const VISIBLE_LINES: usize = 16;
+    /// Height of each line.
+    const LINE_HEIGHT: f32 = 25.;
+
+    pub fn new() -> Self {
+        Self {
+            lines: Vec::new(),
+            entities: Vec::new(),
+            offset: 0,
+            up: Repeat::new(0.4, 0.08),
+            down: Repeat::new(0.4, 0.08),
+            close: Action::default(),
+        }
+    }
+
+    /// Update the visible lines after scrolling.
+    fn refresh(&self, world: &mut World) {
+        let mut texts = world.write_storage::<UiText>();
+
+        for (i, e) in self.entities.iter().skip(1).enumerate() {
+            if let Some(text) = texts.get_mut(*e) {
+                text.text = self
+                    .lines
+                    .get(self.offset + i)
+                    .cloned()
+                    .unwrap_or_default();
+            }
+        }
+    }
+}
+
+impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for ChangelogState {
+    fn on_start(&mut self, data: StateData<Data>) {
+        let world = data.world;
+        world.write_resource::<InputContexts>().push(InputContext::Menu);
+
+        self.lines = Changelog::embedded().lines();
+        self.offset = 0;
+
+        let font = world.read_resource::<Score>().font.clone();
+
+        let title_transform = UiTransform::new(
+            "ChangelogTitle".to_string(),
+            Anchor::TopMiddle,
+            0.,
+            -50.,
+            2.,
+            600.,
+            50.,
+            0,
+        );
+
+        let title = world
+            .create_entity()
+            .with(title_transform)
+            .with(UiText::new(
+                font.clone(),
+                "What's new".to_string(),
+                [1.0, 1.0, 1.0, 1.0],
+                40.,
+            )).build();
+
+        self.entities.push(title);
+
+        for i in 0..Self::VISIBLE_LINES {
+            let transform = UiTransform::new(
+                format!("ChangelogLine{}", i),
+                Anchor::TopMiddle,
+                0.,
+                -110. - i as f32 * Self::LINE_HEIGHT,
+                2.,
+                600.,
+                Self::LINE_HEIGHT,
+                0,
+            );
+
+            let line = world
+                .create_entity()
+                .with(transform)
+                .with(UiText::new(
+                    font.clone(),
+                    String::new(),
+                    [0.8, 0.8, 0.8, 1.0],
+                    18.,
+                )).build();
+
+            self.entities.push(line);
+        }
+
+        self.refresh(world);
+    }
+
+    fn on_stop(&mut self, data: StateData<Data>) {
+        data.world.write_resource::<InputContexts>().pop(InputContext::Menu);
+
+        if let Err(e) = data.world.delete_entities(&self.entities) {
+            error!("failed to delete changelog entities: {}", e);
+        }
+
+        self.entities.clear();
+    }
+
+    fn update(&mut self, data: StateData<Data>) -> CustomTrans<'a, 'b> {
+        let StateData {
+            data,
+            world,
+            ..
+        } = data;
+
+        data.base.update(world);
+
+        let time_delta = world.read_resource::<Time>().delta_seconds();
+
+        let (up, down, mut close) = {
+            let input = world.read_resource::<InputHandler<String, String>>();
+
+            let up = self.up.test(&input, "menu_up", time_delta);
+            let down = self.down.test(&input, "menu_down", time_delta);
+
+            let mut close = false;
+            self.close.test(&input, "changelog").activated(|| close = true);
+            (up as usize, down as usize, close)
+        };
+
+        // pausing again also closes the changelog, returning to the pause screen.
+        {
+            let mut game = world.write_resource::<Game>();
+            close |= mem::replace(&mut game.pause, false);
+        }
+
+        if close {
+            return Trans::Pop;
+        }
+
+        let max_offset = self.lines.len().saturating_sub(Self::VISIBLE_LINES);
+        let offset = (self.offset + down).saturating_sub(up).min(max_offset);
+
+        if offset != self.offset {
+            self.offset = offset;
+            self.refresh(world);
+        }
+
+        Trans::None
+    }
+}
+
+/// Lets the player pick the active profile, or type in the name of a new one.
+///
+/// * Up and down move between profiles, Enter picks the highlighted one and Escape closes.
+/// * Typing while "New profile" is highlighted enters the name of a new profile.
+pub struct ProfileState {
+    /// The entity showing the title, and one entity for each visible row.
+    entities: Vec<Entity>,
+    /// Highlighted row, where the row after the last profile is for a new profile.
+    cursor: usize,
+    /// Name of the new profile.
+    entry: TextEntry,
+    up: Repeat,
+    down: Repeat,
+    confirm: Action,
+    close: Action,
+}
+
+impl ProfileState {
+    /// Number of rows visible at once.
+    const VISIBLE_ROWS: usize = 10;
+    /// Height of each row.
+    const ROW_HEIGHT: f32 = 30.;
+
+    pub fn new() -> Self {
+        Self {
+            entities: Vec::new(),
+            cursor: 0,
+            entry: TextEntry::new(Profiles::MAX_NAME),
+            up: Repeat::new(0.4, 0.08),
+            down: Repeat::new(0.4, 0.08),
+            confirm: Action::default(),
+            close: Action::default(),
+        }
+    }
+
+    /// If the row for a new profile is highlighted.
+    fn is_entering(&self, world: &World) -> bool {
+        self.cursor >= world.read_resource::<Profiles>().profiles.len()
+    }
+
+    /// Update the visible rows after moving the cursor or typing.
+    fn refresh(&self, world: &mut World) {
+        let profiles = world.read_resource::<Profiles>();
+        let mut texts = world.write_storage::<UiText>();
+
+        let first = (self.cursor + 1).saturating_sub(Self::VISIBLE_ROWS);
+
+        for (i, e) in self.entities.iter().skip(1).enumerate() {
+            let row = first + i;
+            let marker = if row == self.cursor { ">" } else { " " };
+
+            let line = match profiles.profiles.get(row) {
+                Some(profile) => {
+                    let active = if profiles.active == Some(row) { "*" } else { " " };
+
+                    match profile.best() {
+                        Some(best) => format!("{}{} {} ({})", marker, active, profile.name, best),
+                        None => format!("{}{} {}", marker, active, profile.name),
+                    }
+                }
+                None if row == profiles.profiles.len() => {
+                    let cursor = if row == self.cursor { "_" } else { "" };
+                    format!("{}  New profile: {}{}", marker, self.entry.text, cursor)
+                }
+                None => String::new(),
+            };
+
+            if let Some(text) = texts.get_mut(*e) {
+                text.text = line;
+            }
+        }
+    }
+}
+
+impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for ProfileState {
+    fn on_start(&mut self, data: StateData<Data>) {
+        let world = data.world;
+        world.write_resource::<InputContexts>().push(InputContext::TextEntry);
+
+        self.cursor = {
+            let profiles = world.read_resource::<Profiles>();
+            profiles.active.unwrap_or_else(|| profiles.profiles.len())
+        };
+
+        let font = world.read_resource::<Score>().font.clone();
+
+        let title_transform = UiTransform::new(
+            "ProfilesTitle".to_string(),
+            Anchor::TopMiddle,
+            0.,
+            -50.,
+            2.,
+            600.,
+            50.,
+            0,
+        );
+
+        let title = world
+            .create_entity()
+            .with(title_transform)
+            .with(UiText::new(
+                font.clone(),
+                "Profiles".to_string(),
+                [1.0, 1.0, 1.0, 1.0],
+                40.,
+            )).build();
+
+        self.entities.push(title);
+
+        for i in 0..Self::VISIBLE_ROWS {
+            let transform = UiTransform::new(
+                format!("ProfilesRow{}", i),
+                Anchor::TopMiddle,
+                0.,
+                -120. - i as f32 * Self::ROW_HEIGHT,
+                2.,
+                600.,
+                Self::ROW_HEIGHT,
+                0,
+            );
+
+            let row = world
+                .create_entity()
+                .with(transform)
+                .with(UiText::new(
+                    font.clone(),
+                    String::new(),
+                    [0.8, 0.8, 0.8, 1.0],
+                    20.,
+                )).build();
+
+            self.entities.push(row);
+        }
+
+        self.refresh(world);
+    }
+
+    fn on_stop(&mut self, data: StateData<Data>) {
+        data.world.write_resource::<InputContexts>().pop(InputContext::TextEntry);
+
+        if let Err(e) = data.world.delete_entities(&self.entities) {
+            error!("failed to delete profile entities: {}", e);
+        }
+
+        self.entities.clear();
+    }
+
+    fn update(&mut self, data: StateData<Data>) -> CustomTrans<'a, 'b> {
+        let StateData {
+            data,
+            world,
+            ..
+        } = data;
+
+        data.base.update(world);
+
+        let time_delta = world.read_resource::<Time>().delta_seconds();
+
+        let (up, down, confirm, close) = {
+            let input = world.read_resource::<InputHandler<String, String>>();
+
+            let up = self.up.test(&input, "menu_up", time_delta);
+            let down = self.down.test(&input, "menu_down", time_delta);
+
+            let mut confirm = false;
+            let mut close = false;
+            self.confirm.test(&input, "confirm").activated(|| confirm = true);
+            self.close.test(&input, "quit").activated(|| close = true);
+            (up as usize, down as usize, confirm, close)
+        };
+
+        if close {
+            return Trans::Pop;
+        }
+
+        if confirm {
+            let mut profiles = world.write_resource::<Profiles>();
+
+            let name = match profiles.profiles.get(self.cursor) {
+                Some(profile) => Some(profile.name.clone()),
+                None if !self.entry.text.trim().is_empty() => Some(self.entry.text.clone()),
+                None => None,
+            };
+
+            if let Some(name) = name {
+                profiles.select(&name);
+                profiles.save();
+                return Trans::Pop;
+            }
+        }
+
+        let rows = world.read_resource::<Profiles>().profiles.len() + 1;
+        let cursor = (self.cursor + down).saturating_sub(up).min(rows - 1);
+
+        if cursor != self.cursor {
+            self.cursor = cursor;
+            self.refresh(world);
+        }
+
+        Trans::None
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<Data>,
+        event: StateEvent,
+    ) -> CustomTrans<'a, 'b> {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+
+            if self.is_entering(data.world) && self.entry.handle_event(event) {
+                self.refresh(data.world);
+            }
+        }
+
+        Trans::None
+    }
+}
+
+/// Watches a run from an exported game log, without any interaction.
+///
+/// Asteroids and hull chunks are spawned and removed as recorded in the log, and moved using the
+/// regular physics in between.
+pub struct LogViewerState {
+    path: PathBuf,
+    records: Vec<Record>,
+    /// Index of the next record to apply.
+    next: usize,
+    /// Time since the start of the run being viewed.
+    clock: f32,
+    /// Asteroids being shown, by their id in the log.
+    asteroids: HashMap<u32, Entity>,
+    /// Hull chunks being shown, by their id in the log.
+    hulls: HashMap<u32, Entity>,
+    /// Text showing the status of the run.
+    status: Option<Entity>,
+    /// Error raised while initializing the viewer, if any.
+    error: Option<InitError>,
+}
+
+impl LogViewerState {
+    /// How long to keep showing the run after the last event (s).
+    const LINGER: f32 = 3.0;
+
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            records: Vec::new(),
+            next: 0,
+            clock: 0f32,
+            asteroids: HashMap::new(),
+            hulls: HashMap::new(),
+            status: None,
+            error: None,
+        }
+    }
+
+    /// Set up the world for viewing the log.
+    fn initialize(&mut self, world: &mut World) -> Result<(), InitError> {
+        self.records = match GameLog::read(&self.path) {
+            Ok(records) => records,
+            Err(e) => {
+                error!("failed to read game log: {}: {}", self.path.display(), e);
+                Vec::new()
+            }
+        };
+
+        Asteroids::initialize(world)?;
+        Comets::initialize(world)?;
+        world.add_resource(RandomStreams::new(RandomGen::random_seed()));
+
+        let game = Game::default();
+        initialize_score(world, &game, DevTools::default(), 1)?;
+        world.add_resource(game);
+
+        initialise_camera(world);
+
+        let font = world.read_resource::<Score>().font.clone();
+
+        let status_transform = UiTransform::new(
+            "Status".to_string(),
+            Anchor::BottomMiddle,
+            0.,
+            50.,
+            1.,
+            600.,
+            50.,
+            0,
+        );
+
+        let status = world
+            .create_entity()
+            .with(status_transform)
+            .with(UiText::new(
+                font,
+                format!("Viewing {}", self.path.display()),
+                [1.0, 1.0, 1.0, 1.0],
+                20.,
+            )).build();
+
+        self.status = Some(status);
+        Ok(())
+    }
+
+    /// Apply a single event from the log to the world.
+    fn apply(&mut self, world: &mut World, event: LogEvent) {
+        match event {
+            LogEvent::Started => {}
+            LogEvent::AsteroidSpawned {
+                id,
+                position,
+                velocity,
+                rotation,
+                lateral_acceleration,
+                scale,
+                comet,
+            } => {
+                let mut local = Transform::default();
+                local.set_xyz(position[0], position[1], 0.0);
+                *local.scale_mut() = Vector3::new(scale, scale, 1.0);
+
+                let mut physical = Physical::new();
+                physical.velocity = Vector2::new(velocity[0], velocity[1]);
+                physical.rotation = rotation;
+                physical.lateral_acceleration = lateral_acceleration;
+
+                let (sprite_render, bounded) = if comet {
+                    let comets = world.read_resource::<Comets>();
+                    (comets.new_sprite_render(), comets.new_bounded())
+                } else {
+                    let asteroids = world.read_resource::<Asteroids>();
+                    let streams = world.read_resource::<RandomStreams>();
+                    let sprite_render = asteroids.new_sprite_render(streams.get(Stream::States));
+                    (sprite_render, asteroids.new_bounded(scale))
+                };
+
+                let e = world
+                    .create_entity()
+                    .with(local)
+                    .with(physical)
+                    .with(ConstrainedObject)
+                    .with(sprite_render)
+                    .with(bounded)
+                    .build();
+
+                if let Some(previous) = self.asteroids.insert(id, e) {
+                    warn!("asteroid spawned twice in game log: {}", id);
+                    let _ = world.delete_entity(previous);
+                }
+            }
+            LogEvent::AsteroidRemoved { id } => {
+                if let Some(e) = self.asteroids.remove(&id) {
+                    if let Err(e) = world.delete_entity(e) {
+                        error!("failed to delete entity: {}", e);
+                    }
+                }
+            }
+            LogEvent::AsteroidDestroyed { points } => replay_points(world, points, 1),
+            LogEvent::UfoDestroyed { points } => replay_points(world, points, 0),
+            LogEvent::HullSpawned {
+                id,
+                position,
+                velocity,
+                rotation,
+            } => {
+                let mut local = Transform::default();
+                local.set_xyz(position[0], position[1], 0.0);
+                *local.scale_mut() = Vector3::new(Hulls::SCALE, Hulls::SCALE, 1.0);
+
+                let mut physical = Physical::new();
+                physical.velocity = Vector2::new(velocity[0], velocity[1]);
+                physical.rotation = rotation;
+
+                let sprite_render = {
+                    let asteroids = world.read_resource::<Asteroids>();
+                    let streams = world.read_resource::<RandomStreams>();
+                    asteroids.new_sprite_render(streams.get(Stream::States))
+                };
+
+                let e = world
+                    .create_entity()
+                    .with(local)
+                    .with(physical)
+                    .with(ConstrainedObject)
+                    .with(sprite_render)
+                    .with(Rgba::from(Hulls::TINT))
+                    .with(Hulls::new_bounded())
+                    .build();
+
+                if let Some(previous) = self.hulls.insert(id, e) {
+                    warn!("hull chunk spawned twice in game log: {}", id);
+                    let _ = world.delete_entity(previous);
+                }
+            }
+            LogEvent::HullRemoved { id } => {
+                if let Some(e) = self.hulls.remove(&id) {
+                    if let Err(e) = world.delete_entity(e) {
+                        error!("failed to delete entity: {}", e);
+                    }
+                }
+            }
+            LogEvent::HullDestroyed { points } => replay_points(world, points, 0),
+            LogEvent::PlayerDied => {
+                if let Some(status) = self.status {
+                    if let Some(text) = world.write_storage::<UiText>().get_mut(status) {
+                        text.text = "Player died".to_string();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Award points for something destroyed in a replayed run.
+fn replay_points(world: &mut World, points: u32, asteroids: u32) {
+    let (score_text, total) = {
+        let mut score = world.write_resource::<Score>();
+        score.asteroids += asteroids;
+        score.points += points;
+        (score.score_text, score.points)
+    };
+
+    if let Some(text) = world.write_storage::<UiText>().get_mut(score_text) {
+        text.text = total.to_string();
+    }
+}
+
+impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for LogViewerState {
+    fn on_start(&mut self, data: StateData<Data>) {
+        let StateData { world, .. } = data;
+
+        if let Err(e) = self.initialize(world) {
+            self.error = Some(e);
+        }
+    }
+
+    fn update(&mut self, data: StateData<Data>) -> CustomTrans<'a, 'b> {
+        let StateData {
+            data,
+            world,
+            ..
+        } = data;
+
+        if let Some(error) = self.error.take() {
+            world.delete_all();
+            let retry = Retry::Viewer(self.path.clone());
+            return Trans::Switch(Box::new(ErrorState::new(error, retry)));
+        }
+
+        data.base.update(world);
+
+        self.clock += world.read_resource::<Time>().delta_seconds();
+
+        while self.next < self.records.len() && self.records[self.next].time <= self.clock {
+            let event = self.records[self.next].event.clone();
+            self.next += 1;
+            self.apply(world, event);
+        }
+
+        PhysicsSystem.run_now(&world.res);
+        LimitObjectsSystem.run_now(&world.res);
+
+        let end = self.records.last().map(|r| r.time).unwrap_or(0f32);
+
+        if self.next >= self.records.len() && self.clock > end + Self::LINGER {
+            return Trans::Quit;
+        }
+
+        Trans::None
+    }
+
+    fn handle_event(
+        &mut self,
+        _: StateData<Data>,
+        event: StateEvent,
+    ) -> CustomTrans<'a, 'b> {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+        }
+
+        Trans::None
+    }
+}
+
+/// What to retry after failing to initialize.
+pub enum Retry {
+    /// Show the main menu with the given settings.
+    Menu(SessionSettings),
+    /// Start a new game with the given settings.
+    Game(SessionSettings),
+    /// View the game log at the given path.
+    Viewer(PathBuf),
+    /// Edit the level at the given path.
+    Editor(PathBuf, SessionSettings),
+}
+
+/// Shows an error raised while initializing, instead of panicking.
+///
+/// The player can retry, for example after fixing a broken asset, or quit.
+pub struct ErrorState {
+    error: InitError,
+    retry: Option<Retry>,
+    quit: Action,
+}
+
+impl ErrorState {
+    pub fn new(error: InitError, retry: Retry) -> Self {
+        Self {
+            error,
+            retry: Some(retry),
+            quit: Action::default(),
+        }
+    }
+}
+
+impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for ErrorState {
+    fn on_start(&mut self, data: StateData<Data>) {
+        let StateData { world, .. } = data;
+
+        error!("failed to initialize: {}", self.error);
+        limit_frame_rate(world, true);
+
+        // NB: retrying uses the same binding as restarting the game.
+        world.write_resource::<InputContexts>().push(InputContext::Gameplay);
+
+        // NB: the global input system expects the game to be present.
+        world.add_resource(Game::default());
+
+        // the font itself might be what's missing, in which case the error is only logged.
+        let font = match load_font(world) {
+            Ok(font) => font,
+            Err(e) => {
+                error!("failed to show error: {}", e);
+                return;
+            }
+        };
+
+        let lines = [
+            ("ErrorTitle", "Failed to start the game".to_string(), 30.),
+            ("ErrorMessage", self.error.to_string(), 18.),
+            ("ErrorHelp", "Press R to retry, or Escape to quit".to_string(), 18.),
+        ];
+
+        for (i, (id, text, size)) in lines.iter().enumerate() {
+            let transform = UiTransform::new(
+                id.to_string(),
+                Anchor::Middle,
+                0.,
+                50. - i as f32 * 50.,
+                1.,
+                800.,
+                50.,
+                0,
+            );
+
+            world
+                .create_entity()
+                .with(transform)
+                .with(UiText::new(
+                    font.clone(),
+                    text.clone(),
+                    [1.0, 0.3, 0.3, 1.0],
+                    *size,
+                )).build();
+        }
+    }
+
+    fn on_stop(&mut self, data: StateData<Data>) {
+        data.world.write_resource::<InputContexts>().pop(InputContext::Gameplay);
+    }
+
+    fn update(&mut self, data: StateData<Data>) -> CustomTrans<'a, 'b> {
+        let StateData {
+            data,
+            world,
+            ..
+        } = data;
+
+        data.base.update(world);
+
+        let mut quit = false;
+
+        self.quit
+            .test(&world.read_resource::<InputHandler<String, String>>(), "quit")
+            .activated(|| quit = true);
+
+        if quit {
+            return Trans::Quit;
+        }
+
+        let restart = mem::replace(&mut world.write_resource::<Game>().restart, false);
+
+        if restart {
+            world.delete_all();
+
+            match self.retry.take() {
+                Some(Retry::Menu(session)) => {
+                    return Trans::Switch(Box::new(MenuState::new(session)));
+                }
+                Some(Retry::Game(session)) => {
+                    return Trans::Switch(Box::new(MainGameState::new(session)));
+                }
+                Some(Retry::Viewer(path)) => {
+                    return Trans::Switch(Box::new(LogViewerState::new(path)));
+                }
+                Some(Retry::Editor(path, session)) => {
+                    return Trans::Switch(Box::new(EditorState::new(path, session)));
+                }
+                None => {}
+            }
+        }
+
+        Trans::None
+    }
+
+    fn handle_event(
+        &mut self,
+        _: StateData<Data>,
+        event: StateEvent,
+    ) -> CustomTrans<'a, 'b> {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+        }
+
+        Trans::None
+    }
+}
+
+/// A simple level editor, for placing asteroids and play-testing the result.
+///
+/// * Click to place an asteroid, and drag before releasing to give it a velocity.
+/// * Hold shift while clicking to place a large asteroid, or control for a small one.
+/// * Backspace removes the last asteroid placed, F5 saves the level and Enter play-tests it.
+pub struct EditorState {
+    path: PathBuf,
+    session: SessionSettings,
+    level: Level,
+    /// Entities showing the asteroids of the level, in the same order.
+    entities: Vec<Entity>,
+    /// The asteroid being placed, and where it was placed, while its velocity is dragged out.
+    placing: Option<(usize, (f32, f32))>,
+    place: Action,
+    undo: Action,
+    save: Action,
+    playtest: Action,
+    /// Error raised while initializing the editor, if any.
+    error: Option<InitError>,
+}
+
+impl EditorState {
+    /// Scale of asteroids placed while holding control.
+    const SMALL: f32 = 1.0;
+    /// Scale of asteroids placed without modifiers.
+    const MEDIUM: f32 = 1.5;
+    /// Scale of asteroids placed while holding shift.
+    const LARGE: f32 = 2.0;
+    /// Velocity given for each unit dragged (1 / s).
+    const DRAG_VELOCITY: f32 = 1.0;
+
+    pub fn new(path: PathBuf, session: SessionSettings) -> Self {
+        Self {
+            path,
+            session,
+            level: Level::default(),
+            entities: Vec::new(),
+            placing: None,
+            place: Action::default(),
+            undo: Action::default(),
+            save: Action::default(),
+            playtest: Action::default(),
+            error: None,
+        }
+    }
+
+    /// Set up the world for editing the level.
+    fn initialize(&mut self, world: &mut World) -> Result<(), InitError> {
+        if self.path.is_file() {
+            self.level = Level::load_no_fallback(&self.path)
+                .map_err(|e| InitError::BrokenAsset(self.path.clone(), e.to_string()))?;
+        }
+
+        Asteroids::initialize(world)?;
+        world.add_resource(RandomStreams::new(RandomGen::random_seed()));
+        world.add_resource(Game::default());
+        initialise_camera(world);
+
+        for asteroid in &self.level.asteroids {
+            self.entities.push(level_asteroid(world, asteroid).build());
+        }
+
+        let font = load_font(world)?;
+
+        let help_transform = UiTransform::new(
+            "EditorHelp".to_string(),
+            Anchor::BottomMiddle,
+            0.,
+            30.,
+            1.,
+            800.,
+            30.,
+            0,
+        );
+
+        world
+            .create_entity()
+            .with(help_transform)
+            .with(UiText::new(
+                font,
+                "Click: place, Backspace: undo, F5: save, Enter: play-test".to_string(),
+                [1.0, 1.0, 1.0, 1.0],
+                16.,
+            )).build();
+
+        Ok(())
+    }
+
+    /// Save the level being edited.
+    fn save_level(&self) {
+        match self.level.write(&self.path) {
+            Ok(()) => info!("saved level: {}", self.path.display()),
+            Err(e) => warn!("failed to save level: {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+impl<'a, 'b> State<Data<'a, 'b>, StateEvent> for EditorState {
+    fn on_start(&mut self, data: StateData<Data>) {
+        let StateData { world, .. } = data;
+
+        if let Err(e) = self.initialize(world) {
+            self.error = Some(e);
+        }
+
+        world.write_resource::<InputContexts>().push(InputContext::Editor);
+    }
+
+    fn on_stop(&mut self, data: StateData<Data>) {
+        data.world.write_resource::<InputContexts>().pop(InputContext::Editor);
+    }
+
+    fn update(&mut self, data: StateData<Data>) -> CustomTrans<'a, 'b> {
+        let StateData {
+            data,
+            world,
+            ..
+        } = data;
+
+        if let Some(error) = self.error.take() {
+            world.delete_all();
+            let retry = Retry::Editor(self.path.clone(), self.session.clone());
+            return Trans::Switch(Box::new(ErrorState::new(error, retry)));
+        }
+
+        data.base.update(world);
+
+        let mut placed = None;
+        let mut released = None;
+        let mut undo = false;
+        let mut save = false;
+        let mut playtest = false;
+
+        {
+            let input = world.read_resource::<InputHandler<String, String>>();
+            let screen = world.read_resource::<ScreenDimensions>();
+            let mouse = mouse_in_arena(&input, &screen);
+
+            let transition = self.place.test(&input, "place");
+            transition.activated(|| placed = mouse);
+            transition.deactivated(|| released = mouse);
+
+            self.undo.test(&input, "undo").activated(|| undo = true);
+            self.save.test(&input, "save").activated(|| save = true);
+            self.playtest.test(&input, "playtest").activated(|| playtest = true);
+
+            if let Some(position) = placed {
+                let shift = input.key_is_down(VirtualKeyCode::LShift)
+                    || input.key_is_down(VirtualKeyCode::RShift);
+                let control = input.key_is_down(VirtualKeyCode::LControl)
+                    || input.key_is_down(VirtualKeyCode::RControl);
+
+                let scale = if shift {
+                    Self::LARGE
+                } else if control {
+                    Self::SMALL
+                } else {
+                    Self::MEDIUM
+                };
+
+                self.placing = Some((self.level.asteroids.len(), position));
+
+                self.level.asteroids.push(LevelAsteroid {
+                    position: [position.0, position.1],
+                    velocity: [0f32, 0f32],
+                    scale,
+                });
+            }
+        }
+
+        if placed.is_some() {
+            if let Some(asteroid) = self.level.asteroids.last() {
+                self.entities.push(level_asteroid(world, asteroid).build());
+            }
+        }
+
+        if let (Some((index, start)), Some(end)) = (self.placing, released) {
+            if let Some(asteroid) = self.level.asteroids.get_mut(index) {
+                asteroid.velocity = [
+                    (end.0 - start.0) * Self::DRAG_VELOCITY,
+                    (end.1 - start.1) * Self::DRAG_VELOCITY,
+                ];
+            }
+
+            self.placing = None;
+        }
+
+        if undo {
+            self.level.asteroids.pop();
+            self.placing = None;
+
+            if let Some(e) = self.entities.pop() {
+                if let Err(e) = world.delete_entity(e) {
+                    error!("failed to delete entity: {}", e);
+                }
+            }
+        }
+
+        if save || playtest {
+            self.save_level();
+        }
+
+        if playtest {
+            world.delete_all();
+
+            let mut session = self.session.clone();
+            session.level = Some(self.path.clone());
+            return Trans::Switch(Box::new(MainGameState::new(session)));
+        }
+
+        Trans::None
+    }
+
+    fn handle_event(
+        &mut self,
+        _: StateData<Data>,
+        event: StateEvent,
+    ) -> CustomTrans<'a, 'b> {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
         }
 
         Trans::None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::DeferredCollider;
+
+    #[test]
+    fn lazy_insert_survives_pause_and_resume() {
+        let mut world = World::new();
+        let pool: ArcThreadPool = Arc::new(ThreadPoolBuilder::new().build().unwrap());
+        world.add_resource(pool);
+        world.add_resource(Time::default());
+        world.add_resource(FrameLimiter::default());
+        world.add_resource(AudioMix::default());
+        world.add_resource(InputContexts::default());
+        world.add_resource(Game::default());
+        world.register::<Collider>();
+        world.register::<Bounded>();
+        world.register::<Transform>();
+
+        let entity = world.create_entity().build();
+        // NB: without a bounding volume, the deferred collider is never resolved by the game.
+        let deferred = world.create_entity().with(Transform::default()).build();
+        let mut data = DataBuilder::default().build(&mut world);
+
+        {
+            let lazy = world.read_resource::<LazyUpdate>();
+            lazy.insert(entity, Collider::Asteroid);
+            lazy.insert(deferred, Collider::Deferred(DeferredCollider::Asteroid));
+        }
+
+        // NB: the states are driven by hand like the state machine would, since starting the game
+        // loads its assets.
+        let mut main = MainGameState::new(SessionSettings::default());
+        world.write_resource::<Game>().pause = true;
+
+        let mut pause = match main.update(StateData::new(&mut world, &mut data)) {
+            Trans::Push(pause) => pause,
+            _ => panic!("expected the pause menu to be pushed"),
+        };
+
+        {
+            let colliders = world.read_storage::<Collider>();
+            assert_eq!(colliders.get(entity), Some(&Collider::Asteroid));
+            assert_eq!(colliders.get(deferred), Some(&Collider::Asteroid));
+        }
+
+        main.on_pause(StateData::new(&mut world, &mut data));
+        pause.on_start(StateData::new(&mut world, &mut data));
+        world.maintain();
+
+        world.write_resource::<Game>().pause = true;
+
+        match pause.update(StateData::new(&mut world, &mut data)) {
+            Trans::Pop => {}
+            _ => panic!("expected the pause menu to be popped"),
+        }
+
+        pause.on_stop(StateData::new(&mut world, &mut data));
+        main.on_resume(StateData::new(&mut world, &mut data));
+        world.maintain();
+
+        match main.update(StateData::new(&mut world, &mut data)) {
+            Trans::None => {}
+            _ => panic!("expected the game to keep running"),
+        }
+
+        world.maintain();
+
+        let colliders = world.read_storage::<Collider>();
+        assert_eq!(colliders.join().count(), 2);
+        assert_eq!(colliders.get(entity), Some(&Collider::Asteroid));
+        assert_eq!(colliders.get(deferred), Some(&Collider::Asteroid));
+    }
+}
@@ -1,666 +1,6386 @@
 use amethyst::{
     assets::AssetStorage,
-    audio::{output::Output, Source},
+    audio::{output::Output, AudioSink, Source},
     core::{
-        nalgebra::{UnitQuaternion, Vector2, Vector3},
+        nalgebra::{Point3, UnitQuaternion, Vector2, Vector3},
         timing::Time,
-        transform::Transform,
+        transform::{Parent, Transform},
     },
     ecs::{
-        prelude::{Entities, Entity, Join, LazyUpdate, Read, ReadStorage, System, WriteStorage},
+        prelude::{
+            Entities, Entity, Join, LazyUpdate, Read, ReadStorage, Resources, System, SystemData,
+            Write, WriteStorage,
+        },
         ReadExpect, WriteExpect,
     },
     input::InputHandler,
-    ui::UiText,
+    renderer::{
+        Camera, DebugLines, Event, Hidden, Rgba, ScreenDimensions, SpriteRender, WindowEvent,
+        WindowMessages,
+    },
+    shrev::{EventChannel, ReaderId},
+    ui::{Anchor, UiText, UiTransform},
+    utils::fps_counter::FPSCounter,
 };
 use crate::{
-    audio::Sounds,
-    components::{Bounded, Bullet, DeferredCollider, Collider, ConstrainedObject, Physical, Ship},
-    resources::{Asteroids, Bullets, Game, RandomGen, Score},
+    audio::{AudioMix, SfxCategory, SfxQueue, Sounds, Stinger, Stingers, MUSIC_VOLUME},
+    components::{
+        AimDot, Boss, Bounded, Bullet, Collider, CollisionGrace, Comet, ConstrainedObject, Culled,
+        DeferredCollider, Easing, Energy, Ghost, Health, Hull, Invulnerable, LatencyMarker, Layout,
+        Magnetized, Owner, Particle, Pebble, Phantom, Physical, Planet, PowerUp, PowerUpKind,
+        RamVisual, ScorePopup, ShieldVisual, Ship, ShipDecal, Shockwave, Station, Tween,
+        TweenTarget, Ufo, Unstable, VelocityArrow,
+    },
+    resources::{
+        ArenaBounds, AsteroidSize, Asteroids, BulletTime, Bullets, Comets, DebugOverlay, DevTools,
+        EnergyBar, Game, HealthBar, HistogramOverlay, Hulls, LatencyProbe, Lives, Overlaps,
+        Particles, Pebbles, PhotoCamera, PowerUps, RandomGen, RandomStreams, Score, SessionSettings,
+        Ships, Shockwaves, SpawnPoint, Splinter, SplinterQueue, Squeeze, StationBar, Stream, Ufos,
+        Wave,
+    },
+    config::{ArenaEdges, GameConfig, SplinterPolicy},
+    events::{GameEvent, Wrapped},
+    gamelog::{GameLog, LogEvent},
+    input::{Action, InputContexts},
+    mutations::Mutations,
+    net::NetSession,
+    profiles::ShipLook,
+    replay::{Playback, Recorder},
+    schedule::{CurrentState, StateId},
+    script::WaveScript,
+    settings::{AudioSettings, Settings, WindowSettings},
+    spawner::{roll_from_edge, ActiveSpawner, Snapshot, Spawn},
     ARENA_HEIGHT, ARENA_WIDTH,
 };
-use log::{error, trace};
-use ncollide2d::broad_phase::{BroadPhase, DBVTBroadPhase};
+use log::{error, info, trace};
+use ncollide2d::{
+    bounding_volume::AABB,
+    broad_phase::{BroadPhase, DBVTBroadPhase, ProxyHandle},
+};
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 
-#[derive(Debug, Clone, Copy)]
-pub enum Action {
-    Active,
-    Inactive,
-}
-
-impl Default for Action {
-    fn default() -> Self {
-        Action::Inactive
-    }
-}
-
-impl Action {
-    /// Test an action, and transition it into a different state if applicable.
-    pub fn test(&mut self, input: &InputHandler<String, String>, name: &str) -> ActionTransition {
-        let down = input.action_is_down(name).unwrap_or(false);
-
-        match *self {
-            Action::Inactive => {
-                if down {
-                    *self = Action::Active;
-                    return ActionTransition::Activated;
-                }
-            }
-            Action::Active => {
-                if !down {
-                    *self = Action::Inactive;
-                    return ActionTransition::Deactivated;
-                }
-            }
-        }
-
-        ActionTransition::None
-    }
-}
-
-/// The transition of an action.
-#[derive(Debug, Clone, Copy)]
-pub enum ActionTransition {
-    Activated,
-    Deactivated,
-    None,
-}
-
-impl ActionTransition {
-    /// Call the given callback if action is activated.
-    pub fn activated(self, mut c: impl FnMut()) {
-        if let ActionTransition::Activated = self {
-            c();
-        }
-    }
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
-    /// Call the given callback if action is deactivated.
-    #[allow(unused)]
-    pub fn deactivated(self, mut c: impl FnMut()) {
-        if let ActionTransition::Deactivated = self {
-            c();
-        }
-    }
-}
+#[cfg(feature = "external_control")]
+use crate::external::{AsteroidObservation, ExternalControl, Observation, ShipObservation};
+#[cfg(feature = "external_control")]
+use std::path::PathBuf;
+#[cfg(feature = "clips")]
+use crate::clip::ClipRecorder;
+#[cfg(feature = "god_view")]
+use crate::god_view::GodView;
+#[cfg(any(feature = "god_view", feature = "clips"))]
+use crate::sketch;
+#[cfg(feature = "timings")]
+use crate::timings::{micros, SystemTimings};
 
 #[derive(Default)]
 pub struct GlobalInputSystem {
     immortal: Action,
+    hitboxes: Action,
+    debug: Action,
     restart: Action,
     pause: Action,
+    trajectories: Action,
+    volume_up: Action,
+    volume_down: Action,
+    mute: Action,
 }
 
 impl<'s> System<'s> for GlobalInputSystem {
     type SystemData = (
         Read<'s, InputHandler<String, String>>,
+        Read<'s, InputContexts>,
         WriteExpect<'s, Game>,
+        Write<'s, DevTools>,
+        Write<'s, Settings>,
     );
 
-    fn run(&mut self, (input, mut game): Self::SystemData) {
-        self.immortal.test(&input, "immortal").activated(|| {
-            game.modifiers.player_is_immortal = !game.modifiers.player_is_immortal;
+    fn run(&mut self, data: Self::SystemData) {
+        let (input, contexts, mut game, mut dev_tools, mut settings) = data;
+
+        {
+            let audio = &mut settings.audio;
+
+            contexts.test(&mut self.volume_up, &input, "volume_up").activated(|| {
+                audio.adjust(AudioSettings::STEP);
+            });
+
+            contexts.test(&mut self.volume_down, &input, "volume_down").activated(|| {
+                audio.adjust(-AudioSettings::STEP);
+            });
+
+            contexts.test(&mut self.mute, &input, "mute").activated(|| {
+                audio.muted = !audio.muted;
+            });
+
+            game.modifiers.volume = audio.percent();
+            game.modifiers.muted = audio.muted;
+        }
+
+        contexts.test(&mut self.immortal, &input, "immortal").activated(|| {
+            dev_tools.immortal = !dev_tools.immortal;
+        });
+
+        contexts.test(&mut self.hitboxes, &input, "hitboxes").activated(|| {
+            dev_tools.hitboxes = !dev_tools.hitboxes;
         });
 
-        self.restart.test(&input, "restart").activated(|| {
+        contexts.test(&mut self.debug, &input, "debug").activated(|| {
+            dev_tools.debug = !dev_tools.debug;
+        });
+
+        contexts.test(&mut self.restart, &input, "restart").activated(|| {
             game.restart = true;
         });
 
-        self.pause.test(&input, "pause").activated(|| {
+        contexts.test(&mut self.pause, &input, "pause").activated(|| {
             game.pause = true;
         });
+
+        contexts.test(&mut self.trajectories, &input, "trajectories").activated(|| {
+            settings.trajectories = !settings.trajectories;
+        });
     }
 }
 
-pub struct ShipInputSystem;
-
-/// Handle inputs and mutate world accordingly.
+/// Keeps track of the window geometry in `Settings`, so that it can be restored the next time the
+/// game is started.
 ///
-/// * Applies rotation (axes `rotate`) and acceleration (axes `accelerate`) to your ship.
-/// * Spawns bullets on `shoot` action..
-impl<'s> System<'s> for ShipInputSystem {
+/// Also restores the window position from the previous run when first run.
+///
+/// Window events don't say if the window is fullscreen, so the window is checked after every
+/// resize to see if it covers its whole monitor.
+#[derive(Default)]
+pub struct WindowSettingsSystem {
+    reader: Option<ReaderId<Event>>,
+    restored: bool,
+    /// If the window was fullscreen when last checked, until it's been saved in the settings.
+    fullscreen: Arc<Mutex<Option<bool>>>,
+}
+
+impl<'s> System<'s> for WindowSettingsSystem {
     type SystemData = (
-        WriteStorage<'s, Ship>,
-        WriteStorage<'s, Physical>,
-        ReadStorage<'s, Transform>,
-        Read<'s, Time>,
-        Read<'s, InputHandler<String, String>>,
-        ReadExpect<'s, Bullets>,
-        ReadExpect<'s, RandomGen>,
-        ReadExpect<'s, Sounds>,
-        Read<'s, AssetStorage<Source>>,
-        Option<Read<'s, Output>>,
-        Entities<'s>,
-        Read<'s, LazyUpdate>,
+        Read<'s, EventChannel<Event>>,
+        Write<'s, Settings>,
+        Write<'s, WindowMessages>,
     );
 
-    fn run(&mut self, system: Self::SystemData) {
-        let (
-            mut ships,
-            mut physicals,
-            locals,
-            time,
-            input,
-            bullet_resource,
-            rand,
-            sounds,
-            audio_storage,
-            audio,
-            entities,
-            lazy,
-        ) = system;
+    fn run(&mut self, (events, mut settings, mut messages): Self::SystemData) {
+        if !self.restored {
+            self.restored = true;
 
-        let time_delta = time.delta_seconds();
+            if let Some((x, y)) = settings.window.and_then(|w| w.position) {
+                messages.send_command(move |window| window.set_position((x, y).into()));
+            }
+        }
 
-        let rotate = input.axis_value("rotate");
-        let accelerate = input.axis_value("accelerate");
-        let shoot = input.action_is_down("shoot").unwrap_or(false);
+        // NB: checked by a window command sent on a previous frame.
+        let fullscreen = self
+            .fullscreen
+            .lock()
+            .expect("fullscreen lock poisoned")
+            .take();
 
-        let mut new_bullets = SmallVec::<[NewBullet; 4]>::new();
+        if let (Some(fullscreen), Some(window)) = (fullscreen, settings.window.as_mut()) {
+            window.fullscreen = fullscreen;
+        }
 
-        for (ship, physical, local) in (&mut ships, &mut physicals, &locals).join() {
-            // handle acceleration.
-            if let Some(acceleration) = accelerate {
-                let added = Vector3::y() * ship.acceleration * time_delta * acceleration as f32;
-                let added = local.rotation() * added;
-                physical.velocity += Vector2::new(added.x, added.y);
+        let reader = self.reader.as_mut().expect("reader not set up");
 
-                // limit velocity.
-                let magnitude = physical.velocity.magnitude();
+        for event in events.read(reader) {
+            let event = match *event {
+                Event::WindowEvent { ref event, .. } => event,
+                _ => continue,
+            };
 
-                if magnitude > physical.max_velocity {
-                    physical.velocity /= magnitude / physical.max_velocity;
+            match *event {
+                WindowEvent::Moved(position) => {
+                    if let Some(window) = settings.window.as_mut() {
+                        window.position = Some((position.x, position.y));
+                    }
                 }
-            }
+                WindowEvent::Resized(size) => {
+                    let window = settings.window.get_or_insert(WindowSettings {
+                        position: None,
+                        size: (size.width, size.height),
+                        fullscreen: false,
+                    });
 
-            // handle rotation
-            if let Some(rotation) = rotate {
-                physical.rotation = ship.rotation * time_delta * rotation as f32;
-            } else {
-                physical.rotation = 0f32;
-            }
+                    window.size = (size.width, size.height);
 
-            // handle shooting with a reload.
-            if ship.reload_timer <= 0.0f32 {
-                if shoot {
-                    ship.reload_timer = ship.time_to_reload;
+                    let checked = self.fullscreen.clone();
 
-                    let mut local = local.clone();
+                    messages.send_command(move |window| {
+                        let monitor = window.get_current_monitor().get_dimensions();
 
-                    // apply a bit of jitter on the bullet positions.
-                    let jitter = Vector3::x() * (rand.next_f32() - 0.5) * ship.bullet_jitter;
-                    let jitter = local.rotation() * jitter;
-                    *local.translation_mut() += jitter;
+                        let fullscreen = match window.get_inner_size() {
+                            Some(size) => {
+                                let size = size.to_physical(window.get_hidpi_factor());
+                                size.width >= monitor.width && size.height >= monitor.height
+                            }
+                            None => false,
+                        };
 
-                    new_bullets.push(NewBullet {
-                        local,
-                        velocity: ship.bullet_velocity,
+                        *checked.lock().expect("fullscreen lock poisoned") = Some(fullscreen);
                     });
                 }
-            } else {
-                ship.reload_timer -= time_delta;
-
-                if ship.reload_timer < 0.0f32 {
-                    ship.reload_timer = 0.0f32;
-                }
+                _ => {}
             }
         }
+    }
 
-        if !new_bullets.is_empty() {
-            sounds
-                .pew_sfx
-                .play(&rand, &audio_storage, audio.as_ref().map(|o| &**o));
-        }
+    fn setup(&mut self, res: &mut Resources) {
+        Self::SystemData::setup(res);
+        self.reader = Some(res.fetch_mut::<EventChannel<Event>>().register_reader());
+    }
+}
+
+/// Announces key game events in text, for players using a screen reader.
+///
+/// Announcements are printed to stdout, and with the `tts` feature also spoken through the
+/// platform's text-to-speech command. Only active if enabled through `Settings::announce`, or for
+/// the session with `--announce`.
+#[derive(Default)]
+pub struct AnnounceSystem {
+    reader: Option<ReaderId<GameEvent>>,
+}
 
-        for new_bullet in new_bullets {
-            let NewBullet { local, velocity } = new_bullet;
+impl AnnounceSystem {
+    fn announce(text: &str) {
+        use std::io::Write;
 
-            let velocity = local.rotation() * Vector3::y() * velocity;
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        let _ = writeln!(stdout, "{}", text);
+        let _ = stdout.flush();
 
-            let mut physical = Physical::new();
-            physical.velocity = Vector2::new(velocity.x, velocity.y);
+        #[cfg(feature = "tts")]
+        Self::speak(text);
+    }
 
-            let e = entities.create();
+    #[cfg(feature = "tts")]
+    fn speak(text: &str) {
+        use std::process::Command;
 
-            lazy.insert(e, local);
-            lazy.insert(e, physical);
-            lazy.insert(e, ConstrainedObject);
-            lazy.insert(e, bullet_resource.new_sprite_render());
-            lazy.insert(e, Bullet::new());
-            lazy.insert(e, bullet_resource.new_bounded());
-            lazy.insert(e, Collider::Deferred(DeferredCollider::Bullet));
-        }
+        let program = if cfg!(target_os = "macos") {
+            "say"
+        } else {
+            "espeak"
+        };
 
-        struct NewBullet {
-            local: Transform,
-            velocity: f32,
+        if let Err(e) = Command::new(program).arg(text).spawn() {
+            error!("failed to speak using `{}`: {}", program, e);
         }
     }
 }
 
-/// Limit objects within arena.
-///
-/// If an object goes out of bounds, moves it to the other side of the arena.
-pub struct LimitObjectsSystem;
-
-impl<'s> System<'s> for LimitObjectsSystem {
+impl<'s> System<'s> for AnnounceSystem {
     type SystemData = (
-        WriteStorage<'s, Transform>,
-        ReadStorage<'s, ConstrainedObject>,
+        Read<'s, EventChannel<GameEvent>>,
+        Read<'s, Settings>,
+        Read<'s, SessionSettings>,
     );
 
-    fn run(&mut self, (mut locals, constrained): Self::SystemData) {
-        for (local, _) in (&mut locals, &constrained).join() {
-            let mut t = *local.translation();
+    fn run(&mut self, (events, settings, session): Self::SystemData) {
+        let reader = self.reader.as_mut().expect("reader not set up");
 
-            if t.x < 0f32 {
-                t.x += ARENA_WIDTH;
-            } else if t.x > ARENA_WIDTH {
-                t.x -= ARENA_WIDTH;
+        for event in events.read(reader) {
+            if !session.announce(&settings) {
+                continue;
             }
 
-            if t.y < 0f32 {
-                t.y += ARENA_HEIGHT;
-            } else if t.y > ARENA_HEIGHT {
-                t.y -= ARENA_HEIGHT;
+            if let Some(text) = event.announcement() {
+                Self::announce(&text);
             }
-
-            *local.translation_mut() = t;
         }
     }
+
+    fn setup(&mut self, res: &mut Resources) {
+        Self::SystemData::setup(res);
+        self.reader = Some(res.fetch_mut::<EventChannel<GameEvent>>().register_reader());
+    }
 }
 
-pub struct KillBulletsSystem;
+/// Measures the latency between pressing shoot and the resulting bullet being rendered.
+///
+/// * Flashes a quad on the frame where the `shoot` action is pressed.
+/// * Bullets spawned by the press carry a `LatencyMarker`, which is measured and removed the first
+///   frame they are part of the world being rendered.
+///
+/// Does nothing unless the `LatencyProbe` resource is present.
+#[derive(Default)]
+pub struct LatencyProbeSystem {
+    shoot: Action,
+}
 
-impl<'s> System<'s> for KillBulletsSystem {
-    type SystemData = (Entities<'s>, WriteStorage<'s, Bullet>, Read<'s, Time>);
+impl<'s> System<'s> for LatencyProbeSystem {
+    type SystemData = (
+        Entities<'s>,
+        Option<Write<'s, LatencyProbe>>,
+        WriteStorage<'s, LatencyMarker>,
+        WriteStorage<'s, Hidden>,
+        Read<'s, InputHandler<String, String>>,
+        Read<'s, InputContexts>,
+        Read<'s, Time>,
+    );
 
     fn run(&mut self, system: Self::SystemData) {
-        let (entities, mut bullets, time) = system;
+        let (entities, probe, mut markers, mut hidden, input, contexts, time) = system;
 
-        let time_delta = time.delta_seconds();
+        let mut probe = match probe {
+            Some(probe) => probe,
+            None => return,
+        };
 
-        for (e, bullet) in (&*entities, &mut bullets).join() {
-            bullet.time_to_live -= time_delta;
+        let mut measured = SmallVec::<[Entity; 4]>::new();
 
-            if bullet.time_to_live <= 0.0f32 {
-                if let Err(e) = entities.delete(e) {
-                    error!("failed to destroy entity: {}", e);
-                }
+        for (e, marker) in (&*entities, &markers).join() {
+            let sample = marker.pressed_at.elapsed();
+            info!("shoot latency: {:?} (frame {})", sample, time.frame_number());
+            probe.record(sample);
+            measured.push(e);
+        }
 
-                continue;
+        for e in measured {
+            markers.remove(e);
+        }
+
+        if probe.flash_timer > 0f32 {
+            probe.flash_timer -= time.delta_seconds();
+
+            if probe.flash_timer <= 0f32 {
+                if let Err(e) = hidden.insert(probe.flash, Hidden) {
+                    error!("failed to hide latency flash: {}", e);
+                }
             }
         }
+
+        // NB: a press which didn't spawn a bullet the frame it happened isn't measured.
+        probe.pressed_at = None;
+        let input_at = probe.input_at.take();
+
+        contexts.test(&mut self.shoot, &input, "shoot").activated(|| {
+            // NB: controllers aren't read through window events, so they aren't stamped.
+            probe.pressed_at = Some(input_at.unwrap_or_else(Instant::now));
+            probe.flash_timer = LatencyProbe::FLASH_TIME;
+            hidden.remove(probe.flash);
+        });
     }
 }
 
-/// System to spawn random asteroids.
-///
-/// Asteroids are always spawned by the lower and upper edges, but with random velocity vectors
-/// capped by the parameters in this system.
-pub struct RandomAsteroidSystem {
-    pub time_to_spawn: f32,
-    pub max_velocity: f32,
-    pub max_rotation: f32,
-    pub average_spawn_time: f32,
-}
+pub struct ShipInputSystem;
 
-impl RandomAsteroidSystem {
-    pub fn new() -> Self {
-        Self {
-            time_to_spawn: 2f32,
-            max_velocity: 100f32,
-            max_rotation: 15f32,
-            average_spawn_time: 0.5f32,
+impl ShipInputSystem {
+    /// How far away asteroids are considered when snapping aim (units).
+    const SNAP_RANGE: f32 = 200.0;
+    /// How close a shot has to be to lining up with an asteroid for aim to snap (radians).
+    const SNAP_ANGLE: f32 = 0.1;
+
+    /// Angle the ship has to turn to line up a shot with the nearest asteroid it is nearly
+    /// pointing at, if any (radians, counter-clockwise).
+    fn snap_angle(
+        ship: &Transform,
+        colliders: &ReadStorage<Collider>,
+        locals: &ReadStorage<Transform>,
+    ) -> Option<f32> {
+        let heading = ship.rotation() * Vector3::y();
+        let heading = Vector2::new(heading.x, heading.y);
+
+        let mut nearest = None::<(f32, f32)>;
+
+        for (collider, local) in (colliders, locals).join() {
+            if *collider != Collider::Asteroid {
+                continue;
+            }
+
+            let offset = wrapped_delta(ship.translation(), local.translation());
+            let distance = offset.norm();
+
+            if distance > Self::SNAP_RANGE {
+                continue;
+            }
+
+            let angle = (heading.x * offset.y - heading.y * offset.x).atan2(heading.dot(&offset));
+
+            if angle.abs() > Self::SNAP_ANGLE {
+                continue;
+            }
+
+            if nearest.map(|(d, _)| distance < d).unwrap_or(true) {
+                nearest = Some((distance, angle));
+            }
         }
+
+        nearest.map(|(_, angle)| angle)
     }
 }
 
-impl<'s> System<'s> for RandomAsteroidSystem {
+/// Handle inputs and mutate world accordingly.
+///
+/// * Applies rotation (axes `rotate`) and acceleration (axes `accelerate`) to your ship.
+/// * Spawns bullets on `shoot` action..
+/// * Boosts thrust on `afterburner`, and raises shields on `shield`, for as long as there's energy.
+/// * Jumps to a random position in the arena on `hyperspace`, with a chance of the ship being
+///   destroyed on arrival.
+///
+/// Each player reads their controls through the binding profile assigned to them in `Settings`.
+/// By default the first player uses the plain bindings, and the second player the ones suffixed
+/// with `_p2`, like `rotate_p2` and `shoot_p2`.
+///
+/// If enabled through `Settings::aim_snap` or `--aim-snap`, rotation is gently pulled toward the
+/// nearest asteroid when a shot is nearly lined up with it.
+///
+/// The controls are recorded in the `Recorder`. While a replay is played back, the recorded
+/// controls are used instead, and the ships drift once the replay runs out.
+///
+/// Online, only the local player is controlled from this game, with the bindings of the first
+/// player. The controls of both players are exchanged through the `NetSession`. Spectators
+/// control nothing, and only receive the controls of both players.
+impl<'s> System<'s> for ShipInputSystem {
     type SystemData = (
-        Entities<'s>,
-        ReadExpect<'s, Asteroids>,
-        ReadExpect<'s, RandomGen>,
-        Read<'s, Time>,
-        Read<'s, LazyUpdate>,
+        Read<'s, InputHandler<String, String>>,
+        Read<'s, InputContexts>,
+        Read<'s, Settings>,
+        ReadStorage<'s, Collider>,
+        Option<Write<'s, Recorder>>,
+        Option<Write<'s, Playback>>,
+        Option<Write<'s, NetSession>>,
+        ShipControlData<'s>,
     );
 
     fn run(&mut self, system: Self::SystemData) {
-        let (entities, asteroid_resource, rand, time, lazy) = system;
+        let (input, contexts, settings, colliders, recorder, playback, mut net, data) = system;
 
-        self.time_to_spawn -= time.delta_seconds();
+        if let Some(mut playback) = playback.filter(|p| p.is_active()) {
+            let controls = playback.next_controls().map(|c| c.to_vec()).unwrap_or_default();
+            control_ships(&controls, data);
+            return;
+        }
 
-        if self.time_to_spawn <= 0.0f32 {
-            let mut local = Transform::default();
-            local.translation_mut().x = rand.next_f32() * ARENA_HEIGHT;
-            local.translation_mut().y = ARENA_WIDTH;
+        let controls = {
+            let (ref ships, _, _, ref locals, ref time, .., ref owners, ref session, _, _) = data;
 
-            let scale = 1.0f32 + rand.next_f32();
+            let online = net.is_some();
+            let local_player = net.as_ref().and_then(|n| n.local_player);
 
-            let r = || (rand.next_f32() - 0.5) * 2.0 * self.max_velocity;
-            let velocity = Vector2::new(r(), r());
+            let mut controls = (0..session.players())
+                .map(|player| {
+                    // NB: online, the local player always uses the bindings of the first player.
+                    let bindings = match local_player {
+                        Some(local_player) if player == local_player => settings.bindings(0),
+                        _ if online => None,
+                        _ => settings.bindings(player),
+                    };
 
-            spawn_asteroid(
-                &entities,
-                &lazy,
-                &rand,
-                &asteroid_resource,
-                local,
-                scale,
-                velocity,
-                self.max_rotation,
-                false,
-            );
+                    match bindings {
+                        Some(bindings) => bindings.controls(&input, &contexts),
+                        None => ShipControls::default(),
+                    }
+                }).collect::<SmallVec<[ShipControls; 2]>>();
 
-            self.time_to_spawn = rand.next_f32() * self.average_spawn_time;
-        }
+            let strength = session.aim_snap(&settings);
+
+            if strength > 0f32 {
+                for (_, local, owner) in (ships, locals, owners.maybe()).join() {
+                    let player = owner.map(|o| o.player).unwrap_or(0);
+
+                    // NB: the other player snaps their own aim before sending their controls.
+                    if online && local_player != Some(player) {
+                        continue;
+                    }
+
+                    let controls = match controls.get_mut(player) {
+                        Some(controls) => controls,
+                        None => continue,
+                    };
+
+                    if let Some(angle) = Self::snap_angle(local, &colliders, locals) {
+                        // the pull weakens as the shot lines up, so that it doesn't overshoot.
+                        let bias = f64::from(strength * angle / Self::SNAP_ANGLE);
+                        let rotate = controls.rotate.unwrap_or(0f64) + bias;
+                        controls.rotate = Some(rotate.max(-1.0).min(1.0));
+                    }
+                }
+            }
+
+            if let Some(net) = net.as_mut() {
+                let local = local_player.and_then(|p| controls.get(p).cloned());
+                let local = local.unwrap_or_default();
+                controls = net.advance(local).iter().cloned().collect();
+            }
+
+            if let Some(mut recorder) = recorder {
+                recorder.record(time.delta_real_seconds(), &controls);
+            }
+
+            controls
+        };
+
+        control_ships(&controls, data);
     }
 }
 
-fn spawn_asteroid(
+/// Spawn the ship at the spawn point, along with the entities attached to it.
+///
+/// The ship is tinted and decorated according to the given look.
+pub fn spawn_ship(
     entities: &Entities,
-    lazy: &Read<LazyUpdate>,
-    rand: &ReadExpect<RandomGen>,
-    asteroid_resource: &ReadExpect<Asteroids>,
-    mut local: Transform,
-    scale: f32,
-    velocity: Vector2<f32>,
-    max_rotation: f32,
-    defer_adding_bounds: bool,
-) {
-    *local.scale_mut() = Vector3::new(scale, scale, 1.0f32);
+    lazy: &LazyUpdate,
+    ships: &Ships,
+    power_ups: &PowerUps,
+    config: &GameConfig,
+    mutations: &Mutations,
+    spawn: SpawnPoint,
+    player: usize,
+    look: ShipLook,
+) -> Entity {
+    let mut local = Transform::default();
+    local.set_xyz(spawn.0.x, spawn.0.y, 0.0);
 
+    let ship = entities.create();
+
+    lazy.insert(ship, ships.new_sprite_render());
     let mut physical = Physical::new();
-    physical.velocity = velocity;
-    physical.rotation = max_rotation * rand.next_f32();
+    physical.max_velocity = config.ship.max_velocity;
 
-    let e = entities.create();
+    // upgrades drafted during the run carry over to new ships.
+    let mut ship_component = Ship::new(config);
+    let mut energy = Energy::new(&config.energy);
+    mutations.apply(&mut ship_component, &mut energy);
 
-    lazy.insert(e, local);
-    lazy.insert(e, physical);
-    lazy.insert(e, ConstrainedObject);
-    lazy.insert(e, asteroid_resource.new_sprite_render(rand));
-    lazy.insert(e, asteroid_resource.new_bounded(scale));
+    lazy.insert(ship, ship_component);
+    lazy.insert(ship, energy);
+    lazy.insert(ship, Health::new(config.ship.hit_points));
+    lazy.insert(ship, Owner { player });
+    lazy.insert(ship, physical);
+    lazy.insert(ship, ConstrainedObject);
+    lazy.insert(ship, local);
+    lazy.insert(ship, Collider::Ship);
+    lazy.insert(ship, ships.new_bounded(config));
 
-    let collider = if defer_adding_bounds {
-        Collider::Deferred(DeferredCollider::Asteroid)
-    } else {
-        Collider::Asteroid
-    };
+    lazy.insert(ship, Rgba::from(look.color));
 
-    lazy.insert(e, collider);
-}
+    // NB: decals which don't exist are ignored, since profiles can be edited by hand.
+    if let Some(decal) = look.decal.filter(|d| *d < ShipLook::DECALS.len()) {
+        let mut decal_local = Transform::default();
+        decal_local.set_xyz(0.0, 0.0, 0.05);
 
-/// Applies physics to `Physical` entities.
-///
-/// The system applies velocity and rotation to the objects in the system.
+        let e = entities.create();
+        lazy.insert(e, ships.new_decal_sprite_render(decal));
+        lazy.insert(e, ShipDecal);
+        lazy.insert(e, Parent { entity: ship });
+        lazy.insert(e, decal_local);
+    }
 
+    let mut ram_local = Transform::default();
+    ram_local.set_xyz(0.0, 2.0, 0.1);
 
-pub struct PhysicsSystem;
+    let ram = entities.create();
+    lazy.insert(ram, power_ups.new_ram_sprite_render());
+    lazy.insert(ram, RamVisual);
+    lazy.insert(ram, Parent { entity: ship });
+    lazy.insert(ram, ram_local);
+    lazy.insert(ram, Hidden);
 
-impl<'s> System<'s> for PhysicsSystem {
-    type SystemData = (
-        ReadStorage<'s, Physical>,
-        WriteStorage<'s, Transform>,
-        Read<'s, Time>,
-    );
+    let mut shield_local = Transform::default();
+    shield_local.set_xyz(0.0, 0.0, 0.1);
+    *shield_local.scale_mut() = Vector3::new(ShieldVisual::SCALE, ShieldVisual::SCALE, 1.0);
 
-    fn run(&mut self, (physicals, mut locals, time): Self::SystemData) {
-        let time_delta = time.delta_seconds();
+    let shield = entities.create();
+    lazy.insert(shield, power_ups.new_shield_sprite_render());
+    lazy.insert(shield, ShieldVisual);
+    lazy.insert(shield, Parent { entity: ship });
+    lazy.insert(shield, shield_local);
+    lazy.insert(shield, Hidden);
 
-        for (physical, local) in (&physicals, &mut locals).join() {
-            // Apply existing velocity and rotational velocity.
-            let movement = physical.velocity * time_delta;
+    let arrow = entities.create();
+    lazy.insert(arrow, ships.new_velocity_sprite_render());
+    lazy.insert(arrow, VelocityArrow);
+    lazy.insert(arrow, Parent { entity: ship });
+    lazy.insert(arrow, Transform::default());
+    lazy.insert(arrow, Hidden);
 
-            local.move_global(Vector3::new(movement.x, movement.y, 0f32));
-            local.roll_local(physical.rotation * time_delta);
-        }
-    }
+    ship
 }
 
-/// Handle very simple collisions through ncollide2d's broad-phase DBVT implementation.
+/// Lets an external program control the ship through `ExternalControl`, instead of the player.
+///
+/// Each frame the program is shown the ship and the asteroids nearest to it, and answers with how
+/// to control the ship. Without a connected program the ship drifts.
+#[cfg(feature = "external_control")]
+pub struct ExternalControlSystem {
+    control: ExternalControl,
+    /// Reused buffer of asteroids, and their distance to the ship.
+    nearest: Vec<(f32, AsteroidObservation)>,
+}
+
+#[cfg(feature = "external_control")]
+impl ExternalControlSystem {
+    /// Number of asteroids included in each observation.
+    const NEAREST: usize = 8;
+
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            control: ExternalControl::bind(path),
+            nearest: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "external_control")]
+impl<'s> System<'s> for ExternalControlSystem {
+    type SystemData = (
+        ReadStorage<'s, Collider>,
+        ReadStorage<'s, Bounded>,
+        ReadExpect<'s, Score>,
+        ShipControlData<'s>,
+    );
+
+    fn run(&mut self, (colliders, bounded, score, data): Self::SystemData) {
+        let observation = {
+            let (ref ships, ref physicals, ref energies, ref locals, ref time, .., ref config) =
+                data;
+
+            let ship = (ships, physicals, energies, locals).join().next();
+
+            self.nearest.clear();
+
+            if let Some((_, _, _, ship_local)) = ship {
+                let asteroids = (&colliders, &bounded, physicals, locals).join();
+
+                for (collider, bounded, physical, local) in asteroids {
+                    if *collider != Collider::Asteroid {
+                        continue;
+                    }
+
+                    let offset = wrapped_delta(ship_local.translation(), local.translation());
+
+                    self.nearest.push((
+                        offset.norm(),
+                        AsteroidObservation {
+                            offset: [offset.x, offset.y],
+                            velocity: [physical.velocity.x, physical.velocity.y],
+                            radius: bounded.shape.radius(),
+                        },
+                    ));
+                }
+            }
+
+            self.nearest
+                .sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+            Observation {
+                delta: time.delta_seconds(),
+                points: score.points,
+                ship: ship.map(|(ship, physical, energy, local)| {
+                    let heading = local.rotation() * Vector3::y();
+
+                    ShipObservation {
+                        position: [local.translation().x, local.translation().y],
+                        velocity: [physical.velocity.x, physical.velocity.y],
+                        heading: heading.y.atan2(heading.x),
+                        can_shoot: ship.reload_timer <= 0f32
+                            && energy.current >= config.energy.shot,
+                        energy: energy.fraction(),
+                    }
+                }),
+                asteroids: self
+                    .nearest
+                    .drain(..)
+                    .take(Self::NEAREST)
+                    .map(|(_, asteroid)| asteroid)
+                    .collect(),
+            }
+        };
+
+        let command = self.control.exchange(&observation).unwrap_or_default();
+
+        let controls = ShipControls {
+            rotate: Some(f64::from(command.rotate.max(-1.0).min(1.0))),
+            accelerate: Some(f64::from(command.thrust.max(-1.0).min(1.0))),
+            shoot: command.shoot,
+            afterburner: command.afterburner,
+            shield: command.shield,
+            hyperspace: command.hyperspace,
+        };
+
+        control_ships(&[controls], data);
+    }
+}
+
+/// How ships are controlled during a single frame.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct ShipControls {
+    /// Rotation, from -1 to 1.
+    pub rotate: Option<f64>,
+    /// Acceleration, from -1 to 1.
+    pub accelerate: Option<f64>,
+    /// If ships should shoot.
+    pub shoot: bool,
+    /// If ships should use their afterburner.
+    pub afterburner: bool,
+    /// If ships should raise their shields.
+    pub shield: bool,
+    /// If ships should jump through hyperspace.
+    pub hyperspace: bool,
+}
+
+/// Data needed to apply `ShipControls`, shared by the systems which decide on the controls.
+type ShipControlData<'s> = (
+    WriteStorage<'s, Ship>,
+    WriteStorage<'s, Physical>,
+    WriteStorage<'s, Energy>,
+    ReadStorage<'s, Transform>,
+    Read<'s, Time>,
+    ReadExpect<'s, Bullets>,
+    ReadExpect<'s, RandomStreams>,
+    ReadExpect<'s, Sounds>,
+    Read<'s, AssetStorage<Source>>,
+    Option<Read<'s, Output>>,
+    Read<'s, AudioMix>,
+    Entities<'s>,
+    Read<'s, LazyUpdate>,
+    Write<'s, EventChannel<GameEvent>>,
+    Read<'s, DevTools>,
+    Option<Write<'s, LatencyProbe>>,
+    ReadStorage<'s, Owner>,
+    Read<'s, SessionSettings>,
+    ReadExpect<'s, Particles>,
+    Read<'s, GameConfig>,
+);
+
+/// Apply controls to all ships, moving them and spawning the bullets they shoot.
+///
+/// Ships are controlled by the controls of the player who owns them, indexed by player.
+fn control_ships(controls: &[ShipControls], data: ShipControlData) {
+    let (
+        mut ships,
+        mut physicals,
+        mut energies,
+        locals,
+        time,
+        bullet_resource,
+        streams,
+        sounds,
+        audio_storage,
+        audio,
+        mix,
+        entities,
+        lazy,
+        mut game_events,
+        dev_tools,
+        mut latency_probe,
+        owners,
+        session,
+        particles,
+        config,
+    ) = data;
+    let rand = streams.get(Stream::Ships);
+
+    let time_delta = time.delta_seconds();
+
+    let mut new_bullets = SmallVec::<[NewBullet; 4]>::new();
+    let mut jumped = false;
+
+    for (e, ship, physical, energy, local, owner) in (
+        &*entities,
+        &mut ships,
+        &mut physicals,
+        &mut energies,
+        &locals,
+        owners.maybe(),
+    )
+        .join()
+    {
+        let player = owner.map(|o| o.player).unwrap_or(0);
+
+        let ShipControls {
+            rotate,
+            accelerate,
+            shoot,
+            afterburner,
+            shield,
+            hyperspace,
+        } = controls.get(player).cloned().unwrap_or_default();
+
+        // handle hyperspace with a cooldown, as long as there's energy for the jump.
+        if ship.hyperspace_timer > 0f32 {
+            ship.hyperspace_timer = f32::max(ship.hyperspace_timer - time_delta, 0f32);
+        } else if hyperspace && energy.spend(config.energy.hyperspace, &config.energy) {
+            ship.hyperspace_timer = ship.hyperspace_cooldown;
+            jumped = true;
+
+            let mut arrival = local.clone();
+            arrival.translation_mut().x = rand.next_f32() * ARENA_WIDTH;
+            arrival.translation_mut().y = rand.next_f32() * ARENA_HEIGHT;
+
+            physical.velocity = Vector2::zeros();
+            physical.rotation = 0f32;
+
+            spawn_hyperspace_burst(local, &rand, &particles, &entities, &lazy);
+            spawn_hyperspace_burst(&arrival, &rand, &particles, &entities, &lazy);
+
+            let failed = !dev_tools.immortal
+                && rand.next_f32() < config.ship.hyperspace_failure_chance;
+
+            if failed {
+                // the ship materialized badly, the `RespawnSystem` takes it from here.
+                let t = arrival.translation();
+
+                game_events.single_write(GameEvent::ShipDestroyed { player });
+                game_events.single_write(GameEvent::Explosion {
+                    position: Vector2::new(t.x, t.y),
+                    radius: Ship::RADIUS,
+                });
+
+                let shockwave = entities.create();
+                lazy.insert(shockwave, arrival);
+                lazy.insert(shockwave, Shockwave::new());
+
+                if let Err(e) = entities.delete(e) {
+                    error!("failed to delete entity: {}", e);
+                }
+            } else {
+                lazy.insert(e, arrival);
+            }
+
+            // the rest of the controls apply once the ship has arrived.
+            continue;
+        }
+
+        // shields stay up for as long as there's energy to hold them.
+        let shield_drain = config.energy.shield * ship.shield_drain;
+        ship.shield_raised = shield && energy.drain(shield_drain, time_delta, &config.energy);
+
+        // ramming ships get a speed boost.
+        let mut boost = if ship.is_ramming() {
+            Ship::RAM_SPEED_BOOST
+        } else {
+            1f32
+        };
+
+        // the afterburner only burns energy while thrusting forward.
+        let thrusting = accelerate.map(|a| a > 0f64).unwrap_or(false);
+
+        if afterburner
+            && thrusting
+            && energy.drain(config.energy.afterburner, time_delta, &config.energy)
+        {
+            boost *= Ship::AFTERBURNER_BOOST;
+        }
+
+        // handle acceleration.
+        if let Some(acceleration) = accelerate {
+            let added =
+                Vector3::y() * ship.acceleration * boost * time_delta * acceleration as f32;
+            let added = local.rotation() * added;
+            physical.velocity += Vector2::new(added.x, added.y);
+
+            // limit velocity.
+            let magnitude = physical.velocity.magnitude();
+            let max_velocity = physical.max_velocity * boost;
+
+            if magnitude > max_velocity {
+                physical.velocity /= magnitude / max_velocity;
+            }
+
+            // thrusting forward leaves a trail of exhaust.
+            if acceleration > 0f64 {
+                ship.exhaust_timer -= time_delta;
+
+                while ship.exhaust_timer <= 0f32 {
+                    ship.exhaust_timer += Ship::EXHAUST_INTERVAL;
+                    spawn_exhaust(local, physical, &rand, &particles, &entities, &lazy);
+                }
+            }
+        }
+
+        // handle rotation
+        if let Some(rotation) = rotate {
+            physical.rotation = ship.rotation * time_delta * rotation as f32;
+        } else {
+            physical.rotation = 0f32;
+        }
+
+        // handle shooting with a reload, as long as there's energy for the shot.
+        if ship.reload_timer <= 0.0f32 {
+            if shoot && energy.spend(config.energy.shot, &config.energy) {
+                ship.reload_timer = ship.reload_time();
+
+                let mut local = local.clone();
+
+                // apply a bit of jitter on the bullet positions.
+                let jitter = Vector3::x() * (rand.next_f32() - 0.5) * ship.bullet_jitter;
+                let jitter = local.rotation() * jitter;
+                *local.translation_mut() += jitter;
+
+                let mut angles = SmallVec::<[f32; 8]>::new();
+                angles.push(0.0);
+
+                for pair in 1..=ship.side_pairs() {
+                    let angle = Ship::SPREAD_ANGLE * pair as f32;
+                    angles.push(angle);
+                    angles.push(-angle);
+                }
+
+                for angle in angles {
+                    let mut local = local.clone();
+                    local.roll_local(angle);
+
+                    new_bullets.push(NewBullet {
+                        local,
+                        velocity: ship.bullet_velocity,
+                        time_to_live: ship.bullet_time_to_live,
+                        owner: owner.cloned(),
+                    });
+                }
+            }
+        } else {
+            ship.reload_timer -= time_delta;
+
+            if ship.reload_timer < 0.0f32 {
+                ship.reload_timer = 0.0f32;
+            }
+        }
+    }
+
+    if !new_bullets.is_empty() {
+        sounds
+            .pew_sfx
+            .play(&audio_storage, audio.as_ref().map(|o| &**o), &mix);
+    }
+
+    if jumped {
+        sounds
+            .hyperspace_sfx
+            .play(&audio_storage, audio.as_ref().map(|o| &**o), &mix);
+    }
+
+    for new_bullet in new_bullets {
+        let NewBullet {
+            local,
+            velocity,
+            time_to_live,
+            owner,
+        } = new_bullet;
+
+        let velocity = local.rotation() * Vector3::y() * velocity;
+
+        let mut physical = Physical::new();
+        physical.velocity = Vector2::new(velocity.x, velocity.y);
+
+        let e = entities.create();
+
+        lazy.insert(e, local);
+        lazy.insert(e, physical);
+        lazy.insert(e, ConstrainedObject);
+        lazy.insert(e, bullet_resource.new_sprite_render());
+        lazy.insert(e, Bullet::new(time_to_live));
+        lazy.insert(e, bullet_resource.new_bounded());
+        lazy.insert(e, Collider::Deferred(DeferredCollider::Bullet));
+
+        if let Some(owner) = owner {
+            lazy.insert(e, owner);
+
+            if let Some(color) = session.player_color(owner.player) {
+                lazy.insert(e, Rgba::from(color));
+            }
+        }
+
+        if let Some(pressed_at) = latency_probe.as_mut().and_then(|p| p.pressed_at.take()) {
+            lazy.insert(e, LatencyMarker { pressed_at });
+        }
+    }
+
+    struct NewBullet {
+        local: Transform,
+        velocity: f32,
+        time_to_live: f32,
+        owner: Option<Owner>,
+    }
+}
+
+/// Spawn a ring of particles where a ship leaves or arrives from a hyperspace jump.
+fn spawn_hyperspace_burst(
+    local: &Transform,
+    rand: &RandomGen,
+    particles: &ReadExpect<Particles>,
+    entities: &Entities,
+    lazy: &Read<LazyUpdate>,
+) {
+    /// Number of particles in the ring.
+    const COUNT: usize = 12;
+    /// Speed at which particles leave the ring (units / s).
+    const VELOCITY: f32 = 50.0;
+    /// How long the particles live (s).
+    const LIFETIME: f32 = 0.4;
+
+    let step = 2.0 * std::f32::consts::PI / COUNT as f32;
+    // NB: rotate the ring at random, so that consecutive jumps don't look the same.
+    let offset = rand.next_f32() * step;
+
+    for i in 0..COUNT {
+        let angle = offset + step * i as f32;
+        let direction = Vector2::new(angle.cos(), angle.sin());
+
+        let mut particle = Transform::default();
+        *particle.translation_mut() = *local.translation();
+
+        spawn_particle(
+            entities,
+            lazy,
+            particles.new_sprite_render(),
+            particle,
+            direction * VELOCITY,
+            LIFETIME,
+            0.8,
+        );
+    }
+}
+
+/// Spawn a single exhaust particle behind a thrusting ship.
+fn spawn_exhaust(
+    local: &Transform,
+    physical: &Physical,
+    rand: &RandomGen,
+    particles: &ReadExpect<Particles>,
+    entities: &Entities,
+    lazy: &Read<LazyUpdate>,
+) {
+    /// Speed at which exhaust leaves the ship (units / s).
+    const VELOCITY: f32 = 40.0;
+    /// Random spread of the exhaust velocity (units / s).
+    const SPREAD: f32 = 10.0;
+    /// How long exhaust particles live (s).
+    const LIFETIME: f32 = 0.3;
+
+    let backwards = local.rotation() * -Vector3::y();
+
+    let mut exhaust = Transform::default();
+    *exhaust.translation_mut() = *local.translation() + backwards * 5.0;
+
+    let r = || (rand.next_f32() - 0.5) * 2.0 * SPREAD;
+    let velocity = Vector2::new(backwards.x, backwards.y) * VELOCITY + Vector2::new(r(), r());
+
+    spawn_particle(
+        entities,
+        lazy,
+        particles.new_exhaust_sprite_render(),
+        exhaust,
+        physical.velocity + velocity,
+        LIFETIME,
+        0.6,
+    );
+}
+
+/// Draws a dotted line predicting the trajectory of bullets fired by the ship, including where
+/// they continue after wrapping around the arena.
+///
+/// Only active if enabled through `Settings::aim_assist`, or for the session with `--aim-assist`.
+#[derive(Default)]
+pub struct AimAssistSystem {
+    dots: Vec<Entity>,
+}
+
+impl AimAssistSystem {
+    /// Number of dots in the trajectory line.
+    const DOTS: usize = 12;
+    /// How far ahead the trajectory is predicted (s).
+    const PREDICTION: f32 = 1.5;
+    /// Scale of each dot relative to a bullet.
+    const DOT_SCALE: f32 = 0.5;
+}
+
+impl<'s> System<'s> for AimAssistSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, Settings>,
+        Read<'s, SessionSettings>,
+        ReadStorage<'s, Ship>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, SpriteRender>,
+        WriteStorage<'s, AimDot>,
+        WriteStorage<'s, Hidden>,
+        ReadExpect<'s, Bullets>,
+    );
+
+    fn run(&mut self, system: Self::SystemData) {
+        let (
+            entities,
+            settings,
+            session,
+            ships,
+            mut locals,
+            mut sprites,
+            mut aim_dots,
+            mut hidden,
+            bullet_resource,
+        ) = system;
+
+        if !session.aim_assist(&settings) {
+            return;
+        }
+
+        // dots are lost when the world is cleared on restart.
+        if self.dots.iter().any(|e| !entities.is_alive(*e)) || self.dots.is_empty() {
+            self.dots.clear();
+
+            for _ in 0..Self::DOTS {
+                let mut local = Transform::default();
+                *local.scale_mut() = Vector3::new(Self::DOT_SCALE, Self::DOT_SCALE, 1.0);
+
+                let e = entities
+                    .build_entity()
+                    .with(local, &mut locals)
+                    .with(bullet_resource.new_sprite_render(), &mut sprites)
+                    .with(AimDot, &mut aim_dots)
+                    .with(Hidden, &mut hidden)
+                    .build();
+
+                self.dots.push(e);
+            }
+        }
+
+        let aim = (&ships, &locals).join().next().map(|(ship, local)| {
+            let velocity = local.rotation() * Vector3::y() * ship.bullet_velocity;
+            (*local.translation(), velocity)
+        });
+
+        let (start, velocity) = match aim {
+            Some(aim) => aim,
+            None => {
+                for e in &self.dots {
+                    if !hidden.contains(*e) {
+                        if let Err(e) = hidden.insert(*e, Hidden) {
+                            error!("failed to hide aim dot: {}", e);
+                        }
+                    }
+                }
+
+                return;
+            }
+        };
+
+        for (i, e) in self.dots.iter().enumerate() {
+            let t = Self::PREDICTION * (i + 1) as f32 / Self::DOTS as f32;
+            let mut p = start + velocity * t;
+
+            // continue the line on the other side of the arena when wrapping.
+            p.x = wrap(p.x, ARENA_WIDTH);
+            p.y = wrap(p.y, ARENA_HEIGHT);
+
+            if let Some(local) = locals.get_mut(*e) {
+                *local.translation_mut() = p;
+            }
+
+            hidden.remove(*e);
+        }
+    }
+}
+
+/// Draws faint lines predicting where asteroids are headed, including where they continue after
+/// wrapping around or bouncing off the edges of the arena.
+///
+/// Only active if enabled through `Settings::trajectories`. The lines are only traced every now and
+/// then, to bound the cost in dense fields.
+#[derive(Default)]
+pub struct TrajectorySystem {
+    /// Line segments of the most recently traced trajectories.
+    segments: Vec<(Point3<f32>, Point3<f32>)>,
+    /// Time until trajectories are traced again (s).
+    timer: f32,
+}
+
+impl TrajectorySystem {
+    /// How far ahead trajectories are predicted (s).
+    const PREDICTION: f32 = 1.5;
+    /// How often trajectories are traced (s).
+    const REFRESH: f32 = 0.1;
+    /// Maximum number of times a trajectory is followed across the edges of the arena.
+    const MAX_CROSSINGS: usize = 4;
+    /// Color of the lines, faint enough to not be mistaken for anything solid.
+    const COLOR: Rgba = Rgba(0.25, 0.25, 0.3, 1.0);
+
+    /// Trace the trajectory of an asteroid, adding its segments.
+    fn trace(
+        &mut self,
+        start: &Vector3<f32>,
+        mut velocity: Vector2<f32>,
+        radius: f32,
+        edges: ArenaEdges,
+    ) {
+        // NB: things bounce off walls once their edge touches them.
+        let (min, max) = match edges {
+            ArenaEdges::Wrap => (Vector2::new(0.0, 0.0), Vector2::new(ARENA_WIDTH, ARENA_HEIGHT)),
+            ArenaEdges::Walls { .. } => (
+                Vector2::new(radius, radius),
+                Vector2::new(ARENA_WIDTH - radius, ARENA_HEIGHT - radius),
+            ),
+        };
+
+        // time until the next edge is reached along one axis.
+        let until = |p: f32, v: f32, min: f32, max: f32| {
+            if v > 0f32 {
+                ((max - p) / v).max(0f32)
+            } else if v < 0f32 {
+                ((min - p) / v).max(0f32)
+            } else {
+                std::f32::INFINITY
+            }
+        };
+
+        let mut p = Vector2::new(start.x, start.y);
+        let mut remaining = Self::PREDICTION;
+
+        for _ in 0..=Self::MAX_CROSSINGS {
+            let tx = until(p.x, velocity.x, min.x, max.x);
+            let ty = until(p.y, velocity.y, min.y, max.y);
+            let t = remaining.min(tx).min(ty);
+
+            let end = p + velocity * t;
+            let segment = (Point3::new(p.x, p.y, start.z), Point3::new(end.x, end.y, start.z));
+            self.segments.push(segment);
+            p = end;
+
+            if t >= remaining {
+                break;
+            }
+
+            remaining -= t;
+
+            if tx <= ty {
+                match edges {
+                    ArenaEdges::Wrap => p.x = if velocity.x > 0f32 { min.x } else { max.x },
+                    ArenaEdges::Walls { restitution } => velocity.x *= -restitution,
+                }
+            }
+
+            if ty <= tx {
+                match edges {
+                    ArenaEdges::Wrap => p.y = if velocity.y > 0f32 { min.y } else { max.y },
+                    ArenaEdges::Walls { restitution } => velocity.y *= -restitution,
+                }
+            }
+        }
+    }
+}
+
+impl<'s> System<'s> for TrajectorySystem {
+    type SystemData = (
+        Read<'s, Settings>,
+        Option<Write<'s, DebugLines>>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, Physical>,
+        ReadStorage<'s, Bounded>,
+        ReadStorage<'s, Collider>,
+        ReadStorage<'s, Comet>,
+        Read<'s, GameConfig>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (settings, lines, locals, physicals, bounded, colliders, comets, config, time) = data;
+
+        if !settings.trajectories {
+            self.segments.clear();
+            return;
+        }
+
+        let mut lines = match lines {
+            Some(lines) => lines,
+            None => return,
+        };
+
+        self.timer -= time.delta_seconds();
+
+        if self.timer <= 0f32 {
+            self.timer = Self::REFRESH;
+            self.segments.clear();
+
+            let asteroids = (&locals, &physicals, &bounded, &colliders, !&comets).join();
+
+            for (local, physical, bounded, collider, _) in asteroids {
+                if *collider != Collider::Asteroid {
+                    continue;
+                }
+
+                let radius = bounded.shape.radius();
+                self.trace(local.translation(), physical.velocity, radius, config.edges);
+            }
+        }
+
+        for (a, b) in &self.segments {
+            lines.draw_line(*a, *b, Self::COLOR);
+        }
+    }
+}
+
+/// Distance between two points in the arena, taking into account that the shortest path might be
+/// across the arena edges.
+fn wrapped_distance(a: &Vector3<f32>, b: &Vector3<f32>) -> f32 {
+    wrapped_delta(a, b).norm()
+}
+
+/// Shortest vector from `a` to `b` in the arena, taking into account that it might go across the
+/// arena edges.
+fn wrapped_delta(a: &Vector3<f32>, b: &Vector3<f32>) -> Vector2<f32> {
+    let shortest = |d: f32, max: f32| {
+        if d > max / 2.0 {
+            d - max
+        } else if d < -max / 2.0 {
+            d + max
+        } else {
+            d
+        }
+    };
+
+    Vector2::new(shortest(b.x - a.x, ARENA_WIDTH), shortest(b.y - a.y, ARENA_HEIGHT))
+}
+
+/// Center of mass of weighted points in the arena.
+///
+/// Since points can be on opposite sides of an arena edge while being close to each other, each
+/// axis is averaged as an angle on a circle instead of linearly.
+fn wrapped_center_of_mass(points: impl IntoIterator<Item = (Vector3<f32>, f32)>) -> Vector3<f32> {
+    use std::f32::consts;
+
+    let mut x = Vector2::new(0f32, 0f32);
+    let mut y = Vector2::new(0f32, 0f32);
+    let mut z = 0f32;
+    let mut total = 0f32;
+
+    for (p, w) in points {
+        let ax = p.x / ARENA_WIDTH * 2.0 * consts::PI;
+        let ay = p.y / ARENA_HEIGHT * 2.0 * consts::PI;
+        x += Vector2::new(ax.cos(), ax.sin()) * w;
+        y += Vector2::new(ay.cos(), ay.sin()) * w;
+        z += p.z * w;
+        total += w;
+    }
+
+    if total <= 0f32 {
+        return Vector3::new(0f32, 0f32, 0f32);
+    }
+
+    let mean = |v: Vector2<f32>, max: f32| wrap(v.y.atan2(v.x) / (2.0 * consts::PI) * max, max);
+    Vector3::new(mean(x, ARENA_WIDTH), mean(y, ARENA_HEIGHT), z / total)
+}
+
+/// Wrap a coordinate into the range `[0, max)`.
+fn wrap(v: f32, max: f32) -> f32 {
+    ((v % max) + max) % max
+}
+
+/// Closes in two opposing edges of the arena every now and then, if configured to.
+///
+/// The edges along a random axis become solid walls, which move inward for a while before
+/// retracting and becoming regular edges again. The walls are drawn where they are.
+pub struct SqueezeSystem;
+
+impl SqueezeSystem {
+    /// Color of the walls.
+    const COLOR: Rgba = Rgba(1.0, 0.4, 0.2, 1.0);
+    /// Number of lines drawn for each wall, side by side.
+    const THICKNESS: usize = 3;
+}
+
+impl<'s> System<'s> for SqueezeSystem {
+    type SystemData = (
+        Option<Write<'s, Squeeze>>,
+        Option<Write<'s, ArenaBounds>>,
+        Option<Write<'s, DebugLines>>,
+        ReadExpect<'s, RandomStreams>,
+        Read<'s, GameConfig>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (squeeze, bounds, lines, streams, config, time): Self::SystemData) {
+        let rand = streams.get(Stream::Squeeze);
+
+        let (mut squeeze, mut bounds) = match (squeeze, bounds) {
+            (Some(squeeze), Some(bounds)) => (squeeze, bounds),
+            _ => return,
+        };
+
+        let policy = match config.squeeze {
+            Some(policy) => policy,
+            None => return,
+        };
+
+        let delta = time.delta_seconds();
+
+        let (axis, elapsed) = match squeeze.active {
+            Some((axis, elapsed)) => (axis, elapsed + delta),
+            None => {
+                squeeze.idle += delta;
+
+                if squeeze.idle < policy.interval {
+                    return;
+                }
+
+                squeeze.idle = 0f32;
+                let axis = if rand.next_f32() < 0.5 { 0 } else { 1 };
+                (axis, 0f32)
+            }
+        };
+
+        let progress = if elapsed < policy.duration {
+            elapsed / policy.duration
+        } else {
+            1.0 - (elapsed - policy.duration) / policy.retract.max(0.01)
+        };
+
+        *bounds = ArenaBounds::default();
+
+        if progress <= 0f32 {
+            squeeze.active = None;
+            return;
+        }
+
+        squeeze.active = Some((axis, elapsed));
+
+        let size = bounds.max[axis];
+        let inset = size * policy.depth.max(0.0).min(0.45) * progress.min(1.0);
+        bounds.min[axis] = inset;
+        bounds.max[axis] = size - inset;
+        bounds.walls = Some((axis, policy.restitution));
+
+        let mut lines = match lines {
+            Some(lines) => lines,
+            None => return,
+        };
+
+        let other = 1 - axis;
+
+        for i in 0..Self::THICKNESS {
+            let offset = i as f32;
+
+            for (at, outward) in &[(bounds.min[axis], -1f32), (bounds.max[axis], 1f32)] {
+                let mut a = Point3::new(0f32, 0f32, 0f32);
+                let mut b = a;
+                a[axis] = at + outward * offset;
+                b[axis] = at + outward * offset;
+                b[other] = bounds.max[other];
+                lines.draw_line(a, b, Self::COLOR);
+            }
+        }
+    }
+}
+
+/// Limit objects within arena.
+///
+/// Depending on the configured `ArenaEdges`, an object going out of bounds is either moved to the
+/// other side of the arena, or bounced off the wall it hit. While the walls close in, the edges
+/// they're on are walls regardless, which are kept inside of the `ArenaBounds`.
+pub struct LimitObjectsSystem;
+
+impl LimitObjectsSystem {
+    /// Reflect a single axis of position and velocity against walls at `min` and `max`.
+    fn reflect(p: &mut f32, v: &mut f32, min: f32, max: f32, restitution: f32) {
+        if min > max {
+            return;
+        }
+
+        if *p < min {
+            *p = min + (min - *p);
+            *v = v.abs() * restitution;
+        } else if *p > max {
+            *p = max - (*p - max);
+            *v = -v.abs() * restitution;
+        }
+
+        // guard against overshooting the opposite wall.
+        *p = p.max(min).min(max);
+    }
+}
+
+impl<'s> System<'s> for LimitObjectsSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, Physical>,
+        ReadStorage<'s, Bounded>,
+        ReadStorage<'s, ConstrainedObject>,
+        Read<'s, GameConfig>,
+        Option<Read<'s, ArenaBounds>>,
+        Write<'s, EventChannel<Wrapped>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut locals,
+            mut physicals,
+            bounded,
+            constrained,
+            config,
+            bounds,
+            mut wrapped,
+        ) = data;
+
+        let bounds = bounds.map(|b| *b).unwrap_or_default();
+
+        for (e, local, physical, bounded, _) in (
+            &*entities,
+            &mut locals,
+            (&mut physicals).maybe(),
+            bounded.maybe(),
+            &constrained,
+        )
+            .join()
+        {
+            let mut t = *local.translation();
+            let mut wrapping = false;
+
+            let r = bounded.map(|b| b.shape.radius()).unwrap_or(0f32);
+            let mut v = physical
+                .as_ref()
+                .map(|p| p.velocity)
+                .unwrap_or_else(|| Vector2::new(0f32, 0f32));
+
+            for axis in 0..2 {
+                let restitution = match config.edges {
+                    ArenaEdges::Walls { restitution } => Some(restitution),
+                    ArenaEdges::Wrap => bounds.wall_restitution(axis),
+                };
+
+                let (min, max) = (bounds.min[axis], bounds.max[axis]);
+
+                match restitution {
+                    Some(restitution) => {
+                        Self::reflect(&mut t[axis], &mut v[axis], min + r, max - r, restitution);
+                    }
+                    None => {
+                        if t[axis] < min {
+                            t[axis] += max - min;
+                            wrapping = true;
+                        } else if t[axis] > max {
+                            t[axis] -= max - min;
+                            wrapping = true;
+                        }
+                    }
+                }
+            }
+
+            // NB: wrapping through a corner only counts once.
+            if wrapping {
+                wrapped.single_write(Wrapped(e));
+            }
+
+            if let Some(physical) = physical {
+                physical.velocity = v;
+            }
+
+            *local.translation_mut() = t;
+        }
+    }
+}
+
+/// A ghost of an object, by the object and which side of the arena the ghost is on.
+type GhostKey = (Entity, i8, i8);
+
+/// Shows ghosts of objects crossing the edges of a wrapping arena on the opposite side, so that
+/// they slide across the edge instead of popping over once their center wraps.
+///
+/// Ghosts are removed once they're no longer needed.
+#[derive(Default)]
+pub struct GhostSystem {
+    /// Ghosts currently shown.
+    ghosts: HashMap<GhostKey, Entity>,
+    /// Reused buffer of ghosts needed this frame, and how to show them.
+    needed: Vec<(GhostKey, Transform, SpriteRender, Option<Rgba>, bool)>,
+    /// Reused buffer of ghosts which were updated this frame.
+    seen: HashSet<GhostKey>,
+}
+
+impl GhostSystem {
+    /// Extra distance from the edges at which ghosts are shown, since sprites can stick out of
+    /// their bounding volume.
+    const MARGIN: f32 = 2.0;
+
+    /// Side of the arena along one axis a ghost is needed on, if any.
+    fn side(p: f32, radius: f32, max: f32) -> i8 {
+        if p - radius < 0f32 {
+            1
+        } else if p + radius > max {
+            -1
+        } else {
+            0
+        }
+    }
+}
+
+impl<'s> System<'s> for GhostSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, SpriteRender>,
+        WriteStorage<'s, Rgba>,
+        WriteStorage<'s, Hidden>,
+        WriteStorage<'s, Ghost>,
+        ReadStorage<'s, Bounded>,
+        ReadStorage<'s, ConstrainedObject>,
+        Read<'s, GameConfig>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut locals,
+            mut sprites,
+            mut tints,
+            mut hidden,
+            mut ghosts,
+            bounded,
+            constrained,
+            config,
+        ) = data;
+
+        // NB: objects never cross the edges of an arena with walls.
+        if let ArenaEdges::Wrap = config.edges {
+            for (e, local, bounded, sprite, _) in
+                (&*entities, &locals, &bounded, &sprites, &constrained).join()
+            {
+                let t = local.translation();
+                let r = bounded.shape.radius() + Self::MARGIN;
+                let x = Self::side(t.x, r, ARENA_WIDTH);
+                let y = Self::side(t.y, r, ARENA_HEIGHT);
+
+                let mut sides = SmallVec::<[(i8, i8); 3]>::new();
+
+                if x != 0 {
+                    sides.push((x, 0));
+                }
+
+                if y != 0 {
+                    sides.push((0, y));
+                }
+
+                // near a corner, the object also shows up diagonally across the arena.
+                if x != 0 && y != 0 {
+                    sides.push((x, y));
+                }
+
+                for (x, y) in sides {
+                    let mut local = local.clone();
+                    local.translation_mut().x += f32::from(x) * ARENA_WIDTH;
+                    local.translation_mut().y += f32::from(y) * ARENA_HEIGHT;
+
+                    let tint = tints.get(e).cloned();
+                    let is_hidden = hidden.contains(e);
+
+                    self.needed.push(((e, x, y), local, sprite.clone(), tint, is_hidden));
+                }
+            }
+        }
+
+        for (key, local, sprite, tint, is_hidden) in self.needed.drain(..) {
+            let ghost = match self.ghosts.get(&key) {
+                Some(ghost) if entities.is_alive(*ghost) => *ghost,
+                _ => {
+                    let ghost = entities.create();
+                    self.ghosts.insert(key, ghost);
+                    ghost
+                }
+            };
+
+            let result = locals
+                .insert(ghost, local)
+                .map(|_| ())
+                .and_then(|_| sprites.insert(ghost, sprite).map(|_| ()))
+                .and_then(|_| ghosts.insert(ghost, Ghost).map(|_| ()));
+
+            if let Err(e) = result {
+                error!("failed to update ghost: {}", e);
+                continue;
+            }
+
+            match tint {
+                Some(tint) => {
+                    if let Err(e) = tints.insert(ghost, tint) {
+                        error!("failed to tint ghost: {}", e);
+                    }
+                }
+                None => {
+                    tints.remove(ghost);
+                }
+            }
+
+            if is_hidden {
+                if !hidden.contains(ghost) {
+                    if let Err(e) = hidden.insert(ghost, Hidden) {
+                        error!("failed to hide ghost: {}", e);
+                    }
+                }
+            } else {
+                hidden.remove(ghost);
+            }
+
+            self.seen.insert(key);
+        }
+
+        let seen = &mut self.seen;
+
+        self.ghosts.retain(|key, ghost| {
+            if seen.contains(key) {
+                return true;
+            }
+
+            if entities.is_alive(*ghost) {
+                if let Err(e) = entities.delete(*ghost) {
+                    error!("failed to delete ghost: {}", e);
+                }
+            }
+
+            false
+        });
+
+        seen.clear();
+    }
+}
+
+/// Shows a phantom twin of every asteroid when playing with double vision, wobbling around it.
+///
+/// Phantoms only have a sprite, so they never collide with anything. They are removed together
+/// with their asteroid.
+#[derive(Default)]
+pub struct PhantomSystem {
+    /// Phantoms currently shown, by the asteroid they belong to.
+    phantoms: HashMap<Entity, Entity>,
+    /// Reused buffer of phantoms needed this frame, and how to show them.
+    needed: Vec<(Entity, Transform, SpriteRender, Option<Rgba>)>,
+    /// Reused buffer of phantoms which were updated this frame.
+    seen: HashSet<Entity>,
+    /// Time since the system started (s), which drives the wobble.
+    clock: f32,
+}
+
+impl<'s> System<'s> for PhantomSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, SpriteRender>,
+        WriteStorage<'s, Rgba>,
+        WriteStorage<'s, Phantom>,
+        ReadStorage<'s, Collider>,
+        Read<'s, GameConfig>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        use std::f32::consts;
+
+        let (entities, mut locals, mut sprites, mut tints, mut phantoms, colliders, config, time) =
+            data;
+
+        self.clock += time.delta_seconds();
+
+        if let Some(policy) = config.double_vision {
+            for (e, local, sprite, collider) in (&*entities, &locals, &sprites, &colliders).join() {
+                match *collider {
+                    Collider::Asteroid | Collider::Deferred(DeferredCollider::Asteroid) => {}
+                    _ => continue,
+                }
+
+                // NB: offset the wobble of each asteroid, so that phantoms don't move in unison.
+                let phase = (e.id() % 16) as f32 / 16.0;
+                let angle = (self.clock * policy.wobble + phase) * 2.0 * consts::PI;
+
+                let mut local = local.clone();
+                local.translation_mut().x += angle.cos() * policy.distance;
+                local.translation_mut().y += angle.sin() * policy.distance;
+
+                let tint = tints.get(e).cloned();
+                self.needed.push((e, local, sprite.clone(), tint));
+            }
+        }
+
+        for (parent, local, sprite, tint) in self.needed.drain(..) {
+            let phantom = match self.phantoms.get(&parent) {
+                Some(phantom) if entities.is_alive(*phantom) => *phantom,
+                _ => {
+                    let phantom = entities.create();
+                    self.phantoms.insert(parent, phantom);
+                    phantom
+                }
+            };
+
+            let result = locals
+                .insert(phantom, local)
+                .map(|_| ())
+                .and_then(|_| sprites.insert(phantom, sprite).map(|_| ()))
+                .and_then(|_| phantoms.insert(phantom, Phantom).map(|_| ()));
+
+            if let Err(e) = result {
+                error!("failed to update phantom: {}", e);
+                continue;
+            }
+
+            match tint {
+                Some(tint) => {
+                    if let Err(e) = tints.insert(phantom, tint) {
+                        error!("failed to tint phantom: {}", e);
+                    }
+                }
+                None => {
+                    tints.remove(phantom);
+                }
+            }
+
+            self.seen.insert(parent);
+        }
+
+        let seen = &mut self.seen;
+
+        self.phantoms.retain(|parent, phantom| {
+            if seen.contains(parent) {
+                return true;
+            }
+
+            if entities.is_alive(*phantom) {
+                if let Err(e) = entities.delete(*phantom) {
+                    error!("failed to delete phantom: {}", e);
+                }
+            }
+
+            false
+        });
+
+        seen.clear();
+    }
+}
+
+/// Removes bullets which have run out of time to live, or wrapped around the arena too many times.
+///
+/// Emits a `GameEvent::BulletExpired` for every expired bullet, and records bullets fired by the
+/// ship as misses.
+#[derive(Default)]
+pub struct KillBulletsSystem {
+    reader: Option<ReaderId<Wrapped>>,
+}
+
+impl<'s> System<'s> for KillBulletsSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Bullet>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, Collider>,
+        Read<'s, Time>,
+        Read<'s, EventChannel<Wrapped>>,
+        Write<'s, EventChannel<GameEvent>>,
+        WriteExpect<'s, Score>,
+    );
+
+    fn run(&mut self, system: Self::SystemData) {
+        let (
+            entities,
+            mut bullets,
+            locals,
+            colliders,
+            time,
+            wrapped,
+            mut game_events,
+            mut score,
+        ) = system;
+
+        let reader = self.reader.as_mut().expect("reader not initialized");
+
+        for Wrapped(e) in wrapped.read(reader) {
+            let bullet = match bullets.get_mut(*e) {
+                Some(bullet) => bullet,
+                None => continue,
+            };
+
+            if bullet.wraps == 0 {
+                bullet.time_to_live = 0f32;
+            } else {
+                bullet.wraps -= 1;
+            }
+        }
+
+        let time_delta = time.delta_seconds();
+
+        for (e, bullet) in (&*entities, &mut bullets).join() {
+            bullet.time_to_live -= time_delta;
+
+            if bullet.time_to_live <= 0.0f32 {
+                if colliders.get(e) != Some(&Collider::UfoBullet) {
+                    score.accuracy.record(false);
+                }
+
+                if let Some(local) = locals.get(e) {
+                    let t = local.translation();
+                    game_events.single_write(GameEvent::BulletExpired(Vector2::new(t.x, t.y)));
+                }
+
+                if let Err(e) = entities.delete(e) {
+                    error!("failed to destroy entity: {}", e);
+                }
+
+                continue;
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut Resources) {
+        Self::SystemData::setup(res);
+        self.reader = Some(res.fetch_mut::<EventChannel<Wrapped>>().register_reader());
+    }
+}
+
+/// Records gameplay events into the `GameLog`, if it is present.
+///
+/// Asteroids and hull chunks can be removed in many different ways, so instead of relying on events
+/// this keeps track of them and records them as removed once they are no longer alive.
+#[derive(Default)]
+pub struct GameLogSystem {
+    reader: Option<ReaderId<GameEvent>>,
+    /// Time since the start of the run.
+    clock: f32,
+    /// Asteroids which have been recorded as spawned, but not yet as removed.
+    alive: Vec<Entity>,
+    /// Hull chunks which have been recorded as spawned, but not yet as removed.
+    hulls: Vec<Entity>,
+}
+
+impl<'s> System<'s> for GameLogSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, EventChannel<GameEvent>>,
+        Option<Write<'s, GameLog>>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (entities, events, log, time): Self::SystemData) {
+        let reader = self.reader.as_mut().expect("reader not set up");
+
+        let mut log = match log {
+            Some(log) => log,
+            None => {
+                // NB: keep the reader drained.
+                for _ in events.read(reader) {}
+                return;
+            }
+        };
+
+        self.clock += time.delta_seconds();
+        let clock = self.clock;
+
+        // NB: removals are recorded first, since ids are reused by entities spawned after them.
+        self.alive.retain(|e| {
+            if entities.is_alive(*e) {
+                return true;
+            }
+
+            log.push(clock, LogEvent::AsteroidRemoved { id: e.id() });
+            false
+        });
+
+        self.hulls.retain(|e| {
+            if entities.is_alive(*e) {
+                return true;
+            }
+
+            log.push(clock, LogEvent::HullRemoved { id: e.id() });
+            false
+        });
+
+        for event in events.read(reader) {
+            let event = match *event {
+                GameEvent::GameStarted => {
+                    self.clock = 0f32;
+                    self.alive.clear();
+                    self.hulls.clear();
+                    log.records.clear();
+                    LogEvent::Started
+                }
+                GameEvent::AsteroidSpawned {
+                    entity,
+                    position,
+                    velocity,
+                    rotation,
+                    lateral_acceleration,
+                    scale,
+                    comet,
+                } => {
+                    self.alive.push(entity);
+
+                    LogEvent::AsteroidSpawned {
+                        id: entity.id(),
+                        position: [position.x, position.y],
+                        velocity: [velocity.x, velocity.y],
+                        rotation,
+                        lateral_acceleration,
+                        scale,
+                        comet,
+                    }
+                }
+                GameEvent::AsteroidDestroyed { points, .. } => {
+                    LogEvent::AsteroidDestroyed { points }
+                }
+                GameEvent::UfoDestroyed { points } => LogEvent::UfoDestroyed { points },
+                GameEvent::HullSpawned {
+                    entity,
+                    position,
+                    velocity,
+                    rotation,
+                } => {
+                    self.hulls.push(entity);
+
+                    LogEvent::HullSpawned {
+                        id: entity.id(),
+                        position: [position.x, position.y],
+                        velocity: [velocity.x, velocity.y],
+                        rotation,
+                    }
+                }
+                GameEvent::HullDestroyed { points } => LogEvent::HullDestroyed { points },
+                GameEvent::PlayerDied => LogEvent::PlayerDied,
+                _ => continue,
+            };
+
+            log.push(self.clock, event);
+        }
+    }
+
+    fn setup(&mut self, res: &mut Resources) {
+        Self::SystemData::setup(res);
+        self.reader = Some(res.fetch_mut::<EventChannel<GameEvent>>().register_reader());
+    }
+}
+
+/// Spawns visual effects in response to game events.
+///
+/// * Expired bullets leave a small fizzle behind.
+/// * Explosions scatter debris, more of it the larger the thing which blew up.
+#[derive(Default)]
+pub struct EffectsSystem {
+    reader: Option<ReaderId<GameEvent>>,
+}
+
+impl EffectsSystem {
+    /// How long a fizzle lasts (s).
+    const FIZZLE_LIFETIME: f32 = 0.25;
+    /// Scale of a fizzle.
+    const FIZZLE_SCALE: f32 = 0.6;
+    /// Pieces of debris scattered for each unit of radius of the thing which blew up.
+    const DEBRIS_PER_RADIUS: f32 = 1.5;
+    /// Maximum pieces of debris scattered by a single explosion.
+    const MAX_DEBRIS: usize = 16;
+    /// Maximum speed of debris (units / s).
+    const DEBRIS_VELOCITY: f32 = 60.0;
+    /// Maximum time debris lives (s).
+    const DEBRIS_LIFETIME: f32 = 0.8;
+}
+
+impl<'s> System<'s> for EffectsSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, EventChannel<GameEvent>>,
+        ReadExpect<'s, Particles>,
+        ReadExpect<'s, RandomStreams>,
+        Read<'s, LazyUpdate>,
+    );
+
+    fn run(&mut self, (entities, events, particles, streams, lazy): Self::SystemData) {
+        use std::f32::consts;
+
+        let rand = streams.get(Stream::Effects);
+
+        let reader = self.reader.as_mut().expect("reader not set up");
+
+        for event in events.read(reader) {
+            match *event {
+                GameEvent::BulletExpired(position) => {
+                    let mut local = Transform::default();
+                    local.translation_mut().x = position.x;
+                    local.translation_mut().y = position.y;
+
+                    spawn_particle(
+                        &entities,
+                        &lazy,
+                        particles.new_sprite_render(),
+                        local,
+                        Vector2::new(0f32, 0f32),
+                        Self::FIZZLE_LIFETIME,
+                        Self::FIZZLE_SCALE,
+                    );
+                }
+                GameEvent::Explosion { position, radius } => {
+                    let count = ((radius * Self::DEBRIS_PER_RADIUS) as usize).min(Self::MAX_DEBRIS);
+
+                    for _ in 0..count {
+                        let angle = rand.next_f32() * consts::PI * 2.0;
+                        let direction = Vector2::new(angle.cos(), angle.sin());
+
+                        // debris starts out spread over the thing which blew up.
+                        let mut local = Transform::default();
+                        local.translation_mut().x = position.x + direction.x * radius * 0.5;
+                        local.translation_mut().y = position.y + direction.y * radius * 0.5;
+                        local.roll_local(angle);
+
+                        let speed = (0.3 + 0.7 * rand.next_f32()) * Self::DEBRIS_VELOCITY;
+                        let lifetime = (0.5 + 0.5 * rand.next_f32()) * Self::DEBRIS_LIFETIME;
+
+                        spawn_particle(
+                            &entities,
+                            &lazy,
+                            particles.new_debris_sprite_render(),
+                            local,
+                            direction * speed,
+                            lifetime,
+                            0.5 + 0.5 * rand.next_f32(),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut Resources) {
+        Self::SystemData::setup(res);
+        self.reader = Some(res.fetch_mut::<EventChannel<GameEvent>>().register_reader());
+    }
+}
+
+/// Spawns asteroids in endless mode, with the spawner picked by `SpawnPolicy::spawner`.
+///
+/// The spawner itself lives in the `ActiveSpawner` resource, and only sees a snapshot of the
+/// world. Only active in endless mode.
+#[derive(Default)]
+pub struct SpawnerSystem {
+    asteroids: Vec<(Vector2<f32>, f32)>,
+    ships: Vec<Vector2<f32>>,
+    spawns: Vec<Spawn>,
+}
+
+impl<'s> System<'s> for SpawnerSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Collider>,
+        ReadStorage<'s, Bounded>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, Ship>,
+        Read<'s, SplinterQueue>,
+        Option<Write<'s, ActiveSpawner>>,
+        ReadExpect<'s, Asteroids>,
+        ReadExpect<'s, Comets>,
+        ReadExpect<'s, RandomStreams>,
+        Read<'s, Time>,
+        Read<'s, LazyUpdate>,
+        Read<'s, GameConfig>,
+        Write<'s, EventChannel<GameEvent>>,
+        Read<'s, SessionSettings>,
+        ReadStorage<'s, Hull>,
+    );
+
+    fn run(&mut self, system: Self::SystemData) {
+        let (
+            entities,
+            colliders,
+            bounded,
+            locals,
+            ships,
+            splinter_queue,
+            spawner,
+            asteroid_resource,
+            comet_resource,
+            streams,
+            time,
+            lazy,
+            config,
+            mut game_events,
+            session,
+            hulls,
+        ) = system;
+        let rand = streams.get(Stream::Spawner);
+
+        if !session.endless {
+            return;
+        }
+
+        let mut spawner = match spawner {
+            Some(spawner) => spawner,
+            None => return,
+        };
+
+        self.asteroids.clear();
+        self.ships.clear();
+
+        for (collider, bounded, local) in (&colliders, &bounded, &locals).join() {
+            if collider.is_asteroid() {
+                let t = local.translation();
+                self.asteroids.push((Vector2::new(t.x, t.y), bounded.shape.radius()));
+            }
+        }
+
+        for (_, local) in (&ships, &locals).join() {
+            let t = local.translation();
+            self.ships.push(Vector2::new(t.x, t.y));
+        }
+
+        let snapshot = Snapshot {
+            delta: time.delta_seconds(),
+            asteroids: &self.asteroids,
+            ships: &self.ships,
+            cleared: asteroids_cleared(&splinter_queue, &colliders),
+        };
+
+        spawner.0.spawn(&snapshot, &rand, &config, &mut self.spawns);
+
+        for spawn in self.spawns.drain(..) {
+            match spawn {
+                Spawn::Comet(position) => {
+                    let mut local = Transform::default();
+                    *local.translation_mut() = position;
+
+                    spawn_comet(
+                        &entities,
+                        &lazy,
+                        &rand,
+                        &comet_resource,
+                        &mut game_events,
+                        local,
+                    );
+                }
+                Spawn::Asteroid(roll) => {
+                    if roll.boss {
+                        clear_hulls(&entities, &hulls);
+                    }
+
+                    spawn_rolled_asteroid(
+                        &entities,
+                        &lazy,
+                        &rand,
+                        &asteroid_resource,
+                        &mut game_events,
+                        roll,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Spawns waves of asteroids, according to `GameConfig::waves`.
+///
+/// The next wave arrives once every asteroid of the current one has been destroyed, with more and
+/// faster asteroids than the last. Not active in endless mode.
+pub struct WaveSystem;
+
+impl WaveSystem {
+    /// Roll an asteroid of the given wave.
+    pub fn roll(rand: &RandomGen, config: &GameConfig, wave: u32) -> AsteroidRoll {
+        let position = Vector3::new(rand.next_f32() * ARENA_WIDTH, ARENA_HEIGHT, 0.0);
+        roll_asteroid(rand, config, position, config.waves.velocity(wave))
+    }
+
+    /// Roll the boss of the given wave, a single huge asteroid.
+    pub fn roll_boss(rand: &RandomGen, config: &GameConfig, wave: u32) -> AsteroidRoll {
+        let mut roll = Self::roll(rand, config, wave);
+        // NB: the boss is larger than the asteroid rolled, so it has to be contained again.
+        let radius = Asteroids::radius(Asteroids::BOSS_SCALE);
+        roll.scale = Asteroids::BOSS_SCALE;
+        roll.position = config.edges.contain(roll.position, radius);
+        // NB: bosses are never unstable or magnetized.
+        roll.fuse = None;
+        roll.magnetized = false;
+        roll.boss = true;
+        roll
+    }
+}
+
+impl<'s> System<'s> for WaveSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteExpect<'s, Wave>,
+        ReadStorage<'s, Collider>,
+        WriteStorage<'s, UiText>,
+        WriteStorage<'s, Tween>,
+        Read<'s, SplinterQueue>,
+        ReadExpect<'s, Asteroids>,
+        ReadExpect<'s, RandomStreams>,
+        Read<'s, Time>,
+        Read<'s, LazyUpdate>,
+        Read<'s, GameConfig>,
+        Write<'s, EventChannel<GameEvent>>,
+        Read<'s, SessionSettings>,
+        ReadStorage<'s, Hull>,
+        Option<Read<'s, WaveScript>>,
+        Option<Write<'s, Stingers>>,
+    );
+
+    fn run(&mut self, system: Self::SystemData) {
+        let (
+            entities,
+            mut wave,
+            colliders,
+            mut texts,
+            mut tweens,
+            splinter_queue,
+            asteroid_resource,
+            streams,
+            time,
+            lazy,
+            config,
+            mut game_events,
+            session,
+            hulls,
+            script,
+            mut stingers,
+        ) = system;
+        let rand = streams.get(Stream::Waves);
+
+        if session.endless {
+            return;
+        }
+
+        // NB: waves start once the script of the level has run.
+        if script.map(|s| !s.is_finished()).unwrap_or(false) {
+            return;
+        }
+
+        let policy = config.waves;
+
+        let time_to_spawn = match wave.time_to_spawn {
+            Some(time_to_spawn) => time_to_spawn - time.delta_seconds(),
+            None => {
+                if asteroids_cleared(&splinter_queue, &colliders) {
+                    wave.time_to_spawn = Some(policy.delay);
+                    // NB: nothing has been cleared before the first wave.
+                    wave.draft_pending = wave.number > 0;
+
+                    if let Some(stingers) = stingers.as_mut().filter(|_| wave.number > 0) {
+                        stingers.request(Stinger::WaveClear);
+                    }
+                }
+
+                return;
+            }
+        };
+
+        if time_to_spawn > 0f32 {
+            wave.time_to_spawn = Some(time_to_spawn);
+            return;
+        }
+
+        wave.time_to_spawn = None;
+        wave.number += 1;
+
+        for _ in 0..policy.count(wave.number) {
+            let roll = Self::roll(&rand, &config, wave.number);
+
+            spawn_rolled_asteroid(
+                &entities,
+                &lazy,
+                &rand,
+                &asteroid_resource,
+                &mut game_events,
+                roll,
+            );
+        }
+
+        if policy.is_boss(wave.number) {
+            clear_hulls(&entities, &hulls);
+
+            let roll = Self::roll_boss(&rand, &config, wave.number);
+
+            spawn_rolled_asteroid(
+                &entities,
+                &lazy,
+                &rand,
+                &asteroid_resource,
+                &mut game_events,
+                roll,
+            );
+        }
+
+        if let Some(text) = texts.get_mut(wave.banner) {
+            text.text = format!("Wave {}", wave.number);
+        }
+
+        let fade = Tween::new(Wave::BANNER_TIME, Easing::QuadIn)
+            .with(TweenTarget::TextColor([1.0, 1.0, 1.0, 1.0], [1.0, 1.0, 1.0, 0.0]));
+
+        if let Err(e) = tweens.insert(wave.banner, fade) {
+            error!("failed to fade wave banner: {}", e);
+        }
+
+        game_events.single_write(GameEvent::WaveStarted { wave: wave.number });
+    }
+}
+
+/// Runs the scripted timeline of the level, if it has one.
+pub struct ScriptRunnerSystem;
+
+impl<'s> System<'s> for ScriptRunnerSystem {
+    type SystemData = (
+        Entities<'s>,
+        Option<Write<'s, WaveScript>>,
+        ReadStorage<'s, Collider>,
+        Read<'s, SplinterQueue>,
+        ReadExpect<'s, Asteroids>,
+        ReadExpect<'s, RandomStreams>,
+        Read<'s, Time>,
+        Read<'s, LazyUpdate>,
+        Read<'s, GameConfig>,
+        Write<'s, EventChannel<GameEvent>>,
+        ReadStorage<'s, Hull>,
+    );
+
+    fn run(&mut self, system: Self::SystemData) {
+        let (
+            entities,
+            script,
+            colliders,
+            splinter_queue,
+            asteroid_resource,
+            streams,
+            time,
+            lazy,
+            config,
+            mut game_events,
+            hulls,
+        ) = system;
+        let rand = streams.get(Stream::Script);
+
+        let mut script = match script {
+            Some(script) => script,
+            None => return,
+        };
+
+        if script.is_finished() {
+            return;
+        }
+
+        script.elapsed += time.delta_seconds();
+
+        let mut cleared = asteroids_cleared(&splinter_queue, &colliders);
+
+        while let Some(line) = script.next_line(cleared) {
+            for _ in 0..line.count {
+                let roll = roll_from_edge(&rand, &config, line.size, line.edge);
+
+                if roll.boss {
+                    clear_hulls(&entities, &hulls);
+                }
+
+                spawn_rolled_asteroid(
+                    &entities,
+                    &lazy,
+                    &rand,
+                    &asteroid_resource,
+                    &mut game_events,
+                    roll,
+                );
+            }
+
+            // NB: asteroids spawned this frame aren't joinable yet.
+            cleared = cleared && line.count == 0;
+        }
+    }
+}
+
+/// An asteroid about to be spawned by one of the spawners.
+#[derive(Debug, Clone, Copy)]
+pub struct AsteroidRoll {
+    pub position: Vector3<f32>,
+    pub scale: f32,
+    pub velocity: Vector2<f32>,
+    pub rotation: f32,
+    /// Fuse of the asteroid if it's unstable, see `UnstablePolicy`.
+    pub fuse: Option<f32>,
+    /// If the asteroid is magnetized, see `MagnetizedPolicy`.
+    pub magnetized: bool,
+    /// If the asteroid is a boss, which leaves its hull behind when destroyed.
+    pub boss: bool,
+}
+
+/// Roll the size and motion of an asteroid spawned at the given position on the top edge.
+///
+/// The velocity along each axis is random, up to `max_velocity`.
+pub fn roll_asteroid(
+    rand: &RandomGen,
+    config: &GameConfig,
+    position: Vector3<f32>,
+    max_velocity: f32,
+) -> AsteroidRoll {
+    let scale = 1.0f32 + rand.next_f32();
+
+    // with walls, rocks have to be spawned fully inside of the arena.
+    let position = config.edges.contain(position, Asteroids::radius(scale));
+
+    let r = || (rand.next_f32() - 0.5) * 2.0 * max_velocity;
+    let velocity = Vector2::new(r(), r());
+
+    // asteroids are spawned on the top edge.
+    let rotation = config.spin.spin(Vector2::new(0.0, -1.0), velocity, rand);
+    let fuse = config.unstable.and_then(|u| u.roll(rand));
+    let magnetized = fuse.is_none() && config.magnetized.map_or(false, |m| m.roll(rand));
+
+    AsteroidRoll {
+        position,
+        scale,
+        velocity,
+        rotation,
+        fuse,
+        magnetized,
+        boss: false,
+    }
+}
+
+/// Clear the hull left behind by the last boss out of the way of the next one.
+fn clear_hulls(entities: &Entities, hulls: &ReadStorage<Hull>) {
+    for (e, _) in (&**entities, hulls).join() {
+        if let Err(err) = entities.delete(e) {
+            error!("failed to delete entity: {:?}: {}", e, err);
+        }
+    }
+}
+
+/// Spawn an asteroid rolled by one of the spawners.
+fn spawn_rolled_asteroid(
+    entities: &Entities,
+    lazy: &Read<LazyUpdate>,
+    rand: &RandomGen,
+    asteroid_resource: &ReadExpect<Asteroids>,
+    game_events: &mut EventChannel<GameEvent>,
+    roll: AsteroidRoll,
+) {
+    let mut local = Transform::default();
+    *local.translation_mut() = roll.position;
+
+    let e = spawn_asteroid(
+        entities,
+        lazy,
+        asteroid_resource.new_sprite_render(rand),
+        asteroid_resource,
+        game_events,
+        local,
+        roll.scale,
+        roll.velocity,
+        roll.rotation,
+        false,
+    );
+
+    if let Some(fuse) = roll.fuse {
+        lazy.insert(e, Unstable::new(fuse, roll.scale));
+        lazy.insert(e, Rgba::from(Unstable::TINT));
+    }
+
+    if roll.magnetized {
+        lazy.insert(e, Magnetized::default());
+        lazy.insert(e, Rgba::from(Magnetized::TINT));
+    }
+
+    if roll.boss {
+        lazy.insert(e, Boss);
+    }
+}
+
+fn spawn_asteroid(
+    entities: &Entities,
+    lazy: &Read<LazyUpdate>,
+    sprite_render: SpriteRender,
+    asteroid_resource: &ReadExpect<Asteroids>,
+    game_events: &mut EventChannel<GameEvent>,
+    mut local: Transform,
+    scale: f32,
+    velocity: Vector2<f32>,
+    rotation: f32,
+    defer_adding_bounds: bool,
+) -> Entity {
+    *local.scale_mut() = Vector3::new(scale, scale, 1.0f32);
+
+    let mut physical = Physical::new();
+    physical.velocity = velocity;
+    physical.rotation = rotation;
+
+    let e = entities.create();
+
+    let t = local.translation();
+
+    game_events.single_write(GameEvent::AsteroidSpawned {
+        entity: e,
+        position: Vector2::new(t.x, t.y),
+        velocity,
+        rotation,
+        lateral_acceleration: 0f32,
+        scale,
+        comet: false,
+    });
+
+    lazy.insert(e, local);
+    lazy.insert(e, physical);
+    lazy.insert(e, ConstrainedObject);
+    lazy.insert(e, sprite_render);
+    lazy.insert(e, asteroid_resource.new_bounded(scale));
+    lazy.insert(e, Health::new(Asteroids::hit_points(Asteroids::radius(scale))));
+
+    let collider = if defer_adding_bounds {
+        Collider::Deferred(DeferredCollider::Asteroid)
+    } else {
+        Collider::Asteroid
+    };
+
+    lazy.insert(e, collider);
+    e
+}
+
+/// Test if every asteroid has been destroyed, including those which can't collide yet and those
+/// about to splinter.
+fn asteroids_cleared(splinter_queue: &SplinterQueue, colliders: &ReadStorage<Collider>) -> bool {
+    // NB: asteroids about to splinter haven't been spawned yet.
+    splinter_queue.queue.is_empty() && !colliders.join().any(|c| c.is_asteroid())
+}
+
+/// Spawn a comet, which travels in a curved path through the arena.
+fn spawn_comet(
+    entities: &Entities,
+    lazy: &Read<LazyUpdate>,
+    rand: &RandomGen,
+    comet_resource: &ReadExpect<Comets>,
+    game_events: &mut EventChannel<GameEvent>,
+    local: Transform,
+) {
+    use std::f32::consts;
+
+    let angle = rand.next_f32() * consts::PI * 2.0;
+
+    let mut physical = Physical::new();
+    physical.velocity = Vector2::new(angle.cos(), angle.sin()) * Comets::VELOCITY;
+    physical.lateral_acceleration =
+        (rand.next_f32() - 0.5) * 2.0 * Comets::MAX_LATERAL_ACCELERATION;
+
+    let e = entities.create();
+
+    let t = local.translation();
+
+    game_events.single_write(GameEvent::AsteroidSpawned {
+        entity: e,
+        position: Vector2::new(t.x, t.y),
+        velocity: physical.velocity,
+        rotation: physical.rotation,
+        lateral_acceleration: physical.lateral_acceleration,
+        scale: 1f32,
+        comet: true,
+    });
+
+    lazy.insert(e, local);
+    lazy.insert(e, physical);
+    lazy.insert(e, ConstrainedObject);
+    lazy.insert(e, comet_resource.new_sprite_render());
+    lazy.insert(e, comet_resource.new_bounded());
+    lazy.insert(e, Comet::default());
+    lazy.insert(e, Collider::Asteroid);
+}
+
+/// Pulls everything which moves towards planets.
+pub struct GravitySystem;
+
+impl<'s> System<'s> for GravitySystem {
+    type SystemData = (
+        ReadStorage<'s, Planet>,
+        ReadStorage<'s, Bounded>,
+        ReadStorage<'s, Transform>,
+        WriteStorage<'s, Physical>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (planets, bounded, locals, mut physicals, time): Self::SystemData) {
+        let time_delta = time.delta_seconds();
+
+        for (planet, planet_bounded, planet_local) in (&planets, &bounded, &locals).join() {
+            let center = planet_local.translation();
+            // NB: limit the pull to what it is at the surface, so that it doesn't explode.
+            let min_distance = planet_bounded.shape.radius().max(1.0);
+
+            for (physical, local) in (&mut physicals, &locals).join() {
+                let delta = wrapped_delta(local.translation(), center);
+                let distance = delta.norm().max(min_distance);
+                let acceleration = planet.gravity / (distance * distance);
+                physical.velocity += delta / distance * acceleration * time_delta;
+            }
+        }
+    }
+}
+
+/// Applies physics to `Physical` entities.
+///
+/// The system applies acceleration, velocity and rotation to the objects in the system.
+pub struct PhysicsSystem;
+
+impl<'s> System<'s> for PhysicsSystem {
+    type SystemData = (
+        WriteStorage<'s, Physical>,
+        WriteStorage<'s, Transform>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (mut physicals, mut locals, time): Self::SystemData) {
+        let time_delta = time.delta_seconds();
+
+        for (physical, local) in (&mut physicals, &mut locals).join() {
+            // Apply lateral acceleration, which turns the velocity while preserving speed.
+            if physical.lateral_acceleration != 0f32 {
+                let speed = physical.velocity.magnitude();
+
+                if speed > 0f32 {
+                    let normal = Vector2::new(-physical.velocity.y, physical.velocity.x) / speed;
+                    physical.velocity += normal * physical.lateral_acceleration * time_delta;
+                    physical.velocity = physical.velocity.normalize() * speed;
+                }
+            }
+
+            physical.velocity += physical.acceleration * time_delta;
+
+            // Apply existing velocity and rotational velocity.
+            let movement = physical.velocity * time_delta;
+
+            local.move_global(Vector3::new(movement.x, movement.y, 0f32));
+            local.roll_local(physical.rotation * time_delta);
+        }
+    }
+}
+
+/// Handle very simple collisions through ncollide2d's broad-phase DBVT implementation.
 ///
 /// It _should_ be good enough since we are using very simple primitive (and zero margins) to
 /// detect collisions.
 ///
-/// I'm a bit concerned about re-creating the phase for every frame, but we don't have a ton of
-/// objects so it should be fine.
-pub struct CollisionSystem;
+/// The broad phase is kept between frames. Proxies are created for entities as they become
+/// collidable and removed once they no longer are, so only their bounding boxes are updated each
+/// frame.
+pub struct CollisionSystem {
+    broad_phase: DBVTBroadPhase<f32, AABB<f32>, Entity>,
+    /// Proxy of each collidable entity, and the last frame it was seen.
+    proxies: HashMap<Entity, (ProxyHandle, u64)>,
+    /// Number of the current frame.
+    frame: u64,
+    /// Pairs of entities whose bounding boxes overlap, as reported by the broad phase.
+    ///
+    /// NB: ordered, so that collisions are handled in the same order every time.
+    overlapping: BTreeSet<(Entity, Entity)>,
+    /// Reused buffer of proxies to remove.
+    removed: Vec<ProxyHandle>,
+    /// Reused buffer of deferred colliders, and the collider they turn into once resolved.
+    deferred: HashMap<Entity, Collider>,
+}
+
+impl CollisionSystem {
+    pub fn new() -> Self {
+        Self {
+            broad_phase: DBVTBroadPhase::new(0f32),
+            proxies: HashMap::new(),
+            frame: 0,
+            overlapping: BTreeSet::new(),
+            removed: Vec::new(),
+            deferred: HashMap::new(),
+        }
+    }
+
+    /// A pair of entities, in the order they are stored in `overlapping`.
+    fn pair(a: Entity, b: Entity) -> (Entity, Entity) {
+        if a < b {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    }
+}
+
+impl<'s> System<'s> for CollisionSystem {
+    type SystemData = (
+        ReadStorage<'s, Bounded>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, Collider>,
+        Read<'s, DevTools>,
+        WriteStorage<'s, UiText>,
+        WriteExpect<'s, Score>,
+        Read<'s, LazyUpdate>,
+        Write<'s, SplinterQueue>,
+        ReadExpect<'s, RandomStreams>,
+        Write<'s, SfxQueue>,
+        Entities<'s>,
+        WriteStorage<'s, Ship>,
+        ReadStorage<'s, PowerUp>,
+        ReadExpect<'s, PowerUps>,
+        ReadExpect<'s, ScreenDimensions>,
+        Read<'s, GameConfig>,
+        Write<'s, EventChannel<GameEvent>>,
+        (
+            ReadStorage<'s, Comet>,
+            ReadStorage<'s, Magnetized>,
+            ReadStorage<'s, Boss>,
+        ),
+        ReadStorage<'s, Owner>,
+        Read<'s, SessionSettings>,
+        ReadStorage<'s, CollisionGrace>,
+        ReadStorage<'s, SpriteRender>,
+        ReadStorage<'s, Invulnerable>,
+        Write<'s, Overlaps>,
+        WriteStorage<'s, Physical>,
+        WriteStorage<'s, Health>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        use std::fmt::Write;
+
+        let (
+            bounding_volumes,
+            locals,
+            colliders,
+            dev_tools,
+            mut text,
+            mut score,
+            lazy,
+            mut splinters,
+            streams,
+            mut sfx,
+            entities,
+            mut ships,
+            power_ups,
+            power_up_resource,
+            screen,
+            config,
+            mut game_events,
+            (comets, magnets, bosses),
+            owners,
+            session,
+            graces,
+            sprites,
+            invulnerable,
+            mut overlaps,
+            mut physicals,
+            mut healths,
+        ) = data;
+        let rand = streams.get(Stream::Collisions);
+
+        let broad_phase = &mut self.broad_phase;
+        let overlapping = &mut self.overlapping;
+        let deferred = &mut self.deferred;
+        let frame = self.frame;
+
+        self.frame += 1;
+        deferred.clear();
+
+        for (e, local, collider, bounding_volume) in
+            (&entities, &locals, &colliders, &bounding_volumes).join()
+        {
+            let bounding_box = bounding_volume.bounding_box(local);
+
+            match self.proxies.get_mut(&e) {
+                Some((proxy, seen)) => {
+                    broad_phase.deferred_set_bounding_volume(*proxy, bounding_box);
+                    *seen = frame;
+                }
+                None => {
+                    let proxy = broad_phase.create_proxy(bounding_box, e);
+                    self.proxies.insert(e, (proxy, frame));
+                }
+            }
+
+            if let Collider::Deferred(next) = *collider {
+                deferred.insert(e, next.to_collider());
+            }
+        }
+
+        // remove proxies of entities which were deleted, or are no longer collidable.
+        let removed = &mut self.removed;
+
+        self.proxies.retain(|_, (proxy, seen)| {
+            if *seen == frame {
+                return true;
+            }
+
+            removed.push(*proxy);
+            false
+        });
+
+        if !removed.is_empty() {
+            broad_phase.remove(&removed[..], &mut |a, b| {
+                overlapping.remove(&Self::pair(*a, *b));
+            });
+
+            removed.clear();
+        }
+
+        broad_phase.update(&mut |a, b| a != b, &mut |a, b, started| {
+            if started {
+                overlapping.insert(Self::pair(*a, *b));
+            } else {
+                overlapping.remove(&Self::pair(*a, *b));
+            }
+        });
+
+        let exact = |e: Entity| Some((locals.get(e)?, bounding_volumes.get(e)?));
+
+        let ship_positions = (&ships, &locals)
+            .join()
+            .map(|(_, l)| Vector2::new(l.translation().x, l.translation().y))
+            .collect::<SmallVec<[Vector2<f32>; 4]>>();
+
+        // priority of sounds made by the given entity, the louder and nearer the ships the higher.
+        let priority = |e: Entity, radius: f32| {
+            let distance = locals.get(e).and_then(|l| {
+                let p = Vector2::new(l.translation().x, l.translation().y);
+                ship_positions
+                    .iter()
+                    .map(|s| (s - p).norm())
+                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            });
+
+            SfxQueue::priority(radius, distance)
+        };
+
+        let mut spawned = 0;
+
+        // NB: pairs are handled for as long as they overlap, since shapes might only intersect
+        // some time after their bounding boxes started overlapping.
+        for &(x, y) in overlapping.iter() {
+            use self::Collider::*;
+
+            let (a, b) = match (colliders.get(x), colliders.get(y)) {
+                (Some(a), Some(b)) => ((a.clone(), x), (b.clone(), y)),
+                _ => continue,
+            };
+
+            let (a, b) = (&a, &b);
+
+            // the broad phase only compares bounding boxes, so check that the shapes themselves
+            // intersect.
+            if let (Some((la, ba)), Some((lb, bb))) = (exact(a.1), exact(b.1)) {
+                if !ba.intersects(la, bb, lb) {
+                    continue;
+                }
+            }
+
+            // asteroids and hull chunks in their grace period pass harmlessly through ships, and
+            // everything passes through invulnerable or shielded ships.
+            let graced = match (a, b) {
+                ((Ship, _), (Asteroid, r))
+                | ((Asteroid, r), (Ship, _))
+                | ((Ship, _), (Collider::Hull, r))
+                | ((Collider::Hull, r), (Ship, _))
+                    if graces.contains(*r) =>
+                {
+                    true
+                }
+                ((Ship, s), (other, _)) | ((other, _), (Ship, s)) if other.is_lethal() => {
+                    invulnerable.contains(*s)
+                        || ships.get(*s).map(|s| s.is_shielded()).unwrap_or(false)
+                }
+                _ => false,
+            };
+
+            if graced {
+                continue;
+            }
+
+            // with accretion, small asteroids pass through each other and are merged by the
+            // `AccretionSystem` if they keep overlapping.
+            if config.accretion.is_some() {
+                if let ((Asteroid, x), (Asteroid, y)) = (a, b) {
+                    let small = |e: &Entity| {
+                        !comets.contains(*e)
+                            && bounding_volumes
+                                .get(*e)
+                                .map(|b| Asteroids::size(b.shape.radius()) == AsteroidSize::Small)
+                                .unwrap_or(false)
+                    };
+
+                    if small(x) && small(y) {
+                        overlaps.pairs.push((*x, *y));
+                        continue;
+                    }
+                }
+            }
+
+            // play the appropriate sound, pitched by the size of the asteroid.
+            match (a, b) {
+                ((Asteroid, r), _) | (_, (Asteroid, r)) => {
+                    let radius = bounding_volumes
+                        .get(*r)
+                        .map(|b| b.shape.radius())
+                        .unwrap_or(Asteroids::MIN_RADIUS);
+
+                    sfx.request(
+                        SfxCategory::Collision,
+                        priority(*r, radius),
+                        Asteroids::pitch(radius),
+                    );
+                }
+                _ => {}
+            }
+
+            match (a, b) {
+                // pebbles deflect the bullets of the player, and are swallowed by planets.
+                ((Collider::Pebble, _), (Bullet, b)) | ((Bullet, b), (Collider::Pebble, _)) => {
+                    if let Some(physical) = physicals.get_mut(*b) {
+                        let angle = (rand.next_f32() - 0.5) * 2.0 * Pebbles::DEFLECTION;
+                        let rotation = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), angle);
+                        let v = physical.velocity;
+                        let v = rotation * Vector3::new(v.x, v.y, 0f32);
+                        physical.velocity = Vector2::new(v.x, v.y);
+                    }
+                }
+                ((Collider::Pebble, _), (c, _)) | ((c, _), (Collider::Pebble, _))
+                    if *c != Collider::Planet =>
+                {
+                    continue;
+                }
+                // hull chunks only block bullets and ships, everything else drifts over them.
+                ((Collider::Hull, _), (c, _)) | ((c, _), (Collider::Hull, _))
+                    if *c == Asteroid || *c == Collider::Ufo || *c == Collider::Hull =>
+                {
+                    continue;
+                }
+                // check if deferred things are still intersecting.
+                ((Deferred(_), a), (Deferred(_), b)) => {
+                    deferred.remove(&a);
+                    deferred.remove(&b);
+                    continue;
+                }
+                ((Deferred(_), a), _) | (_, (Deferred(_), a)) => {
+                    deferred.remove(&a);
+                    continue;
+                }
+                // pick up the power-up.
+                ((Ship, s), (Collider::PowerUp, p)) | ((Collider::PowerUp, p), (Ship, s)) => {
+                    if let (Some(ship), Some(power_up)) = (ships.get_mut(*s), power_ups.get(*p)) {
+                        power_up.apply(ship);
+                        game_events.single_write(GameEvent::PowerUpPickedUp(power_up.kind));
+                    }
+
+                    if let Err(e) = entities.delete(*p) {
+                        error!("failed to delete entity: {:?}: {}", p, e);
+                    }
+
+                    continue;
+                }
+                // power-ups don't interact with anything else.
+                ((Collider::PowerUp, _), _) | (_, (Collider::PowerUp, _)) => continue,
+                // ships pass through each other and the bullets of players, so that there is no
+                // friendly fire in co-op.
+                ((Ship, _), (Ship, _)) | ((Ship, _), (Bullet, _)) | ((Bullet, _), (Ship, _)) => {
+                    continue;
+                }
+                // UFOs don't shoot each other, and their bullets pass by the bullets of the player.
+                ((Collider::UfoBullet, _), (Collider::Ufo, _))
+                | ((Collider::Ufo, _), (Collider::UfoBullet, _))
+                | ((Collider::UfoBullet, _), (Bullet, _))
+                | ((Bullet, _), (Collider::UfoBullet, _)) => continue,
+                // ships dock at the station instead of colliding with it, see the
+                // `StationSystem`, and only asteroids damage it.
+                ((Collider::Station, _), (c, _)) | ((c, _), (Collider::Station, _))
+                    if *c != Asteroid =>
+                {
+                    continue;
+                }
+                // ships are destroyed as usual when falling into a planet.
+                ((Collider::Planet, _), (Ship, _)) | ((Ship, _), (Collider::Planet, _)) => {}
+                // everything else is swallowed without a trace.
+                ((Collider::Planet, _), (c, e)) | ((c, e), (Collider::Planet, _)) => {
+                    // a bullet which falls into a planet missed.
+                    if *c == Bullet {
+                        score.accuracy.record(false);
+                    }
+
+                    if let Err(err) = entities.delete(*e) {
+                        error!("failed to delete entity: {:?}: {}", e, err);
+                    }
+
+                    continue;
+                }
+                _ => {}
+            }
+
+            // a ramming ship survives colliding with asteroids which aren't large.
+            let rammed = match (a, b) {
+                ((Ship, s), (Asteroid, r)) | ((Asteroid, r), (Ship, s)) => {
+                    let ramming = ships.get(*s).map(|s| s.is_ramming()).unwrap_or(false);
+
+                    let size = bounding_volumes
+                        .get(*r)
+                        .map(|b| Asteroids::size(b.shape.radius()));
+
+                    ramming && size != Some(AsteroidSize::Large)
+                }
+                _ => false,
+            };
+
+            // things with hit points left take a hit instead of being destroyed, and ships stay
+            // invulnerable for a moment after surviving one.
+            let hit = match (a, b) {
+                ((Ship, s), (other, _)) | ((other, _), (Ship, s))
+                    if other.is_lethal()
+                        && *other != Collider::Planet
+                        && !rammed
+                        && !dev_tools.immortal =>
+                {
+                    Some(*s)
+                }
+                ((Bullet, _), (Asteroid, r)) | ((Asteroid, r), (Bullet, _)) => Some(*r),
+                ((Bullet, _), (Collider::Hull, h)) | ((Collider::Hull, h), (Bullet, _)) => Some(*h),
+                ((Collider::Station, s), (Asteroid, _))
+                | ((Asteroid, _), (Collider::Station, s)) => Some(*s),
+                _ => None,
+            };
+
+            let survivor = hit.filter(|e| healths.get_mut(*e).map(|h| h.hit()).unwrap_or(false));
+
+            if let Some(s) = survivor.filter(|e| ships.contains(*e)) {
+                lazy.insert(s, Invulnerable::new(Invulnerable::HIT));
+            }
+
+            let destroyed = match (a, b) {
+                ((Bullet, _), (Asteroid, r)) | ((Asteroid, r), (Bullet, _))
+                    if survivor != Some(*r) =>
+                {
+                    Some(*r)
+                }
+                ((Ship, _), (Asteroid, r)) | ((Asteroid, r), (Ship, _)) if rammed => Some(*r),
+                ((Bullet, _), (Collider::Ufo, u)) | ((Collider::Ufo, u), (Bullet, _)) => Some(*u),
+                ((Bullet, _), (Collider::Pebble, p)) | ((Collider::Pebble, p), (Bullet, _)) => {
+                    Some(*p)
+                }
+                ((Bullet, _), (Collider::Hull, h)) | ((Collider::Hull, h), (Bullet, _))
+                    if survivor != Some(*h) =>
+                {
+                    Some(*h)
+                }
+                _ => None,
+            };
+
+            let ufo = match (a, b) {
+                ((Collider::Ufo, _), _) | (_, (Collider::Ufo, _)) => true,
+                _ => false,
+            };
+
+            let pebble = match (a, b) {
+                ((Collider::Pebble, _), _) | (_, (Collider::Pebble, _)) => true,
+                _ => false,
+            };
+
+            let hull = match (a, b) {
+                ((Collider::Hull, _), _) | (_, (Collider::Hull, _)) => true,
+                _ => false,
+            };
+
+            // bullets which destroy something count towards the accuracy multiplier, unless they
+            // pass through a pebble and keep going.
+            let shot = match (a, b) {
+                ((Bullet, _), _) | (_, (Bullet, _)) => true,
+                _ => false,
+            };
+
+            if shot && !pebble && (destroyed.is_some() || survivor.is_some()) {
+                score.accuracy.record(true);
+            }
+
+            // we get a point!
+            if let Some(target) = destroyed {
+                let radius = bounding_volumes
+                    .get(target)
+                    .map(|b| b.shape.radius())
+                    .unwrap_or(Asteroids::MIN_RADIUS);
+
+                sfx.request(
+                    SfxCategory::Explosion,
+                    priority(target, radius),
+                    Asteroids::pitch(radius),
+                );
+
+                let size = Asteroids::size(radius);
+
+                // comets, UFOs, magnetized asteroids and hull chunks are worth bonus points, and
+                // pebbles are hardly worth anything.
+                let (points, color) = match comets.get(target) {
+                    _ if ufo => (Ufos::POINTS, Ufos::COLOR),
+                    _ if pebble => (Pebbles::POINTS, Pebbles::COLOR),
+                    _ if hull => (Hulls::POINTS, Hulls::TINT),
+                    Some(_) => (Comets::POINTS, Comets::COLOR),
+                    None if magnets.contains(target) => {
+                        let multiplier = config.magnetized.map_or(1, |m| m.points_multiplier);
+                        (size.points() * multiplier, Magnetized::TINT)
+                    }
+                    None => (size.points(), size.color()),
+                };
+
+                // accurate shooting multiplies the points of asteroids.
+                let points = if ufo {
+                    points
+                } else {
+                    score.accuracy.apply(points)
+                };
+
+                // in co-op, popups are tinted by the player who destroyed the target.
+                let owner = match (a, b) {
+                    ((Bullet, e), _) | (_, (Bullet, e)) => owners.get(*e),
+                    ((Ship, e), _) | (_, (Ship, e)) => owners.get(*e),
+                    _ => None,
+                };
+
+                let color = owner
+                    .and_then(|o| session.player_color(o.player))
+                    .unwrap_or(color);
+
+                let previous = score.points;
+                score.points += points;
+
+                if let Some(p) = owner.and_then(|o| score.player_points.get_mut(o.player)) {
+                    *p += points;
+                }
+
+                if ufo {
+                    game_events.single_write(GameEvent::UfoDestroyed { points });
+                } else if hull {
+                    game_events.single_write(GameEvent::HullDestroyed { points });
+                } else if !pebble {
+                    score.asteroids += 1;
+                    game_events.single_write(GameEvent::AsteroidDestroyed { size, points });
+                }
+
+                let milestone = score.points / Score::MILESTONE * Score::MILESTONE;
+
+                if milestone > previous {
+                    game_events.single_write(GameEvent::ScoreMilestone(milestone));
+                }
+
+                if let Some(text) = text.get_mut(score.score_text) {
+                    // NB: reuse the allocated text.
+                    text.text.clear();
+                    let _ = write!(text.text, "{}", score.points);
+                }
+
+                if session.coop {
+                    if let Some(text) = text.get_mut(score.players_text) {
+                        text.text = score.players_text();
+                    }
+                }
+
+                if let Some(local) = locals.get(target) {
+                    spawn_score_popup(local, points, color, &score, &screen, &entities, &lazy);
+                }
+
+                if !pebble && rand.next_f32() < PowerUps::DROP_CHANCE {
+                    if let Some(local) = locals.get(target) {
+                        drop_power_up(
+                            local.clone(),
+                            PowerUps::random_kind(&rand),
+                            &entities,
+                            &lazy,
+                            &power_up_resource,
+                        );
+                    }
+                }
+            }
+
+            // asteroids destroyed in this collision, which splinter together.
+            let mut asteroids = SmallVec::<[(Transform, f32, Option<usize>); 2]>::new();
+
+            for c in &[a, b] {
+                // things which blow up leave debris behind.
+                match *c {
+                    (_, e) if survivor == Some(*e) => {}
+                    (Collider::Ship, _) if rammed || dev_tools.immortal => {}
+                    (Collider::Hull, e) if destroyed != Some(*e) => {}
+                    (Collider::Ship, e)
+                    | (Collider::Asteroid, e)
+                    | (Collider::Hull, e)
+                    | (Collider::Ufo, e)
+                    | (Collider::Station, e) => {
+                        let local = locals.get(*e);
+                        let volume = bounding_volumes.get(*e);
+
+                        if let (Some(local), Some(volume)) = (local, volume) {
+                            let t = local.translation();
+
+                            game_events.single_write(GameEvent::Explosion {
+                                position: Vector2::new(t.x, t.y),
+                                radius: volume.shape.radius(),
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+
+                let e = match *c {
+                    (_, e) if survivor == Some(*e) => continue,
+                    // bullets keep going through pebbles.
+                    (Collider::Bullet, _) if pebble => continue,
+                    (Collider::Ship, _) if rammed => continue,
+                    (Collider::Ship, _) if dev_tools.immortal => continue,
+                    (Collider::Planet, _) => continue,
+                    // hull chunks only break apart once chipped away by bullets.
+                    (Collider::Hull, e) if destroyed != Some(*e) => continue,
+                    (Collider::Ship, e) => {
+                        // we lost a ship, the `RespawnSystem` takes it from here.
+                        let player = owners.get(*e).map(|o| o.player).unwrap_or(0);
+                        game_events.single_write(GameEvent::ShipDestroyed { player });
+
+                        if let Some(local) = locals.get(*e) {
+                            let shockwave = entities.create();
+                            lazy.insert(shockwave, local.clone());
+                            lazy.insert(shockwave, Shockwave::new());
+                        }
+
+                        e
+                    }
+                    // an asteroid collided with something
+                    // this is interesting, since there is a chance that asteroids splinter!
+                    // comets burn up without splintering.
+                    (Collider::Asteroid, e) if comets.get(*e).is_some() => e,
+                    (Collider::Asteroid, e) => {
+                        asteroids.extend(asteroid_data(*e, &bounding_volumes, &locals, &sprites));
+                        spawn_pebbles(*e, &locals, &physicals, &sprites, &rand, &entities, &lazy);
+
+                        if bosses.contains(*e) {
+                            spawn_hull(
+                                *e,
+                                &locals,
+                                &bounding_volumes,
+                                &sprites,
+                                &rand,
+                                &entities,
+                                &lazy,
+                                &mut game_events,
+                            );
+                        }
+
+                        e
+                    }
+                    (_, e) => e,
+                };
+
+                if let Err(e) = entities.delete(*e) {
+                    error!("failed to delete entity: {:?}: {}", a, e);
+                }
+            }
+
+            // splinters are pieces of the largest asteroid, and keep its rotation.
+            let largest = asteroids
+                .iter()
+                .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+
+            if let Some((parent, _, sprite)) = largest {
+                let volume = asteroids.iter().map(|(_, v, _)| v).sum::<f32>();
+
+                let mut local = Transform::default();
+                *local.translation_mut() = wrapped_center_of_mass(
+                    asteroids.iter().map(|(t, v, _)| (*t.translation(), *v)),
+                );
+                *local.rotation_mut() = *parent.rotation();
+
+                spawned += queue_asteroid_cluster(
+                    local,
+                    volume,
+                    *sprite,
+                    &config.splinters,
+                    &mut splinters,
+                    &rand,
+                );
+            }
+        }
+
+        // undefer deferred
+        for (e, next) in deferred.drain() {
+            lazy.insert(e, next);
+        }
+
+        if spawned > 0 {
+            trace!("Asteroids Queued: {}", spawned);
+        }
+
+        fn spawn_score_popup(
+            local: &Transform,
+            points: u32,
+            color: [f32; 4],
+            score: &Score,
+            screen: &ScreenDimensions,
+            entities: &Entities,
+            lazy: &Read<LazyUpdate>,
+        ) {
+            // translate from arena to screen coordinates.
+            let t = local.translation();
+            let x = t.x / ARENA_WIDTH * screen.width();
+            let y = t.y / ARENA_HEIGHT * screen.height();
+
+            let transform = UiTransform::new(
+                "ScorePopup".to_string(),
+                Anchor::BottomLeft,
+                x,
+                y,
+                1.,
+                100.,
+                30.,
+                0,
+            );
+
+            let text = UiText::new(score.font.clone(), points.to_string(), color, 20.);
+
+            let e = entities.create();
+
+            let mut faded = color;
+            faded[3] = 0f32;
+
+            lazy.insert(e, transform);
+            lazy.insert(e, text);
+            lazy.insert(e, ScorePopup::new());
+            lazy.insert(
+                e,
+                Tween::new(ScorePopup::TIME_TO_LIVE, Easing::Linear)
+                    .with(TweenTarget::TextColor(color, faded)),
+            );
+        }
+
+        /// Leave a few pebbles behind where the given asteroid was destroyed.
+        fn spawn_pebbles(
+            parent: Entity,
+            locals: &ReadStorage<Transform>,
+            physicals: &WriteStorage<Physical>,
+            sprites: &ReadStorage<SpriteRender>,
+            rand: &RandomGen,
+            entities: &Entities,
+            lazy: &Read<LazyUpdate>,
+        ) {
+            use std::f32::consts;
+
+            let (local, sprite) = match (locals.get(parent), sprites.get(parent)) {
+                (Some(local), Some(sprite)) => (local, sprite),
+                _ => return,
+            };
+
+            let inherited = physicals
+                .get(parent)
+                .map(|p| p.velocity * Pebbles::INHERITED_VELOCITY)
+                .unwrap_or_else(|| Vector2::new(0f32, 0f32));
+
+            let spread = Pebbles::MAX_COUNT - Pebbles::MIN_COUNT + 1;
+            let count = Pebbles::MIN_COUNT + rand.next_usize() % spread;
+
+            for _ in 0..count {
+                let angle = rand.next_f32() * consts::PI * 2.0;
+
+                let mut physical = Physical::new();
+                physical.velocity =
+                    inherited + Vector2::new(angle.cos(), angle.sin()) * Pebbles::VELOCITY;
+                physical.rotation = (rand.next_f32() - 0.5) * 2.0;
+
+                let mut local = local.clone();
+                *local.scale_mut() = Vector3::new(Pebbles::SCALE, Pebbles::SCALE, 1.0f32);
+
+                let e = entities.create();
+
+                lazy.insert(e, local);
+                lazy.insert(e, physical);
+                lazy.insert(e, ConstrainedObject);
+                lazy.insert(e, sprite.clone());
+                lazy.insert(e, Pebbles::new_bounded());
+                lazy.insert(e, Pebble::new(Pebbles::TIME_TO_LIVE));
+                lazy.insert(e, Collider::Pebble);
+            }
+        }
+
+        fn spawn_hull(
+            parent: Entity,
+            locals: &ReadStorage<Transform>,
+            bounding_volumes: &ReadStorage<Bounded>,
+            sprites: &ReadStorage<SpriteRender>,
+            rand: &RandomGen,
+            entities: &Entities,
+            lazy: &Read<LazyUpdate>,
+            game_events: &mut EventChannel<GameEvent>,
+        ) {
+            use std::f32::consts;
+
+            let (local, volume, sprite) = match (
+                locals.get(parent),
+                bounding_volumes.get(parent),
+                sprites.get(parent),
+            ) {
+                (Some(local), Some(volume), Some(sprite)) => (local, volume, sprite),
+                _ => return,
+            };
+
+            // NB: chunks are spread out evenly, so that they don't start out overlapping.
+            let offset = volume.shape.radius() - Asteroids::radius(Hulls::SCALE);
+            let start = rand.next_f32() * consts::PI * 2.0;
+
+            for i in 0..Hulls::COUNT {
+                let angle = start + i as f32 * consts::PI * 2.0 / Hulls::COUNT as f32;
+                let direction = Vector2::new(angle.cos(), angle.sin());
+
+                let mut physical = Physical::new();
+                physical.velocity = direction * Hulls::VELOCITY;
+                physical.rotation = (rand.next_f32() - 0.5) * 2.0 * Hulls::ROTATION;
+
+                let mut local = local.clone();
+                *local.translation_mut() += Vector3::new(direction.x, direction.y, 0f32) * offset;
+                *local.scale_mut() = Vector3::new(Hulls::SCALE, Hulls::SCALE, 1.0f32);
+
+                let e = entities.create();
+
+                let t = local.translation();
+
+                game_events.single_write(GameEvent::HullSpawned {
+                    entity: e,
+                    position: Vector2::new(t.x, t.y),
+                    velocity: physical.velocity,
+                    rotation: physical.rotation,
+                });
+
+                lazy.insert(e, local);
+                lazy.insert(e, physical);
+                lazy.insert(e, ConstrainedObject);
+                lazy.insert(e, sprite.clone());
+                lazy.insert(e, Rgba::from(Hulls::TINT));
+                lazy.insert(e, Hulls::new_bounded());
+                lazy.insert(e, Health::new(Hulls::HIT_POINTS));
+                lazy.insert(e, Hull);
+                lazy.insert(e, Collider::Deferred(DeferredCollider::Hull));
+
+                // chunks can't kill a player caught next to the boss right away.
+                lazy.insert(
+                    e,
+                    CollisionGrace {
+                        time_to_live: CollisionGrace::HULL,
+                    },
+                );
+            }
+        }
+
+        fn drop_power_up(
+            local: Transform,
+            kind: PowerUpKind,
+            entities: &Entities,
+            lazy: &Read<LazyUpdate>,
+            power_up_resource: &ReadExpect<PowerUps>,
+        ) {
+            let e = entities.create();
+
+            lazy.insert(e, local);
+            lazy.insert(e, power_up_resource.new_sprite_render(kind));
+            lazy.insert(e, power_up_resource.new_bounded());
+            lazy.insert(e, PowerUp::new(kind));
+            lazy.insert(e, Collider::PowerUp);
+        }
+    }
+}
+
+/// Transform, area and sprite of an asteroid, used to splinter it.
+fn asteroid_data(
+    e: Entity,
+    bounding_volumes: &ReadStorage<Bounded>,
+    locals: &ReadStorage<Transform>,
+    sprites: &ReadStorage<SpriteRender>,
+) -> Option<(Transform, f32, Option<usize>)> {
+    use std::f32::consts;
+
+    let volume = match bounding_volumes.get(e) {
+        Some(volume) => volume,
+        None => return None,
+    };
+
+    let local = match locals.get(e) {
+        Some(local) => local.clone(),
+        None => return None,
+    };
+
+    let sprite = sprites.get(e).map(|s| s.sprite_number);
+    Some((local.clone(), volume.shape.radius().powf(2.0) * consts::PI, sprite))
+}
+
+/// Queue up a cluster of splinters with the given total area, returning how many were queued.
+fn queue_asteroid_cluster(
+    local: Transform,
+    area: f32,
+    sprite: Option<usize>,
+    policy: &SplinterPolicy,
+    splinters: &mut SplinterQueue,
+    rand: &RandomGen,
+) -> usize {
+    let count = policy.count(area);
+    let (_, _, parent_angle) = local.rotation().euler_angles();
+
+    for angle in policy.angles(count, rand) {
+        let rotation = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), angle);
+        let velocity = rotation * Vector3::x() * policy.max_velocity * rand.next_f32();
+        let velocity = Vector2::new(velocity.x, velocity.y);
+
+        splinters.queue.push_back(Splinter {
+            local: local.clone(),
+            velocity,
+            piece: sprite.map(|sprite| (sprite, angle - parent_angle)),
+        });
+    }
+
+    return count;
+}
+
+/// Spawns splinters queued up by the `CollisionSystem`.
+///
+/// At most `SplinterQueue::BUDGET` splinters are spawned each frame, the rest are left in the queue
+/// for subsequent frames.
+pub struct SplinterSystem;
+
+impl<'s> System<'s> for SplinterSystem {
+    type SystemData = (
+        Entities<'s>,
+        Write<'s, SplinterQueue>,
+        ReadExpect<'s, Asteroids>,
+        ReadExpect<'s, RandomStreams>,
+        Read<'s, LazyUpdate>,
+        Write<'s, EventChannel<GameEvent>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut splinters, asteroid_resource, streams, lazy, mut game_events) = data;
+        let rand = streams.get(Stream::Splinters);
+
+        let count = usize::min(splinters.queue.len(), SplinterQueue::BUDGET);
+
+        for Splinter {
+            local,
+            velocity,
+            piece,
+        } in splinters.queue.drain(..count)
+        {
+            let sprite_render = match piece {
+                Some((parent, angle)) => {
+                    asteroid_resource.piece_sprite_render(parent, angle, &rand)
+                }
+                None => asteroid_resource.new_sprite_render(&rand),
+            };
+
+            let e = spawn_asteroid(
+                &entities,
+                &lazy,
+                sprite_render,
+                &asteroid_resource,
+                &mut game_events,
+                local,
+                1.0,
+                velocity,
+                0.10 * rand.next_f32(),
+                true,
+            );
+
+            // splinters can't kill the player right away.
+            lazy.insert(
+                e,
+                CollisionGrace {
+                    time_to_live: CollisionGrace::SPLINTER,
+                },
+            );
+        }
+
+        if !splinters.queue.is_empty() {
+            trace!("Splinters Pending: {}", splinters.queue.len());
+        }
+    }
+}
+
+/// Pulses unstable asteroids, and splits them once their fuse runs out.
+///
+/// Asteroids which split on their own aren't worth any points, but leave splinters behind like
+/// when shot.
+pub struct UnstableSystem;
+
+impl<'s> System<'s> for UnstableSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Unstable>,
+        WriteStorage<'s, Tween>,
+        ReadStorage<'s, Bounded>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, SpriteRender>,
+        Write<'s, SplinterQueue>,
+        ReadExpect<'s, RandomStreams>,
+        Read<'s, Time>,
+        Read<'s, GameConfig>,
+        Write<'s, EventChannel<GameEvent>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut unstables,
+            mut tweens,
+            bounding_volumes,
+            locals,
+            sprites,
+            mut splinters,
+            streams,
+            time,
+            config,
+            mut game_events,
+        ) = data;
+        let rand = streams.get(Stream::Unstable);
+
+        let time_delta = time.delta_seconds();
+
+        for (e, unstable) in (&*entities, &mut unstables).join() {
+            unstable.fuse -= time_delta;
+            unstable.time_to_pulse -= time_delta;
+
+            if unstable.fuse <= 0f32 {
+                if let Some((local, area, sprite)) =
+                    asteroid_data(e, &bounding_volumes, &locals, &sprites)
+                {
+                    let t = local.translation();
+
+                    game_events.single_write(GameEvent::Explosion {
+                        position: Vector2::new(t.x, t.y),
+                        radius: Asteroids::radius(unstable.scale),
+                    });
+
+                    queue_asteroid_cluster(
+                        local,
+                        area,
+                        sprite,
+                        &config.splinters,
+                        &mut splinters,
+                        &rand,
+                    );
+                }
+
+                if let Err(e) = entities.delete(e) {
+                    error!("failed to delete unstable asteroid: {}", e);
+                }
+
+                continue;
+            }
+
+            if unstable.time_to_pulse <= 0f32 {
+                let interval = unstable.pulse_interval();
+                unstable.time_to_pulse = interval;
+
+                let big = unstable.scale * (1.0 + Unstable::PULSE_SCALE);
+                let pulse = Tween::new(interval * 0.8, Easing::QuadOut)
+                    .with(TweenTarget::Scale(big, unstable.scale));
+
+                if let Err(e) = tweens.insert(e, pulse) {
+                    error!("failed to pulse unstable asteroid: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Steers magnetized asteroids toward the nearest ship in range, and has them crackle.
+///
+/// Asteroids turn no faster than `MagnetizedPolicy::turn_rate` and keep their speed, so that they
+/// can be outmaneuvered.
+pub struct MagnetSystem;
+
+impl MagnetSystem {
+    /// Speed at which crackles leave the asteroid (units / s).
+    const CRACKLE_VELOCITY: f32 = 15.0;
+    /// How long crackles live (s).
+    const CRACKLE_LIFETIME: f32 = 0.2;
+}
+
+impl<'s> System<'s> for MagnetSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Magnetized>,
+        WriteStorage<'s, Physical>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, Bounded>,
+        ReadStorage<'s, Ship>,
+        ReadExpect<'s, Particles>,
+        ReadExpect<'s, RandomStreams>,
+        Read<'s, Time>,
+        Read<'s, LazyUpdate>,
+        Read<'s, GameConfig>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        use std::f32::consts;
+
+        let (
+            entities,
+            mut magnets,
+            mut physicals,
+            locals,
+            bounding_volumes,
+            ships,
+            particles,
+            streams,
+            time,
+            lazy,
+            config,
+        ) = data;
+        let rand = streams.get(Stream::Magnets);
+
+        let policy = match config.magnetized {
+            Some(policy) => policy,
+            None => return,
+        };
+
+        let time_delta = time.delta_seconds();
+        let max_turn = policy.turn_rate.to_radians() * time_delta;
+
+        let targets = (&ships, &locals)
+            .join()
+            .map(|(_, local)| *local.translation())
+            .collect::<SmallVec<[Vector3<f32>; 2]>>();
+
+        let magnetized = (&mut magnets, &mut physicals, &locals, &bounding_volumes).join();
+
+        for (magnet, physical, local, bounding_volume) in magnetized {
+            let position = local.translation();
+
+            let nearest = targets
+                .iter()
+                .map(|t| wrapped_delta(position, t))
+                .filter(|d| d.norm() <= policy.range)
+                .min_by(|a, b| {
+                    a.norm()
+                        .partial_cmp(&b.norm())
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
+
+            let delta = match nearest {
+                Some(delta) => delta,
+                None => continue,
+            };
+
+            let velocity = physical.velocity;
+
+            if velocity.norm() > 0f32 {
+                let heading = velocity.y.atan2(velocity.x);
+                let mut turn = delta.y.atan2(delta.x) - heading;
+
+                // turn the short way around.
+                if turn > consts::PI {
+                    turn -= 2.0 * consts::PI;
+                } else if turn < -consts::PI {
+                    turn += 2.0 * consts::PI;
+                }
+
+                let (sin, cos) = turn.max(-max_turn).min(max_turn).sin_cos();
+
+                physical.velocity = Vector2::new(
+                    velocity.x * cos - velocity.y * sin,
+                    velocity.x * sin + velocity.y * cos,
+                );
+            }
+
+            magnet.time_to_crackle -= time_delta;
+
+            if magnet.time_to_crackle > 0f32 {
+                continue;
+            }
+
+            magnet.time_to_crackle = Magnetized::CRACKLE_INTERVAL * (0.5 + rand.next_f32());
+
+            // crackles jump off the surface of the asteroid.
+            let angle = rand.next_f32() * 2.0 * consts::PI;
+            let direction = Vector2::new(angle.cos(), angle.sin());
+            let radius = bounding_volume.shape.radius();
+
+            let mut crackle = Transform::default();
+            *crackle.translation_mut() =
+                position + Vector3::new(direction.x, direction.y, 0.0) * radius;
+
+            spawn_particle(
+                &entities,
+                &lazy,
+                particles.new_sprite_render(),
+                crackle,
+                physical.velocity + direction * Self::CRACKLE_VELOCITY,
+                Self::CRACKLE_LIFETIME,
+                0.5,
+            );
+        }
+    }
+}
+
+/// Merges small asteroids which have overlapped for long enough, when accretion is enabled.
+///
+/// The merged asteroid has the combined area of both, and their velocity averaged by area.
+#[derive(Default)]
+pub struct AccretionSystem {
+    /// How long each pair of asteroids has been overlapping (s).
+    overlapping: HashMap<(Entity, Entity), f32>,
+    /// Reused buffer for the overlaps of the current frame.
+    current: HashMap<(Entity, Entity), f32>,
+}
+
+impl<'s> System<'s> for AccretionSystem {
+    type SystemData = (
+        Entities<'s>,
+        Write<'s, Overlaps>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, Physical>,
+        ReadStorage<'s, Bounded>,
+        ReadExpect<'s, Asteroids>,
+        ReadExpect<'s, RandomStreams>,
+        Read<'s, GameConfig>,
+        Read<'s, Time>,
+        Read<'s, LazyUpdate>,
+        Write<'s, EventChannel<GameEvent>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut overlaps,
+            locals,
+            physicals,
+            bounding_volumes,
+            asteroid_resource,
+            streams,
+            config,
+            time,
+            lazy,
+            mut game_events,
+        ) = data;
+        let rand = streams.get(Stream::Accretion);
+
+        let policy = match config.accretion {
+            Some(policy) => policy,
+            None => {
+                overlaps.pairs.clear();
+                self.overlapping.clear();
+                return;
+            }
+        };
+
+        let time_delta = time.delta_seconds();
+
+        // pairs which no longer overlap start over.
+        self.current.clear();
+
+        for (a, b) in overlaps.pairs.drain(..) {
+            let pair = if a.id() < b.id() { (a, b) } else { (b, a) };
+            let overlapping = self.overlapping.get(&pair).cloned().unwrap_or(0f32);
+            self.current.insert(pair, overlapping + time_delta);
+        }
+
+        std::mem::swap(&mut self.overlapping, &mut self.current);
+
+        // asteroids can only be merged once per frame.
+        let mut merged = SmallVec::<[Entity; 8]>::new();
+
+        for (&(a, b), overlapping) in &self.overlapping {
+            if *overlapping < policy.time || merged.contains(&a) || merged.contains(&b) {
+                continue;
+            }
+
+            let asteroid = |e: Entity| {
+                let local = locals.get(e)?;
+                let physical = physicals.get(e)?;
+                let radius = bounding_volumes.get(e)?.shape.radius();
+                Some((local, physical, radius))
+            };
+
+            let ((local_a, physical_a, radius_a), (local_b, physical_b, radius_b)) =
+                match (asteroid(a), asteroid(b)) {
+                    (Some(a), Some(b)) => (a, b),
+                    _ => continue,
+                };
+
+            let area_a = radius_a.powi(2);
+            let area_b = radius_b.powi(2);
+            let area = area_a + area_b;
+
+            let mut local = Transform::default();
+            *local.translation_mut() = wrapped_center_of_mass(
+                [(*local_a.translation(), area_a), (*local_b.translation(), area_b)]
+                    .iter()
+                    .cloned(),
+            );
+
+            let velocity = (physical_a.velocity * area_a + physical_b.velocity * area_b) / area;
+            let rotation = (physical_a.rotation * area_a + physical_b.rotation * area_b) / area;
+
+            for e in &[a, b] {
+                if let Err(err) = entities.delete(*e) {
+                    error!("failed to delete entity: {:?}: {}", e, err);
+                }
+            }
+
+            merged.push(a);
+            merged.push(b);
+
+            // NB: the merged asteroid might overlap others, so it's added with a deferred collider.
+            spawn_asteroid(
+                &entities,
+                &lazy,
+                asteroid_resource.new_sprite_render(&rand),
+                &asteroid_resource,
+                &mut game_events,
+                local,
+                area.sqrt() / Asteroids::MIN_RADIUS,
+                velocity,
+                rotation,
+                true,
+            );
+        }
+    }
+}
+
+/// Counts down collision grace periods, removing them once expired.
+pub struct CollisionGraceSystem;
+
+impl<'s> System<'s> for CollisionGraceSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, CollisionGrace>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (entities, mut graces, time): Self::SystemData) {
+        let time_delta = time.delta_seconds();
+
+        let mut expired = SmallVec::<[Entity; 8]>::new();
+
+        for (e, grace) in (&*entities, &mut graces).join() {
+            grace.time_to_live -= time_delta;
+
+            if grace.time_to_live <= 0f32 {
+                expired.push(e);
+            }
+        }
+
+        for e in expired {
+            graces.remove(e);
+        }
+    }
+}
+
+/// Takes lives when the ship is destroyed, and respawns it after a delay while lives remain.
+///
+/// The game is over once the last life is lost.
+#[derive(Default)]
+pub struct RespawnSystem {
+    reader: Option<ReaderId<GameEvent>>,
+    /// Number of lives of each player currently shown.
+    shown: Option<Vec<u32>>,
+}
+
+impl<'s> System<'s> for RespawnSystem {
+    type SystemData = (
+        Entities<'s>,
+        Write<'s, EventChannel<GameEvent>>,
+        WriteExpect<'s, Lives>,
+        WriteExpect<'s, Game>,
+        WriteStorage<'s, UiText>,
+        ReadStorage<'s, Parent>,
+        ReadStorage<'s, RamVisual>,
+        ReadStorage<'s, ShieldVisual>,
+        ReadStorage<'s, VelocityArrow>,
+        ReadExpect<'s, Ships>,
+        ReadExpect<'s, PowerUps>,
+        Read<'s, GameConfig>,
+        Read<'s, SpawnPoint>,
+        Read<'s, LazyUpdate>,
+        Read<'s, Time>,
+        Read<'s, SessionSettings>,
+        Read<'s, Mutations>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut game_events,
+            mut lives,
+            mut game,
+            mut texts,
+            parents,
+            ram_visuals,
+            shield_visuals,
+            velocity_arrows,
+            ships,
+            power_ups,
+            config,
+            spawn,
+            lazy,
+            time,
+            session,
+            mutations,
+        ) = data;
+
+        let reader = self.reader.as_mut().expect("reader not set up");
+
+        let mut destroyed = SmallVec::<[usize; 2]>::new();
+
+        for e in game_events.read(reader) {
+            if let GameEvent::ShipDestroyed { player } = *e {
+                // NB: the ship might collide with more than one asteroid in the same frame.
+                if !destroyed.contains(&player) {
+                    destroyed.push(player);
+                }
+            }
+        }
+
+        for player in destroyed {
+            let respawning = lives.respawn_timers.get(player).cloned().unwrap_or(None);
+
+            let remaining = match lives.remaining.get_mut(player) {
+                Some(remaining) if *remaining > 0 && respawning.is_none() => remaining,
+                _ => continue,
+            };
+
+            *remaining -= 1;
+            let remaining = *remaining;
+
+            if remaining > 0 {
+                lives.respawn_timers[player] = Some(Lives::RESPAWN_DELAY);
+                game_events.single_write(GameEvent::LifeLost { remaining });
+                continue;
+            }
+
+            // in co-op, the game goes on for as long as any player has lives left.
+            if lives.all_lost() && !game.modifiers.player_is_dead {
+                game.modifiers.player_is_dead = true;
+                game_events.single_write(GameEvent::PlayerDied);
+            }
+        }
+
+        let time_delta = time.delta_seconds();
+        let players = lives.respawn_timers.len();
+
+        for player in 0..players {
+            let timer = match lives.respawn_timers[player] {
+                Some(timer) => timer - time_delta,
+                None => continue,
+            };
+
+            if timer > 0f32 {
+                lives.respawn_timers[player] = Some(timer);
+                continue;
+            }
+
+            lives.respawn_timers[player] = None;
+
+            // the entities attached to the destroyed ship are left behind.
+            for (e, parent) in (&*entities, &parents).join() {
+                let attached = ram_visuals.contains(e)
+                    || shield_visuals.contains(e)
+                    || velocity_arrows.contains(e);
+
+                if attached && !entities.is_alive(parent.entity) {
+                    if let Err(e) = entities.delete(e) {
+                        error!("failed to delete entity: {}", e);
+                    }
+                }
+            }
+
+            let look = session.look(player);
+            let spawn = spawn.for_player(player, players);
+
+            let ship = spawn_ship(
+                &entities, &lazy, &ships, &power_ups, &config, &mutations, spawn, player, look,
+            );
+            lazy.insert(ship, Invulnerable::new(Invulnerable::RESPAWN));
+        }
+
+        if self.shown.as_ref() != Some(&lives.remaining) {
+            self.shown = Some(lives.remaining.clone());
+
+            if let Some(text) = texts.get_mut(lives.text) {
+                text.text = lives.as_text();
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut Resources) {
+        Self::SystemData::setup(res);
+        self.reader = Some(res.fetch_mut::<EventChannel<GameEvent>>().register_reader());
+    }
+}
+
+/// Regenerates the energy of ships, and shows it in the `EnergyBar`.
+#[derive(Default)]
+pub struct EnergySystem {
+    /// The text currently shown, to avoid updating it every frame.
+    shown: Option<String>,
+}
+
+impl<'s> System<'s> for EnergySystem {
+    type SystemData = (
+        WriteStorage<'s, Energy>,
+        ReadStorage<'s, Owner>,
+        Option<Read<'s, EnergyBar>>,
+        WriteStorage<'s, UiText>,
+        Read<'s, SessionSettings>,
+        Read<'s, GameConfig>,
+        Read<'s, Time>,
+    );
+
+    fn run(
+        &mut self,
+        (mut energies, owners, energy_bar, mut texts, session, config, time): Self::SystemData,
+    ) {
+        let time_delta = time.delta_seconds();
+
+        let mut fractions = SmallVec::<[Option<f32>; 2]>::new();
+        fractions.resize(session.players(), None);
+
+        for (energy, owner) in (&mut energies, owners.maybe()).join() {
+            energy.regenerate(time_delta, &config.energy);
+
+            let player = owner.map(|o| o.player).unwrap_or(0);
+
+            if let Some(fraction) = fractions.get_mut(player) {
+                *fraction = Some(energy.fraction());
+            }
+        }
+
+        let energy_bar = match energy_bar {
+            Some(energy_bar) => energy_bar,
+            None => return,
+        };
+
+        let text = EnergyBar::as_text(&fractions);
+
+        if self.shown.as_ref() == Some(&text) {
+            return;
+        }
+
+        if let Some(ui_text) = texts.get_mut(energy_bar.text) {
+            ui_text.text = text.clone();
+        }
+
+        self.shown = Some(text);
+    }
+}
+
+/// Shows the health of ships in the `HealthBar`.
+#[derive(Default)]
+pub struct HealthBarSystem {
+    /// The text currently shown, to avoid updating it every frame.
+    shown: Option<String>,
+}
+
+impl<'s> System<'s> for HealthBarSystem {
+    type SystemData = (
+        ReadStorage<'s, Health>,
+        ReadStorage<'s, Ship>,
+        ReadStorage<'s, Owner>,
+        Option<Read<'s, HealthBar>>,
+        WriteStorage<'s, UiText>,
+        Read<'s, SessionSettings>,
+    );
+
+    fn run(&mut self, (healths, ships, owners, health_bar, mut texts, session): Self::SystemData) {
+        let health_bar = match health_bar {
+            Some(health_bar) => health_bar,
+            None => return,
+        };
+
+        let mut bars = SmallVec::<[Option<Health>; 2]>::new();
+        bars.resize(session.players(), None);
+
+        for (health, _, owner) in (&healths, &ships, owners.maybe()).join() {
+            let player = owner.map(|o| o.player).unwrap_or(0);
+
+            if let Some(bar) = bars.get_mut(player) {
+                *bar = Some(*health);
+            }
+        }
+
+        let text = HealthBar::as_text(&bars);
+
+        if self.shown.as_ref() == Some(&text) {
+            return;
+        }
+
+        if let Some(ui_text) = texts.get_mut(health_bar.text) {
+            ui_text.text = text.clone();
+        }
+
+        self.shown = Some(text);
+    }
+}
+
+/// Runs the station of objective mode, see `StationPolicy`.
+///
+/// * Ships which stay slow in the docking zone for long enough dock, which repairs them, refills
+///   their energy, and banks the points of their player.
+/// * Players lose the points they haven't banked along with their ship.
+/// * The run is over once the station is destroyed.
+#[derive(Default)]
+pub struct StationSystem {
+    reader: Option<ReaderId<GameEvent>>,
+    /// The text currently shown, to avoid updating it every frame.
+    shown: Option<String>,
+}
+
+impl<'s> System<'s> for StationSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Station>,
+        WriteStorage<'s, Health>,
+        WriteStorage<'s, Energy>,
+        ReadStorage<'s, Ship>,
+        ReadStorage<'s, Owner>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, Physical>,
+        WriteStorage<'s, UiText>,
+        Option<Read<'s, StationBar>>,
+        WriteExpect<'s, Score>,
+        WriteExpect<'s, Game>,
+        Write<'s, EventChannel<GameEvent>>,
+        Read<'s, Time>,
+        Read<'s, GameConfig>,
+        Read<'s, SessionSettings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut stations,
+            mut healths,
+            mut energies,
+            ships,
+            owners,
+            locals,
+            physicals,
+            mut texts,
+            station_bar,
+            mut score,
+            mut game,
+            mut game_events,
+            time,
+            config,
+            session,
+        ) = data;
+
+        let reader = self.reader.as_mut().expect("reader not set up");
+
+        let mut destroyed = SmallVec::<[usize; 2]>::new();
+
+        for e in game_events.read(reader) {
+            if let GameEvent::ShipDestroyed { player } = *e {
+                destroyed.push(player);
+            }
+        }
+
+        let (policy, station_bar) = match (config.station, station_bar) {
+            (Some(policy), Some(station_bar)) => (policy, station_bar),
+            _ => return,
+        };
+
+        let mut score_changed = false;
+
+        // players lose whatever they scored since they last docked.
+        for player in destroyed {
+            let banked = score.banked.get(player).cloned().unwrap_or(0);
+
+            let unbanked = match score.player_points.get_mut(player) {
+                Some(points) => std::mem::replace(points, banked).saturating_sub(banked),
+                None => continue,
+            };
+
+            score.points = score.points.saturating_sub(unbanked);
+            score_changed = score_changed || unbanked > 0;
+        }
+
+        let station = (&*entities, &mut stations).join().next();
+
+        let (station, health, center) = match station {
+            Some((e, station)) => match (healths.get(e), locals.get(e)) {
+                (Some(health), Some(local)) => (station, *health, *local.translation()),
+                _ => return,
+            },
+            None => {
+                if !game.modifiers.player_is_dead {
+                    game.modifiers.player_is_dead = true;
+                    game_events.single_write(GameEvent::StationDestroyed);
+                    game_events.single_write(GameEvent::PlayerDied);
+                }
+
+                self.show(StationBar::as_text(None, 0f32), &station_bar, &mut texts);
+                return;
+            }
+        };
+
+        let time_delta = time.delta_seconds();
+        station.docking.resize(session.players(), 0f32);
+
+        let mut docked = SmallVec::<[(Entity, usize); 2]>::new();
+
+        for (e, _, local, physical, owner) in
+            (&*entities, &ships, &locals, &physicals, owners.maybe()).join()
+        {
+            let player = owner.map(|o| o.player).unwrap_or(0);
+
+            let timer = match station.docking.get_mut(player) {
+                Some(timer) => timer,
+                None => continue,
+            };
+
+            let docking = wrapped_distance(local.translation(), &center) <= policy.dock_radius
+                && physical.velocity.norm() <= policy.dock_speed;
+
+            if !docking {
+                *timer = 0f32;
+                continue;
+            }
+
+            let before = *timer;
+            *timer += time_delta;
+
+            // NB: ships only dock once each time they enter the docking zone.
+            if before < policy.dock_time && *timer >= policy.dock_time {
+                docked.push((e, player));
+            }
+        }
+
+        for (e, player) in docked {
+            if let Some(health) = healths.get_mut(e) {
+                health.current = health.max;
+            }
+
+            if let Some(energy) = energies.get_mut(e) {
+                energy.current = energy.capacity;
+            }
+
+            let points = score.player_points.get(player).cloned().unwrap_or(0);
+
+            let banked = match score.banked.get_mut(player) {
+                Some(banked) => points.saturating_sub(std::mem::replace(banked, points)),
+                None => continue,
+            };
+
+            game_events.single_write(GameEvent::Docked { player, banked });
+        }
+
+        if score_changed {
+            if let Some(text) = texts.get_mut(score.score_text) {
+                text.text = score.points.to_string();
+            }
+
+            if session.coop {
+                if let Some(text) = texts.get_mut(score.players_text) {
+                    text.text = score.players_text();
+                }
+            }
+        }
+
+        let docking = station.docking.iter().cloned().fold(0f32, f32::max);
+        let text = StationBar::as_text(Some(health), docking / policy.dock_time.max(0.1));
+        self.show(text, &station_bar, &mut texts);
+    }
+
+    fn setup(&mut self, res: &mut Resources) {
+        Self::SystemData::setup(res);
+        self.reader = Some(res.fetch_mut::<EventChannel<GameEvent>>().register_reader());
+    }
+}
+
+impl StationSystem {
+    /// Show the given text in the station bar, unless it's already shown.
+    fn show(&mut self, text: String, station_bar: &StationBar, texts: &mut WriteStorage<UiText>) {
+        if self.shown.as_ref() == Some(&text) {
+            return;
+        }
+
+        if let Some(ui_text) = texts.get_mut(station_bar.text) {
+            ui_text.text = text.clone();
+        }
+
+        self.shown = Some(text);
+    }
+}
+
+/// Blinks invulnerable ships, and makes them vulnerable again when their time is up.
+pub struct InvulnerabilitySystem;
+
+impl<'s> System<'s> for InvulnerabilitySystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Invulnerable>,
+        WriteStorage<'s, Hidden>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (entities, mut invulnerable, mut hidden, time): Self::SystemData) {
+        let time_delta = time.delta_seconds();
+
+        let mut expired = SmallVec::<[Entity; 2]>::new();
+
+        for (e, invulnerable) in (&*entities, &mut invulnerable).join() {
+            invulnerable.time_to_live -= time_delta;
+
+            if invulnerable.time_to_live <= 0f32 {
+                expired.push(e);
+                continue;
+            }
+
+            invulnerable.blink_timer -= time_delta;
+
+            if invulnerable.blink_timer > 0f32 {
+                continue;
+            }
+
+            invulnerable.blink_timer += Invulnerable::BLINK_INTERVAL;
+
+            if hidden.remove(e).is_none() {
+                if let Err(e) = hidden.insert(e, Hidden) {
+                    error!("failed to hide entity: {}", e);
+                }
+            }
+        }
+
+        for e in expired {
+            invulnerable.remove(e);
+            hidden.remove(e);
+        }
+    }
+}
+
+/// Keeps the decals of ships shown and hidden along with their ship, which blinks while it's
+/// invulnerable.
+///
+/// Decals left behind by destroyed ships are deleted.
+pub struct ShipDecalSystem;
+
+impl<'s> System<'s> for ShipDecalSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, ShipDecal>,
+        ReadStorage<'s, Parent>,
+        WriteStorage<'s, Hidden>,
+    );
+
+    fn run(&mut self, (entities, decals, parents, mut hidden): Self::SystemData) {
+        for (e, _, parent) in (&*entities, &decals, &parents).join() {
+            if !entities.is_alive(parent.entity) {
+                if let Err(e) = entities.delete(e) {
+                    error!("failed to delete entity: {}", e);
+                }
+
+                continue;
+            }
+
+            if !hidden.contains(parent.entity) {
+                hidden.remove(e);
+            } else if !hidden.contains(e) {
+                if let Err(e) = hidden.insert(e, Hidden) {
+                    error!("failed to hide entity: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Floats score popups upwards while fading them out.
+pub struct ScorePopupSystem;
+
+impl<'s> System<'s> for ScorePopupSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, ScorePopup>,
+        WriteStorage<'s, UiTransform>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (entities, mut popups, mut transforms, time): Self::SystemData) {
+        let time_delta = time.delta_seconds();
+
+        for (e, popup, transform) in (&*entities, &mut popups, &mut transforms).join() {
+            popup.time_to_live -= time_delta;
+
+            if popup.time_to_live <= 0f32 {
+                if let Err(e) = entities.delete(e) {
+                    error!("failed to destroy entity: {}", e);
+                }
+
+                continue;
+            }
+
+            transform.local_y += ScorePopup::SPEED * time_delta;
+        }
+    }
+}
+
+/// Crumbles pebbles away once they have lingered for long enough.
+pub struct PebbleSystem;
+
+impl<'s> System<'s> for PebbleSystem {
+    type SystemData = (Entities<'s>, WriteStorage<'s, Pebble>, Read<'s, Time>);
+
+    fn run(&mut self, (entities, mut pebbles, time): Self::SystemData) {
+        let time_delta = time.delta_seconds();
+
+        for (e, pebble) in (&*entities, &mut pebbles).join() {
+            pebble.time_to_live -= time_delta;
+
+            if pebble.time_to_live <= 0f32 {
+                if let Err(e) = entities.delete(e) {
+                    error!("failed to destroy entity: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Emits a tail of particles behind comets.
+pub struct CometSystem;
+
+impl CometSystem {
+    /// How long tail particles live (s).
+    const TAIL_LIFETIME: f32 = 0.6;
+    /// Random spread of tail particle velocities (units / s).
+    const TAIL_SPREAD: f32 = 10.0;
+}
+
+impl<'s> System<'s> for CometSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Comet>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, Physical>,
+        ReadExpect<'s, Particles>,
+        ReadExpect<'s, RandomStreams>,
+        Read<'s, LazyUpdate>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut comets, locals, physicals, particles, streams, lazy, time) = data;
+        let rand = streams.get(Stream::Comets);
+
+        let time_delta = time.delta_seconds();
+
+        for (comet, local, physical) in (&mut comets, &locals, &physicals).join() {
+            comet.tail_timer -= time_delta;
+
+            while comet.tail_timer <= 0f32 {
+                comet.tail_timer += Comets::TAIL_INTERVAL;
+
+                // particles drift slowly away from the direction of travel.
+                let r = || (rand.next_f32() - 0.5) * 2.0 * Self::TAIL_SPREAD;
+                let velocity = physical.velocity * -0.2 + Vector2::new(r(), r());
+
+                let mut tail = Transform::default();
+                *tail.translation_mut() = *local.translation();
+
+                spawn_particle(
+                    &entities,
+                    &lazy,
+                    particles.new_sprite_render(),
+                    tail,
+                    velocity,
+                    Self::TAIL_LIFETIME,
+                    1.0,
+                );
+            }
+        }
+    }
+}
+
+/// Sends a UFO across the arena every now and then.
+///
+/// Only one UFO is in the arena at a time.
+pub struct UfoSpawnSystem {
+    pub time_to_spawn: f32,
+}
+
+impl UfoSpawnSystem {
+    pub fn new() -> Self {
+        Self {
+            time_to_spawn: Ufos::AVERAGE_SPAWN_TIME,
+        }
+    }
+}
+
+impl<'s> System<'s> for UfoSpawnSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Ufo>,
+        ReadExpect<'s, Ufos>,
+        ReadExpect<'s, RandomStreams>,
+        Read<'s, Time>,
+        Read<'s, LazyUpdate>,
+        Read<'s, GameConfig>,
+        Write<'s, EventChannel<GameEvent>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, ufos, ufo_resource, streams, time, lazy, config, mut game_events) = data;
+        let rand = streams.get(Stream::UfoSpawn);
+
+        if (&ufos).join().next().is_some() {
+            return;
+        }
+
+        self.time_to_spawn -= time.delta_seconds();
+
+        if self.time_to_spawn > 0f32 {
+            return;
+        }
+
+        self.time_to_spawn = (0.5 + rand.next_f32()) * Ufos::AVERAGE_SPAWN_TIME;
+
+        // UFOs enter from either the left or the right edge.
+        let (x, direction) = if rand.next_f32() < 0.5 {
+            (0f32, 1f32)
+        } else {
+            (ARENA_WIDTH, -1f32)
+        };
+
+        let mut local = Transform::default();
+        *local.translation_mut() = Vector3::new(x, rand.next_f32() * ARENA_HEIGHT, 0f32);
+        *local.translation_mut() = config.edges.contain(*local.translation(), Ufos::RADIUS);
+
+        let mut physical = Physical::new();
+        physical.velocity = Vector2::new(direction * Ufos::VELOCITY, 0f32);
+
+        let ufo = Ufo {
+            fire_timer: Ufos::FIRE_INTERVAL,
+            ..Ufo::new(ARENA_WIDTH / Ufos::VELOCITY)
+        };
+
+        let e = entities.create();
+
+        lazy.insert(e, local);
+        lazy.insert(e, physical);
+        lazy.insert(e, ConstrainedObject);
+        lazy.insert(e, ufo_resource.new_sprite_render());
+        lazy.insert(e, ufo_resource.new_bounded());
+        lazy.insert(e, ufo);
+        lazy.insert(e, Collider::Ufo);
+
+        game_events.single_write(GameEvent::UfoSpawned);
+    }
+}
+
+/// Steers UFOs across the arena, and has them shoot at the nearest ship.
+pub struct UfoAiSystem;
+
+impl UfoAiSystem {
+    /// Pitch of the sound of UFOs firing, which is lower than that of the ship.
+    const PEW_PITCH: f32 = 0.7;
+}
+
+impl<'s> System<'s> for UfoAiSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Ufo>,
+        WriteStorage<'s, Physical>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, Ship>,
+        ReadExpect<'s, Bullets>,
+        ReadExpect<'s, RandomStreams>,
+        ReadExpect<'s, Sounds>,
+        Read<'s, AssetStorage<Source>>,
+        Option<Read<'s, Output>>,
+        Read<'s, AudioMix>,
+        Read<'s, Time>,
+        Read<'s, LazyUpdate>,
+        Read<'s, GameConfig>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut ufos,
+            mut physicals,
+            locals,
+            ships,
+            bullet_resource,
+            streams,
+            sounds,
+            audio_storage,
+            audio,
+            mix,
+            time,
+            lazy,
+            config,
+        ) = data;
+        let rand = streams.get(Stream::UfoAi);
+
+        let time_delta = time.delta_seconds();
+
+        let targets = (&ships, &locals)
+            .join()
+            .map(|(_, local)| *local.translation())
+            .collect::<SmallVec<[Vector3<f32>; 2]>>();
+
+        for (e, ufo, physical, local) in (&*entities, &mut ufos, &mut physicals, &locals).join() {
+            ufo.time_to_live -= time_delta;
+
+            // the UFO has made it across the arena.
+            if ufo.time_to_live <= 0f32 {
+                if let Err(err) = entities.delete(e) {
+                    error!("failed to delete entity: {:?}: {}", e, err);
+                }
+
+                continue;
+            }
+
+            ufo.course_timer -= time_delta;
+
+            if ufo.course_timer <= 0f32 {
+                ufo.course_timer += Ufos::COURSE_INTERVAL;
+                physical.velocity.y = (rand.next_f32() - 0.5) * 2.0 * Ufos::VELOCITY;
+            }
+
+            ufo.fire_timer -= time_delta;
+
+            if ufo.fire_timer > 0f32 {
+                continue;
+            }
+
+            ufo.fire_timer += Ufos::FIRE_INTERVAL;
+
+            let position = local.translation();
+
+            let target = targets.iter().min_by(|a, b| {
+                wrapped_distance(position, a)
+                    .partial_cmp(&wrapped_distance(position, b))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+
+            let target = match target {
+                Some(target) => target,
+                None => continue,
+            };
+
+            let delta = wrapped_delta(position, target);
+            let aim = (rand.next_f32() - 0.5) * 2.0 * Ufos::INACCURACY;
+            let angle = delta.y.atan2(delta.x) + aim;
+
+            // bullets travel along their local y axis.
+            let mut bullet = Transform::default();
+            *bullet.translation_mut() = *position;
+            bullet.roll_local(angle - std::f32::consts::FRAC_PI_2);
+
+            let mut physical = Physical::new();
+            physical.velocity = Vector2::new(angle.cos(), angle.sin()) * Ufos::BULLET_VELOCITY;
+
+            let b = entities.create();
+
+            lazy.insert(b, bullet);
+            lazy.insert(b, physical);
+            lazy.insert(b, ConstrainedObject);
+            lazy.insert(b, bullet_resource.new_sprite_render());
+            lazy.insert(b, Rgba::from(Ufos::COLOR));
+            lazy.insert(b, Bullet::new(config.bullet_time_to_live));
+            lazy.insert(b, bullet_resource.new_bounded());
+            lazy.insert(b, Collider::UfoBullet);
+
+            sounds.pew_sfx.play_pitched(
+                &audio_storage,
+                audio.as_ref().map(|o| &**o),
+                sounds.device.as_ref(),
+                &mix,
+                Self::PEW_PITCH,
+            );
+        }
+    }
+}
+
+/// Spawn a single particle.
+fn spawn_particle(
+    entities: &Entities,
+    lazy: &Read<LazyUpdate>,
+    sprite_render: SpriteRender,
+    mut local: Transform,
+    velocity: Vector2<f32>,
+    lifetime: f32,
+    scale: f32,
+) {
+    *local.scale_mut() = Vector3::new(scale, scale, 1.0f32);
+
+    let e = entities.create();
+
+    lazy.insert(e, local);
+    lazy.insert(e, Particle::new(velocity, lifetime, scale));
+    lazy.insert(e, sprite_render);
+}
+
+/// Moves particles, shrinks them as they fade and removes expired ones.
+pub struct ParticleSystem;
+
+impl<'s> System<'s> for ParticleSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Particle>,
+        WriteStorage<'s, Transform>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (entities, mut particles, mut locals, time): Self::SystemData) {
+        let time_delta = time.delta_seconds();
+
+        for (e, particle, local) in (&*entities, &mut particles, &mut locals).join() {
+            particle.time_to_live -= time_delta;
+
+            if particle.time_to_live <= 0f32 {
+                if let Err(e) = entities.delete(e) {
+                    error!("failed to destroy entity: {}", e);
+                }
+
+                continue;
+            }
+
+            let movement = particle.velocity * time_delta;
+            local.move_global(Vector3::new(movement.x, movement.y, 0f32));
+
+            let scale = particle.scale * particle.time_to_live / particle.lifetime;
+            *local.scale_mut() = Vector3::new(scale, scale, 1.0f32);
+        }
+    }
+}
+
+/// Pushes asteroids away from new shockwaves, and sets up the animation of the expanding ring.
+///
+/// The push falls off linearly with the distance from the center of the shockwave.
+pub struct ShockwaveSystem;
+
+impl<'s> System<'s> for ShockwaveSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Shockwave>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, Collider>,
+        WriteStorage<'s, Physical>,
+        ReadExpect<'s, Shockwaves>,
+        Read<'s, LazyUpdate>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut shockwaves,
+            locals,
+            colliders,
+            mut physicals,
+            shockwave_resource,
+            lazy,
+            time,
+        ) = data;
+
+        let time_delta = time.delta_seconds();
+
+        for (e, shockwave) in (&*entities, &mut shockwaves).join() {
+            let center = match locals.get(e) {
+                Some(local) => *local.translation(),
+                None => continue,
+            };
+
+            if !shockwave.pushed {
+                shockwave.pushed = true;
+
+                for (collider, physical, local) in (&colliders, &mut physicals, &locals).join() {
+                    if *collider != Collider::Asteroid {
+                        continue;
+                    }
+
+                    let delta = wrapped_delta(&center, local.translation());
+                    let distance = delta.norm();
+
+                    if distance >= Shockwave::RADIUS || distance <= 0f32 {
+                        continue;
+                    }
+
+                    let falloff = 1.0 - distance / Shockwave::RADIUS;
+                    physical.velocity += delta / distance * Shockwave::IMPULSE * falloff;
+                }
+
+                let scale = Shockwave::RADIUS / Shockwaves::SPRITE_RADIUS;
+
+                lazy.insert(e, shockwave_resource.new_sprite_render());
+                lazy.insert(
+                    e,
+                    Tween::new(Shockwave::LIFETIME, Easing::QuadOut)
+                        .with(TweenTarget::Scale(0f32, scale))
+                        .with(TweenTarget::Alpha(1f32, 0f32)),
+                );
+            }
+
+            shockwave.time_to_live -= time_delta;
+
+            if shockwave.time_to_live <= 0f32 {
+                if let Err(e) = entities.delete(e) {
+                    error!("failed to destroy entity: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Advances tweens and applies them to the properties they animate.
+#[derive(Default)]
+pub struct TweenSystem {
+    /// Reused buffer of tweens which completed this frame.
+    done: Vec<Entity>,
+}
+
+impl<'s> System<'s> for TweenSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Tween>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, UiText>,
+        WriteStorage<'s, Rgba>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut tweens, mut locals, mut texts, mut tints, time) = data;
+
+        let time_delta = time.delta_seconds();
+
+        for (e, tween) in (&*entities, &mut tweens).join() {
+            tween.elapsed += time_delta;
+            let t = tween.progress();
+
+            for target in &tween.targets {
+                match *target {
+                    TweenTarget::Scale(from, to) => {
+                        if let Some(local) = locals.get_mut(e) {
+                            let scale = lerp(from, to, t);
+                            *local.scale_mut() = Vector3::new(scale, scale, 1.0f32);
+                        }
+                    }
+                    TweenTarget::TextColor(from, to) => {
+                        if let Some(text) = texts.get_mut(e) {
+                            for (c, (from, to)) in text.color.iter_mut().zip(from.iter().zip(&to)) {
+                                *c = lerp(*from, *to, t);
+                            }
+                        }
+                    }
+                    TweenTarget::Alpha(from, to) => {
+                        let alpha = lerp(from, to, t);
+
+                        if let Some(tint) = tints.get_mut(e) {
+                            tint.3 = alpha;
+                            continue;
+                        }
+
+                        if let Err(e) = tints.insert(e, Rgba(1.0, 1.0, 1.0, alpha)) {
+                            error!("failed to tint entity: {}", e);
+                        }
+                    }
+                }
+            }
+
+            if tween.is_done() {
+                self.done.push(e);
+            }
+        }
+
+        for e in self.done.drain(..) {
+            tweens.remove(e);
+        }
+
+        fn lerp(from: f32, to: f32, t: f32) -> f32 {
+            from + (to - from) * t
+        }
+    }
+}
+
+/// Ticks the timers of power-ups.
+///
+/// * Removes power-ups which haven't been picked up in time.
+/// * Counts down active power-ups on ships.
+/// * Shows the battering ram in front of ramming ships.
+pub struct PowerUpSystem;
+
+impl<'s> System<'s> for PowerUpSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, PowerUp>,
+        WriteStorage<'s, Ship>,
+        ReadStorage<'s, RamVisual>,
+        ReadStorage<'s, ShieldVisual>,
+        ReadStorage<'s, Parent>,
+        WriteStorage<'s, Hidden>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, system: Self::SystemData) {
+        let (
+            entities,
+            mut power_ups,
+            mut ships,
+            ram_visuals,
+            shield_visuals,
+            parents,
+            mut hidden,
+            time,
+        ) = system;
+
+        let time_delta = time.delta_seconds();
+
+        for (e, power_up) in (&*entities, &mut power_ups).join() {
+            power_up.time_to_live -= time_delta;
+
+            if power_up.time_to_live <= 0f32 {
+                if let Err(e) = entities.delete(e) {
+                    error!("failed to destroy entity: {}", e);
+                }
+            }
+        }
+
+        for ship in (&mut ships).join() {
+            ship.tick_power_ups(time_delta);
+        }
+
+        for (e, parent, ram_visual) in (&*entities, &parents, ram_visuals.maybe()).join() {
+            if ram_visual.is_none() && !shield_visuals.contains(e) {
+                continue;
+            }
+
+            let shown = match ships.get(parent.entity) {
+                Some(ship) if ram_visual.is_some() => ship.is_ramming(),
+                Some(ship) => ship.is_shielded(),
+                None => {
+                    // parent ship is gone.
+                    if let Err(e) = entities.delete(e) {
+                        error!("failed to destroy entity: {}", e);
+                    }
+
+                    continue;
+                }
+            };
+
+            if shown {
+                hidden.remove(e);
+            } else if !hidden.contains(e) {
+                if let Err(e) = hidden.insert(e, Hidden) {
+                    error!("failed to hide entity: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Pans the camera with `rotate` and `accelerate`, and zooms it with `zoom_in` and `zoom_out`,
+/// while in photo mode or spectating an online game.
+///
+/// The view always stays inside of the arena.
+pub struct PhotoCameraSystem;
+
+impl<'s> System<'s> for PhotoCameraSystem {
+    type SystemData = (
+        Read<'s, InputHandler<String, String>>,
+        Read<'s, Time>,
+        Read<'s, CurrentState>,
+        Option<Read<'s, NetSession>>,
+        Option<Write<'s, PhotoCamera>>,
+        WriteStorage<'s, Camera>,
+    );
+
+    fn run(&mut self, (input, time, state, net, photo, mut cameras): Self::SystemData) {
+        let spectating = net.map(|n| n.is_spectating()).unwrap_or(false);
+
+        if state.0 != StateId::Photo && !spectating {
+            return;
+        }
+
+        let mut photo = match photo {
+            Some(photo) => photo,
+            None => return,
+        };
+
+        // NB: the game might be frozen, so only real time passes.
+        let delta = time.delta_real_seconds();
+        let factor = PhotoCamera::ZOOM_SPEED.powf(delta);
+
+        if input.action_is_down("zoom_in").unwrap_or(false) {
+            photo.zoom *= factor;
+        }
+
+        if input.action_is_down("zoom_out").unwrap_or(false) {
+            photo.zoom /= factor;
+        }
+
+        let axis = |name: &str| input.axis_value(name).unwrap_or(0f64) as f32;
+        let pan = Vector2::new(axis("rotate"), axis("accelerate"));
+        let speed = PhotoCamera::PAN_SPEED / photo.zoom;
+        photo.center += pan * speed * delta;
+        photo.constrain();
+
+        for camera in (&mut cameras).join() {
+            *camera = Camera::from(photo.projection());
+        }
+    }
+}
+
+/// Points the velocity arrow of each ship in the direction it's drifting.
+///
+/// The arrow is scaled by the speed of the ship relative to its max velocity, and hidden while the
+/// ship is standing still.
+pub struct VelocityArrowSystem;
+
+impl<'s> System<'s> for VelocityArrowSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, VelocityArrow>,
+        ReadStorage<'s, Parent>,
+        ReadStorage<'s, Physical>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, Hidden>,
+    );
+
+    fn run(&mut self, system: Self::SystemData) {
+        let (entities, arrows, parents, physicals, mut locals, mut hidden) = system;
+
+        for (e, _, parent) in (&*entities, &arrows, &parents).join() {
+            let (physical, parent_rotation) =
+                match (physicals.get(parent.entity), locals.get(parent.entity)) {
+                    (Some(physical), Some(local)) => (physical, *local.rotation()),
+                    _ => continue,
+                };
+
+            let speed = physical.velocity.magnitude();
+
+            if speed < VelocityArrow::MIN_SPEED {
+                if !hidden.contains(e) {
+                    if let Err(e) = hidden.insert(e, Hidden) {
+                        error!("failed to hide entity: {}", e);
+                    }
+                }
+
+                continue;
+            }
+
+            hidden.remove(e);
+
+            let local = match locals.get_mut(e) {
+                Some(local) => local,
+                None => continue,
+            };
+
+            let length = VelocityArrow::MAX_LENGTH * f32::min(speed / physical.max_velocity, 1f32);
+            let angle = physical.velocity.y.atan2(physical.velocity.x);
+
+            // the arrow is a child of the ship, so undo the rotation of the ship.
+            let inverse = parent_rotation.inverse();
+            let rotation = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), angle);
+            let direction = rotation * Vector3::x();
+
+            *local.translation_mut() =
+                inverse * direction * (VelocityArrow::OFFSET + length / 2.0) + Vector3::z() * 0.1;
+            *local.rotation_mut() = inverse * rotation;
+            *local.scale_mut() = Vector3::new(length / VelocityArrow::SPRITE_WIDTH, 1.0, 1.0);
+        }
+    }
+}
+
+/// Hides sprites which are fully outside of the camera view, to keep draw submission bounded.
+///
+/// The view is assumed to cover one arena in size, starting at the position of the camera. Child
+/// entities follow their parents and are left alone.
+pub struct CullingSystem;
+
+impl CullingSystem {
+    /// Margin around the view, large enough to cover the largest sprite.
+    const MARGIN: f32 = 16.0;
+}
+
+impl<'s> System<'s> for CullingSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Camera>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, SpriteRender>,
+        ReadStorage<'s, Parent>,
+        WriteStorage<'s, Culled>,
+        WriteStorage<'s, Hidden>,
+    );
+
+    fn run(&mut self, system: Self::SystemData) {
+        let (entities, cameras, locals, sprites, parents, mut culled, mut hidden) = system;
+
+        let view = match (&cameras, &locals).join().next() {
+            Some((_, local)) => *local.translation(),
+            None => return,
+        };
+
+        let min_x = view.x - Self::MARGIN;
+        let max_x = view.x + ARENA_WIDTH + Self::MARGIN;
+        let min_y = view.y - Self::MARGIN;
+        let max_y = view.y + ARENA_HEIGHT + Self::MARGIN;
+
+        for (e, local, _, _) in (&*entities, &locals, &sprites, !&parents).join() {
+            let t = local.translation();
+            let visible = t.x >= min_x && t.x <= max_x && t.y >= min_y && t.y <= max_y;
+
+            if visible {
+                if culled.remove(e).is_some() {
+                    hidden.remove(e);
+                }
+
+                continue;
+            }
+
+            // don't interfere with entities hidden for other reasons.
+            if culled.contains(e) || hidden.contains(e) {
+                continue;
+            }
+
+            if let Err(e) = culled.insert(e, Culled) {
+                error!("failed to cull entity: {}", e);
+                continue;
+            }
+
+            if let Err(e) = hidden.insert(e, Hidden) {
+                error!("failed to hide entity: {}", e);
+            }
+        }
+    }
+}
+
+/// Keeps the number of bullets and asteroids within the budget set by `GameConfig`.
+///
+/// Instead of refusing to spawn new entities, this culls the least interesting ones: the oldest
+/// bullets and the asteroids furthest away from the ship.
+#[derive(Default)]
+pub struct EntityBudgetSystem {
+    /// Reused buffer of cull candidates, ordered by how much we want to keep them.
+    candidates: Vec<(f32, Entity)>,
+}
+
+impl EntityBudgetSystem {
+    /// Delete the least interesting candidates until at most `max` remain.
+    fn cull(&mut self, entities: &Entities, max: usize) {
+        if self.candidates.len() <= max {
+            return;
+        }
+
+        self.candidates
+            .sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        for (_, e) in self.candidates.drain(max..) {
+            if let Err(e) = entities.delete(e) {
+                error!("failed to cull entity: {}", e);
+            }
+        }
+    }
+}
+
+impl<'s> System<'s> for EntityBudgetSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, GameConfig>,
+        ReadStorage<'s, Bullet>,
+        ReadStorage<'s, Ship>,
+        ReadStorage<'s, Collider>,
+        ReadStorage<'s, Transform>,
+    );
+
+    fn run(&mut self, (entities, config, bullets, ships, colliders, locals): Self::SystemData) {
+        self.candidates.clear();
+
+        // bullets with the least time left to live are the oldest.
+        for (e, bullet) in (&*entities, &bullets).join() {
+            self.candidates.push((bullet.time_to_live, e));
+        }
+
+        let culled = self.candidates.len().saturating_sub(config.max_bullets);
+        self.cull(&entities, config.max_bullets);
+
+        if culled > 0 {
+            trace!("Bullets Culled: {}", culled);
+        }
+
+        self.candidates.clear();
+
+        for (e, collider, local) in (&*entities, &colliders, &locals).join() {
+            match *collider {
+                Collider::Asteroid | Collider::Deferred(DeferredCollider::Asteroid) => {}
+                _ => continue,
+            }
+
+            let t = local.translation();
+
+            // keep asteroids close to any ship, negated since candidates are sorted descending.
+            let distance = (&ships, &locals)
+                .join()
+                .map(|(_, s)| {
+                    let s = s.translation();
+                    ((t.x - s.x).powi(2) + (t.y - s.y).powi(2)).sqrt()
+                }).fold(std::f32::INFINITY, f32::min);
+
+            self.candidates.push((-distance, e));
+        }
+
+        let culled = self.candidates.len().saturating_sub(config.max_asteroids);
+        self.cull(&entities, config.max_asteroids);
+
+        if culled > 0 {
+            trace!("Asteroids Culled: {}", culled);
+        }
+    }
+}
+
+/// Plays a ticking sound which speeds up as the nearest asteroid gets closer to the ship.
+///
+/// Only active if enabled through `Settings::geiger`, or for the session with `--geiger`.
+#[derive(Default)]
+pub struct GeigerSystem {
+    /// Time until the next tick.
+    timer: f32,
+}
+
+impl GeigerSystem {
+    /// Asteroids further away than this are not considered threats (units).
+    const RANGE: f32 = 100f32;
+    /// Interval between ticks for asteroids right next to the ship (s).
+    const MIN_INTERVAL: f32 = 0.05f32;
+    /// Interval between ticks for asteroids at the edge of the range (s).
+    const MAX_INTERVAL: f32 = 1.0f32;
+}
+
+impl<'s> System<'s> for GeigerSystem {
+    type SystemData = (
+        Read<'s, Settings>,
+        Read<'s, SessionSettings>,
+        ReadStorage<'s, Ship>,
+        ReadStorage<'s, Collider>,
+        ReadStorage<'s, Transform>,
+        Read<'s, Time>,
+        ReadExpect<'s, Sounds>,
+        Read<'s, AssetStorage<Source>>,
+        Option<Read<'s, Output>>,
+        Read<'s, AudioMix>,
+    );
+
+    fn run(&mut self, system: Self::SystemData) {
+        let (settings, session, ships, colliders, locals, time, sounds, audio_storage, audio, mix) =
+            system;
+
+        if !session.geiger(&settings) {
+            return;
+        }
+
+        let mut nearest = None::<f32>;
+
+        for (_, ship_local) in (&ships, &locals).join() {
+            let s = ship_local.translation();
+
+            for (collider, local) in (&colliders, &locals).join() {
+                match *collider {
+                    Collider::Asteroid | Collider::Deferred(DeferredCollider::Asteroid) => {}
+                    _ => continue,
+                }
+
+                let distance = wrapped_distance(s, local.translation());
+
+                nearest = Some(nearest.map_or(distance, |n| f32::min(n, distance)));
+            }
+        }
+
+        let distance = match nearest {
+            Some(distance) if distance < Self::RANGE => distance,
+            _ => {
+                self.timer = 0f32;
+                return;
+            }
+        };
+
+        let interval =
+            Self::MIN_INTERVAL + (Self::MAX_INTERVAL - Self::MIN_INTERVAL) * distance / Self::RANGE;
+
+        // tick sooner if the threat got closer since the last tick.
+        self.timer = f32::min(self.timer, interval) - time.delta_seconds();
+
+        if self.timer <= 0f32 {
+            sounds
+                .tick_sfx
+                .play(&audio_storage, audio.as_ref().map(|o| &**o), &mix);
+
+            self.timer = interval;
+        }
+    }
+}
+
+/// Detects when the ship narrowly escapes an asteroid, and plays a stinger to celebrate.
+#[derive(Default)]
+pub struct NearMissSystem {
+    /// If an asteroid was dangerously close to the ship last frame.
+    close: bool,
+    /// Time until another near miss can be celebrated.
+    cooldown: f32,
+}
+
+impl NearMissSystem {
+    /// How close the edges of an asteroid and the ship need to get to count as a near miss.
+    const MARGIN: f32 = 6.0;
+    /// Minimum time between celebrated near misses (s).
+    const COOLDOWN: f32 = 5.0;
+}
+
+impl<'s> System<'s> for NearMissSystem {
+    type SystemData = (
+        ReadStorage<'s, Ship>,
+        ReadStorage<'s, Collider>,
+        ReadStorage<'s, Bounded>,
+        ReadStorage<'s, Transform>,
+        WriteExpect<'s, Stingers>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (ships, colliders, bounded, locals, mut stingers, time): Self::SystemData) {
+        self.cooldown = f32::max(self.cooldown - time.delta_seconds(), 0f32);
+
+        let mut alive = false;
+        let mut close = false;
+
+        for (_, ship_bounded, ship_local) in (&ships, &bounded, &locals).join() {
+            alive = true;
+
+            for (collider, bounded, local) in (&colliders, &bounded, &locals).join() {
+                if *collider != Collider::Asteroid {
+                    continue;
+                }
+
+                let distance = wrapped_distance(ship_local.translation(), local.translation());
+                let gap = distance - ship_bounded.shape.radius() - bounded.shape.radius();
+
+                if gap < Self::MARGIN {
+                    close = true;
+                }
+            }
+        }
+
+        // the asteroid got away without killing us.
+        if self.close && !close && alive && self.cooldown <= 0f32 {
+            stingers.request(Stinger::NearMiss);
+            self.cooldown = Self::COOLDOWN;
+        }
+
+        self.close = close;
+    }
+}
+
+/// Slows down time for a moment when a player on their last life is about to be hit by an
+/// asteroid, giving them a chance to dodge.
+///
+/// Bullet time can only be triggered a limited number of times in each wave, and the charges left
+/// are shown in the HUD.
+#[derive(Default)]
+pub struct BulletTimeSystem {
+    /// The wave charges were last refilled for.
+    wave: u32,
+    /// The text currently shown, to avoid updating it every frame.
+    shown: Option<String>,
+}
+
+impl BulletTimeSystem {
+    /// Time until two circles moving in straight lines first touch (s), if they ever do.
+    ///
+    /// `offset` is the position of the second circle relative to the first, and `velocity` its
+    /// velocity relative to the first.
+    fn time_to_collision(offset: Vector2<f32>, velocity: Vector2<f32>, radius: f32) -> Option<f32> {
+        let c = offset.norm_squared() - radius * radius;
+
+        if c <= 0f32 {
+            return Some(0f32);
+        }
+
+        let a = velocity.norm_squared();
+        let b = 2.0 * offset.dot(&velocity);
+        let discriminant = b * b - 4.0 * a * c;
+
+        if a <= std::f32::EPSILON || discriminant < 0f32 {
+            return None;
+        }
+
+        let t = (-b - discriminant.sqrt()) / (2.0 * a);
+
+        if t < 0f32 {
+            return None;
+        }
+
+        Some(t)
+    }
+}
+
+impl<'s> System<'s> for BulletTimeSystem {
+    type SystemData = (
+        ReadStorage<'s, Ship>,
+        ReadStorage<'s, Owner>,
+        ReadStorage<'s, Collider>,
+        ReadStorage<'s, Physical>,
+        ReadStorage<'s, Bounded>,
+        ReadStorage<'s, Transform>,
+        ReadExpect<'s, Lives>,
+        ReadExpect<'s, Wave>,
+        Option<Write<'s, BulletTime>>,
+        WriteStorage<'s, UiText>,
+        Write<'s, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            ships,
+            owners,
+            colliders,
+            physicals,
+            bounded,
+            locals,
+            lives,
+            wave,
+            bullet_time,
+            mut texts,
+            mut time,
+        ) = data;
+
+        let mut bullet_time = match bullet_time {
+            Some(bullet_time) => bullet_time,
+            None => return,
+        };
+
+        if wave.number != self.wave {
+            self.wave = wave.number;
+            bullet_time.charges = BulletTime::CHARGES;
+        }
+
+        if let Some(remaining) = bullet_time.remaining {
+            let remaining = remaining - time.delta_real_seconds();
+
+            if remaining > 0f32 {
+                bullet_time.remaining = Some(remaining);
+            } else {
+                bullet_time.remaining = None;
+                time.set_time_scale(1.0);
+            }
+        } else if bullet_time.charges > 0 {
+            let mut threatened = false;
+
+            let ships = (&ships, owners.maybe(), &physicals, &bounded, &locals).join();
+
+            for (_, owner, ship_physical, ship_bounded, ship_local) in ships {
+                let player = owner.map(|o| o.player).unwrap_or(0);
+
+                if lives.remaining.get(player).cloned() != Some(1) {
+                    continue;
+                }
+
+                let asteroids = (&colliders, &physicals, &bounded, &locals).join();
+
+                for (collider, physical, bounded, local) in asteroids {
+                    if *collider != Collider::Asteroid {
+                        continue;
+                    }
+
+                    let offset = wrapped_delta(ship_local.translation(), local.translation());
+                    let velocity = physical.velocity - ship_physical.velocity;
+                    let radius = ship_bounded.shape.radius() + bounded.shape.radius();
+
+                    let t = Self::time_to_collision(offset, velocity, radius);
+
+                    // NB: too late to dodge something already touching the ship.
+                    if t.map(|t| t > 0f32 && t < BulletTime::HORIZON).unwrap_or(false) {
+                        threatened = true;
+                    }
+                }
+            }
+
+            if threatened {
+                bullet_time.charges -= 1;
+                bullet_time.remaining = Some(BulletTime::DURATION);
+            }
+        }
+
+        // NB: reapplied every frame, since the scale is reset when the game is paused.
+        if bullet_time.remaining.is_some() {
+            time.set_time_scale(BulletTime::SCALE);
+        }
+
+        let text = bullet_time.as_text();
+
+        if self.shown.as_ref() == Some(&text) {
+            return;
+        }
+
+        if let Some(ui_text) = texts.get_mut(bullet_time.text) {
+            ui_text.text = text.clone();
+        }
+
+        self.shown = Some(text);
+    }
+}
+
+/// Plays requested stingers, which duck the music while they play.
+pub struct StingerSystem;
+
+impl<'s> System<'s> for StingerSystem {
+    type SystemData = (
+        WriteExpect<'s, Stingers>,
+        ReadExpect<'s, Sounds>,
+        Read<'s, AudioMix>,
+        Read<'s, Time>,
+        Read<'s, AssetStorage<Source>>,
+        Option<Read<'s, Output>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut stingers, sounds, mix, time, audio_storage, audio) = data;
+
+        stingers.update(
+            time.delta_seconds(),
+            &audio_storage,
+            audio.as_ref().map(|o| &**o),
+            sounds.device.as_ref(),
+            &mix,
+        );
+    }
+}
+
+/// Fades the audio mix in and out of being muffled, applies the master volume, and sets the volume
+/// of the music.
+pub struct AudioMixSystem;
+
+impl<'s> System<'s> for AudioMixSystem {
+    type SystemData = (
+        Write<'s, AudioMix>,
+        Option<Read<'s, Stingers>>,
+        Option<Write<'s, AudioSink>>,
+        Read<'s, Settings>,
+        Read<'s, Time>,
+        Write<'s, SfxQueue>,
+        Option<Read<'s, Sounds>>,
+        Read<'s, AssetStorage<Source>>,
+        Option<Read<'s, Output>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            mut mix,
+            stingers,
+            sink,
+            settings,
+            time,
+            mut queue,
+            sounds,
+            audio_storage,
+            audio,
+        ) = data;
+
+        mix.volume = settings.audio.effective_volume();
+        mix.update(time.delta_seconds());
+
+        queue.update(
+            time.delta_seconds(),
+            &settings.audio.budgets,
+            sounds.as_ref().map(|s| &**s),
+            &audio_storage,
+            audio.as_ref().map(|o| &**o),
+            &mix,
+        );
+
+        let duck = stingers.map(|s| s.duck()).unwrap_or(1.0);
+
+        if let Some(mut sink) = sink {
+            sink.set_volume(MUSIC_VOLUME * duck * mix.music_volume());
+        }
+    }
+}
+
+/// Lays out UI elements with a `Layout`, when they are added and when the window is resized or the
+/// UI scale changes.
+#[derive(Default)]
+pub struct LayoutSystem {
+    /// Scale the layouts were last applied with.
+    scale: Option<f32>,
+}
+
+impl<'s> System<'s> for LayoutSystem {
+    type SystemData = (
+        ReadExpect<'s, ScreenDimensions>,
+        Read<'s, Settings>,
+        WriteStorage<'s, Layout>,
+        WriteStorage<'s, UiTransform>,
+        WriteStorage<'s, UiText>,
+    );
+
+    fn run(
+        &mut self,
+        (screen, settings, mut layouts, mut transforms, mut texts): Self::SystemData,
+    ) {
+        let scale = Layout::scale(&screen, settings.ui_scale());
+        let resized = self.scale != Some(scale);
+        self.scale = Some(scale);
+
+        let elements = (&mut layouts, &mut transforms, (&mut texts).maybe()).join();
+
+        for (layout, transform, text) in elements {
+            if resized || !layout.applied {
+                layout.apply(scale, transform, text);
+            }
+        }
+    }
+}
+
+/// Handle the user interface.
+///
+/// Modifies text on screen and such when their underlying state has been modified.
+pub struct HandleUiSystem;
+
+impl<'s> System<'s> for HandleUiSystem {
+    type SystemData = (
+        ReadExpect<'s, Game>,
+        Read<'s, DevTools>,
+        WriteStorage<'s, UiText>,
+        WriteExpect<'s, Score>,
+    );
+
+    fn run(&mut self, (game, dev_tools, mut text, mut score): Self::SystemData) {
+        if game.modifiers != score.current_modifiers {
+            score.current_modifiers = game.modifiers;
+
+            if let Some(text) = text.get_mut(score.modifiers_text) {
+                text.text = game.modifiers.as_text();
+            }
+        }
+
+        if score.points > score.best {
+            score.best = score.points;
+
+            if let Some(text) = text.get_mut(score.best_text) {
+                text.text = Score::best_text(score.best);
+            }
+        }
+
+        let multiplier = score.accuracy.multiplier();
+
+        if multiplier != score.current_multiplier {
+            score.current_multiplier = multiplier;
+
+            if let Some(text) = text.get_mut(score.multiplier_text) {
+                text.text = Score::multiplier_text(multiplier);
+            }
+        }
+
+        if *dev_tools != score.current_dev_tools {
+            score.current_dev_tools = *dev_tools;
+
+            if let Some(text) = text.get_mut(score.dev_tools_text) {
+                text.text = dev_tools.as_text();
+            }
+        }
+    }
+}
+
+/// Shows the frame rate and how many entities there are of each kind in the `DebugOverlay`, when
+/// enabled through `DevTools::debug`.
+///
+/// The measured input latency and the slowest systems are shown whenever they're being measured.
+///
+/// Hitboxes are drawn separately by the `HitboxSystem`.
+#[derive(Default)]
+pub struct DebugOverlaySystem {
+    /// Time until the overlay is refreshed (s).
+    refresh_timer: f32,
+    /// If the overlay is currently showing anything.
+    shown: bool,
+}
 
-impl<'s> System<'s> for CollisionSystem {
+impl DebugOverlaySystem {
+    /// Interval at which the overlay is refreshed (s).
+    const REFRESH_INTERVAL: f32 = 0.25;
+}
+
+impl<'s> System<'s> for DebugOverlaySystem {
     type SystemData = (
-        ReadStorage<'s, Bounded>,
-        ReadStorage<'s, Transform>,
-        ReadStorage<'s, Collider>,
-        WriteExpect<'s, Game>,
+        Read<'s, DevTools>,
+        Option<Read<'s, DebugOverlay>>,
+        Option<Read<'s, LatencyProbe>>,
         WriteStorage<'s, UiText>,
-        WriteExpect<'s, Score>,
-        Read<'s, LazyUpdate>,
-        ReadExpect<'s, Asteroids>,
-        ReadExpect<'s, RandomGen>,
-        ReadExpect<'s, Sounds>,
-        Read<'s, AssetStorage<Source>>,
-        Option<Read<'s, Output>>,
+        Read<'s, FPSCounter>,
+        Read<'s, Time>,
         Entities<'s>,
+        ReadStorage<'s, Collider>,
+        ReadStorage<'s, Bullet>,
+        ReadStorage<'s, Particle>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        use std::collections::HashMap;
+        use std::fmt::Write;
 
         let (
-            bounding_volumes,
-            locals,
-            colliders,
-            mut game,
-            mut text,
-            mut score,
-            lazy,
-            asteroids_resource,
-            rand,
-            sounds,
-            audio_storage,
-            audio,
+            dev_tools,
+            overlay,
+            probe,
+            mut texts,
+            fps,
+            time,
             entities,
+            colliders,
+            bullets,
+            particles,
         ) = data;
 
-        let mut broad_phase = DBVTBroadPhase::new(0f32);
-
-        let mut deferred = HashMap::new();
+        let overlay = match overlay {
+            Some(overlay) => overlay,
+            None => return,
+        };
 
-        for (e, local, collider, bounding_volume) in
-            (&entities, &locals, &colliders, &bounding_volumes).join()
-        {
-            let _ =
-                bounding_volume.apply_to_broad_phase(collider.clone(), e, local, &mut broad_phase);
+        let text = match texts.get_mut(overlay.text) {
+            Some(text) => text,
+            None => return,
+        };
 
-            if let Collider::Deferred(next) = *collider {
-                deferred.insert(e, next.to_collider());
+        if !dev_tools.debug && probe.is_none() && overlay.timings.is_empty() {
+            if self.shown {
+                text.text.clear();
+                self.shown = false;
             }
+
+            return;
         }
 
-        let mut spawned = 0;
+        self.refresh_timer -= time.delta_seconds();
 
-        broad_phase.update(&mut |a, b| a != b, &mut |a, b, _| {
-            use self::Collider::*;
+        if self.shown && self.refresh_timer > 0f32 {
+            return;
+        }
 
-            // play the appropriate sound.
-            match (a, b) {
-                ((Asteroid, _), _) | (_, (Asteroid, _)) => {
-                    sounds
-                        .collision_sfx
-                        .play(&rand, &audio_storage, audio.as_ref().map(|o| &**o));
-                }
-                _ => {}
-            }
+        self.refresh_timer = Self::REFRESH_INTERVAL;
+        self.shown = true;
 
-            match (a, b) {
-                // check if deferred things are still intersecting.
-                ((Deferred(_), a), (Deferred(_), b)) => {
-                    deferred.remove(&a);
-                    deferred.remove(&b);
-                    return;
-                }
-                ((Deferred(_), a), _) | (_, (Deferred(_), a)) => {
-                    deferred.remove(&a);
-                    return;
-                }
-                // we get a point!
-                ((Bullet, _), (Asteroid, _)) | ((Asteroid, _), (Bullet, _)) => {
-                    sounds
-                        .explosion_sfx
-                        .play(&rand, &audio_storage, audio.as_ref().map(|o| &**o));
+        // NB: reuse the allocated text.
+        text.text.clear();
 
-                    score.asteroids += 1;
+        if dev_tools.debug {
+            let asteroids = (&colliders).join().filter(|c| c.is_asteroid()).count();
 
-                    if let Some(text) = text.get_mut(score.score_text) {
-                        text.text = score.asteroids.to_string();
-                    }
-                }
-                _ => {}
+            let _ = writeln!(text.text, "fps: {:.0}", fps.sampled_fps());
+            let _ = writeln!(text.text, "entities: {}", (&*entities).join().count());
+            let _ = writeln!(text.text, "asteroids: {}", asteroids);
+            let _ = writeln!(text.text, "bullets: {}", (&bullets).join().count());
+            let _ = writeln!(text.text, "particles: {}", (&particles).join().count());
+            let _ = writeln!(text.text, "hitboxes (F4): {}", on_off(dev_tools.hitboxes));
+        }
+
+        if let Some(probe) = probe {
+            let _ = writeln!(text.text, "{}", probe.as_text());
+        }
+
+        text.text.push_str(&overlay.timings);
+
+        fn on_off(value: bool) -> &'static str {
+            if value {
+                "on"
+            } else {
+                "off"
             }
+        }
+    }
+}
 
-            for c in &[a, b] {
-                let mut asteroids = SmallVec::<[(Transform, f32); 2]>::new();
+/// Plots histograms of the radii and speeds of the asteroids in the `HistogramOverlay`, when
+/// enabled through `DevTools::debug`.
+///
+/// Used to see how changes to spawning and splintering play out. The bins span from zero to the
+/// largest value seen.
+#[derive(Default)]
+pub struct HistogramSystem {
+    /// Time until the histograms are refreshed (s).
+    refresh_timer: f32,
+    /// If the overlay is currently showing anything.
+    shown: bool,
+    /// Reused buffer of asteroid radii.
+    radii: Vec<f32>,
+    /// Reused buffer of asteroid speeds.
+    speeds: Vec<f32>,
+}
 
-                let e = match *c {
-                    (Collider::Ship, _) if game.modifiers.player_is_immortal => continue,
-                    (Collider::Ship, e) => {
-                        // we died!
-                        game.modifiers.player_is_dead = true;
-                        e
-                    }
-                    // an asteroid collided with something
-                    // this is interesting, since there is a chance that asteroids splinter!
-                    (Collider::Asteroid, e) => {
-                        asteroids.extend(asteroid_data(*e, &bounding_volumes, &locals));
-                        e
-                    }
-                    (_, e) => e,
-                };
+impl HistogramSystem {
+    /// Interval at which the histograms are refreshed (s).
+    const REFRESH_INTERVAL: f32 = 1.0;
+    /// Number of bins in each histogram.
+    const BINS: usize = 8;
+    /// Length of the bar of the fullest bin (characters).
+    const BAR_LENGTH: usize = 20;
 
-                if !asteroids.is_empty() {
-                    let mut volume = 0.0f32;
-                    let c = asteroids.len() as f32;
+    /// Write a histogram of the given values.
+    fn plot(out: &mut String, title: &str, values: &[f32]) {
+        use std::fmt::Write;
 
-                    let mut local = Transform::default();
+        let _ = writeln!(out, "{} ({})", title, values.len());
 
-                    for (t, v) in asteroids {
-                        *local.translation_mut() += t.translation();
-                        volume += v;
-                    }
+        let max = values.iter().cloned().fold(0f32, f32::max);
 
-                    *local.translation_mut() /= c;
+        if max <= 0f32 {
+            return;
+        }
 
-                    spawned += spawn_asteroid_cluster(
-                        local,
-                        volume,
-                        &entities,
-                        &lazy,
-                        &asteroids_resource,
-                        &rand,
-                    );
-                }
+        let width = max / Self::BINS as f32;
+        let mut counts = [0usize; Self::BINS];
 
-                if let Err(e) = entities.delete(*e) {
-                    error!("failed to delete entity: {:?}: {}", a, e);
-                }
+        for value in values {
+            let bin = ((value / width) as usize).min(Self::BINS - 1);
+            counts[bin] += 1;
+        }
+
+        let fullest = counts.iter().cloned().max().unwrap_or(0).max(1);
+
+        for (i, count) in counts.iter().enumerate() {
+            let bar = (count * Self::BAR_LENGTH + fullest - 1) / fullest;
+
+            let _ = writeln!(
+                out,
+                "{:>5.0}-{:<5.0} {:<bar_length$} {}",
+                i as f32 * width,
+                (i + 1) as f32 * width,
+                "#".repeat(bar),
+                count,
+                bar_length = Self::BAR_LENGTH,
+            );
+        }
+    }
+}
+
+impl<'s> System<'s> for HistogramSystem {
+    type SystemData = (
+        Read<'s, DevTools>,
+        Option<Read<'s, HistogramOverlay>>,
+        WriteStorage<'s, UiText>,
+        Read<'s, Time>,
+        ReadStorage<'s, Collider>,
+        ReadStorage<'s, Physical>,
+        ReadStorage<'s, Transform>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (dev_tools, overlay, mut texts, time, colliders, physicals, locals) = data;
+
+        let text = match overlay.and_then(|o| texts.get_mut(o.text)) {
+            Some(text) => text,
+            None => return,
+        };
+
+        if !dev_tools.debug {
+            if self.shown {
+                text.text.clear();
+                self.shown = false;
             }
-        });
 
-        // undefer deferred
-        for (e, next) in deferred {
-            lazy.insert(e, next);
+            return;
         }
 
-        if spawned > 0 {
-            trace!("Asteroids Spawned: {}", spawned);
+        self.refresh_timer -= time.delta_seconds();
+
+        if self.shown && self.refresh_timer > 0f32 {
+            return;
         }
 
-        fn asteroid_data(
-            e: Entity,
-            bounding_volumes: &ReadStorage<Bounded>,
-            locals: &ReadStorage<Transform>,
-        ) -> Option<(Transform, f32)> {
-            use std::f32::consts;
+        self.refresh_timer = Self::REFRESH_INTERVAL;
+        self.shown = true;
 
-            let volume = match bounding_volumes.get(e) {
-                Some(volume) => volume,
-                None => return None,
-            };
+        self.radii.clear();
+        self.speeds.clear();
 
-            let local = match locals.get(e) {
-                Some(local) => local.clone(),
-                None => return None,
-            };
+        for (collider, physical, local) in (&colliders, &physicals, &locals).join() {
+            if *collider != Collider::Asteroid {
+                continue;
+            }
 
-            Some((local.clone(), volume.shape.radius().powf(2.0) * consts::PI))
+            self.radii.push(Asteroids::radius(local.scale().x));
+            self.speeds.push(physical.velocity.magnitude());
         }
 
-        fn spawn_asteroid_cluster(
-            local: Transform,
-            mut c: f32,
-            entities: &Entities,
-            lazy: &Read<LazyUpdate>,
-            asteroids_resource: &ReadExpect<Asteroids>,
-            rand: &ReadExpect<RandomGen>,
-        ) -> usize {
-            use std::f32::consts;
+        // NB: reuse the allocated text.
+        text.text.clear();
+        Self::plot(&mut text.text, "radius", &self.radii);
+        Self::plot(&mut text.text, "speed", &self.speeds);
+    }
+}
+
+/// Draws the hitboxes of everything which collides, when enabled through `DevTools`.
+pub struct HitboxSystem;
+
+impl HitboxSystem {
+    /// Number of line segments used to draw each hitbox.
+    const SEGMENTS: usize = 16;
+
+    /// Color of the hitbox of the given kind of collider.
+    pub fn color(collider: &Collider) -> Rgba {
+        match *collider {
+            Collider::Ship => Rgba::green(),
+            Collider::Bullet => Rgba::blue(),
+            Collider::Asteroid => Rgba::red(),
+            Collider::PowerUp => Rgba::white(),
+            Collider::Planet => Rgba(1.0, 0.6, 0.2, 1.0),
+            Collider::Station => Rgba::from(Station::TINT),
+            Collider::Ufo | Collider::UfoBullet => Rgba::from(Ufos::COLOR),
+            Collider::Pebble => Rgba::from(Pebbles::COLOR),
+            Collider::Hull => Rgba::from(Hulls::TINT),
+            // not colliding yet.
+            Collider::Deferred(_) => Rgba(0.5, 0.5, 0.5, 1.0),
+        }
+    }
+}
+
+impl<'s> System<'s> for HitboxSystem {
+    type SystemData = (
+        Read<'s, DevTools>,
+        Option<Write<'s, DebugLines>>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, Bounded>,
+        ReadStorage<'s, Collider>,
+    );
+
+    fn run(&mut self, (dev_tools, lines, locals, bounding_volumes, colliders): Self::SystemData) {
+        use std::f32::consts;
+
+        if !dev_tools.hitboxes {
+            return;
+        }
+
+        let mut lines = match lines {
+            Some(lines) => lines,
+            None => return,
+        };
 
-            let min_area = Asteroids::MIN_RADIUS.powf(2.0) * consts::PI;
+        let step = 2.0 * consts::PI / Self::SEGMENTS as f32;
 
-            let mut angle = 0.0f32;
+        for (local, bounded, collider) in (&locals, &bounding_volumes, &colliders).join() {
+            let color = Self::color(collider);
 
-            let mut count = 0;
+            let t = local.translation();
 
-            while c > min_area * 2.0 {
-                c -= min_area;
-                count += 1;
+            if let Some(ref hull) = bounded.hull {
+                let isometry = Bounded::isometry(local);
+                let points = hull.points();
+
+                for (i, p) in points.iter().enumerate() {
+                    let a = isometry * *p;
+                    let b = isometry * points[(i + 1) % points.len()];
+                    lines.draw_line(Point3::new(a.x, a.y, t.z), Point3::new(b.x, b.y, t.z), color);
+                }
+
+                continue;
             }
 
-            for _ in 0..count {
-                angle += rand.next_f32() * consts::PI;
-
-                let rotation = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), angle);
-                let velocity = rotation * Vector3::x() * 100.0 * rand.next_f32();
-                let velocity = Vector2::new(velocity.x, velocity.y);
-
-                spawn_asteroid(
-                    entities,
-                    lazy,
-                    rand,
-                    asteroids_resource,
-                    local.clone(),
-                    1.0,
-                    velocity,
-                    0.10,
-                    true,
-                );
+            let radius = bounded.shape.radius();
+
+            let point = |i: usize| {
+                let angle = step * i as f32;
+                Point3::new(t.x + radius * angle.cos(), t.y + radius * angle.sin(), t.z)
+            };
+
+            for i in 0..Self::SEGMENTS {
+                lines.draw_line(point(i), point(i + 1), color);
+            }
+        }
+    }
+}
+
+/// Sketch the outline of everything which collides.
+#[cfg(any(feature = "god_view", feature = "clips"))]
+fn sketch_frame(
+    locals: &ReadStorage<Transform>,
+    bounding_volumes: &ReadStorage<Bounded>,
+    colliders: &ReadStorage<Collider>,
+    physicals: &ReadStorage<Physical>,
+) -> sketch::Frame {
+    // Pack a color as `0RGB`.
+    let pack = |color: Rgba| {
+        let channel = |c: f32| (c.max(0.0).min(1.0) * 255.0) as u32;
+        (channel(color.0) << 16) | (channel(color.1) << 8) | channel(color.2)
+    };
+
+    let mut frame = sketch::Frame {
+        arena: [ARENA_WIDTH, ARENA_HEIGHT],
+        objects: Vec::new(),
+    };
+
+    for (local, bounded, collider, physical) in
+        (locals, bounding_volumes, colliders, physicals.maybe()).join()
+    {
+        let t = local.translation();
+
+        let shape = match bounded.hull {
+            Some(ref hull) => {
+                let isometry = Bounded::isometry(local);
+
+                let points = hull
+                    .points()
+                    .iter()
+                    .map(|p| {
+                        let p = isometry * *p;
+                        [p.x, p.y]
+                    }).collect();
+
+                sketch::Shape::Polygon(points)
             }
+            None => sketch::Shape::Circle([t.x, t.y], bounded.shape.radius()),
+        };
+
+        let velocity = physical.map(|p| [p.velocity.x, p.velocity.y]).unwrap_or_default();
+
+        frame.objects.push(sketch::Object {
+            shape,
+            position: [t.x, t.y],
+            velocity,
+            color: pack(HitboxSystem::color(collider)),
+        });
+    }
+
+    frame
+}
+
+/// Shows everything which collides in the god view window.
+///
+/// Added as a thread-local system, so that the window is driven from the main thread.
+#[cfg(feature = "god_view")]
+pub struct GodViewSystem {
+    god_view: GodView,
+}
 
-            return count;
+#[cfg(feature = "god_view")]
+impl GodViewSystem {
+    pub fn new() -> Self {
+        Self {
+            god_view: GodView::open(),
         }
     }
 }
 
-/// Handle the user interface.
+#[cfg(feature = "god_view")]
+impl<'s> System<'s> for GodViewSystem {
+    type SystemData = (
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, Bounded>,
+        ReadStorage<'s, Collider>,
+        ReadStorage<'s, Physical>,
+    );
+
+    fn run(&mut self, (locals, bounding_volumes, colliders, physicals): Self::SystemData) {
+        if !self.god_view.is_open() {
+            return;
+        }
+
+        let frame = sketch_frame(&locals, &bounding_volumes, &colliders, &physicals);
+        self.god_view.show(&frame);
+    }
+}
+
+/// Captures frames of the running game for clips, see `ClipRecorder`.
+#[cfg(feature = "clips")]
+#[derive(Default)]
+pub struct ClipSystem {
+    /// Time until the next frame is captured (s).
+    time_to_capture: f32,
+}
+
+#[cfg(feature = "clips")]
+impl<'s> System<'s> for ClipSystem {
+    type SystemData = (
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, Bounded>,
+        ReadStorage<'s, Collider>,
+        ReadStorage<'s, Physical>,
+        WriteExpect<'s, ClipRecorder>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (locals, bounding_volumes, colliders, physicals, mut clips, time) = data;
+
+        self.time_to_capture -= time.delta_real_seconds();
+
+        if self.time_to_capture > 0f32 {
+            return;
+        }
+
+        // NB: frames are captured at a steady rate, regardless of how much time is slowed down.
+        self.time_to_capture = (self.time_to_capture + 1.0 / ClipRecorder::FRAME_RATE).max(0f32);
+        clips.capture(sketch_frame(&locals, &bounding_volumes, &colliders, &physicals));
+    }
+
+    fn setup(&mut self, res: &mut Resources) {
+        res.insert(ClipRecorder::start());
+        Self::SystemData::setup(res);
+    }
+}
+
+/// Completes each frame of `SystemTimings`, shows the slowest systems in the `DebugOverlay`, and
+/// dumps the recorded timings to CSV on `dump_timings`.
 ///
-/// Modifies text on screen and such when their underlying state has been modified.
-pub struct HandleUiSystem;
+/// A `DispatchPlan` is also derived from the recorded timings when dumping, which is logged and
+/// saved for `--tuned-dispatch`.
+#[cfg(feature = "timings")]
+#[derive(Default)]
+pub struct TimingsSystem {
+    dump: Action,
+    /// Time until the shown timings are refreshed (s).
+    refresh_timer: f32,
+}
 
-impl<'s> System<'s> for HandleUiSystem {
+#[cfg(feature = "timings")]
+impl TimingsSystem {
+    /// Interval at which the shown timings are refreshed (s).
+    const REFRESH_INTERVAL: f32 = 0.5;
+    /// Number of systems shown.
+    const SHOWN: usize = 8;
+}
+
+#[cfg(feature = "timings")]
+impl<'s> System<'s> for TimingsSystem {
     type SystemData = (
-        ReadExpect<'s, Game>,
-        WriteStorage<'s, UiText>,
-        WriteExpect<'s, Score>,
+        Read<'s, SystemTimings>,
+        Option<Write<'s, DebugOverlay>>,
+        Read<'s, InputHandler<String, String>>,
+        Read<'s, InputContexts>,
+        Read<'s, Time>,
     );
 
-    fn run(&mut self, (game, mut text, mut score): Self::SystemData) {
-        if game.modifiers != score.current_modifiers {
-            score.current_modifiers = game.modifiers;
+    fn run(&mut self, (timings, overlay, input, contexts, time): Self::SystemData) {
+        use std::fmt::Write;
 
-            if let Some(text) = text.get_mut(score.modifiers_text) {
-                text.text = game.modifiers.as_text();
+        timings.end_frame();
+
+        let mut dump = false;
+        contexts.test(&mut self.dump, &input, "dump_timings").activated(|| dump = true);
+
+        if dump {
+            let result = SystemTimings::path().and_then(|path| {
+                timings.write_csv(&path)?;
+                Ok(path)
+            });
+
+            match result {
+                Ok(path) => info!("wrote system timings: {}", path.display()),
+                Err(e) => error!("failed to write system timings: {}", e),
             }
+
+            let plan = timings.plan(rayon::current_num_threads());
+            plan.log();
+            plan.save();
+        }
+
+        self.refresh_timer -= time.delta_seconds();
+
+        if self.refresh_timer > 0f32 {
+            return;
+        }
+
+        self.refresh_timer = Self::REFRESH_INTERVAL;
+
+        let mut overlay = match overlay {
+            Some(overlay) => overlay,
+            None => return,
+        };
+
+        // NB: reuse the allocated text.
+        overlay.timings.clear();
+
+        for (name, duration) in timings.averages().into_iter().take(Self::SHOWN) {
+            let _ = writeln!(overlay.timings, "{}: {} us", name, micros(&duration));
         }
     }
 }
@@ -6,6 +6,10 @@ use amethyst::{
         Texture, TextureMetadata,
     },
 };
+use crate::{
+    assets::{self, InitError},
+    theme::Theme,
+};
 
 /// A handle for a sprite sheet.
 pub struct SpriteSheet {
@@ -16,7 +20,12 @@ pub struct SpriteSheet {
 impl SpriteSheet {
     /// Load a sprite sheet from the given path, expecting a <path>.ron file for the mapping and a
     /// <path>.png file for the texture.
-    pub fn from_path(world: &mut World, path: &str) -> SpriteSheet {
+    ///
+    /// Fails if either file is missing, or if the mapping can't be parsed.
+    pub fn from_path(world: &mut World, path: &str) -> Result<SpriteSheet, InitError> {
+        assets::require(&format!("{}.png", path))?;
+        assets::require_ron(&format!("{}.ron", path))?;
+
         let texture_handle = {
             let loader = world.read_resource::<Loader>();
             let texture_storage = world.read_resource::<AssetStorage<Texture>>();
@@ -43,7 +52,17 @@ impl SpriteSheet {
             )
         };
 
-        SpriteSheet { handle }
+        Ok(SpriteSheet { handle })
+    }
+
+    /// Load the sprite sheet with the given name from the active theme, see `Theme::texture`.
+    pub fn themed(world: &mut World, name: &str) -> Result<SpriteSheet, InitError> {
+        let path = match world.res.try_fetch::<Theme>() {
+            Some(theme) => theme.texture(name),
+            None => Theme::default().texture(name),
+        };
+
+        SpriteSheet::from_path(world, &path)
     }
 
     /// Construct a render handle for the given sprite in the sprite sheet.
@@ -0,0 +1,119 @@
+//! Theme packs, which change how the arena looks and sounds.
+//!
+//! Themes are declared in `assets/themes.ron`. Each one bundles a directory of sprite sheets, the
+//! colors of the background and the stars drawn on it, and the music tracks to play. Sprite sheets
+//! missing from the directory of a theme are taken from `texture`, so a theme only has to ship
+//! the sheets it changes.
+//!
+//! The theme is picked from the main menu and takes effect when the next game starts, except for
+//! the background color which is set up with the window, and changes the next time the game
+//! launches.
+
+use crate::assets;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// A theme pack.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// Name of the theme, as shown in menus.
+    pub name: String,
+    /// Directory of the sprite sheets of the theme, relative to the asset directory.
+    pub textures: String,
+    /// Color of the background.
+    pub background: [f32; 4],
+    /// Color of the stars drawn on the background.
+    pub stars: [f32; 4],
+    /// Music tracks, played in order.
+    pub music: Vec<String>,
+}
+
+impl Theme {
+    /// Directory of the sprite sheets every theme falls back to.
+    const TEXTURES: &'static str = "texture";
+
+    /// Path to the sprite sheet with the given name, without extension.
+    ///
+    /// Falls back to the default sprite sheet if the theme doesn't have one.
+    pub fn texture(&self, name: &str) -> String {
+        let path = format!("{}/{}", self.textures, name);
+
+        if assets::require(&format!("{}.png", path)).is_ok() {
+            return path;
+        }
+
+        format!("{}/{}", Self::TEXTURES, name)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            name: "Classic".to_string(),
+            textures: Self::TEXTURES.to_string(),
+            background: [0.0, 0.0, 0.0, 1.0],
+            stars: [0.6, 0.6, 0.7, 1.0],
+            music: vec!["audio/music.wav".to_string()],
+        }
+    }
+}
+
+/// Every theme which can be picked, as declared in the theme manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Themes {
+    pub themes: Vec<Theme>,
+}
+
+impl Themes {
+    /// Path to the theme manifest, relative to the asset directory.
+    const PATH: &'static str = "themes.ron";
+
+    /// Read the theme manifest, falling back to the classic theme if it is missing or broken.
+    pub fn read() -> Themes {
+        match assets::load_ron::<Themes>(Self::PATH) {
+            Ok(ref themes) if themes.themes.is_empty() => {
+                warn!("no themes declared in manifest");
+                Themes::default()
+            }
+            Ok(themes) => themes,
+            Err(e) => {
+                warn!("failed to load themes: {}", e);
+                Themes::default()
+            }
+        }
+    }
+
+    /// The theme with the given name, or the first one if there is no such theme.
+    pub fn get(&self, name: &str) -> Theme {
+        self.themes
+            .iter()
+            .find(|t| t.name == name)
+            .or_else(|| self.themes.first())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Name of the theme after the one with the given name, starting over after the last one.
+    pub fn next(&self, name: &str) -> String {
+        let next = self
+            .themes
+            .iter()
+            .position(|t| t.name == name)
+            .map(|i| (i + 1) % self.themes.len())
+            .unwrap_or(0);
+
+        self.themes
+            .get(next)
+            .map(|t| t.name.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Default for Themes {
+    fn default() -> Self {
+        Self {
+            themes: vec![Theme::default()],
+        }
+    }
+}
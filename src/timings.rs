@@ -0,0 +1,256 @@
+//! Per-system timing instrumentation, enabled with the `timings` feature.
+//!
+//! Every system scheduled through `schedule::Schedule` records how long it took to run each frame.
+//! The slowest systems are shown in the debug overlay, and the recorded frames can be dumped to CSV
+//! along with a `DispatchPlan` suggesting how to dispatch the systems more efficiently.
+
+use amethyst::utils::application_root_dir;
+use crate::schedule::DispatchPlan;
+
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+/// Execution time of each scheduled system, over the most recent frames.
+///
+/// Systems run in parallel, so timings are recorded through a lock instead of requiring write
+/// access to the resource.
+#[derive(Debug, Default)]
+pub struct SystemTimings {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// Names of systems, in the order they were first recorded.
+    names: Vec<String>,
+    /// Time spent by each system during the current frame, indexed like `names`.
+    current: Vec<Duration>,
+    /// If anything was recorded during the current frame.
+    recorded: bool,
+    /// Completed frames, oldest first.
+    frames: VecDeque<Vec<Duration>>,
+    /// Scheduled systems and the systems they depend on, in the order they were set up.
+    deps: Vec<(String, Vec<String>)>,
+}
+
+impl SystemTimings {
+    /// Number of frames kept around.
+    pub const MAX_FRAMES: usize = 600;
+
+    /// Path timings are dumped to.
+    pub fn path() -> io::Result<PathBuf> {
+        Ok(application_root_dir()?.join("timings.csv"))
+    }
+
+    /// Declare which systems a scheduled system depends on.
+    pub fn declare(&self, name: &str, deps: &[String]) {
+        let mut inner = self.inner.lock().expect("timings lock poisoned");
+        inner.deps.push((name.to_string(), deps.to_vec()));
+    }
+
+    /// Record how long a system took to run during the current frame.
+    pub fn record(&self, name: &str, duration: Duration) {
+        let mut inner = self.inner.lock().expect("timings lock poisoned");
+
+        let index = match inner.names.iter().position(|n| n == name) {
+            Some(index) => index,
+            None => {
+                inner.names.push(name.to_string());
+                inner.current.push(Duration::default());
+                inner.names.len() - 1
+            }
+        };
+
+        inner.current[index] += duration;
+        inner.recorded = true;
+    }
+
+    /// Complete the current frame, unless no system ran during it.
+    pub fn end_frame(&self) {
+        let mut inner = self.inner.lock().expect("timings lock poisoned");
+
+        if !inner.recorded {
+            return;
+        }
+
+        let frame = inner.current.clone();
+
+        for duration in &mut inner.current {
+            *duration = Duration::default();
+        }
+
+        if inner.frames.len() == Self::MAX_FRAMES {
+            inner.frames.pop_front();
+        }
+
+        inner.frames.push_back(frame);
+        inner.recorded = false;
+    }
+
+    /// Average time each system took over the recorded frames, slowest first.
+    pub fn averages(&self) -> Vec<(String, Duration)> {
+        let inner = self.inner.lock().expect("timings lock poisoned");
+
+        let count = inner.frames.len().max(1) as u32;
+
+        let mut averages = inner
+            .names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                // NB: systems first recorded after a frame was completed are missing from it.
+                let total = inner
+                    .frames
+                    .iter()
+                    .filter_map(|f| f.get(i))
+                    .fold(Duration::default(), |a, b| a + *b);
+
+                (name.clone(), total / count)
+            }).collect::<Vec<_>>();
+
+        averages.sort_by(|a, b| b.1.cmp(&a.1));
+        averages
+    }
+
+    /// Suggest how to dispatch the scheduled systems, from their average timings and declared
+    /// dependencies, using at most `max_threads` threads.
+    ///
+    /// NB: systems which access the same resources can't run in parallel either, so the number of
+    /// threads is an upper bound.
+    pub fn plan(&self, max_threads: usize) -> DispatchPlan {
+        let averages = self.averages().into_iter().collect::<HashMap<_, _>>();
+
+        let inner = self.inner.lock().expect("timings lock poisoned");
+
+        let names = inner.deps.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>();
+        let count = names.len();
+
+        let cost = names
+            .iter()
+            .map(|n| averages.get(*n).map(micros).unwrap_or(0))
+            .collect::<Vec<_>>();
+
+        // systems depending on each system, and the number of dependencies of each system.
+        // NB: dependencies on systems which aren't scheduled, like global ones, are ignored.
+        let mut dependents = vec![Vec::new(); count];
+        let mut pending = vec![0usize; count];
+
+        for (i, (_, deps)) in inner.deps.iter().enumerate() {
+            for d in deps.iter().filter_map(|d| names.iter().position(|n| n == d)) {
+                dependents[d].push(i);
+                pending[i] += 1;
+            }
+        }
+
+        let mut topological = Vec::with_capacity(count);
+        let mut remaining = pending.clone();
+        let mut ready = (0..count).filter(|i| pending[*i] == 0).collect::<Vec<_>>();
+
+        while let Some(i) = ready.pop() {
+            topological.push(i);
+
+            for &j in &dependents[i] {
+                remaining[j] -= 1;
+
+                if remaining[j] == 0 {
+                    ready.push(j);
+                }
+            }
+        }
+
+        // time of the longest chain of dependent systems starting with each system (us).
+        let mut chain = cost.clone();
+
+        for &i in topological.iter().rev() {
+            chain[i] = cost[i] + dependents[i].iter().map(|j| chain[*j]).max().unwrap_or(0);
+        }
+
+        // longer chains first, and the order systems were set up in on ties.
+        let priority = |i: usize| (chain[i], Reverse(i));
+
+        // schedule the system starting the longest chain among those whose dependencies are
+        // scheduled.
+        let mut order = Vec::with_capacity(count);
+        let mut remaining = pending;
+        let mut ready = (0..count).filter(|i| remaining[*i] == 0).collect::<Vec<_>>();
+
+        while let Some(k) = (0..ready.len()).max_by_key(|k| priority(ready[*k])) {
+            let i = ready.swap_remove(k);
+            order.push(names[i].to_string());
+
+            for &j in &dependents[i] {
+                remaining[j] -= 1;
+
+                if remaining[j] == 0 {
+                    ready.push(j);
+                }
+            }
+        }
+
+        let mut critical_path = Vec::new();
+        let mut next = (0..count).max_by_key(|i| priority(*i));
+
+        while let Some(i) = next {
+            critical_path.push(names[i].to_string());
+            next = dependents[i].iter().cloned().max_by_key(|j| priority(*j));
+        }
+
+        let total = cost.iter().sum::<u64>();
+        let critical = chain.iter().cloned().max().unwrap_or(0);
+
+        let threads = if critical > 0 {
+            ((total + critical - 1) / critical) as usize
+        } else {
+            1
+        };
+
+        DispatchPlan {
+            order,
+            critical_path,
+            total,
+            critical,
+            threads: threads.max(1).min(max_threads.max(1)),
+        }
+    }
+
+    /// Write the recorded frames as CSV, with one row per frame and one column per system in
+    /// microseconds.
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        let inner = self.inner.lock().expect("timings lock poisoned");
+
+        let mut out = BufWriter::new(File::create(path)?);
+
+        write!(out, "frame")?;
+
+        for name in &inner.names {
+            write!(out, ",{}", name)?;
+        }
+
+        writeln!(out)?;
+
+        for (n, frame) in inner.frames.iter().enumerate() {
+            write!(out, "{}", n)?;
+
+            for i in 0..inner.names.len() {
+                let value = frame.get(i).map(micros).unwrap_or(0);
+                write!(out, ",{}", value)?;
+            }
+
+            writeln!(out)?;
+        }
+
+        out.flush()
+    }
+}
+
+/// Whole microseconds in a duration.
+pub fn micros(duration: &Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+}